@@ -31,9 +31,11 @@ pub async fn init() -> std::io::Result<()> {
     // Insert new setup here
     let user = Data::new(UserDBService::new());
     let u = user.clone();
-    let scrapers = Arc::new(
-        manga_scraper::init::register(&config.root_folder).expect("Failed to load scrapers"),
-    );
+    let (scrapers, errors) = manga_scraper::init::register(&config.root_folder);
+    for err in errors {
+        log::error!("Failed to load scraper: {}", err);
+    }
+    let scrapers = Arc::new(scrapers);
     let scrapers2 = scrapers.clone();
     let c = config.clone();
     tokio::spawn(async move {
@@ -484,7 +484,8 @@ impl MangaDBService {
                 Order::Created => "created",
                 Order::Updated => "updated",
                 Order::LastRead => "reading.updated",
-                Order::Popularity => todo!(),
+                // this api keeps no activity counters or search scores
+                Order::Popularity | Order::Trending | Order::Relevance => "updated",
                 Order::Random => "rand()",
                 Order::Status => "status",
                 Order::ChapterCount => "chapter_count",
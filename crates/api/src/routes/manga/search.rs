@@ -47,6 +47,7 @@ pub async fn convert_to_search_response(
         status: Status::try_from(v.data.status).unwrap(),
         ext,
         number: number as u32,
+        highlight: None,
     })
 }
 
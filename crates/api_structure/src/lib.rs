@@ -320,6 +320,41 @@ pub enum Permission {
     ManageExternalServices,
 }
 
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::None => "none",
+            Permission::Verify => "verify",
+            Permission::Read => "read",
+            Permission::Create => "create",
+            Permission::Review => "review",
+            Permission::Delete => "delete",
+            Permission::RequestDelete => "request_delete",
+            Permission::Impersonate => "impersonate",
+            Permission::ManageExternalServices => "manage_external_services",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(permission: &str) -> Result<Self, Self::Err> {
+        Ok(match permission {
+            "none" => Self::None,
+            "verify" => Self::Verify,
+            "read" => Self::Read,
+            "create" => Self::Create,
+            "review" => Self::Review,
+            "delete" => Self::Delete,
+            "request_delete" => Self::RequestDelete,
+            "impersonate" => Self::Impersonate,
+            "manage_external_services" => Self::ManageExternalServices,
+            _ => return Err(()),
+        })
+    }
+}
+
 impl TryFrom<u32> for Role {
     type Error = ();
 
@@ -365,6 +400,9 @@ impl Claim {
             role,
             exp: expiration.as_millis() as u64,
             r#type: jwt_type,
+            session: None,
+            jti: None,
+            api_key: None,
         }
     }
 
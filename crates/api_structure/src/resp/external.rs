@@ -4,6 +4,8 @@ use apistos::ApiComponent;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::search::ValidSearches;
+
 #[derive(ApiComponent, Deserialize, Serialize, JsonSchema)]
 pub struct ToScrape {
     pub manga_id: String,
@@ -26,3 +28,14 @@ pub struct ScrapeChapterListResponse {
     pub link: Option<String>,
     pub state: String,
 }
+
+/// Capabilities of a registered external site
+#[derive(Serialize, ApiComponent, JsonSchema)]
+pub struct ExternalService {
+    pub uri: String,
+    pub icon_source: Option<String>,
+    /// Valid search parameters. `None` if the site cannot be searched
+    pub search: Option<ValidSearches>,
+    pub metadata: bool,
+    pub reader: bool,
+}
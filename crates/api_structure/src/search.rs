@@ -159,7 +159,7 @@ pub enum ValidSearches {
     },
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ApiComponent, JsonSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ApiComponent, JsonSchema)]
 pub struct SearchRequest {
     pub order: String,
     pub desc: bool,
@@ -174,7 +174,12 @@ pub enum Order {
     Created,
     Updated,
     LastRead,
+    /// Weighted reads, list additions & page views
     Popularity,
+    /// Popularity of the last 7 days, recent activity counts more
+    Trending,
+    /// How well titles, character & tag names and the description match the full text items of the query
+    Relevance,
     Random,
     Status,
     ChapterCount,
@@ -190,6 +195,8 @@ impl TryFrom<String> for Order {
             "updated" => Self::Updated,
             "last_read" => Self::LastRead,
             "popularity" => Self::Popularity,
+            "trending" => Self::Trending,
+            "relevance" => Self::Relevance,
             "random" => Self::Random,
             "status" => Self::Status,
             "chapter_count" => Self::ChapterCount,
@@ -213,6 +220,8 @@ impl Display for Order {
                 Order::Updated => "updated",
                 Order::LastRead => "last_read",
                 Order::Popularity => "popularity",
+                Order::Trending => "trending",
+                Order::Relevance => "relevance",
                 Order::Random => "random",
                 Order::Status => "status",
                 Order::ChapterCount => "chapter_count",
@@ -222,7 +231,7 @@ impl Display for Order {
 }
 
 /// can contain item or array
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ApiComponent, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, ApiComponent, JsonSchema)]
 #[serde(untagged)]
 pub enum ItemOrArray {
     Item(Item),
@@ -243,7 +252,7 @@ impl Display for ItemOrArray {
 }
 
 /// array joined with and or or
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ApiComponent, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, ApiComponent, JsonSchema)]
 pub struct Array {
     pub or: bool,
     pub not: bool,
@@ -270,7 +279,7 @@ impl Display for Array {
 }
 
 /// item include or exclude
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ApiComponent, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, ApiComponent, JsonSchema)]
 pub struct Item {
     pub not: bool,
     pub or_post: Option<bool>,
@@ -311,7 +320,7 @@ impl Item {
 }
 
 /// field and value
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ApiComponent, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, ApiComponent, JsonSchema)]
 pub struct ItemData {
     pub name: String,
     pub value: ItemValue,
@@ -359,7 +368,7 @@ impl ItemKind {
 }
 
 /// enum with different values
-#[derive(Serialize, Deserialize, Debug, ApiComponent, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, ApiComponent, JsonSchema)]
 pub enum ItemValue {
     None,
     Bool(bool),
@@ -554,6 +563,81 @@ impl Field {
     }
 }
 
+/// Fields which are understood by the manga search
+pub fn manga_fields() -> Vec<Field> {
+    let none: Vec<&str> = vec![];
+    vec![
+        Field::new("title", none.clone(), ItemKind::String),
+        Field::new("description", none.clone(), ItemKind::String),
+        Field::new("kind", vec!["k"], ItemKind::String),
+        Field::new("tag", vec!["t"], ItemKind::String),
+        Field::new("male", vec!["m"], ItemKind::String),
+        Field::new("female", vec!["f"], ItemKind::String),
+        Field::new("both", vec!["b"], ItemKind::String),
+        Field::new("male2female", vec!["mf"], ItemKind::String),
+        Field::new("female2male", vec!["fm"], ItemKind::String),
+        Field::new("none", vec!["n"], ItemKind::String),
+        Field::new("unknown", vec!["u"], ItemKind::String),
+        Field::new("status", vec!["s"], ItemKind::Int),
+        Field::new("uploader", none.clone(), ItemKind::String),
+        Field::new("artist", none.clone(), ItemKind::String),
+        Field::new("author", vec!["a"], ItemKind::String),
+        Field::new("publisher", vec!["p"], ItemKind::String),
+        Field::new("chapters", vec!["c"], ItemKind::CmpInt),
+        Field::new("list", vec!["l"], ItemKind::String),
+        Field::new("saved", none.clone(), ItemKind::String),
+        Field::new("next-available", none, ItemKind::None),
+    ]
+}
+
+/// Saves the search under the name. Replaces the search if the name is already used
+#[derive(Deserialize, Serialize, Debug, ApiComponent, JsonSchema)]
+pub struct SaveSearchRequest {
+    pub name: String,
+    pub search: SearchRequest,
+}
+
+#[derive(Deserialize, Serialize, Debug, ApiComponent, JsonSchema)]
+pub struct DeleteSavedSearchRequest {
+    pub name: String,
+}
+
+/// Results of a saved search with the order & page size it was saved with
+#[derive(Deserialize, Serialize, Debug, ApiComponent, JsonSchema)]
+pub struct SavedSearchPageRequest {
+    pub name: String,
+    pub page: u32,
+}
+
+#[derive(Deserialize, Serialize, ApiComponent, JsonSchema)]
+pub struct SuggestRequest {
+    /// Query as typed by the user
+    pub query: String,
+    /// Char offset of the cursor
+    pub cursor: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ApiComponent, JsonSchema)]
+pub enum SuggestionKind {
+    Field,
+    Tag,
+    Author,
+    List,
+    Kind,
+    SavedSearch,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ApiComponent, JsonSchema)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    /// Text which replaces the query from `start` to `end`. Quoted if needed
+    pub text: String,
+    /// Char offset
+    pub start: u32,
+    /// Char offset
+    pub end: u32,
+}
+
 fn parse<T: FromStr>(s: &str) -> Result<(bool, bool, T), String> {
     let (str, b, s) = if let Some(v) = s.strip_prefix('>') {
         (v, true, false)
@@ -583,6 +667,9 @@ pub struct SearchResponse {
     pub status: Status,
     pub ext: String,
     pub number: u32,
+    /// Matching title with `<mark>` around the matched words, only set when the query contains a title match
+    #[serde(default)]
+    pub highlight: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, ApiComponent, JsonSchema)]
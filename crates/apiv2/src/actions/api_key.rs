@@ -0,0 +1,168 @@
+use std::{collections::HashSet, str::FromStr as _, sync::Arc};
+
+use api_structure::{
+    v1::{self, Claim, CreateApiKeyRequest, CreatedApiKey, Role},
+    Permission,
+};
+use chrono::{DateTime, Utc};
+use db::{api_key::ApiKeyDBService, error::DbError};
+use sha2::{Digest as _, Sha256};
+
+use crate::error::{ApiError, ApiResult};
+
+/// Every key starts with this, so the validator can tell keys & jwts apart
+pub const API_KEY_PREFIX: &str = "mr_";
+
+pub struct ApiKeyActions {
+    pub(crate) keys: Arc<ApiKeyDBService>,
+}
+
+fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+fn parse_permissions(permissions: &[String]) -> ApiResult<HashSet<Permission>> {
+    permissions
+        .iter()
+        .map(|v| {
+            Permission::from_str(v)
+                .map_err(|_| ApiError::invalid_input(&format!("unknown permission {v}")))
+        })
+        .collect()
+}
+
+/// Lowest role up to `owner` which has all `permissions`.
+/// Role based checks like visibility should not grant a key more than its permissions
+fn scoped_role(owner: Role, permissions: &[Permission]) -> Role {
+    (0..=owner as i32)
+        .filter_map(Role::from_i32)
+        .find(|role| {
+            let allowed = role.get_permissions();
+            permissions.iter().all(|v| allowed.contains(v))
+        })
+        .unwrap_or(owner)
+}
+
+impl ApiKeyActions {
+    /// Returns the key. It cannot be looked up later
+    pub async fn create(
+        &self,
+        claim: &Claim,
+        data: CreateApiKeyRequest,
+    ) -> ApiResult<CreatedApiKey> {
+        if claim.api_key.is_some() {
+            return Err(ApiError::invalid_input("api keys cannot manage api keys"));
+        }
+        let name = data.name.trim();
+        if name.is_empty() || name.chars().count() > 64 {
+            return Err(ApiError::invalid_input(
+                "name has to be between 1 and 64 characters",
+            ));
+        }
+        let permissions = parse_permissions(&data.permissions)?;
+        if permissions.is_empty() {
+            return Err(ApiError::invalid_input(
+                "at least one permission is required",
+            ));
+        }
+        let allowed = claim.role.get_permissions();
+        if let Some(v) = permissions.iter().find(|v| !allowed.contains(v)) {
+            return Err(ApiError::invalid_input(&format!(
+                "role {} does not have the permission {}",
+                claim.role,
+                v.as_str()
+            )));
+        }
+        let expires = match data.expires {
+            Some(ms) => {
+                let expires = i64::try_from(ms)
+                    .ok()
+                    .and_then(DateTime::<Utc>::from_timestamp_millis)
+                    .ok_or_else(|| ApiError::invalid_input("invalid expiry timestamp"))?;
+                if expires <= Utc::now() {
+                    return Err(ApiError::invalid_input("expiry has to be in the future"));
+                }
+                Some(expires.into())
+            }
+            None => None,
+        };
+
+        let key = format!("{API_KEY_PREFIX}{}", helper::random_string(40));
+        let id = self
+            .keys
+            .create(
+                &claim.id,
+                name.to_owned(),
+                hash_key(&key),
+                key.chars().take(API_KEY_PREFIX.len() + 4).collect(),
+                permissions.iter().map(|v| v.as_str().to_owned()).collect(),
+                expires,
+            )
+            .await?;
+        Ok(CreatedApiKey { id, key })
+    }
+
+    pub async fn list(&self, claim: &Claim) -> ApiResult<Vec<v1::ApiKey>> {
+        Ok(self
+            .keys
+            .list(&claim.id)
+            .await?
+            .into_iter()
+            .map(|v| v1::ApiKey {
+                id: v.id.id().to_string(),
+                name: v.data.name,
+                prefix: v.data.prefix,
+                permissions: v.data.permissions,
+                expires: v
+                    .data
+                    .expires
+                    .map(|v| v.into_inner().0.timestamp_millis() as u64),
+                created: v.data.created.into_inner().0.timestamp_millis() as u64,
+            })
+            .collect())
+    }
+
+    pub async fn revoke(&self, claim: &Claim, id: &str) -> ApiResult<()> {
+        if claim.api_key.is_some() {
+            return Err(ApiError::invalid_input("api keys cannot manage api keys"));
+        }
+        Ok(self.keys.revoke(&claim.id, id).await?)
+    }
+
+    /// Returns a claim for the owner & the permissions of the key.
+    /// Permissions the owner lost since the key was created are dropped
+    pub async fn authenticate(&self, key: &str) -> ApiResult<(Claim, Vec<Permission>)> {
+        let owner = self
+            .keys
+            .get_by_hash(&hash_key(key))
+            .await
+            .map_err(|e| match e {
+                DbError::NotFound => ApiError::invalid_input("invalid api key"),
+                e => e.into(),
+            })?;
+        if owner
+            .expires
+            .is_some_and(|v| v.into_inner().0 <= Utc::now())
+        {
+            return Err(ApiError::ExpiredToken);
+        }
+        if owner.disabled.unwrap_or_default() {
+            return Err(ApiError::invalid_input("invalid api key"));
+        }
+        let role = owner
+            .role
+            .and_then(|v| Role::try_from(v).ok())
+            .ok_or_else(|| ApiError::invalid_input("invalid api key"))?;
+        let allowed = role.get_permissions();
+        let permissions: Vec<_> = parse_permissions(&owner.permissions)?
+            .into_iter()
+            .filter(|v| allowed.contains(v))
+            .collect();
+
+        // only lives for this request
+        let mut claim =
+            Claim::new_access(owner.user.id().to_string(), scoped_role(role, &permissions));
+        claim.api_key = Some(owner.id.id().to_string());
+        Ok((claim, permissions))
+    }
+}
@@ -1,19 +1,21 @@
 use std::{sync::Arc, time::Duration};
 
 use crate::{
-    actions::crytpo::CryptoService,
+    actions::{crytpo::CryptoService, mail::Mailer},
     error::{ApiError, ApiResult},
 };
 
 use api_structure::{
     req::LoginRequest,
-    v1::{ActivationTokenKind, Claim, Gender, JwTsResponse, JwtType, ResetPasswordRequest, Role},
+    v1::{
+        self, ActivationTokenKind, Claim, Gender, JwTsResponse, JwtType, ResetPasswordRequest, Role,
+    },
     REFRESH_SECS,
 };
 use chrono::{DateTime, Utc};
 use db::{
     auth::{is_token_valid, AuthTokenDBService, RecordData},
-    error::DbResult,
+    session::{Rotation, SessionDBService},
     user::{UserDBService, UserRolePassword},
 };
 use storage::{FileBuilderExt as _, FileId, StorageSystem};
@@ -23,6 +25,15 @@ pub struct AuthAction {
     pub(crate) crypto: Arc<CryptoService>,
     pub(crate) token: Arc<AuthTokenDBService>,
     pub(crate) fs: Arc<StorageSystem>,
+    pub(crate) mailer: Arc<Mailer>,
+    pub(crate) sessions: Arc<SessionDBService>,
+}
+
+/// Device & address of a client, stored with its session
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub device: String,
+    pub ip: Option<String>,
 }
 
 impl AuthAction {
@@ -37,6 +48,23 @@ impl AuthAction {
         Role::try_from(value).map_err(|_| ApiError::write_error("invalid role value in database"))
     }
 
+    /// Creates a code which verifies the user & mails it
+    async fn send_verification(&self, uid: &str, email: &str, name: &str) -> ApiResult<()> {
+        self.mailer.take_slot(email)?;
+        let code = self
+            .token
+            .create(
+                Some(uid.to_owned()),
+                ActivationTokenKind {
+                    single: true,
+                    kind: Role::User,
+                },
+                Some(self.mailer.code_expiry()),
+            )
+            .await?;
+        self.mailer.send_verification(email, name, &code).await
+    }
+
     /// creates a user if possible
     pub async fn register(
         &self,
@@ -46,6 +74,7 @@ impl AuthAction {
         gender: Gender,
         birthdate: DateTime<Utc>,
         icon: Option<FileId>,
+        client: &ClientInfo,
     ) -> ApiResult<JwTsResponse> {
         Self::validate_non_empty("email", email)?;
         Self::validate_non_empty("name", &name)?;
@@ -63,7 +92,7 @@ impl AuthAction {
         let user = self
             .users
             .new_user(
-                name,
+                name.clone(),
                 email.to_lowercase(),
                 pw_hash,
                 cover_builder.ext()?,
@@ -71,24 +100,65 @@ impl AuthAction {
                 gender as u32,
             )
             .await?;
-        cover_builder.build(&user.id.id().to_string()).await?;
-        self.new_jwt(&user.id.id().to_string(), Role::NotVerified)
+        let uid = user.id.id().to_string();
+        cover_builder.build(&uid).await?;
+        if self.mailer.enabled() {
+            if let Err(e) = self.send_verification(&uid, email, &name).await {
+                log::error!("Failed to send verification mail to {uid}: {e}");
+            }
+        }
+        self.start_session(&uid, Role::NotVerified, client).await
+    }
+
+    /// Mails a new verification code to an unverified user
+    pub async fn resend_verification(&self, claim: &Claim) -> ApiResult<()> {
+        if !self.mailer.enabled() {
+            return Err(ApiError::invalid_input("mails are disabled on this server"));
+        }
+        let user = self.users.info(&claim.id).await?;
+        if Self::role_from_db(user.data.role)? != Role::NotVerified {
+            return Err(ApiError::invalid_input("account is already verified"));
+        }
+        let name = user.data.names.first().cloned().unwrap_or_default();
+        self.send_verification(&claim.id, &user.data.email, &name)
+            .await
     }
 
     /// helper to generate jwt
-    fn new_jwt(&self, user_id: &str, role: Role) -> ApiResult<JwTsResponse> {
+    fn new_jwt(
+        &self,
+        user_id: &str,
+        role: Role,
+        session: &str,
+        jti: String,
+    ) -> ApiResult<JwTsResponse> {
+        let mut access = Claim::new_access(user_id.to_owned(), role);
+        access.session = Some(session.to_owned());
+        let mut refresh = Claim::new_refresh(user_id.to_owned(), role);
+        refresh.session = Some(session.to_owned());
+        refresh.jti = Some(jti);
         Ok(JwTsResponse {
-            access_token: self
-                .crypto
-                .encode_claim(&Claim::new_access(user_id.to_owned(), role))?,
-            refresh_token: self
-                .crypto
-                .encode_claim(&Claim::new_refresh(user_id.to_owned(), role))?,
+            access_token: self.crypto.encode_claim(&access)?,
+            refresh_token: self.crypto.encode_claim(&refresh)?,
         })
     }
 
+    /// Creates a session for a new login
+    async fn start_session(
+        &self,
+        user_id: &str,
+        role: Role,
+        client: &ClientInfo,
+    ) -> ApiResult<JwTsResponse> {
+        let (session, jti) = self
+            .sessions
+            .create(user_id, client.device.clone(), client.ip.clone())
+            .await?;
+        self.new_jwt(user_id, role, &session, jti)
+    }
+
     /// login action if credentials are valid
-    pub async fn login(&self, data: LoginRequest) -> ApiResult<JwTsResponse> {
+    pub async fn login(&self, data: LoginRequest, client: &ClientInfo) -> ApiResult<JwTsResponse> {
         let password = data.password();
         Self::validate_non_empty("password", &password)?;
         let user = match &data {
@@ -105,41 +175,122 @@ impl AuthAction {
         if !valid {
             return Err(ApiError::PasswordIncorrect);
         }
-        self.new_jwt(
+        self.start_session(
             &user.id.id().to_string(),
             Self::role_from_db(user.data.role)?,
+            client,
         )
+        .await
+    }
+
+    /// Revokes every session of the user
+    pub async fn logout(&self, claim: &Claim) -> ApiResult<()> {
+        self.users.logout(&claim.id).await?;
+        self.revoke_all(&claim.id).await
     }
 
-    pub async fn logout(&self, claim: &Claim) -> DbResult<()> {
-        self.users.logout(&claim.id).await
+    async fn revoke_all(&self, user_id: &str) -> ApiResult<()> {
+        let revoked = self.sessions.revoke_all(user_id).await?;
+        self.crypto.revoke_sessions(revoked);
+        Ok(())
     }
 
-    /// generates refresh token if possible
-    pub async fn refresh(&self, refresh_token: &str) -> ApiResult<JwTsResponse> {
+    /// Replaces the refresh token with a new one. Reusing a replaced refresh token revokes its session
+    pub async fn refresh(
+        &self,
+        refresh_token: &str,
+        client: &ClientInfo,
+    ) -> ApiResult<JwTsResponse> {
         Self::validate_non_empty("refresh_token", refresh_token)?;
         let claim = self.crypto.get_claim(&refresh_token)?;
         if !matches!(claim.r#type, JwtType::RefreshToken) {
             return Err(ApiError::invalid_input("refresh token required"));
         }
+        // tokens from before sessions existed need a new login
+        let (Some(session), Some(jti)) = (&claim.session, &claim.jti) else {
+            return Err(ApiError::ExpiredToken);
+        };
         let (role, generated) = self.users.get_role_and_generated(claim.id.as_str()).await?;
         if generated > claim.exp as u128 - Duration::from_secs(REFRESH_SECS).as_millis() {
             return Err(ApiError::ExpiredToken);
         }
-        self.new_jwt(&claim.id, role)
+        match self
+            .sessions
+            .rotate(session, jti, client.ip.clone())
+            .await?
+        {
+            Rotation::Rotated(jti) => self.new_jwt(&claim.id, role, session, jti),
+            Rotation::Reused => {
+                log::warn!("Refresh token of session {session} was reused, revoked the session");
+                self.crypto.revoke_sessions([session.clone()]);
+                Err(ApiError::ExpiredToken)
+            }
+            Rotation::Revoked => Err(ApiError::ExpiredToken),
+        }
+    }
+
+    /// Sessions of the user which can still be refreshed. `current` is the session of the requester
+    pub async fn sessions(
+        &self,
+        user_id: &str,
+        current: Option<&str>,
+    ) -> ApiResult<Vec<v1::Session>> {
+        Self::validate_non_empty("user_id", user_id)?;
+        Ok(self
+            .sessions
+            .list(user_id)
+            .await?
+            .into_iter()
+            .map(|v| {
+                let id = v.id.id().to_string();
+                v1::Session {
+                    current: current == Some(id.as_str()),
+                    id,
+                    device: v.data.device,
+                    ip: v.data.ip,
+                    last_used: v.data.last_used.into_inner().0.timestamp_millis() as u64,
+                    created: v.data.created.into_inner().0.timestamp_millis() as u64,
+                }
+            })
+            .collect())
     }
 
-    /// Creates a new token with Role::NotVerified
-    pub async fn request_reset_password(&self, uid: String) -> DbResult<()> {
-        self.token
+    /// Revokes a session of `user_id` or of anyone if None
+    pub async fn revoke_session(&self, id: &str, user_id: Option<&str>) -> ApiResult<()> {
+        Self::validate_non_empty("id", id)?;
+        self.sessions.revoke(user_id, id).await?;
+        self.crypto.revoke_sessions([id.to_owned()]);
+        Ok(())
+    }
+
+    /// Creates a new token with Role::NotVerified & mails it to the user if mails are enabled
+    pub async fn request_reset_password(&self, uid: String) -> ApiResult<()> {
+        let user = match self.mailer.enabled() {
+            true => {
+                let user = self.users.info(&uid).await?;
+                self.mailer.take_slot(&user.data.email)?;
+                Some(user)
+            }
+            false => None,
+        };
+        let code = self
+            .token
             .create(
                 Some(uid),
                 ActivationTokenKind {
                     single: true,
                     kind: Role::NotVerified,
                 },
+                user.as_ref().map(|_| self.mailer.code_expiry()),
             )
-            .await
+            .await?;
+        if let Some(user) = user {
+            let name = user.data.names.first().cloned().unwrap_or_default();
+            self.mailer
+                .send_reset_password(&user.data.email, &name, &code)
+                .await?;
+        }
+        Ok(())
     }
 
     pub async fn get_user_id(
@@ -154,7 +305,12 @@ impl AuthAction {
     }
 
     /// resets password with Role::NotVerified token
-    pub async fn reset_password(&self, data: ResetPasswordRequest) -> ApiResult<JwTsResponse> {
+    /// Other sessions are revoked, because whoever knew the old password could have used them
+    pub async fn reset_password(
+        &self,
+        data: ResetPasswordRequest,
+        client: &ClientInfo,
+    ) -> ApiResult<JwTsResponse> {
         Self::validate_non_empty("ident", &data.ident)?;
         Self::validate_non_empty("key", &data.key)?;
         Self::validate_non_empty("password", &data.password)?;
@@ -176,12 +332,19 @@ impl AuthAction {
         if token.data.get_kind().single {
             self.token.delete_(token).await?;
         }
+        let uid = user.id.id().to_string();
+        self.revoke_all(&uid).await?;
         let role = Self::role_from_db(user.data.role)?;
-        self.new_jwt(&user.id.id().to_string(), role)
+        self.start_session(&uid, role, client).await
     }
 
-    /// uses a token and set user role
-    pub async fn verify(&self, key: &str, claim: &Claim) -> ApiResult<JwTsResponse> {
+    /// uses a token and set user role. The session of the claim is replaced, so tokens with the old role stop working
+    pub async fn verify(
+        &self,
+        key: &str,
+        claim: &Claim,
+        client: &ClientInfo,
+    ) -> ApiResult<JwTsResponse> {
         Self::validate_non_empty("key", key)?;
         let find = self.token.find(&key).await?;
         is_token_valid(&find, &claim.id.to_string())?;
@@ -193,6 +356,36 @@ impl AuthAction {
         if kind.single {
             self.token.delete_(find).await?;
         }
-        self.new_jwt(&claim.id, kind.kind)
+        if claim.role == Role::NotVerified && kind.kind != Role::NotVerified {
+            self.welcome(&claim.id).await;
+        }
+        if let Some(session) = &claim.session {
+            match self.revoke_session(session, Some(&claim.id)).await {
+                Ok(()) | Err(ApiError::NotFoundInDB) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.start_session(&claim.id, kind.kind, client).await
+    }
+
+    /// Failures are only logged, the account works without the mail
+    async fn welcome(&self, uid: &str) {
+        if !self.mailer.enabled() {
+            return;
+        }
+        let user = match self.users.info(uid).await {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("Failed to load user {uid} for welcome mail: {e}");
+                return;
+            }
+        };
+        if self.mailer.take_slot(&user.data.email).is_err() {
+            return;
+        }
+        let name = user.data.names.first().cloned().unwrap_or_default();
+        if let Err(e) = self.mailer.send_welcome(&user.data.email, &name).await {
+            log::error!("Failed to send welcome mail to {uid}: {e}");
+        }
     }
 }
@@ -1,10 +1,7 @@
 use std::{collections::HashMap, sync::Mutex};
 
 use actix_web::{
-    dev::ServiceRequest,
-    rt::task::spawn_blocking,
-    web::{Data, ReqData},
-    Error, HttpMessage as _,
+    dev::ServiceRequest, rt::task::spawn_blocking, web::Data, Error, HttpMessage as _,
 };
 use actix_web_grants::authorities::AttachAuthorities;
 
@@ -12,18 +9,22 @@ use actix_web_httpauth::extractors::bearer::BearerAuth;
 
 use api_structure::{
     now,
-    v1::{Claim, JwtType, Role, Visibility},
+    v1::{Claim, JwtType},
 };
 use bcrypt::DEFAULT_COST;
-use db::manga::Manga;
 use jsonwebtoken::{decode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 
-use crate::error::{ApiError, ApiResult};
+use crate::{
+    actions::api_key::{ApiKeyActions, API_KEY_PREFIX},
+    error::{ApiError, ApiResult},
+};
 
 #[derive(Debug)]
 pub struct CryptoService {
     pub secret: Vec<u8>,
     pub claims: Mutex<HashMap<String, Claim>>,
+    /// Map<session id, revoked at in millis>. Access tokens of these sessions are rejected until they expire
+    pub revoked: Mutex<HashMap<String, u64>>,
 }
 
 impl CryptoService {
@@ -31,14 +32,54 @@ impl CryptoService {
         Self {
             secret,
             claims: Mutex::new(HashMap::new()),
+            revoked: Mutex::new(HashMap::new()),
         }
     }
+
+    /// Rejects the access tokens of the sessions from now on
+    pub fn revoke_sessions(&self, ids: impl IntoIterator<Item = String>) {
+        let now = now().as_millis() as u64;
+        let mut revoked = self.revoked.lock().unwrap_or_else(|e| e.into_inner());
+        // access tokens of older revocations expired already
+        revoked.retain(|_, at| *at + ACCESS_MILLIS > now);
+        revoked.extend(ids.into_iter().map(|id| (id, now)));
+    }
+
+    pub fn is_revoked(&self, claim: &Claim) -> bool {
+        let Some(session) = &claim.session else {
+            return false;
+        };
+        self.revoked
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(session)
+    }
 }
 
+/// Lifetime of access tokens
+const ACCESS_MILLIS: u64 = 2 * 60 * 1000;
+
+/// Accepts jwts & api keys
 pub async fn validator(
     req: ServiceRequest,
     cred: BearerAuth,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+    if cred.token().starts_with(API_KEY_PREFIX) {
+        let Some(keys) = req.app_data::<Data<ApiKeyActions>>() else {
+            return Err((
+                ApiError::write_error("ApiKeyActions is missing").into(),
+                req,
+            ));
+        };
+        return match keys.authenticate(cred.token()).await {
+            Ok((claim, permissions)) => {
+                req.attach(permissions);
+                req.extensions_mut().insert(claim);
+                Ok(req)
+            }
+            Err(e) => Err((e.into(), req)),
+        };
+    }
     let Some(secret) = req.app_data::<Data<CryptoService>>() else {
         return Err((
             ApiError::write_error("CryptoService is missing").into(),
@@ -46,6 +87,7 @@ pub async fn validator(
         ));
     };
     match secret.get_claim(cred.token()) {
+        Ok(v) if secret.is_revoked(&v) => Err((ApiError::ExpiredToken.into(), req)),
         Ok(v) => {
             {
                 if matches!(v.r#type, JwtType::AccessToken) {
@@ -61,23 +103,6 @@ pub async fn validator(
 }
 
 impl CryptoService {
-    /// Visibility guard
-    pub fn can_access(&self, user: ReqData<Claim>, manga: Manga) -> bool {
-        let Ok(visibility) = Visibility::try_from(manga.visibility) else {
-            return false;
-        };
-        match visibility {
-            Visibility::Visible => true,
-            Visibility::Hidden => {
-                matches!(user.role, Role::Admin | Role::CoAdmin | Role::Moderator)
-                    || (user.role == Role::Author && manga.uploader.id().to_string() == user.id)
-            }
-            Visibility::AdminReview => {
-                matches!(user.role, Role::Admin | Role::CoAdmin | Role::Moderator)
-            }
-        }
-    }
-
     /// creates a password hash
     pub async fn hash_password(&self, password: &str) -> ApiResult<String> {
         let password = password.to_string();
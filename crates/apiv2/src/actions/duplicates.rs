@@ -0,0 +1,502 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::Arc,
+};
+
+use api_structure::v1::{
+    DuplicatePair, DuplicateVersion, DuplicatesRequest, InferiorVersion, SharedPage,
+};
+use db::page::{HashedVersion, PageDBService, VersionPages};
+use storage::decode_hash;
+use tokio::sync::Mutex;
+
+use crate::error::{ApiError, ApiResult};
+
+use super::quality::{best_score, score_versions};
+
+pub struct DuplicateActions {
+    pub(crate) pages: Arc<PageDBService>,
+    pub(crate) index: Mutex<Option<DuplicateIndex>>,
+}
+
+#[derive(Debug, Default)]
+pub struct DuplicateReport {
+    /// Versions of the same chapter which are visually identical
+    pub identical_versions: Vec<DuplicatePair>,
+    /// The same chapter uploaded with different chapter numbers
+    pub repeated_chapters: Vec<DuplicatePair>,
+    /// Credit & ad pages. They are ignored when comparing chapters
+    pub shared_pages: Vec<SharedPage>,
+}
+
+struct DuplicateOptions {
+    max_distance: u32,
+    min_similarity: f64,
+    min_shared_chapters: usize,
+    page: usize,
+    limit: usize,
+}
+
+impl DuplicateOptions {
+    fn paginate<T>(&self, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.page.saturating_sub(1) * self.limit)
+            .take(self.limit)
+            .collect()
+    }
+}
+
+impl TryFrom<&DuplicatesRequest> for DuplicateOptions {
+    type Error = ApiError;
+
+    fn try_from(value: &DuplicatesRequest) -> Result<Self, Self::Error> {
+        let options = Self {
+            max_distance: value.max_distance.unwrap_or(4),
+            min_similarity: value.min_similarity.unwrap_or(0.9),
+            min_shared_chapters: value.min_shared_chapters.unwrap_or(5) as usize,
+            page: value.page.unwrap_or(1) as usize,
+            limit: value.limit.unwrap_or(50) as usize,
+        };
+        if options.max_distance > 64 {
+            return Err(ApiError::invalid_input("max_distance has to be <= 64"));
+        }
+        if !(options.min_similarity > 0.0 && options.min_similarity <= 1.0) {
+            return Err(ApiError::invalid_input(
+                "min_similarity has to be between 0 & 1",
+            ));
+        }
+        if options.min_shared_chapters < 2 {
+            return Err(ApiError::invalid_input(
+                "min_shared_chapters has to be >= 2",
+            ));
+        }
+        if !(1..=500).contains(&options.limit) {
+            return Err(ApiError::invalid_input("limit has to be between 1 & 500"));
+        }
+        Ok(options)
+    }
+}
+
+pub(crate) fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    let differing: u32 = a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum();
+    differing + 8 * a.len().abs_diff(b.len()) as u32
+}
+
+struct BkNode {
+    hash: Vec<u8>,
+    /// Items with exactly this hash
+    items: Vec<usize>,
+    /// Children by their distance to this node
+    children: HashMap<u32, usize>,
+}
+
+/// BK-tree with the hamming distance as metric, so near hashes are found without comparing all of them
+#[derive(Default)]
+struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+impl BkTree {
+    fn insert(&mut self, hash: Vec<u8>, item: usize) {
+        let mut current = 0;
+        while let Some(node) = self.nodes.get(current) {
+            let distance = hamming(&node.hash, &hash);
+            let next = node.children.get(&distance).copied();
+            if distance == 0 {
+                self.nodes[current].items.push(item);
+                return;
+            }
+            match next {
+                Some(next) => current = next,
+                None => {
+                    let index = self.nodes.len();
+                    self.nodes[current].children.insert(distance, index);
+                    break;
+                }
+            }
+        }
+        self.nodes.push(BkNode {
+            hash,
+            items: vec![item],
+            children: HashMap::new(),
+        });
+    }
+
+    /// Items whose hash differs in at most `max_distance` bits
+    fn find(&self, hash: &[u8], max_distance: u32) -> Vec<usize> {
+        let mut out = vec![];
+        let mut stack = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let distance = hamming(&node.hash, hash);
+            if distance <= max_distance {
+                out.extend_from_slice(&node.items);
+            }
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| d.abs_diff(distance) <= max_distance)
+                    .map(|(_, child)| *child),
+            );
+        }
+        out
+    }
+}
+
+struct Clusters {
+    parent: Vec<usize>,
+}
+
+impl Clusters {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn root(&mut self, mut item: usize) -> usize {
+        while self.parent[item] != item {
+            self.parent[item] = self.parent[self.parent[item]];
+            item = self.parent[item];
+        }
+        item
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+/// Page of [`DuplicateIndex`]
+struct IndexedPage {
+    chapter_version: String,
+    page: String,
+    hash: String,
+    bits: Vec<u8>,
+}
+
+/// Hashed pages of the library. Built on the first report & refreshed with the pages which changed
+/// since, so the library is only loaded once
+#[derive(Default)]
+pub(crate) struct DuplicateIndex {
+    /// By chapter version
+    versions: HashMap<String, HashedVersion>,
+    /// Pages by the items of the tree. Removed pages are `None`, the tree can't remove items
+    pages: Vec<Option<IndexedPage>>,
+    /// Item of every page
+    items: HashMap<String, usize>,
+    tree: BkTree,
+}
+
+impl DuplicateIndex {
+    fn insert(&mut self, version: HashedVersion) {
+        self.remove(&version.chapter_version);
+        for (page, hash) in &version.pages {
+            let Some(bits) = decode_hash(hash) else {
+                continue;
+            };
+            let item = self.pages.len();
+            self.tree.insert(bits.clone(), item);
+            self.items.insert(page.clone(), item);
+            self.pages.push(Some(IndexedPage {
+                chapter_version: version.chapter_version.clone(),
+                page: page.clone(),
+                hash: hash.clone(),
+                bits,
+            }));
+        }
+        self.versions
+            .insert(version.chapter_version.clone(), version);
+    }
+
+    fn remove(&mut self, chapter_version: &str) {
+        let Some(version) = self.versions.remove(chapter_version) else {
+            return;
+        };
+        for (page, _) in &version.pages {
+            if let Some(item) = self.items.remove(page) {
+                self.pages[item] = None;
+            }
+        }
+    }
+
+    fn version_of(&self, page: &str) -> Option<String> {
+        let item = self.items.get(page)?;
+        self.pages[*item]
+            .as_ref()
+            .map(|v| v.chapter_version.clone())
+    }
+
+    /// Builds the tree again once most of its items are removed
+    fn compact(&mut self) {
+        if self.items.len() * 2 >= self.pages.len() {
+            return;
+        }
+        let versions = mem::take(&mut self.versions);
+        *self = Self::default();
+        for version in versions.into_values() {
+            self.insert(version);
+        }
+    }
+}
+
+fn describe(version: &HashedVersion) -> DuplicateVersion {
+    DuplicateVersion {
+        manga_id: version.manga.clone(),
+        chapter_id: version.chapter.clone(),
+        chapter: version.episode,
+        version_id: version.version.clone(),
+        chapter_version_id: version.chapter_version.clone(),
+    }
+}
+
+fn find_duplicates(index: &DuplicateIndex, options: &DuplicateOptions) -> DuplicateReport {
+    let mut versions: Vec<&HashedVersion> = index.versions.values().collect();
+    versions.sort_by(|a, b| a.chapter_version.cmp(&b.chapter_version));
+    let version_of: HashMap<&str, usize> = versions
+        .iter()
+        .enumerate()
+        .map(|(index, v)| (v.chapter_version.as_str(), index))
+        .collect();
+
+    // every cluster is the pages near its first page. Joining all near pages would chain
+    // pages which differ a lot into one cluster
+    let mut clustered = HashSet::new();
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (item, page) in index.pages.iter().enumerate() {
+        let Some(page) = page else {
+            continue;
+        };
+        if clustered.contains(&item) {
+            continue;
+        }
+        let mut near = index.tree.find(&page.bits, options.max_distance);
+        near.retain(|v| index.pages[*v].is_some() && clustered.insert(*v));
+        near.sort_unstable();
+        members.insert(item, near);
+    }
+    let page = |item: &usize| {
+        index.pages[*item]
+            .as_ref()
+            .expect("clusters only contain indexed pages")
+    };
+    let version = |item: &usize| version_of[page(item).chapter_version.as_str()];
+
+    let mut report = DuplicateReport::default();
+    let mut shared = HashSet::new();
+    for (root, items) in &members {
+        let chapters: HashSet<&str> = items
+            .iter()
+            .map(|v| versions[version(v)].chapter.as_str())
+            .collect();
+        if chapters.len() >= options.min_shared_chapters {
+            shared.insert(*root);
+            report.shared_pages.push(SharedPage {
+                hash: page(root).hash.clone(),
+                page_ids: items.iter().map(|v| page(v).page.clone()).collect(),
+                chapters: chapters.len() as u32,
+            });
+        }
+    }
+    report
+        .shared_pages
+        .sort_by(|a, b| b.chapters.cmp(&a.chapters).then(a.hash.cmp(&b.hash)));
+
+    // distinct pages of every version & the versions which contain a page
+    let mut version_pages: Vec<HashSet<usize>> = vec![HashSet::new(); versions.len()];
+    for (root, items) in &members {
+        if shared.contains(root) {
+            continue;
+        }
+        for item in items {
+            version_pages[version(item)].insert(*root);
+        }
+    }
+    let mut page_versions: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (version, roots) in version_pages.iter().enumerate() {
+        for root in roots {
+            page_versions.entry(*root).or_default().push(version);
+        }
+    }
+    let mut common: HashMap<(usize, usize), usize> = HashMap::new();
+    for containing in page_versions.values() {
+        for (i, a) in containing.iter().enumerate() {
+            for b in &containing[i + 1..] {
+                *common.entry((*a.min(b), *a.max(b))).or_default() += 1;
+            }
+        }
+    }
+
+    for ((a, b), count) in common {
+        let similarity = count as f64 / version_pages[a].len().max(version_pages[b].len()) as f64;
+        if similarity < options.min_similarity {
+            continue;
+        }
+        let (first, second) = (versions[a], versions[b]);
+        let pair = DuplicatePair {
+            first: Some(describe(first)),
+            second: Some(describe(second)),
+            similarity,
+        };
+        if first.chapter == second.chapter {
+            report.identical_versions.push(pair);
+        } else if first.manga == second.manga {
+            report.repeated_chapters.push(pair);
+        }
+    }
+    for pairs in [
+        &mut report.identical_versions,
+        &mut report.repeated_chapters,
+    ] {
+        pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    }
+    report
+}
+
+impl DuplicateActions {
+    /// Loads the index on the first call, later calls only load the versions of changed pages
+    async fn refresh(&self, index: &mut Option<DuplicateIndex>) -> ApiResult<()> {
+        let Some(current) = index else {
+            // pages which change while the library is loaded are loaded again by the next refresh
+            self.pages.track_changes();
+            let mut built = DuplicateIndex::default();
+            for version in self.pages.hashed_versions().await? {
+                built.insert(version);
+            }
+            *index = Some(built);
+            return Ok(());
+        };
+        let changed = self.pages.take_changes();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        // versions of deleted pages are gone, the others are loaded again
+        let stale: HashSet<String> = changed
+            .iter()
+            .filter_map(|v| current.version_of(v))
+            .collect();
+        for chapter_version in stale {
+            current.remove(&chapter_version);
+        }
+        match self
+            .pages
+            .hashed_versions_of(changed.into_iter().collect())
+            .await
+        {
+            Ok(versions) => {
+                for version in versions {
+                    current.insert(version);
+                }
+                current.compact();
+                Ok(())
+            }
+            Err(e) => {
+                // the changes are lost, so the index is built again
+                *index = None;
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn duplicates(&self, options: &DuplicateOptions) -> ApiResult<DuplicateReport> {
+        let mut index = self.index.lock().await;
+        self.refresh(&mut index).await?;
+        let index = index.as_ref().expect("index was refreshed");
+        Ok(find_duplicates(index, options))
+    }
+
+    /// Compares the perceptual hashes of all pages in the library. Every list is paginated
+    pub async fn report(&self, request: &DuplicatesRequest) -> ApiResult<DuplicateReport> {
+        let options = DuplicateOptions::try_from(request)?;
+        let report = self.duplicates(&options).await?;
+        Ok(DuplicateReport {
+            identical_versions: options.paginate(report.identical_versions),
+            repeated_chapters: options.paginate(report.repeated_chapters),
+            shared_pages: options.paginate(report.shared_pages),
+        })
+    }
+
+    /// Versions which have a better scored, visually identical version in the same chapter.
+    /// They can be removed without losing anything
+    pub async fn inferior_versions(
+        &self,
+        request: &DuplicatesRequest,
+    ) -> ApiResult<Vec<InferiorVersion>> {
+        let options = DuplicateOptions::try_from(request)?;
+        let report = self.duplicates(&options).await?;
+        let mut versions: Vec<DuplicateVersion> = vec![];
+        let mut index_of: HashMap<String, usize> = HashMap::new();
+        let mut index = |v: &Option<DuplicateVersion>| {
+            let v = v.as_ref()?;
+            let next = versions.len();
+            let index = *index_of.entry(v.chapter_version_id.clone()).or_insert(next);
+            if index == next {
+                versions.push(v.clone());
+            }
+            Some(index)
+        };
+
+        // a group keeps its best version, even if not every member is a duplicate of every other
+        let mut pairs = vec![];
+        for pair in &report.identical_versions {
+            if let (Some(a), Some(b)) = (index(&pair.first), index(&pair.second)) {
+                pairs.push((a, b));
+            }
+        }
+        let mut clusters = Clusters::new(versions.len());
+        for (a, b) in pairs {
+            clusters.join(a, b);
+        }
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for item in 0..versions.len() {
+            groups.entry(clusters.root(item)).or_default().push(item);
+        }
+        let mut pages: HashMap<String, VersionPages> = self
+            .pages
+            .version_pages(
+                versions
+                    .iter()
+                    .map(|v| v.chapter_version_id.clone())
+                    .collect(),
+            )
+            .await?
+            .into_iter()
+            .map(|v| (v.id.id().to_string(), v))
+            .collect();
+
+        let mut out = vec![];
+        for members in groups.values() {
+            let group: Vec<VersionPages> = members
+                .iter()
+                .filter_map(|v| pages.remove(&versions[*v].chapter_version_id))
+                .collect();
+            let scores = score_versions(&group);
+            let Some((best, preferred_score)) = best_score(&scores).cloned() else {
+                continue;
+            };
+            let preferred = versions[index_of[best.as_str()]].clone();
+            for (id, score) in scores {
+                if id == best {
+                    continue;
+                }
+                out.push(InferiorVersion {
+                    version: Some(versions[index_of[id.as_str()]].clone()),
+                    preferred: Some(preferred.clone()),
+                    score,
+                    preferred_score,
+                });
+            }
+        }
+        out.sort_by(|a, b| (b.preferred_score - b.score).total_cmp(&(a.preferred_score - a.score)));
+        Ok(options.paginate(out))
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use api_structure::{
+    resp::external::ExternalService,
+    v1::{self, AddMangaRequest, Status, StringList, Tag, TagSex},
+};
+use manga_scraper::init::{Service, Services};
+use scraper_module::{
+    AttributesDef, ExternalMetadataResponse, ExternalSearchResponse, ScrapedData, SearchQuery,
+};
+use storage::{RegisterTempResult, StorageSystem};
+use tokio::io::AsyncWriteExt as _;
+
+use crate::error::{ApiError, ApiResult};
+
+/// Language key of alternative titles, because sites rarely say which language they are in
+const UNKNOWN_LANGUAGE: &str = "unknown";
+
+pub struct ExternalActions {
+    pub services: Arc<Services>,
+    pub fs: Arc<StorageSystem>,
+}
+
+impl ExternalActions {
+    fn service(&self, uri: &str) -> ApiResult<&Service> {
+        self.services
+            .get_by_uri(uri)
+            .ok_or_else(|| ApiError::invalid_input("unknown external site"))
+    }
+
+    /// All registered sites & what they can do
+    pub fn list(&self) -> Vec<ExternalService> {
+        self.services
+            .services
+            .iter()
+            .map(|service| ExternalService {
+                uri: service.uri.clone(),
+                icon_source: service.register.icon_source(),
+                search: service.searchers.as_ref().map(|v| v.query()),
+                metadata: service.metadata.is_some(),
+                reader: service.reader.is_some(),
+            })
+            .collect()
+    }
+
+    /// Returns the file extension & the bytes of the icon
+    pub fn icon(&self, uri: &str) -> ApiResult<(String, Vec<u8>)> {
+        Ok(self.service(uri)?.register.icon())
+    }
+
+    pub async fn search(&self, uri: &str, query: SearchQuery) -> ApiResult<ExternalSearchResponse> {
+        let searcher = self
+            .service(uri)?
+            .searchers
+            .clone()
+            .ok_or_else(|| ApiError::invalid_input("external site does not support search"))?;
+        if let Some(def) = AttributesDef::for_searches(&searcher.query()) {
+            if !query.validate(def) {
+                return Err(ApiError::invalid_input("invalid search query"));
+            }
+        }
+        Ok(searcher.search(query).await?)
+    }
+
+    /// Scrapes the metadata of `url` with the first site which matches it
+    pub async fn metadata(&self, url: &str) -> ApiResult<ExternalMetadataResponse> {
+        let service = self
+            .services
+            .get(url)
+            .ok_or_else(|| ApiError::invalid_input("no external site matches the url"))?;
+        let metadata = service
+            .metadata
+            .clone()
+            .ok_or_else(|| ApiError::invalid_input("external site does not support metadata"))?;
+        Ok(ExternalMetadataResponse {
+            uri: service.uri.clone(),
+            data: metadata.scrape_metadata(url).await?,
+        })
+    }
+
+    /// Scrapes `url` & converts it into a request for [crate::actions::manga::MangaActions::create].
+    /// The cover is uploaded as temp file & `url` is attached as scraper for `channel`.
+    /// Nothing is added to the db, so the user can edit the result first
+    pub async fn import(&self, url: &str, channel: &str) -> ApiResult<AddMangaRequest> {
+        if url.trim().is_empty() {
+            return Err(ApiError::invalid_input("url cannot be empty"));
+        }
+        if channel.trim().is_empty() {
+            return Err(ApiError::invalid_input("channel cannot be empty"));
+        }
+        let service = self
+            .services
+            .get(url)
+            .ok_or_else(|| ApiError::invalid_input("no external site matches the url"))?;
+        let metadata = service
+            .metadata
+            .clone()
+            .ok_or_else(|| ApiError::invalid_input("external site does not support metadata"))?;
+        let data = metadata.scrape_metadata(url).await?;
+        let mut request = request_from_metadata(url, channel, &data);
+
+        // a missing cover can still be uploaded by the user
+        if let Some(cover) = first(&data, &["cover", "img", "image"]) {
+            let bytes = match metadata.download_file(&cover).await {
+                Ok(bytes) => Ok(bytes),
+                Err(e) => match &service.reader {
+                    Some(reader) => reader.download_file(&cover).await,
+                    None => Err(e),
+                },
+            };
+            match bytes {
+                Ok(bytes) => request.image_temp_name = self.upload(&bytes).await?,
+                Err(e) => log::warn!("Failed to download cover {}: {:?}", cover, e),
+            }
+        }
+        Ok(request)
+    }
+
+    async fn upload(&self, bytes: &[u8]) -> ApiResult<String> {
+        let mut tf = self.fs.new_temp_file().await?;
+        tf.write_all(bytes).await?;
+        tf.flush().await?;
+        match self.fs.register_temp_file(tf).await? {
+            RegisterTempResult::File(id) => Ok(id.inner()),
+            _ => Err(ApiError::invalid_input("scraped cover is not an image")),
+        }
+    }
+}
+
+fn strings(data: &ScrapedData) -> Vec<String> {
+    match data {
+        ScrapedData::Str(v) => vec![v.trim().to_owned()],
+        ScrapedData::Arr(items) => items.iter().flat_map(strings).collect(),
+        ScrapedData::Map2(_) | ScrapedData::Map(_) => vec![],
+    }
+}
+
+/// All non empty values of `keys` without duplicates
+fn all(data: &BTreeMap<String, ScrapedData>, keys: &[&str]) -> Vec<String> {
+    let mut out: Vec<String> = vec![];
+    for value in keys
+        .iter()
+        .filter_map(|key| data.get(*key))
+        .flat_map(strings)
+    {
+        if !value.is_empty() && !out.contains(&value) {
+            out.push(value);
+        }
+    }
+    out
+}
+
+fn first(data: &BTreeMap<String, ScrapedData>, keys: &[&str]) -> Option<String> {
+    all(data, keys).into_iter().next()
+}
+
+fn status(v: &str) -> Option<Status> {
+    let v = v.to_lowercase();
+    let matches = |items: &[&str]| items.iter().any(|item| v.contains(item));
+    if matches(&["ongoing", "releasing", "publishing"]) {
+        Some(Status::Ongoing)
+    } else if matches(&["completed", "complete", "finished"]) {
+        Some(Status::Completed)
+    } else if matches(&["hiatus", "on hold"]) {
+        Some(Status::Hiatus)
+    } else if matches(&["dropped", "cancelled", "canceled"]) {
+        Some(Status::Dropped)
+    } else if matches(&["upcoming", "coming soon", "not yet"]) {
+        Some(Status::Upcoming)
+    } else {
+        None
+    }
+}
+
+fn request_from_metadata(
+    url: &str,
+    channel: &str,
+    data: &BTreeMap<String, ScrapedData>,
+) -> AddMangaRequest {
+    let mut names = HashMap::new();
+    let titles = all(data, &["title"]);
+    if !titles.is_empty() {
+        names.insert("en".to_owned(), StringList { items: titles });
+    }
+    let alternatives = all(data, &["alternative", "alt_title"])
+        .into_iter()
+        .filter(|v| !names.values().any(|titles| titles.items.contains(v)))
+        .collect::<Vec<_>>();
+    if !alternatives.is_empty() {
+        names.insert(
+            UNKNOWN_LANGUAGE.to_owned(),
+            StringList {
+                items: alternatives,
+            },
+        );
+    }
+    AddMangaRequest {
+        names,
+        kind: first(data, &["type"])
+            .map(|v| v.to_lowercase())
+            .unwrap_or_else(|| "manga".to_owned()),
+        status: first(data, &["status"])
+            .and_then(|v| status(&v))
+            .unwrap_or(Status::Ongoing),
+        description: first(data, &["summary", "summery", "description"]),
+        tags: all(data, &["genres", "tags"])
+            .into_iter()
+            .map(|tag| Tag {
+                tag,
+                description: None,
+                sex: TagSex::Unknown,
+            })
+            .collect(),
+        image_temp_name: String::new(),
+        authors: all(data, &["authors", "author"]),
+        publishers: all(data, &["publishers", "publisher"]),
+        artists: all(data, &["artists", "artist"]),
+        sources: vec![url.to_owned()],
+        scrapers: vec![v1::Scraper {
+            channel: channel.to_owned(),
+            url: url.to_owned(),
+            auto_approve: false,
+        }],
+    }
+}
@@ -1,13 +1,21 @@
 use std::sync::Arc;
 
-use api_structure::v1::Claim;
-use db::{lists::ListDBService, manga::MangaDBService};
+use api_structure::{
+    search::{Order, SaveSearchRequest},
+    v1::{Claim, ListsResponse},
+};
+use db::{
+    lists::ListDBService, manga::MangaDBService, popularity::PopularityDBService,
+    saved_search::SavedSearchDBService, user::User, RecordIdType, SurrealTableInfo,
+};
 
 use crate::error::{ApiError, ApiResult};
 
 pub struct ListActions {
     pub mangas: Arc<MangaDBService>,
     pub lists: Arc<ListDBService>,
+    pub popularity: Arc<PopularityDBService>,
+    pub saved_searches: Arc<SavedSearchDBService>,
 }
 
 impl ListActions {
@@ -30,8 +38,33 @@ impl ListActions {
         Ok(())
     }
 
-    pub async fn list(&self, user: &Claim) -> ApiResult<Vec<String>> {
-        Ok(self.lists.get(&user.id).await?)
+    /// Lists & saved searches, which are shown as smart lists
+    pub async fn list(&self, user: &Claim) -> ApiResult<ListsResponse> {
+        Ok(ListsResponse {
+            lists: self.lists.get(&user.id).await?,
+            smart: self.saved_searches.names(&user.id).await?,
+        })
+    }
+    /// Names of the saved searches, which are shown as smart lists
+    pub async fn saved_searches(&self, user: &Claim) -> ApiResult<Vec<String>> {
+        Ok(self.saved_searches.names(&user.id).await?)
+    }
+    pub async fn save_search(&self, data: SaveSearchRequest, user: &Claim) -> ApiResult<()> {
+        Self::validate_list_name(&data.name)?;
+        Order::try_from(data.search.order.clone())
+            .map_err(|v| ApiError::invalid_input(v.message().as_str()))?;
+        if data.search.limit == 0 {
+            return Err(ApiError::invalid_input("limit must be >= 1"));
+        }
+        self.saved_searches
+            .save(&data.name, &user.id, data.search)
+            .await?;
+        Ok(())
+    }
+    pub async fn delete_search(&self, name: &str, user: &Claim) -> ApiResult<()> {
+        Self::validate_list_name(name)?;
+        self.saved_searches.delete(name, &user.id).await?;
+        Ok(())
     }
     pub async fn add(&self, name: &str, user: &Claim) -> ApiResult<()> {
         Self::validate_list_name(name)?;
@@ -48,8 +81,17 @@ impl ListActions {
         if manga_id.trim().is_empty() {
             return Err(ApiError::invalid_input("manga_id cannot be empty"));
         }
-        self.mangas.exists(manga_id).await?;
+        self.mangas
+            .get_visible(
+                manga_id,
+                RecordIdType::from((User::name(), user.id.as_str())),
+                user.role,
+            )
+            .await?;
         self.lists.add_manga(&list, &user.id, &manga_id).await?;
+        if let Err(e) = self.popularity.record_list_add(manga_id, &user.id).await {
+            log::error!("Failed to count list addition of {}: {:?}", manga_id, e);
+        }
         Ok(())
     }
     pub async fn remove_from_list(
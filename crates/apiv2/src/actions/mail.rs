@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use notifier::mail::{Mail, MailTransport};
+
+use crate::error::{ApiError, ApiResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MailKind {
+    Verification,
+    ResetPassword,
+    Welcome,
+}
+
+impl MailKind {
+    pub const ALL: [MailKind; 3] = [
+        MailKind::Verification,
+        MailKind::ResetPassword,
+        MailKind::Welcome,
+    ];
+
+    /// Name of the file which replaces the built-in template
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            MailKind::Verification => "verification.txt",
+            MailKind::ResetPassword => "reset_password.txt",
+            MailKind::Welcome => "welcome.txt",
+        }
+    }
+
+    fn builtin(&self) -> &'static str {
+        match self {
+            MailKind::Verification => include_str!("../../templates/mail/verification.txt"),
+            MailKind::ResetPassword => include_str!("../../templates/mail/reset_password.txt"),
+            MailKind::Welcome => include_str!("../../templates/mail/welcome.txt"),
+        }
+    }
+}
+
+/// `{{var}}` placeholders are replaced when rendering
+#[derive(Debug, Clone)]
+pub struct MailTemplate {
+    subject: String,
+    body: String,
+}
+
+impl MailTemplate {
+    /// The first line is `Subject: ...`, everything after it is the body
+    pub fn parse(text: &str) -> Option<Self> {
+        let (first, body) = text.split_once('\n').unwrap_or((text, ""));
+        let subject = first.trim().strip_prefix("Subject:")?.trim();
+        if subject.is_empty() {
+            return None;
+        }
+        Some(Self {
+            subject: subject.to_owned(),
+            body: body.trim_end().to_owned(),
+        })
+    }
+
+    fn render(&self, vars: &[(&str, &str)]) -> (String, String) {
+        let replace = |text: &str| {
+            vars.iter().fold(text.to_owned(), |text, (key, value)| {
+                text.replace(&format!("{{{{{key}}}}}"), value)
+            })
+        };
+        (replace(&self.subject), replace(&self.body))
+    }
+}
+
+/// Sends account mails. Without a transport nothing is sent & codes have to be handed out by an admin
+pub struct Mailer {
+    transport: Option<Arc<dyn MailTransport>>,
+    templates: HashMap<MailKind, MailTemplate>,
+    site_name: String,
+    base_url: String,
+    /// Mails per address in `window`
+    rate_limit: u32,
+    window: Duration,
+    code_lifetime: Duration,
+    sent: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl Mailer {
+    pub fn new(
+        transport: Option<Arc<dyn MailTransport>>,
+        site_name: String,
+        base_url: String,
+    ) -> Self {
+        Self {
+            transport,
+            templates: MailKind::ALL
+                .into_iter()
+                .filter_map(|kind| Some((kind, MailTemplate::parse(kind.builtin())?)))
+                .collect(),
+            site_name,
+            base_url,
+            rate_limit: 5,
+            window: Duration::from_secs(60 * 60),
+            code_lifetime: Duration::from_secs(60 * 60),
+            sent: Mutex::default(),
+        }
+    }
+
+    /// Allows `max` mails per address in `window`
+    pub fn rate_limit(mut self, max: u32, window: Duration) -> Self {
+        self.rate_limit = max;
+        self.window = window;
+        self
+    }
+
+    /// How long emailed codes can be used
+    pub fn code_lifetime(mut self, lifetime: Duration) -> Self {
+        self.code_lifetime = lifetime;
+        self
+    }
+
+    /// Replaces the built-in templates with the files which exist in `folder`
+    pub fn load_templates(mut self, folder: &Path) -> std::io::Result<Self> {
+        for kind in MailKind::ALL {
+            let path = folder.join(kind.file_name());
+            if !path.is_file() {
+                continue;
+            }
+            let template =
+                MailTemplate::parse(&std::fs::read_to_string(&path)?).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{} has to start with a `Subject:` line", path.display()),
+                    )
+                })?;
+            self.templates.insert(kind, template);
+        }
+        Ok(self)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    /// Expiry of a code created now, as timestamp in seconds
+    pub fn code_expiry(&self) -> u64 {
+        chrono::Utc::now().timestamp() as u64 + self.code_lifetime.as_secs()
+    }
+
+    /// Fails if the address already got too many mails, otherwise counts the next mail
+    pub fn take_slot(&self, to: &str) -> ApiResult<()> {
+        let now = Instant::now();
+        let mut sent = self.sent.lock().unwrap();
+        sent.retain(|_, times| {
+            while times
+                .front()
+                .is_some_and(|v| now.duration_since(*v) >= self.window)
+            {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = sent.entry(to.trim().to_lowercase()).or_default();
+        if times.len() >= self.rate_limit as usize {
+            return Err(ApiError::TooManyRequests);
+        }
+        times.push_back(now);
+        Ok(())
+    }
+
+    pub async fn send_verification(&self, to: &str, name: &str, code: &str) -> ApiResult<()> {
+        self.send(MailKind::Verification, to, name, Some(code))
+            .await
+    }
+
+    pub async fn send_reset_password(&self, to: &str, name: &str, code: &str) -> ApiResult<()> {
+        self.send(MailKind::ResetPassword, to, name, Some(code))
+            .await
+    }
+
+    pub async fn send_welcome(&self, to: &str, name: &str) -> ApiResult<()> {
+        self.send(MailKind::Welcome, to, name, None).await
+    }
+
+    /// Callers check the rate limit with [`Mailer::take_slot`] first, so no code is created for limited addresses
+    async fn send(
+        &self,
+        kind: MailKind,
+        to: &str,
+        name: &str,
+        code: Option<&str>,
+    ) -> ApiResult<()> {
+        let Some(transport) = &self.transport else {
+            return Ok(());
+        };
+        let template = self
+            .templates
+            .get(&kind)
+            .ok_or_else(|| ApiError::write_error("missing mail template"))?;
+        let lifetime = (self.code_lifetime.as_secs() / 60).to_string();
+        let (subject, body) = template.render(&[
+            ("site", &self.site_name),
+            ("url", &self.base_url),
+            ("name", name),
+            ("code", code.unwrap_or_default()),
+            ("lifetime", &lifetime),
+        ]);
+        transport
+            .send(&Mail {
+                to: to.to_owned(),
+                subject,
+                body,
+            })
+            .await
+            .map_err(ApiError::write_error)
+    }
+}
@@ -4,12 +4,12 @@ use crate::{
 };
 use api_structure::{
     search::{
-        Array, HomeResponse, Item, ItemData, ItemOrArray, ItemValue, Order, SearchRequest,
-        SearchResponse,
+        manga_fields, Array, HomeResponse, Item, ItemData, ItemKind, ItemOrArray, ItemValue, Order,
+        SearchRequest, SearchResponse, SuggestRequest, Suggestion, SuggestionKind,
     },
     v1::{
-        self, AddMangaRequest, Chapter, EditMangaRequest, ExternalSite, MangaInfoResponse,
-        Relation, Status, Tag as GlobalTag, Visibility,
+        self, AddMangaRequest, Chapter, Claim, EditMangaRequest, ExternalSite, MangaInfoResponse,
+        Relation, Status, Tag as GlobalTag, TagSex, Visibility,
     },
 };
 use bytes::Bytes;
@@ -21,6 +21,7 @@ use db::{
     lists::ListDBService,
     manga::{Manga, MangaDBService, Scraper},
     page::PageDBService,
+    saved_search::SavedSearchDBService,
     tag::{Tag, TagDBService},
     user::{User, UserDBService},
     version::VersionDBService,
@@ -29,10 +30,11 @@ use db::{
 };
 use futures_util::{stream, Stream, StreamExt as _};
 use rand::{rng, rngs::ThreadRng, seq::IteratorRandom as _};
+use search_parser::{completion_at, CompletionTarget};
 use std::{cmp::Ordering, collections::HashMap, pin::Pin, sync::Arc, task::Poll};
 use storage::{
-    ArtFileBuilder, CoverFileBuilder, FileBuilderExt as _, FileId, RegisterTempResult,
-    StorageSystem,
+    ArtFileBuilder, ByteRange, CoverFileBuilder, FileBuilderExt as _, FileId, Options,
+    RegisterTempResult, StorageSystem,
 };
 use tokio::io::AsyncWriteExt as _;
 
@@ -46,6 +48,7 @@ pub struct MangaActions {
     pub versions: Arc<VersionDBService>,
     pub chapter_versions: Arc<ChapterVersionDBService>,
     pub pages: Arc<PageDBService>,
+    pub saved_searches: Arc<SavedSearchDBService>,
     pub fs: Arc<StorageSystem>,
 }
 
@@ -106,6 +109,18 @@ fn visibility_from_db(value: u64) -> ApiResult<Visibility> {
         .map_err(|_| ApiError::write_error("invalid visibility value in database"))
 }
 
+const SUGGESTION_LIMIT: usize = 20;
+
+/// Quotes values which would otherwise be split by the search parser
+fn quote_suggestion(value: &str) -> String {
+    match value.contains([
+        ' ', ':', '!', '(', ')', '=', '<', '>', '&', '|', '"', '\'', '\\',
+    ]) {
+        true => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+        false => value.to_owned(),
+    }
+}
+
 const MANGA_CONTAINER_MAGIC: &[u8; 8] = b"MRMANG01";
 
 pub type ExportStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
@@ -233,6 +248,7 @@ impl MangaActions {
                 target: self.versions.get(&scraper.channel).await?,
                 enabled: true,
                 url: scraper.url,
+                auto_approve: scraper.auto_approve,
             });
         }
         macro_rules! add_artists {
@@ -254,6 +270,7 @@ impl MangaActions {
 
 pub async fn convert_to_search_response(
     v: RecordData<Manga>,
+    highlight: Option<String>,
     tag_service: &Arc<TagDBService>,
     rng: &mut ThreadRng,
 ) -> ApiResult<SearchResponse> {
@@ -275,12 +292,13 @@ pub async fn convert_to_search_response(
         status: status_from_db(v.data.status)?,
         ext,
         number: number as u32,
+        highlight,
     })
 }
 
 impl MangaActions {
-    pub async fn home(&self, uid: &str) -> ApiResult<HomeResponse> {
-        validate_non_empty("uid", uid)?;
+    pub async fn home(&self, user: &Claim) -> ApiResult<HomeResponse> {
+        validate_non_empty("uid", &user.id)?;
         let generate = |order: Order, desc, query| {
             let items = match query {
                 None => vec![],
@@ -304,16 +322,21 @@ impl MangaActions {
             let mut rng = rng();
             let (_, v) = self
                 .mangas
-                .search(req, RecordIdType::from((User::name(), uid)), false)
+                .search(
+                    req,
+                    RecordIdType::from((User::name(), user.id.as_str())),
+                    user.role,
+                    false,
+                )
                 .await?;
 
             let mut resp: Vec<SearchResponse> = Vec::with_capacity(v.len());
-            for v in v {
-                resp.push(convert_to_search_response(v, &self.tags, &mut rng).await?);
+            for (v, highlight) in v {
+                resp.push(convert_to_search_response(v, highlight, &self.tags, &mut rng).await?);
             }
             Ok::<_, ApiError>(resp)
         };
-        let _trending = generate(Order::Popularity, true, None);
+        let trending = generate(Order::Trending, true, None);
         let newest = generate(Order::Created, true, None);
         let reading = generate(
             Order::LastRead,
@@ -338,7 +361,7 @@ impl MangaActions {
         let random = generate(Order::Random, false, None);
 
         Ok(HomeResponse {
-            trending: vec![], //todo: search(trending).await?,
+            trending: search(trending).await?,
             newest: search(newest).await?,
             latest_updates: search(latest_updates).await?,
             favorites: search(favorites).await?,
@@ -350,29 +373,148 @@ impl MangaActions {
     pub async fn search(
         &self,
         data: SearchRequest,
-        uid: &str,
+        user: &Claim,
     ) -> ApiResult<(Vec<SearchResponse>, u64)> {
-        validate_non_empty("uid", uid)?;
+        validate_non_empty("uid", &user.id)?;
         validate_pagination(data.page, data.limit)?;
         Order::try_from(data.order.clone())
             .map_err(|v| ApiError::invalid_input(v.message().as_str()))?;
         let (max, search) = self
             .mangas
-            .search(data, RecordIdType::from((User::name(), uid)), true)
+            .search(
+                data,
+                RecordIdType::from((User::name(), user.id.as_str())),
+                user.role,
+                true,
+            )
             .await?;
         let mut rng = rng();
 
         let mut resp: Vec<SearchResponse> = Vec::with_capacity(search.len());
-        for v in search {
-            resp.push(convert_to_search_response(v, &self.tags, &mut rng).await?);
+        for (v, highlight) in search {
+            resp.push(convert_to_search_response(v, highlight, &self.tags, &mut rng).await?);
         }
         Ok((resp, max))
     }
 
-    pub async fn info(&self, id: String, uid: &str) -> ApiResult<MangaInfoResponse> {
+    /// Runs a saved search with the order & page size it was saved with
+    pub async fn search_saved(
+        &self,
+        data: SavedSearchPageRequest,
+        user: &Claim,
+    ) -> ApiResult<(Vec<SearchResponse>, u64)> {
+        validate_non_empty("uid", &user.id)?;
+        validate_non_empty("name", &data.name)?;
+        let mut search = self.saved_searches.get(&data.name, &user.id).await?;
+        search.page = data.page;
+        self.search(search, user).await
+    }
+
+    /// Completes the field name or value at the cursor
+    pub async fn suggest(&self, data: SuggestRequest, user: &Claim) -> ApiResult<Vec<Suggestion>> {
+        validate_non_empty("uid", &user.id)?;
+        let completion = completion_at(&data.query, data.cursor as usize);
+        let prefix = completion.prefix.to_lowercase();
+        let starts_with = |v: &String| v.to_lowercase().starts_with(&prefix);
+        let fields = manga_fields();
+        let items = match completion.target {
+            CompletionTarget::Field => fields
+                .into_iter()
+                .filter(|field| {
+                    field.name.starts_with(&prefix) || field.abbr.iter().any(|v| v == &prefix)
+                })
+                .map(|field| match field.kind {
+                    ItemKind::None => field.name,
+                    _ => format!("{}:", field.name),
+                })
+                .map(|text| (SuggestionKind::Field, text))
+                .collect::<Vec<_>>(),
+            CompletionTarget::Value(key) => {
+                let field = fields.into_iter().find(|v| v.matches(&key));
+                let sex = match field.as_ref().map(|v| v.name.as_str()) {
+                    Some("male") => Some(TagSex::Male),
+                    Some("female") => Some(TagSex::Female),
+                    Some("both") => Some(TagSex::Both),
+                    Some("male2female") => Some(TagSex::MaleFemale),
+                    Some("female2male") => Some(TagSex::FemaleMale),
+                    Some("none") => Some(TagSex::None),
+                    Some("unknown") => Some(TagSex::Unknown),
+                    _ => None,
+                };
+                match field.as_ref().map(|v| v.name.as_str()) {
+                    Some(name) if name == "tag" || sex.is_some() => self
+                        .tags
+                        .suggest(&prefix, sex, SUGGESTION_LIMIT as u32)
+                        .await?
+                        .into_iter()
+                        .map(|v| (SuggestionKind::Tag, v.tag))
+                        .collect(),
+                    Some("author" | "artist" | "publisher" | "uploader") => self
+                        .users
+                        .search(&prefix, 1, SUGGESTION_LIMIT as u32)
+                        .await?
+                        .into_iter()
+                        .filter_map(|v| v.data.names.into_iter().find(starts_with))
+                        .map(|v| (SuggestionKind::Author, v))
+                        .collect(),
+                    Some("list") => self
+                        .lists
+                        .get(&user.id)
+                        .await?
+                        .into_iter()
+                        .filter(starts_with)
+                        .map(|v| (SuggestionKind::List, v))
+                        .collect(),
+                    Some("saved") => self
+                        .saved_searches
+                        .names(&user.id)
+                        .await?
+                        .into_iter()
+                        .filter(starts_with)
+                        .map(|v| (SuggestionKind::SavedSearch, v))
+                        .collect(),
+                    Some("kind") => self
+                        .kinds
+                        .all()
+                        .await?
+                        .into_iter()
+                        .filter(starts_with)
+                        .map(|v| (SuggestionKind::Kind, v))
+                        .collect(),
+                    _ => vec![],
+                }
+            }
+        };
+        let mut out: Vec<Suggestion> = Vec::new();
+        for (kind, text) in items {
+            let text = match kind {
+                SuggestionKind::Field => text,
+                _ => quote_suggestion(&text),
+            };
+            if out.iter().any(|v| v.text == text) {
+                continue;
+            }
+            out.push(Suggestion {
+                kind,
+                text,
+                start: completion.start as u32,
+                end: completion.end as u32,
+            });
+            if out.len() == SUGGESTION_LIMIT {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    pub async fn info(&self, id: String, user: &Claim) -> ApiResult<MangaInfoResponse> {
         validate_non_empty("manga_id", &id)?;
-        validate_non_empty("uid", uid)?;
-        let manga = self.mangas.get(&id).await?;
+        validate_non_empty("uid", &user.id)?;
+        let uid = user.id.as_str();
+        let manga = self
+            .mangas
+            .get_visible(&id, RecordIdType::from((User::name(), uid)), user.role)
+            .await?;
         let chapters_ = self.chapters.get_simple(manga.chapters.into_iter()).await?;
         let mut chapters = vec![];
         for v in chapters_ {
@@ -466,6 +608,7 @@ impl MangaActions {
                     scrapers.push(api_structure::v1::Scraper {
                         channel: target.data.name,
                         url: v.url,
+                        auto_approve: v.auto_approve,
                     });
                 }
                 scrapers
@@ -518,6 +661,7 @@ impl MangaActions {
                 channel,
                 url: scraper.url,
                 enabled: scraper.enabled,
+                auto_approve: scraper.auto_approve,
             });
         }
 
@@ -792,7 +936,11 @@ impl MangaActions {
                     if channel.is_empty() || url.is_empty() {
                         None
                     } else {
-                        Some(v1::Scraper { channel, url })
+                        Some(v1::Scraper {
+                            channel,
+                            url,
+                            auto_approve: scraper.auto_approve,
+                        })
                     }
                 })
                 .collect(),
@@ -1072,8 +1220,20 @@ impl PreparedExport {
                     streams.push(chunk_stream(bytes.slice(s..e)));
                 }
                 ExportSegment::Object { key, .. } => {
-                    let source = fs.reader.get(&key, &Default::default()).await?.stream;
-                    streams.push(slice_stream(source, local_start, take));
+                    let options = Options {
+                        range: Some(ByteRange::From {
+                            start: local_start,
+                            end: Some(local_end),
+                        }),
+                        ..Default::default()
+                    };
+                    let obj = fs.reader.get(&key, &options).await?;
+                    // backends which can't read ranges return the whole object
+                    let source = match obj.range {
+                        Some(range) => slice_stream(obj.stream, 0, range.length()),
+                        None => slice_stream(obj.stream, local_start, take),
+                    };
+                    streams.push(source);
                 }
             }
         }
@@ -1,12 +1,19 @@
+pub mod api_key;
 pub mod auth;
 pub mod chapter;
 pub mod chapter_version;
 pub mod character;
 pub mod crytpo;
+pub mod duplicates;
+pub mod external;
 pub mod kind;
 pub mod lists;
+pub mod mail;
 pub mod manga;
+pub mod notification;
+pub mod quality;
 pub mod reader;
+pub mod scraper;
 pub mod tags;
 pub mod token;
 pub mod user;
@@ -0,0 +1,213 @@
+use std::{collections::HashMap, sync::Arc};
+
+use api_structure::v1::{
+    self, Claim, MarkNotificationsReadRequest, NotificationSettingsRequest, NotificationsRequest,
+    NotificationsResponse, PushSubscriptionRequest,
+};
+use db::{
+    auth::RecordData,
+    notification::{NotificationDBService, PendingNotification},
+    user::{NotificationMode, UserDBService},
+};
+use notifier::{
+    Channel, EndpointPolicy, Message, Notifier, NotifyError, PushSubscription, Recipient,
+};
+
+use crate::error::{ApiError, ApiResult};
+
+pub struct NotificationActions {
+    pub notifications: Arc<NotificationDBService>,
+    pub users: Arc<UserDBService>,
+    pub notifier: Arc<Notifier>,
+    /// Which webhooks & push endpoints users can choose
+    pub endpoints: EndpointPolicy,
+}
+
+fn channel_of(mode: &NotificationMode) -> Channel {
+    match mode {
+        NotificationMode::Email => Channel::Email,
+        NotificationMode::Push => Channel::Push,
+        NotificationMode::Webhook => Channel::Webhook,
+    }
+}
+
+/// English title if available
+fn display_title(titles: &HashMap<String, Vec<String>>) -> String {
+    titles
+        .get("en")
+        .and_then(|v| v.first())
+        .or_else(|| {
+            let mut langs = titles.keys().collect::<Vec<_>>();
+            langs.sort();
+            langs.into_iter().find_map(|lang| titles[lang].first())
+        })
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn message(id: String, pending: &PendingNotification) -> Message {
+    let title = display_title(&pending.titles);
+    Message {
+        id,
+        title: format!("New chapter of {title}"),
+        body: format!("Chapter {} of {title} is out", pending.number),
+        manga_id: pending.manga.id().to_string(),
+        chapter_id: pending.chapter.id().to_string(),
+    }
+}
+
+impl NotificationActions {
+    pub async fn list(
+        &self,
+        data: NotificationsRequest,
+        user: &Claim,
+    ) -> ApiResult<NotificationsResponse> {
+        if data.page == 0 {
+            return Err(ApiError::invalid_input("page must be >= 1"));
+        }
+        if data.limit == 0 {
+            return Err(ApiError::invalid_input("limit must be >= 1"));
+        }
+        let (items, unread) = self
+            .notifications
+            .list(&user.id, data.page, data.limit, data.unread_only)
+            .await?;
+        Ok(NotificationsResponse {
+            items: items
+                .into_iter()
+                .map(|v| v1::Notification {
+                    id: v.id.id().to_string(),
+                    manga_id: v.data.manga.id().to_string(),
+                    titles: v
+                        .data
+                        .titles
+                        .into_iter()
+                        .map(|v| (v.0, v.1.into()))
+                        .collect(),
+                    chapter_id: v.data.chapter.id().to_string(),
+                    chapter: v.data.number,
+                    read: v.data.read,
+                    created: v.data.created.into_inner().0.timestamp_millis() as u64,
+                })
+                .collect(),
+            unread,
+        })
+    }
+
+    pub async fn mark_read(
+        &self,
+        data: MarkNotificationsReadRequest,
+        user: &Claim,
+    ) -> ApiResult<()> {
+        if data.ids.is_empty() {
+            self.notifications.mark_all_read(&user.id).await?;
+        } else {
+            if data.ids.iter().any(|v| v.trim().is_empty()) {
+                return Err(ApiError::invalid_input("ids cannot contain empty values"));
+            }
+            self.notifications.mark_read(&user.id, data.ids).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn settings(&self, data: NotificationSettingsRequest, user: &Claim) -> ApiResult<()> {
+        let mode = match data.mode() {
+            v1::NotificationMode::Email => NotificationMode::Email,
+            v1::NotificationMode::Push => NotificationMode::Push,
+            v1::NotificationMode::Webhook => NotificationMode::Webhook,
+        };
+        if !self.notifier.supports(channel_of(&mode)) {
+            return Err(ApiError::invalid_input(
+                "notification mode is not available on this server",
+            ));
+        }
+        if let Some(webhook) = &data.webhook {
+            notifier::validate_endpoint(webhook, self.endpoints)
+                .await
+                .map_err(|e| ApiError::invalid_input(&e.to_string()))?;
+        } else if matches!(mode, NotificationMode::Webhook) {
+            return Err(ApiError::invalid_input(
+                "webhook mode requires a webhook url",
+            ));
+        }
+        self.users
+            .set_notification_mode(&user.id, mode, data.webhook)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn subscribe(&self, data: PushSubscriptionRequest, user: &Claim) -> ApiResult<()> {
+        notifier::validate_endpoint(&data.endpoint, self.endpoints)
+            .await
+            .map_err(|e| ApiError::invalid_input(&e.to_string()))?;
+        if data.p256dh.trim().is_empty() || data.auth.trim().is_empty() {
+            return Err(ApiError::invalid_input("p256dh and auth cannot be empty"));
+        }
+        self.notifications
+            .subscribe(&user.id, data.endpoint, data.p256dh, data.auth)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&self, endpoint: &str, user: &Claim) -> ApiResult<()> {
+        if endpoint.trim().is_empty() {
+            return Err(ApiError::invalid_input("endpoint cannot be empty"));
+        }
+        self.notifications.unsubscribe(&user.id, endpoint).await?;
+        Ok(())
+    }
+
+    /// Sends up to `limit` pending notifications by the channel each user chose.
+    /// Users without a target for their channel only see the notification in the app.
+    /// Returns how many were sent
+    pub async fn deliver(&self, limit: u32) -> ApiResult<usize> {
+        let pending = self.notifications.pending(limit).await?;
+        let mut sent = 0;
+        for RecordData { id, data } in pending {
+            let id = id.id().to_string();
+            let channel = channel_of(data.mode.as_ref().unwrap_or(&NotificationMode::Push));
+            let uid = data.user.id().to_string();
+            let push = match channel {
+                Channel::Push => self
+                    .notifications
+                    .subscriptions(&uid)
+                    .await?
+                    .into_iter()
+                    .map(|v| PushSubscription {
+                        endpoint: v.endpoint,
+                        p256dh: v.p256dh,
+                        auth: v.auth,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            let recipient = Recipient {
+                email: data.email.clone(),
+                webhook: data.webhook.clone(),
+                push,
+            };
+            match self
+                .notifier
+                .send(channel, &recipient, &message(id.clone(), &data))
+                .await
+            {
+                Ok(report) => {
+                    for endpoint in report.expired {
+                        self.notifications.remove_subscription(&endpoint).await?;
+                    }
+                    sent += 1;
+                }
+                Err(NotifyError::NoTarget(_) | NotifyError::Unsupported(_)) => {}
+                Err(e) => {
+                    log::warn!("Failed to deliver notification {id}: {e}");
+                    self.notifications
+                        .set_failed(&id, data.attempts + 1)
+                        .await?;
+                    continue;
+                }
+            }
+            self.notifications.set_delivered(&id).await?;
+        }
+        Ok(sent)
+    }
+}
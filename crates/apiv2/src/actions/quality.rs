@@ -0,0 +1,152 @@
+use db::page::VersionPages;
+use storage::decode_hash;
+
+use super::duplicates::hamming;
+
+/// Pages whose hashes differ in at most this many bits show the same image
+const SAME_PAGE_DISTANCE: u32 = 4;
+
+const RESOLUTION_WEIGHT: f64 = 0.35;
+const DETAIL_WEIGHT: f64 = 0.15;
+const FORMAT_WEIGHT: f64 = 0.1;
+const ALIGNMENT_WEIGHT: f64 = 0.2;
+const COMPLETENESS_WEIGHT: f64 = 0.2;
+
+/// Lossless formats keep more detail than lossy ones with the same resolution
+fn format_weight(ext: &str) -> f64 {
+    match ext {
+        "png" => 1.0,
+        "webp" | "avif" => 0.9,
+        "jpeg" | "jpg" => 0.8,
+        "gif" => 0.6,
+        _ => 0.7,
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+fn ratio(value: f64, max: f64) -> f64 {
+    if max > 0.0 {
+        value / max
+    } else {
+        0.0
+    }
+}
+
+struct Measures {
+    pixels: f64,
+    /// Only known if the size of every page is known
+    bytes_per_pixel: Option<f64>,
+    format: f64,
+    pages: usize,
+    hashes: Vec<Vec<u8>>,
+}
+
+impl From<&VersionPages> for Measures {
+    fn from(version: &VersionPages) -> Self {
+        let pixels: Vec<f64> = version
+            .pages
+            .iter()
+            .map(|v| v.width as f64 * v.height as f64)
+            .collect();
+        let bytes_per_pixel = version
+            .pages
+            .iter()
+            .zip(&pixels)
+            .map(|(page, pixels)| Some(page.size? as f64 / pixels.max(1.0)))
+            .collect::<Option<Vec<_>>>()
+            .filter(|v| !v.is_empty())
+            .map(median);
+        let format = if version.pages.is_empty() {
+            0.0
+        } else {
+            version
+                .pages
+                .iter()
+                .map(|v| format_weight(&v.ext))
+                .sum::<f64>()
+                / version.pages.len() as f64
+        };
+        Self {
+            pixels: median(pixels),
+            bytes_per_pixel,
+            format,
+            pages: version.pages.len(),
+            hashes: version
+                .pages
+                .iter()
+                .filter_map(|v| decode_hash(v.hash.as_deref()?))
+                .collect(),
+        }
+    }
+}
+
+/// Share of the pages which are also in another version.
+/// Versions with wrong or extra pages align worse
+fn alignment(index: usize, measures: &[Measures]) -> f64 {
+    let own = &measures[index].hashes;
+    let others: Vec<&Vec<u8>> = measures
+        .iter()
+        .enumerate()
+        .filter(|(other, _)| *other != index)
+        .flat_map(|(_, v)| &v.hashes)
+        .collect();
+    if own.is_empty() || others.is_empty() {
+        return 1.0;
+    }
+    let matched = own
+        .iter()
+        .filter(|hash| {
+            others
+                .iter()
+                .any(|other| hamming(hash, other) <= SAME_PAGE_DISTANCE)
+        })
+        .count();
+    matched as f64 / own.len() as f64
+}
+
+/// Scores the versions of one chapter between 0 & 1, higher is better.
+/// Scores are relative to the other versions, so they can't be compared across chapters
+pub(crate) fn score_versions(versions: &[VersionPages]) -> Vec<(String, f64)> {
+    let measures: Vec<Measures> = versions.iter().map(Measures::from).collect();
+    let max_pixels = measures.iter().map(|v| v.pixels).fold(0.0, f64::max);
+    let max_pages = measures.iter().map(|v| v.pages).max().unwrap_or_default();
+    // sizes of older pages are unknown, comparing some versions by it would be unfair
+    let max_detail = measures
+        .iter()
+        .map(|v| v.bytes_per_pixel)
+        .collect::<Option<Vec<_>>>()
+        .map(|v| v.into_iter().fold(0.0, f64::max));
+
+    versions
+        .iter()
+        .zip(&measures)
+        .enumerate()
+        .map(|(index, (version, m))| {
+            let mut score = RESOLUTION_WEIGHT * ratio(m.pixels, max_pixels)
+                + FORMAT_WEIGHT * m.format
+                + ALIGNMENT_WEIGHT * alignment(index, &measures)
+                + COMPLETENESS_WEIGHT * ratio(m.pages as f64, max_pages as f64);
+            let mut total =
+                RESOLUTION_WEIGHT + FORMAT_WEIGHT + ALIGNMENT_WEIGHT + COMPLETENESS_WEIGHT;
+            if let (Some(max), Some(detail)) = (max_detail, m.bytes_per_pixel) {
+                score += DETAIL_WEIGHT * ratio(detail, max);
+                total += DETAIL_WEIGHT;
+            }
+            (version.id.id().to_string(), score / total)
+        })
+        .collect()
+}
+
+/// Highest score of [`score_versions`]. Ties are decided by the id, so the choice is stable
+pub(crate) fn best_score(scores: &[(String, f64)]) -> Option<&(String, f64)> {
+    scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+}
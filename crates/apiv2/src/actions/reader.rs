@@ -1,13 +1,26 @@
 use std::sync::Arc;
 
-use api_structure::v1::{ChapterVersion, Claim, MangaReaderResponse, Page, ReaderChapter};
+use api_structure::v1::{
+    ChapterVersion, Claim, MangaReaderResponse, Page, ReaderChapter, VersionPreferenceRequest,
+};
 use db::{
-    chapter::ChapterDBService, kind::KindDBService, lists::ListDBService, manga::MangaDBService,
-    page::PageDBService, progress::UserProgressDBService, version_link::ChapterVersionDBService,
+    chapter::ChapterDBService,
+    kind::KindDBService,
+    lists::ListDBService,
+    manga::MangaDBService,
+    page::PageDBService,
+    popularity::{Activity, PopularityDBService},
+    progress::UserProgressDBService,
+    user::User,
+    version_link::ChapterVersionDBService,
+    version_preference::VersionPreferenceDBService,
+    RecordIdType, SurrealTableInfo,
 };
 
 use crate::error::{ApiError, ApiResult};
 
+use super::quality::{best_score, score_versions};
+
 pub struct ReaderActions {
     pub progresses: Arc<UserProgressDBService>,
     pub chapters: Arc<ChapterDBService>,
@@ -16,6 +29,8 @@ pub struct ReaderActions {
     pub mangas: Arc<MangaDBService>,
     pub lists: Arc<ListDBService>,
     pub kinds: Arc<KindDBService>,
+    pub popularity: Arc<PopularityDBService>,
+    pub version_preferences: Arc<VersionPreferenceDBService>,
 }
 impl ReaderActions {
     pub async fn save_progress(
@@ -32,10 +47,16 @@ impl ReaderActions {
         }
         let progress = progress.clamp(0.0, 1.0);
         let manga_id = self.chapters.get_manga_id(chapter_id).await?;
-        self.progresses
+        let previous = self
+            .progresses
             .update(&claim.id, &manga_id, chapter_id, progress)
             .await?;
         if progress >= 0.95 {
+            if previous.map_or(true, |v| v < 0.95) {
+                if let Err(e) = self.popularity.record(&manga_id, Activity::Read).await {
+                    log::error!("Failed to count read of {}: {:?}", manga_id, e);
+                }
+            }
             let _ = self
                 .progresses
                 .load_next_chapter(&claim.id, &manga_id, &chapter_id)
@@ -53,7 +74,14 @@ impl ReaderActions {
         if manga_id.trim().is_empty() {
             return Err(ApiError::invalid_input("manga_id cannot be empty"));
         }
-        let manga = self.mangas.get(&manga_id).await?;
+        let manga = self
+            .mangas
+            .get_visible(
+                manga_id,
+                RecordIdType::from((User::name(), claim.id.as_str())),
+                claim.role,
+            )
+            .await?;
         let (chapter, progress) = match chapter_id {
             Some(v) => {
                 if v.trim().is_empty() {
@@ -110,6 +138,10 @@ impl ReaderActions {
                 .partial_cmp(&b.chapter)
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
+        let open_version = match chapters.iter().find(|v| v.chapter_id == chapter) {
+            Some(open) => self.open_version(open, manga_id, &claim.id).await?,
+            None => None,
+        };
         Ok(MangaReaderResponse {
             favorite: self.lists.is_favorite(&manga_id, &claim.id).await,
             manga_id: manga_id.to_owned(),
@@ -123,10 +155,76 @@ impl ReaderActions {
             chapters,
             open_chapter: chapter,
             progress,
+            open_version,
         })
     }
 
-    pub async fn pages(&self, chapter_version_id: &str) -> ApiResult<ChapterVersion> {
+    /// Best scored version of `chapter`, unless the user picks versions manually
+    async fn open_version(
+        &self,
+        chapter: &ReaderChapter,
+        manga_id: &str,
+        user: &str,
+    ) -> ApiResult<Option<String>> {
+        let best_quality = self
+            .version_preferences
+            .get(user, manga_id)
+            .await?
+            .unwrap_or(true);
+        if !best_quality {
+            return Ok(None);
+        }
+        if chapter.versions.len() < 2 {
+            return Ok(chapter.versions.values().next().cloned());
+        }
+        let versions = self
+            .pages
+            .version_pages(chapter.versions.values().cloned().collect())
+            .await?;
+        Ok(best_score(&score_versions(&versions)).map(|v| v.0.clone()))
+    }
+
+    pub async fn set_version_preference(
+        &self,
+        data: VersionPreferenceRequest,
+        claim: &Claim,
+    ) -> ApiResult<()> {
+        let Some(manga_id) = data.manga_id else {
+            let best_quality = data.best_quality.ok_or_else(|| {
+                ApiError::invalid_input("best_quality is required without manga_id")
+            })?;
+            self.version_preferences
+                .set(&claim.id, None, best_quality)
+                .await?;
+            return Ok(());
+        };
+        if manga_id.trim().is_empty() {
+            return Err(ApiError::invalid_input("manga_id cannot be empty"));
+        }
+        self.mangas
+            .get_visible(
+                &manga_id,
+                RecordIdType::from((User::name(), claim.id.as_str())),
+                claim.role,
+            )
+            .await?;
+        match data.best_quality {
+            Some(v) => {
+                self.version_preferences
+                    .set(&claim.id, Some(&manga_id), v)
+                    .await?
+            }
+            None => self.version_preferences.clear(&claim.id, &manga_id).await?,
+        }
+        Ok(())
+    }
+
+    /// Pages of the version. A view of the manga is counted once per chapter, user & hour
+    pub async fn pages(
+        &self,
+        chapter_version_id: &str,
+        claim: &Claim,
+    ) -> ApiResult<ChapterVersion> {
         if chapter_version_id.trim().is_empty() {
             return Err(ApiError::invalid_input(
                 "chapter_version_id cannot be empty",
@@ -134,6 +232,21 @@ impl ReaderActions {
         }
         let info = self.chapter_versions.get(chapter_version_id).await?;
         let pages = self.pages.get(info.pages).await?;
+        let view = match self
+            .chapters
+            .get_manga_id_by_version(chapter_version_id)
+            .await
+        {
+            Ok(manga_id) => {
+                self.popularity
+                    .record_view(&manga_id, &claim.id, chapter_version_id)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = view {
+            log::error!("Failed to count view of {}: {:?}", chapter_version_id, e);
+        }
         Ok(ChapterVersion {
             pages: pages
                 .into_iter()
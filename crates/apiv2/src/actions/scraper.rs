@@ -0,0 +1,201 @@
+use std::{sync::Arc, time::Duration};
+
+use api_structure::v1::{PendingScraperGroup, ScrapedChapterEntry, StringList};
+use chrono::Utc;
+use db::{
+    auth::RecordData,
+    manga::MangaDBService,
+    scraper::{ScraperDbService, ScraperEntry, State},
+    version::VersionDBService,
+};
+use manga_scraper::init::{Service, Services};
+use scraper_module::ReaderScraper;
+use storage::{RegisterTempResult, StorageSystem};
+use tokio::io::AsyncWriteExt as _;
+
+use crate::{
+    actions::chapter::ChapterActions,
+    error::{ApiError, ApiResult},
+};
+
+pub struct ScraperActions {
+    pub services: Arc<Services>,
+    pub mangas: Arc<MangaDBService>,
+    pub scraper: Arc<ScraperDbService>,
+    pub versions: Arc<VersionDBService>,
+    pub chapters: ChapterActions,
+    pub fs: Arc<StorageSystem>,
+}
+
+impl ScraperActions {
+    fn service(&self, uri: &str) -> ApiResult<(&Service, Arc<dyn ReaderScraper>)> {
+        let service = self
+            .services
+            .get_by_uri(uri)
+            .ok_or_else(|| ApiError::invalid_input("unknown scraper"))?;
+        let reader = service
+            .reader
+            .clone()
+            .ok_or_else(|| ApiError::invalid_input("scraper cannot read chapters"))?;
+        Ok((service, reader))
+    }
+
+    /// Scrapes the chapter lists of all enabled scrapers which belong to the site `uri`
+    /// and adds new chapters as pending entries
+    pub async fn refresh(&self, uri: &str, recheck_after: Duration) -> ApiResult<()> {
+        let (service, reader) = self.service(uri)?;
+        let since = Utc::now()
+            - chrono::Duration::from_std(recheck_after)
+                .map_err(|e| ApiError::invalid_input(&e.to_string()))?;
+        let recent = self.scraper.get_newer_then(since.into()).await?;
+        for manga in self.mangas.scrapers().await? {
+            let info = match self.mangas.get(&manga.id().to_string()).await {
+                Ok(v) => v,
+                Err(e) => {
+                    log::warn!("Failed to load scrapers of {}: {:?}", manga.id(), e);
+                    continue;
+                }
+            };
+            for scraper in info.scraper {
+                if !scraper.enabled
+                    || !service.register.url_matches(&scraper.url)
+                    || recent.contains(&(manga.clone(), scraper.target.clone()))
+                {
+                    continue;
+                }
+                let chapters = match reader.scrape_chapters(&scraper.url).await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        log::warn!("Failed to scrape {}: {:?}", scraper.url, e);
+                        continue;
+                    }
+                };
+                // one broken entry shouldnt stop the others from being refreshed
+                if let Err(e) = self
+                    .scraper
+                    .update(
+                        manga.clone(),
+                        scraper.target,
+                        chapters,
+                        scraper.auto_approve,
+                    )
+                    .await
+                {
+                    log::warn!("Failed to store chapters of {}: {:?}", scraper.url, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pending entries grouped by manga & version
+    pub async fn pending(&self, page: u32, limit: u32) -> ApiResult<Vec<PendingScraperGroup>> {
+        let mut out = vec![];
+        for group in self.scraper.pending(page, limit).await? {
+            let titles = self.mangas.get(&group.manga.id().to_string()).await?.titles;
+            let version = self.versions.get_(group.version.clone()).await?.data.name;
+            let entries = self
+                .scraper
+                .get_pending_items(group.manga.clone(), group.version.clone())
+                .await?
+                .into_iter()
+                .map(|v| ScrapedChapterEntry {
+                    id: v.id.id().to_string(),
+                    chapter: v.data.data.chapter,
+                    names: v.data.data.names,
+                    url: v.data.data.url,
+                    tags: v.data.data.tags,
+                })
+                .collect();
+            out.push(PendingScraperGroup {
+                manga_id: group.manga.id().to_string(),
+                titles: titles
+                    .into_iter()
+                    .map(|(k, items)| (k, StringList { items }))
+                    .collect(),
+                version_id: group.version.id().to_string(),
+                version,
+                entries,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Approved entries will be downloaded by the scheduler
+    pub async fn approve(&self, ids: Vec<String>) -> ApiResult<usize> {
+        self.set_state(ids, State::Approved).await
+    }
+
+    pub async fn decline(&self, ids: Vec<String>) -> ApiResult<usize> {
+        self.set_state(ids, State::Declined).await
+    }
+
+    async fn set_state(&self, ids: Vec<String>, state: State) -> ApiResult<usize> {
+        if ids.is_empty() {
+            return Err(ApiError::invalid_input("ids cannot be empty"));
+        }
+        if ids.iter().any(|id| id.trim().is_empty()) {
+            return Err(ApiError::invalid_input("ids cannot contain empty values"));
+        }
+        Ok(self.scraper.set_state(ids, state).await?)
+    }
+
+    /// Downloads all approved entries which belong to the site `uri`
+    pub async fn download(&self, uri: &str) -> ApiResult<()> {
+        let (service, reader) = self.service(uri)?;
+        for entry in self.scraper.approved().await? {
+            if !service.register.url_matches(&entry.data.data.url) {
+                continue;
+            }
+            let url = entry.data.data.url.clone();
+            if let Err(e) = self.download_entry(reader.as_ref(), entry).await {
+                log::warn!("Failed to download {}: {:?}", url, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn download_entry(
+        &self,
+        reader: &dyn ReaderScraper,
+        entry: RecordData<ScraperEntry>,
+    ) -> ApiResult<()> {
+        let data = &entry.data;
+        let mut images = vec![];
+        for page in reader.scrape_pages(&data.data.url).await? {
+            let bytes = reader.download_file(&page).await?;
+            let mut tf = self.fs.new_temp_file().await?;
+            tf.write_all(&bytes).await?;
+            tf.flush().await?;
+            match self.fs.register_temp_file(tf).await? {
+                RegisterTempResult::File(id) => images.push(id.inner()),
+                _ => return Err(ApiError::invalid_input("scraped page is not an image")),
+            }
+        }
+        let version = self.versions.get_(data.version.clone()).await?.data.name;
+        let mut titles = data.data.names.clone();
+        if titles.is_empty() {
+            titles.push(format!("Chapter {}", data.data.chapter));
+        }
+        match self
+            .chapters
+            .add(
+                &data.manga.id().to_string(),
+                titles,
+                data.data.chapter,
+                &version,
+                images,
+                vec![],
+                vec![data.data.url.clone()],
+                None,
+            )
+            .await
+        {
+            // was uploaded by someone else in the meantime
+            Ok(()) | Err(ApiError::ChapterVersionAlreadyExists) => {}
+            Err(e) => return Err(e),
+        }
+        self.scraper.remove(entry).await?;
+        Ok(())
+    }
+}
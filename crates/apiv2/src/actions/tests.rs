@@ -3,32 +3,45 @@ use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use api_structure::{
     now,
     req::LoginRequest,
-    search::{Array, Item, ItemData, ItemOrArray, ItemValue, SearchRequest as MangaSearchRequest},
+    search::{
+        Array, Item, ItemData, ItemOrArray, ItemValue, SaveSearchRequest, SavedSearchPageRequest,
+        SearchRequest as MangaSearchRequest, SuggestRequest, Suggestion, SuggestionKind,
+    },
     v1::{
-        self, ActivationTokenKind, AddMangaRequest, Claim, EditChapterRequest, EditMangaRequest,
-        Gender, LoginWithEmailAndPassword, LoginWithUsernameAndPassword, PaginationRequest,
-        PasswordChange, ResetPasswordRequest, Role, SearchRequest as SimpleSearchRequest, Status,
-        StringList, Tag, TagList, TagSex, UpdateUserRequest,
+        self, ActivationTokenKind, AddMangaRequest, Claim, CreateApiKeyRequest, DuplicatesRequest,
+        EditChapterRequest, EditMangaRequest, Gender, LoginWithEmailAndPassword,
+        LoginWithUsernameAndPassword, MarkNotificationsReadRequest, NotificationSettingsRequest,
+        NotificationsRequest, PaginationRequest, PasswordChange, PushSubscriptionRequest,
+        ResetPasswordRequest, Role, SearchRequest as SimpleSearchRequest, Status, StringList, Tag,
+        TagList, TagSex, UpdateUserRequest, VersionPreferenceRequest,
     },
+    Permission,
 };
 use chrono::Utc;
-use db::{init_db, DbConfig, DbHandle, MemoryDbConfig};
+use db::{init_db, DbConfig, DbHandle, MemoryDbConfig, SurrealTableInfo as _};
 use futures_util::StreamExt as _;
+use notifier::{mail::MemoryMailer, Channel, MemoryBackend, Notifier};
 use serde::Deserialize;
 use std::time::Duration;
-use storage::{FileId, MemStorage, RegisterTempResult, StorageSystem};
+use storage::{FileId, HashSink as _, MemStorage, RegisterTempResult, StorageSystem};
 use tokio::io::AsyncWriteExt as _;
 
 use crate::{
     actions::{
-        auth::AuthAction,
+        api_key::ApiKeyActions,
+        auth::{AuthAction, ClientInfo},
         chapter::ChapterActions,
         chapter_version::ChapterVersionActions,
         crytpo::CryptoService,
+        duplicates::DuplicateActions,
+        external::ExternalActions,
         kind::KindActions,
         lists::ListActions,
+        mail::Mailer,
         manga::{MangaActions, VolumeRange},
+        notification::NotificationActions,
         reader::ReaderActions,
+        scraper::ScraperActions,
         tags::TagActions,
         token::TokenAction,
         user::UserActions,
@@ -54,13 +67,22 @@ struct CreatedChapter {
 
 struct TestCtx {
     db: DbHandle,
+    api_key: ApiKeyActions,
     crypto: Arc<CryptoService>,
     auth: AuthAction,
+    /// Mails sent by `auth`
+    mails: MemoryMailer,
     chapter: ChapterActions,
     chapter_version: ChapterVersionActions,
+    duplicates: DuplicateActions,
     kind: KindActions,
     list: ListActions,
     manga: MangaActions,
+    notification: NotificationActions,
+    /// Push messages sent by `notification`
+    pushes: MemoryBackend,
+    /// Emails sent by `notification`
+    emails: MemoryBackend,
     reader: ReaderActions,
     storage: Arc<StorageSystem>,
     tag: TagActions,
@@ -147,11 +169,21 @@ impl TestCtx {
         );
         let crypto = Arc::new(CryptoService::new(b"unit-test-secret".to_vec()));
 
+        let api_key = ApiKeyActions {
+            keys: db.api_keys.clone(),
+        };
+        let mails = MemoryMailer::new();
         let auth = AuthAction {
             users: db.users.clone(),
             crypto: crypto.clone(),
             token: db.tokens.clone(),
             fs: storage.clone(),
+            mailer: Arc::new(Mailer::new(
+                Some(Arc::new(mails.clone())),
+                "ManRead".to_owned(),
+                "https://manread.example".to_owned(),
+            )),
+            sessions: db.sessions.clone(),
         };
         let chapter = ChapterActions {
             chapters: db.chapters.clone(),
@@ -162,6 +194,10 @@ impl TestCtx {
             pages: db.pages.clone(),
             fs: storage.clone(),
         };
+        let duplicates = DuplicateActions {
+            pages: db.pages.clone(),
+            index: Default::default(),
+        };
         let chapter_version = ChapterVersionActions {
             versions: db.versions.clone(),
             chapters: db.chapters.clone(),
@@ -175,6 +211,8 @@ impl TestCtx {
         let list = ListActions {
             mangas: db.mangas.clone(),
             lists: db.lists.clone(),
+            popularity: db.popularity.clone(),
+            saved_searches: db.saved_searches.clone(),
         };
         let manga = MangaActions {
             mangas: db.mangas.clone(),
@@ -186,8 +224,17 @@ impl TestCtx {
             versions: db.versions.clone(),
             chapter_versions: db.chapter_versions.clone(),
             pages: db.pages.clone(),
+            saved_searches: db.saved_searches.clone(),
             fs: storage.clone(),
         };
+        let pushes = MemoryBackend::new(Channel::Push);
+        let emails = MemoryBackend::new(Channel::Email);
+        let notification = NotificationActions {
+            notifications: db.notifications.clone(),
+            users: db.users.clone(),
+            notifier: Arc::new(Notifier::new().with(pushes.clone()).with(emails.clone())),
+            endpoints: Default::default(),
+        };
         let reader = ReaderActions {
             progresses: db.progress.clone(),
             chapters: db.chapters.clone(),
@@ -196,6 +243,8 @@ impl TestCtx {
             mangas: db.mangas.clone(),
             lists: db.lists.clone(),
             kinds: db.kinds.clone(),
+            popularity: db.popularity.clone(),
+            version_preferences: db.version_preferences.clone(),
         };
         let tag = TagActions {
             tags: db.tags.clone(),
@@ -212,13 +261,19 @@ impl TestCtx {
 
         Self {
             db,
+            api_key,
             crypto,
             auth,
+            mails,
             chapter,
             chapter_version,
+            duplicates,
             kind,
             list,
             manga,
+            notification,
+            pushes,
+            emails,
             reader,
             storage,
             tag,
@@ -262,6 +317,7 @@ impl TestCtx {
                 Gender::Unknown,
                 Utc::now(),
                 Some(FileId::new(icon)),
+                &ClientInfo::default(),
             )
             .await
             .expect("user registration should succeed");
@@ -308,6 +364,7 @@ impl TestCtx {
             scrapers: vec![v1::Scraper {
                 channel: "en".to_owned(),
                 url: "https://scraper.example/feed".to_owned(),
+                auto_approve: false,
             }],
         };
 
@@ -391,6 +448,26 @@ impl TestCtx {
             chapter_version_id,
         }
     }
+
+    /// Perceptual hashes are stored in the background after a chapter was added
+    async fn wait_for_hashes(&self, pages: usize) {
+        for _ in 0..250 {
+            let hashed: usize = self
+                .db
+                .pages
+                .hashed_versions()
+                .await
+                .expect("hashes should load")
+                .iter()
+                .map(|v| v.pages.len())
+                .sum();
+            if hashed == pages {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("pages were not hashed");
+    }
 }
 
 #[actix_web::test]
@@ -413,39 +490,51 @@ async fn auth_and_token_actions_cover_primary_flows() {
     assert_eq!(by_mail.id.id(), by_name.id.id());
 
     ctx.auth
-        .login(LoginRequest::Username(LoginWithUsernameAndPassword {
-            username: "alice".to_owned(),
-            password: "password-1".to_owned(),
-        }))
+        .login(
+            LoginRequest::Username(LoginWithUsernameAndPassword {
+                username: "alice".to_owned(),
+                password: "password-1".to_owned(),
+            }),
+            &ClientInfo::default(),
+        )
         .await
         .expect("username login should succeed");
     ctx.auth
-        .login(LoginRequest::Email(LoginWithEmailAndPassword {
-            email: "alice@example.com".to_owned(),
-            password: "password-1".to_owned(),
-        }))
+        .login(
+            LoginRequest::Email(LoginWithEmailAndPassword {
+                email: "alice@example.com".to_owned(),
+                password: "password-1".to_owned(),
+            }),
+            &ClientInfo::default(),
+        )
         .await
         .expect("email login should succeed");
     assert!(matches!(
         ctx.auth
-            .login(LoginRequest::Email(LoginWithEmailAndPassword {
-                email: "alice@example.com".to_owned(),
-                password: "wrong-password".to_owned(),
-            }))
+            .login(
+                LoginRequest::Email(LoginWithEmailAndPassword {
+                    email: "alice@example.com".to_owned(),
+                    password: "wrong-password".to_owned(),
+                }),
+                &ClientInfo::default()
+            )
             .await,
         Err(ApiError::PasswordIncorrect)
     ));
 
     let issued = ctx
         .auth
-        .login(LoginRequest::Username(LoginWithUsernameAndPassword {
-            username: "alice".to_owned(),
-            password: "password-1".to_owned(),
-        }))
+        .login(
+            LoginRequest::Username(LoginWithUsernameAndPassword {
+                username: "alice".to_owned(),
+                password: "password-1".to_owned(),
+            }),
+            &ClientInfo::default(),
+        )
         .await
         .expect("login should produce refresh token");
     ctx.auth
-        .refresh(&issued.refresh_token)
+        .refresh(&issued.refresh_token, &ClientInfo::default())
         .await
         .expect("refresh before logout should succeed");
     tokio::time::sleep(Duration::from_millis(1100)).await;
@@ -454,7 +543,9 @@ async fn auth_and_token_actions_cover_primary_flows() {
         .await
         .expect("logout should persist generated timestamp");
     assert!(matches!(
-        ctx.auth.refresh(&issued.refresh_token).await,
+        ctx.auth
+            .refresh(&issued.refresh_token, &ClientInfo::default())
+            .await,
         Err(ApiError::ExpiredToken)
     ));
 
@@ -469,29 +560,38 @@ async fn auth_and_token_actions_cover_primary_flows() {
         })
         .await;
     ctx.auth
-        .reset_password(ResetPasswordRequest {
-            ident: "alice".to_owned(),
-            email: false,
-            key: reset_key,
-            password: "password-2".to_owned(),
-        })
+        .reset_password(
+            ResetPasswordRequest {
+                ident: "alice".to_owned(),
+                email: false,
+                key: reset_key,
+                password: "password-2".to_owned(),
+            },
+            &ClientInfo::default(),
+        )
         .await
         .expect("reset password should succeed");
 
     assert!(matches!(
         ctx.auth
-            .login(LoginRequest::Username(LoginWithUsernameAndPassword {
-                username: "alice".to_owned(),
-                password: "password-1".to_owned(),
-            }))
+            .login(
+                LoginRequest::Username(LoginWithUsernameAndPassword {
+                    username: "alice".to_owned(),
+                    password: "password-1".to_owned(),
+                }),
+                &ClientInfo::default()
+            )
             .await,
         Err(ApiError::PasswordIncorrect)
     ));
     ctx.auth
-        .login(LoginRequest::Username(LoginWithUsernameAndPassword {
-            username: "alice".to_owned(),
-            password: "password-2".to_owned(),
-        }))
+        .login(
+            LoginRequest::Username(LoginWithUsernameAndPassword {
+                username: "alice".to_owned(),
+                password: "password-2".to_owned(),
+            }),
+            &ClientInfo::default(),
+        )
         .await
         .expect("new password should be active");
 
@@ -514,7 +614,7 @@ async fn auth_and_token_actions_cover_primary_flows() {
     let verify_key = ctx.latest_token_by_kind(verify_kind).await;
     let verified = ctx
         .auth
-        .verify(&verify_key, &user.claim)
+        .verify(&verify_key, &user.claim, &ClientInfo::default())
         .await
         .expect("verification token should update role");
     let verified_claim = ctx
@@ -530,6 +630,637 @@ async fn auth_and_token_actions_cover_primary_flows() {
         .expect("token delete should succeed");
 }
 
+#[actix_web::test]
+async fn sessions_rotate_refresh_tokens_and_detect_reuse() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("sessions", "sessions@example.com", "password")
+        .await;
+    let other = ctx
+        .register_user("sessions-other", "sessions-other@example.com", "password")
+        .await;
+    let firefox = ClientInfo {
+        device: "Firefox".to_owned(),
+        ip: Some("10.0.0.1".to_owned()),
+    };
+    let issued = ctx
+        .auth
+        .login(
+            LoginRequest::Username(LoginWithUsernameAndPassword {
+                username: "sessions".to_owned(),
+                password: "password".to_owned(),
+            }),
+            &firefox,
+        )
+        .await
+        .expect("login should succeed");
+    let access = ctx
+        .crypto
+        .get_claim(&issued.access_token)
+        .expect("access token should decode");
+    let session = access.session.clone().expect("tokens belong to a session");
+
+    let sessions = ctx
+        .auth
+        .sessions(&user.id, Some(&session))
+        .await
+        .expect("sessions should be listed");
+    assert_eq!(sessions.len(), 2);
+    let current = sessions
+        .iter()
+        .find(|v| v.current)
+        .expect("login session should be current");
+    assert_eq!(current.id, session);
+    assert_eq!(current.device, "Firefox");
+    assert_eq!(current.ip.as_deref(), Some("10.0.0.1"));
+
+    let rotated = ctx
+        .auth
+        .refresh(&issued.refresh_token, &firefox)
+        .await
+        .expect("refresh should rotate the token");
+    assert_ne!(rotated.refresh_token, issued.refresh_token);
+    // the replaced token was stolen, so the whole session is revoked
+    assert!(matches!(
+        ctx.auth.refresh(&issued.refresh_token, &firefox).await,
+        Err(ApiError::ExpiredToken)
+    ));
+    assert!(matches!(
+        ctx.auth.refresh(&rotated.refresh_token, &firefox).await,
+        Err(ApiError::ExpiredToken)
+    ));
+    assert!(ctx.crypto.is_revoked(&access));
+    let sessions = ctx
+        .auth
+        .sessions(&user.id, None)
+        .await
+        .expect("sessions should be listed");
+    assert_eq!(sessions.len(), 1);
+    assert_ne!(sessions[0].id, session);
+
+    // users can only revoke their own sessions, admins any
+    assert!(matches!(
+        ctx.auth
+            .revoke_session(&sessions[0].id, Some(&other.id))
+            .await,
+        Err(ApiError::NotFoundInDB)
+    ));
+    ctx.auth
+        .revoke_session(&sessions[0].id, Some(&user.id))
+        .await
+        .expect("own session should be revoked");
+    assert!(ctx
+        .auth
+        .sessions(&user.id, None)
+        .await
+        .expect("sessions should be listed")
+        .is_empty());
+    let other_session = other
+        .claim
+        .session
+        .clone()
+        .expect("register starts a session");
+    ctx.auth
+        .revoke_session(&other_session, None)
+        .await
+        .expect("admin should revoke any session");
+    assert!(ctx.crypto.is_revoked(&other.claim));
+}
+
+#[actix_web::test]
+async fn api_keys_are_scoped_to_the_role_of_the_owner() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("scripts", "scripts@example.com", "password")
+        .await;
+    ctx.db
+        .users
+        .set_role(&user.id, Role::Author)
+        .await
+        .expect("role should be updated");
+    let claim = Claim::new_access(user.id.clone(), Role::Author);
+    let request = |permissions: &[&str], expires: Option<u64>| CreateApiKeyRequest {
+        name: "uploader".to_owned(),
+        permissions: permissions.iter().map(|v| (*v).to_owned()).collect(),
+        expires,
+    };
+
+    for (permissions, expires) in [
+        (&["read", "review"][..], None),
+        (&["read", "bogus"][..], None),
+        (&[][..], None),
+        (&["read"][..], Some(now().as_millis() as u64 - 1000)),
+    ] {
+        assert!(matches!(
+            ctx.api_key
+                .create(&claim, request(permissions, expires))
+                .await,
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+
+    let created = ctx
+        .api_key
+        .create(&claim, request(&["read", "create"], None))
+        .await
+        .expect("key within the role should be created");
+    assert!(created.key.starts_with("mr_"));
+    let keys = ctx
+        .api_key
+        .list(&claim)
+        .await
+        .expect("keys should be listed");
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].id, created.id);
+    assert!(created.key.starts_with(&keys[0].prefix));
+    assert_ne!(keys[0].prefix, created.key);
+
+    let (key_claim, permissions) = ctx
+        .api_key
+        .authenticate(&created.key)
+        .await
+        .expect("key should authenticate");
+    assert_eq!(key_claim.id, user.id);
+    assert_eq!(key_claim.api_key.as_deref(), Some(created.id.as_str()));
+    assert_eq!(key_claim.role, Role::Author);
+    assert_eq!(permissions.len(), 2);
+    assert!(permissions.contains(&Permission::Create));
+    // a key cannot create keys with the full permissions of its owner
+    assert!(matches!(
+        ctx.api_key
+            .create(&key_claim, request(&["read"], None))
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+    assert!(ctx.api_key.authenticate("mr_unknown").await.is_err());
+
+    // demoting the owner shrinks the key
+    ctx.db
+        .users
+        .set_role(&user.id, Role::User)
+        .await
+        .expect("role should be updated");
+    let (key_claim, permissions) = ctx
+        .api_key
+        .authenticate(&created.key)
+        .await
+        .expect("key should authenticate");
+    assert_eq!(permissions, vec![Permission::Read]);
+    assert_eq!(key_claim.role, Role::User);
+
+    // staff keys without review do not see hidden content
+    ctx.db
+        .users
+        .set_role(&user.id, Role::Admin)
+        .await
+        .expect("role should be updated");
+    let (key_claim, _) = ctx
+        .api_key
+        .authenticate(&created.key)
+        .await
+        .expect("key should authenticate");
+    assert_eq!(key_claim.role, Role::Author);
+
+    let other = ctx
+        .register_user("scripts-other", "scripts-other@example.com", "password")
+        .await;
+    assert!(matches!(
+        ctx.api_key.revoke(&other.claim, &created.id).await,
+        Err(ApiError::NotFoundInDB)
+    ));
+    ctx.api_key
+        .revoke(&claim, &created.id)
+        .await
+        .expect("owner should revoke the key");
+    assert!(ctx.api_key.authenticate(&created.key).await.is_err());
+    assert!(ctx
+        .api_key
+        .list(&claim)
+        .await
+        .expect("keys should be listed")
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn duplicate_pages_and_chapters_are_detected() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("duplicates", "duplicates@example.com", "password")
+        .await;
+    let manga_id = ctx.create_manga(&user.id, "Duplicates", "manga").await;
+    let credit = "GF2VDuiBNgkW";
+    let versions = [
+        (
+            1.0,
+            "scan-a",
+            ["k5VlDPk4C47b", "IkprJIoekk6P", "0K4uGpSSozBf", credit],
+        ),
+        // one bit differs
+        (
+            1.0,
+            "scan-b",
+            ["kpVlDPk4C47b", "IkprJIoekk6P", "0K4uGpSSozBf", credit],
+        ),
+        (
+            2.0,
+            "scan-a",
+            ["rohtxlB3lex0", "k0yGfuBXunJJ", "jpLK4NFQV7FZ", credit],
+        ),
+        (
+            9.0,
+            "scan-a",
+            ["rohtxlB3lex0", "k0yGfuBXunJJ", "j5LK4NFQV7FZ", credit],
+        ),
+        (
+            3.0,
+            "scan-a",
+            ["RXmyqhAPu7NP", "46tYBfB2Wiuc", "ZWTq338UKnJm", credit],
+        ),
+        (
+            4.0,
+            "scan-a",
+            ["jEfiI9Fu3YxH", "tGr8W67iYfU7", "JhUtJjuoOwN8", credit],
+        ),
+    ];
+    let mut created = vec![];
+    for (episode, version, _) in &versions {
+        created.push(ctx.create_chapter(&manga_id, *episode, version, 4).await);
+    }
+    // the upload hashes are flushed in the background & would overwrite the fixed ones
+    ctx.wait_for_hashes(versions.len() * 4).await;
+    for (chapter, (_, _, hashes)) in created.iter().zip(&versions) {
+        let pages = ctx
+            .db
+            .chapter_versions
+            .get(&chapter.chapter_version_id)
+            .await
+            .expect("chapter version should exist")
+            .pages;
+        for (page, hash) in pages.iter().zip(hashes) {
+            ctx.db
+                .pages
+                .store_hash(&page.id().to_string(), (*hash).to_owned())
+                .await
+                .expect("hash should be stored");
+        }
+    }
+
+    let report = ctx
+        .duplicates
+        .report(&DuplicatesRequest::default())
+        .await
+        .expect("report should be created");
+    assert_eq!(report.shared_pages.len(), 1);
+    assert_eq!(report.shared_pages[0].chapters, 5);
+    assert_eq!(report.shared_pages[0].page_ids.len(), versions.len());
+
+    assert_eq!(report.identical_versions.len(), 1);
+    let pair = &report.identical_versions[0];
+    let first = pair.first.as_ref().expect("pair has a first version");
+    let second = pair.second.as_ref().expect("pair has a second version");
+    assert_eq!(first.chapter_id, created[0].chapter_id);
+    assert_eq!(second.chapter_id, created[0].chapter_id);
+    assert_ne!(first.version_id, second.version_id);
+    assert_eq!(pair.similarity, 1.0);
+
+    assert_eq!(report.repeated_chapters.len(), 1);
+    let pair = &report.repeated_chapters[0];
+    let mut chapters = [
+        pair.first
+            .as_ref()
+            .expect("pair has a first version")
+            .chapter,
+        pair.second
+            .as_ref()
+            .expect("pair has a second version")
+            .chapter,
+    ];
+    chapters.sort_by(f64::total_cmp);
+    assert_eq!(chapters, [2.0, 9.0]);
+
+    // the credit page is only shared if it is in enough chapters
+    let report = ctx
+        .duplicates
+        .report(&DuplicatesRequest {
+            min_shared_chapters: Some(6),
+            ..Default::default()
+        })
+        .await
+        .expect("report should be created");
+    assert!(report.shared_pages.is_empty());
+    assert_eq!(report.identical_versions.len(), 1);
+    assert_eq!(report.repeated_chapters.len(), 1);
+
+    assert!(matches!(
+        ctx.duplicates
+            .report(&DuplicatesRequest {
+                min_similarity: Some(0.0),
+                ..Default::default()
+            })
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+}
+
+#[actix_web::test]
+async fn duplicate_clusters_do_not_chain_and_follow_new_hashes() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("chained", "chained@example.com", "password")
+        .await;
+    let manga_id = ctx.create_manga(&user.id, "Chained", "manga").await;
+    // every hash differs from the previous one in 3 bits, the first & the last in 9
+    let hashes = [
+        "AAAAAAAAAAAA",
+        "HAAAAAAAAAAA",
+        "HHAAAAAAAAAA",
+        "HHHAAAAAAAAA",
+    ];
+    let mut pages = vec![];
+    for episode in 1..=hashes.len() {
+        let chapter = ctx
+            .create_chapter(&manga_id, episode as f64, "scan", 1)
+            .await;
+        pages.push(
+            ctx.db
+                .chapter_versions
+                .get(&chapter.chapter_version_id)
+                .await
+                .expect("chapter version should exist")
+                .pages[0]
+                .id()
+                .to_string(),
+        );
+    }
+    ctx.wait_for_hashes(hashes.len()).await;
+    for (page, hash) in pages.iter().zip(hashes) {
+        ctx.db
+            .pages
+            .store_hash(page, hash.to_owned())
+            .await
+            .expect("hash should be stored");
+    }
+
+    let request = DuplicatesRequest {
+        min_shared_chapters: Some(4),
+        ..Default::default()
+    };
+    let report = ctx
+        .duplicates
+        .report(&request)
+        .await
+        .expect("report should be created");
+    assert!(report.shared_pages.is_empty());
+
+    // the index is built once & only picks up the changed pages
+    for page in &pages {
+        ctx.db
+            .pages
+            .store_hash(page, hashes[0].to_owned())
+            .await
+            .expect("hash should be stored");
+    }
+    let report = ctx
+        .duplicates
+        .report(&request)
+        .await
+        .expect("report should be created");
+    assert_eq!(report.shared_pages.len(), 1);
+    assert_eq!(report.shared_pages[0].chapters, 4);
+
+    let report = ctx
+        .duplicates
+        .report(&DuplicatesRequest {
+            page: Some(2),
+            limit: Some(1),
+            ..request
+        })
+        .await
+        .expect("report should be created");
+    assert!(report.shared_pages.is_empty());
+    assert!(matches!(
+        ctx.duplicates
+            .report(&DuplicatesRequest {
+                limit: Some(0),
+                ..Default::default()
+            })
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+}
+
+async fn open_version(
+    ctx: &TestCtx,
+    manga_id: &str,
+    chapter_id: &str,
+    claim: &Claim,
+) -> Option<String> {
+    ctx.reader
+        .info(manga_id, Some(chapter_id.to_owned()), claim)
+        .await
+        .expect("reader info should load")
+        .open_version
+}
+
+#[actix_web::test]
+async fn reader_opens_the_best_version_of_a_chapter() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("quality", "quality@example.com", "password")
+        .await;
+    let manga_id = ctx.create_manga(&user.id, "Quality", "manga").await;
+    let low = ctx.create_chapter(&manga_id, 1.0, "scan-low", 3).await;
+    let high = ctx.create_chapter(&manga_id, 1.0, "scan-high", 3).await;
+    ctx.wait_for_hashes(6).await;
+    let pages = ctx
+        .db
+        .chapter_versions
+        .get(&high.chapter_version_id)
+        .await
+        .expect("chapter version should exist")
+        .pages;
+    ctx.db
+        .session
+        .query("UPDATE $pages SET width = 1600, height = 2400")
+        .bind(("pages", pages))
+        .await
+        .expect("pages should be updated")
+        .check()
+        .expect("pages should be updated");
+
+    assert_eq!(
+        open_version(&ctx, &manga_id, &low.chapter_id, &user.claim)
+            .await
+            .as_deref(),
+        Some(high.chapter_version_id.as_str())
+    );
+
+    let inferior = ctx
+        .duplicates
+        .inferior_versions(&DuplicatesRequest::default())
+        .await
+        .expect("inferior versions should load");
+    assert_eq!(inferior.len(), 1);
+    let version = inferior[0].version.as_ref().expect("version should be set");
+    let preferred = inferior[0]
+        .preferred
+        .as_ref()
+        .expect("preferred version should be set");
+    assert_eq!(version.chapter_version_id, low.chapter_version_id);
+    assert_eq!(preferred.chapter_version_id, high.chapter_version_id);
+    assert!(inferior[0].score < inferior[0].preferred_score);
+
+    // the preference for a manga wins over the default of the user
+    ctx.reader
+        .set_version_preference(
+            VersionPreferenceRequest {
+                manga_id: None,
+                best_quality: Some(false),
+            },
+            &user.claim,
+        )
+        .await
+        .expect("default should be set");
+    assert_eq!(
+        open_version(&ctx, &manga_id, &low.chapter_id, &user.claim).await,
+        None
+    );
+    ctx.reader
+        .set_version_preference(
+            VersionPreferenceRequest {
+                manga_id: Some(manga_id.clone()),
+                best_quality: Some(true),
+            },
+            &user.claim,
+        )
+        .await
+        .expect("manga preference should be set");
+    assert_eq!(
+        open_version(&ctx, &manga_id, &low.chapter_id, &user.claim)
+            .await
+            .as_deref(),
+        Some(high.chapter_version_id.as_str())
+    );
+    ctx.reader
+        .set_version_preference(
+            VersionPreferenceRequest {
+                manga_id: Some(manga_id.clone()),
+                best_quality: None,
+            },
+            &user.claim,
+        )
+        .await
+        .expect("manga preference should be removed");
+    assert_eq!(
+        open_version(&ctx, &manga_id, &low.chapter_id, &user.claim).await,
+        None
+    );
+
+    assert!(matches!(
+        ctx.reader
+            .set_version_preference(VersionPreferenceRequest::default(), &user.claim)
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+}
+
+/// Code from a verification or reset mail
+fn mail_code(body: &str) -> String {
+    body.split("code is ")
+        .nth(1)
+        .expect("mail should contain a code")
+        .chars()
+        .take(6)
+        .collect()
+}
+
+#[actix_web::test]
+async fn account_mails_deliver_codes_and_are_rate_limited() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("mailer", "Mailer@example.com", "password-1")
+        .await;
+    let mails = ctx.mails.sent();
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].to, "Mailer@example.com");
+    assert_eq!(mails[0].subject, "Verify your ManRead account");
+    assert!(mails[0].body.contains("Hi mailer,"), "{}", mails[0].body);
+    assert!(mails[0].body.contains("https://manread.example"));
+    assert!(mails[0].body.contains("expires in 60 minutes"));
+
+    let verified = ctx
+        .auth
+        .verify(
+            &mail_code(&mails[0].body),
+            &user.claim,
+            &ClientInfo::default(),
+        )
+        .await
+        .expect("mailed code should verify the user");
+    let verified_claim = ctx
+        .crypto
+        .get_claim(&verified.access_token)
+        .expect("verification jwt should decode");
+    assert_eq!(verified_claim.role, Role::User);
+    let mails = ctx.mails.sent();
+    assert_eq!(mails.len(), 2);
+    assert_eq!(mails[1].subject, "Welcome to ManRead");
+    assert!(matches!(
+        ctx.auth.resend_verification(&user.claim).await,
+        Err(ApiError::InvalidInput(_))
+    ));
+
+    ctx.auth
+        .request_reset_password(user.id.clone())
+        .await
+        .expect("request reset should send a mail");
+    let mails = ctx.mails.sent();
+    assert_eq!(mails.len(), 3);
+    assert_eq!(mails[2].subject, "Reset your ManRead password");
+    ctx.auth
+        .reset_password(
+            ResetPasswordRequest {
+                ident: "mailer".to_owned(),
+                email: false,
+                key: mail_code(&mails[2].body),
+                password: "password-2".to_owned(),
+            },
+            &ClientInfo::default(),
+        )
+        .await
+        .expect("mailed code should reset the password");
+
+    // 5 mails per address & hour
+    for _ in 0..2 {
+        ctx.auth
+            .request_reset_password(user.id.clone())
+            .await
+            .expect("request reset should send a mail");
+    }
+    assert!(matches!(
+        ctx.auth.request_reset_password(user.id.clone()).await,
+        Err(ApiError::TooManyRequests)
+    ));
+    assert_eq!(ctx.mails.sent().len(), 5);
+
+    let other = ctx
+        .register_user("mailer-2", "mailer-2@example.com", "password")
+        .await;
+    ctx.auth
+        .resend_verification(&other.claim)
+        .await
+        .expect("other addresses are not limited");
+    assert_eq!(ctx.mails.sent().len(), 7);
+
+    let templates = std::env::temp_dir().join(format!("apiv2-mail-{}", helper::random_string(8)));
+    std::fs::create_dir_all(&templates).expect("template folder should be created");
+    std::fs::write(templates.join("welcome.txt"), "Hello {{name}}")
+        .expect("template should be written");
+    assert!(Mailer::new(None, String::new(), String::new())
+        .load_templates(&templates)
+        .is_err());
+}
+
 #[actix_web::test]
 async fn auth_register_rejects_case_only_duplicate_identity() {
     let ctx = TestCtx::new().await;
@@ -542,6 +1273,7 @@ async fn auth_register_rejects_case_only_duplicate_identity() {
             Gender::Unknown,
             Utc::now(),
             Some(FileId::new(icon_a)),
+            &ClientInfo::default(),
         )
         .await
         .expect("first registration should succeed");
@@ -556,6 +1288,7 @@ async fn auth_register_rejects_case_only_duplicate_identity() {
                 Gender::Unknown,
                 Utc::now(),
                 Some(FileId::new(icon_b)),
+                &ClientInfo::default(),
             )
             .await,
         Err(ApiError::EmailExists)
@@ -571,6 +1304,7 @@ async fn auth_register_rejects_case_only_duplicate_identity() {
                 Gender::Unknown,
                 Utc::now(),
                 Some(FileId::new(icon_c)),
+                &ClientInfo::default(),
             )
             .await,
         Err(ApiError::NameExists)
@@ -675,10 +1409,13 @@ async fn user_delete_sets_disabled_flag_without_removing_record() {
 
     assert!(matches!(
         ctx.auth
-            .login(LoginRequest::Username(LoginWithUsernameAndPassword {
-                username: "todo".to_owned(),
-                password: "password".to_owned(),
-            }))
+            .login(
+                LoginRequest::Username(LoginWithUsernameAndPassword {
+                    username: "todo".to_owned(),
+                    password: "password".to_owned(),
+                }),
+                &ClientInfo::default()
+            )
             .await,
         Err(ApiError::NotFoundInDB)
     ));
@@ -694,7 +1431,7 @@ async fn manga_actions_cover_create_search_edit_and_state_transitions() {
 
     let info = ctx
         .manga
-        .info(manga_id.clone(), &user.id)
+        .info(manga_id.clone(), &user.claim)
         .await
         .expect("manga info should succeed");
     assert_eq!(info.kind, "manhwa");
@@ -740,6 +1477,7 @@ async fn manga_actions_cover_create_search_edit_and_state_transitions() {
             scrapers: vec![v1::Scraper {
                 channel: "en".to_owned(),
                 url: "https://scraper.example/new".to_owned(),
+                auto_approve: false,
             }],
         })
         .await
@@ -747,7 +1485,7 @@ async fn manga_actions_cover_create_search_edit_and_state_transitions() {
 
     let after_edit = ctx
         .manga
-        .info(manga_id.clone(), &user.id)
+        .info(manga_id.clone(), &user.claim)
         .await
         .expect("edited manga should still load");
     assert_eq!(after_edit.kind, "manga");
@@ -761,14 +1499,14 @@ async fn manga_actions_cover_create_search_edit_and_state_transitions() {
 
     let (search, _) = ctx
         .manga
-        .search(search_by_title("Redux"), &user.id)
+        .search(search_by_title("Redux"), &user.claim)
         .await
         .expect("search should succeed");
     assert!(search.iter().any(|entry| entry.manga_id == manga_id));
 
     let home = ctx
         .manga
-        .home(&user.id)
+        .home(&user.claim)
         .await
         .expect("home generation should succeed");
     assert!(!home.newest.is_empty());
@@ -828,25 +1566,87 @@ async fn manga_actions_cover_create_search_edit_and_state_transitions() {
         .delete(&manga_id)
         .await
         .expect("delete request should set review visibility");
+    let moderator = Claim::new_access(user.id.clone(), Role::Moderator);
+    assert!(matches!(
+        ctx.manga.info(manga_id.clone(), &user.claim).await,
+        Err(ApiError::NotFoundInDB)
+    ));
     let review = ctx
         .manga
-        .info(manga_id.clone(), &user.id)
+        .info(manga_id.clone(), &moderator)
         .await
-        .expect("manga should still be readable");
+        .expect("manga should still be readable by moderators");
     assert_eq!(review.visibility, v1::Visibility::AdminReview);
 
     ctx.manga
         .confirm_delete(&manga_id)
         .await
         .expect("confirm delete should hide manga");
+    assert!(matches!(
+        ctx.manga.info(manga_id.clone(), &user.claim).await,
+        Err(ApiError::NotFoundInDB)
+    ));
     let hidden = ctx
         .manga
-        .info(manga_id, &user.id)
+        .info(manga_id, &moderator)
         .await
-        .expect("manga should still be readable");
+        .expect("manga should still be readable by moderators");
     assert_eq!(hidden.visibility, v1::Visibility::Hidden);
 }
 
+#[actix_web::test]
+async fn hidden_mangas_are_filtered_by_role_and_uploader() {
+    let ctx = TestCtx::new().await;
+    let uploader = ctx
+        .register_user("hidden-uploader", "hidden-uploader@example.com", "password")
+        .await;
+    let other = ctx
+        .register_user("hidden-other", "hidden-other@example.com", "password")
+        .await;
+    let manga_id = ctx
+        .create_manga(&uploader.id, "Hidden Visibility Manga", "manga")
+        .await;
+    ctx.db
+        .mangas
+        .set_visibility(&manga_id, v1::Visibility::Hidden)
+        .await
+        .expect("visibility should be set");
+
+    let manga = &ctx.manga;
+    let search = move |claim: Claim| async move {
+        manga
+            .search(search_by_title("Hidden Visibility"), &claim)
+            .await
+            .expect("search should succeed")
+            .1
+    };
+    let author = Claim::new_access(uploader.id.clone(), Role::Author);
+    let other_author = Claim::new_access(other.id.clone(), Role::Author);
+    assert_eq!(search(uploader.claim.clone()).await, 0);
+    assert_eq!(search(other_author.clone()).await, 0);
+    assert_eq!(search(author.clone()).await, 1);
+    assert_eq!(
+        search(Claim::new_access(other.id.clone(), Role::Admin)).await,
+        1
+    );
+
+    assert!(ctx.manga.info(manga_id.clone(), &author).await.is_ok());
+    assert!(matches!(
+        ctx.manga.info(manga_id.clone(), &other_author).await,
+        Err(ApiError::NotFoundInDB)
+    ));
+    assert!(matches!(
+        ctx.reader.info(&manga_id, None, &other.claim).await,
+        Err(ApiError::NotFoundInDB)
+    ));
+    assert!(matches!(
+        ctx.list
+            .add_to_list("favorites", &manga_id, &other.claim)
+            .await,
+        Err(ApiError::NotFoundInDB)
+    ));
+}
+
 #[actix_web::test]
 async fn manga_export_emits_protobuf_metadata_and_indexed_images() {
     let ctx = TestCtx::new().await;
@@ -976,7 +1776,7 @@ async fn manga_restore_rebuilds_exported_page_objects() {
 
     let (results, _) = ctx
         .manga
-        .search(search_by_title("Restore Manga"), &user.id)
+        .search(search_by_title("Restore Manga"), &user.claim)
         .await
         .expect("restored manga should be searchable");
     assert!(
@@ -990,7 +1790,7 @@ async fn manga_restore_rebuilds_exported_page_objects() {
         .expect("a restored manga copy should exist");
     let info = ctx
         .manga
-        .info(restored.manga_id.clone(), &user.id)
+        .info(restored.manga_id.clone(), &user.claim)
         .await
         .expect("restored manga info should load");
     assert_eq!(info.chapters.len(), 1);
@@ -1061,14 +1861,14 @@ async fn manga_restore_creates_manga_and_chapter_records_when_missing() {
 
     let (found, _) = target
         .manga
-        .search(search_by_title("Restore Source Manga"), &target_user.id)
+        .search(search_by_title("Restore Source Manga"), &target_user.claim)
         .await
         .expect("restored manga should be searchable");
     assert_eq!(found.len(), 1);
 
     let info = target
         .manga
-        .info(found[0].manga_id.clone(), &target_user.id)
+        .info(found[0].manga_id.clone(), &target_user.claim)
         .await
         .expect("restored manga info should load");
     assert_eq!(info.chapters.len(), 1);
@@ -1096,12 +1896,12 @@ async fn manga_relation_actions_are_symmetric() {
         .expect("add relation should succeed");
     let first_info = ctx
         .manga
-        .info(first.clone(), &user.id)
+        .info(first.clone(), &user.claim)
         .await
         .expect("first info should load");
     let second_info = ctx
         .manga
-        .info(second.clone(), &user.id)
+        .info(second.clone(), &user.claim)
         .await
         .expect("second info should load");
     assert!(first_info
@@ -1119,12 +1919,12 @@ async fn manga_relation_actions_are_symmetric() {
         .expect("remove relation should succeed");
     let first_after = ctx
         .manga
-        .info(first, &user.id)
+        .info(first, &user.claim)
         .await
         .expect("first info should load");
     let second_after = ctx
         .manga
-        .info(second, &user.id)
+        .info(second, &user.claim)
         .await
         .expect("second info should load");
     assert!(first_after.relations.is_empty());
@@ -1253,7 +2053,7 @@ async fn chapter_and_reader_actions_cover_add_edit_info_pages_progress_and_delet
 
     let pages = ctx
         .reader
-        .pages(&first.chapter_version_id)
+        .pages(&first.chapter_version_id, &user.claim)
         .await
         .expect("page info should load");
     assert_eq!(pages.pages.len(), 2);
@@ -1264,7 +2064,7 @@ async fn chapter_and_reader_actions_cover_add_edit_info_pages_progress_and_delet
         .expect("chapter delete should succeed");
     let manga_after = ctx
         .manga
-        .info(manga_id, &user.id)
+        .info(manga_id, &user.claim)
         .await
         .expect("manga info should load");
     assert!(!manga_after
@@ -1316,7 +2116,9 @@ async fn chapter_delete_removes_pages_and_storage_objects() {
         .expect("chapter delete should succeed");
 
     assert!(matches!(
-        ctx.reader.pages(&chapter.chapter_version_id).await,
+        ctx.reader
+            .pages(&chapter.chapter_version_id, &user.claim)
+            .await,
         Err(ApiError::NotFoundInDB)
     ));
     let remaining_pages = ctx
@@ -1425,7 +2227,7 @@ async fn chapter_version_actions_cover_edit_list_and_delete() {
         .keys()
         .any(|key| key.ends_with(&second_version.version_id)));
     assert!(matches!(
-        ctx.reader.pages(&german_connection).await,
+        ctx.reader.pages(&german_connection, &user.claim).await,
         Err(ApiError::NotFoundInDB)
     ));
     let remaining_pages = ctx
@@ -1493,6 +2295,531 @@ async fn adding_new_chapter_recomputes_continue_reading_state() {
     assert_eq!(after.progress, 0.0);
 }
 
+#[actix_web::test]
+async fn reads_list_additions_and_views_rank_trending_mangas() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("trending", "trending@example.com", "password")
+        .await;
+    let quiet = ctx.create_manga(&user.id, "Quiet Manga", "manga").await;
+    let popular = ctx.create_manga(&user.id, "Popular Manga", "manga").await;
+    let chapter = ctx.create_chapter(&popular, 1.0, "en", 1).await;
+
+    ctx.list
+        .add("favorites", &user.claim)
+        .await
+        .expect("list add should succeed");
+    ctx.list
+        .add_to_list("favorites", &popular, &user.claim)
+        .await
+        .expect("add to list should succeed");
+    // a manga is only counted the first time a user adds it to a list
+    ctx.list
+        .add("reading", &user.claim)
+        .await
+        .expect("list add should succeed");
+    ctx.list
+        .add_to_list("reading", &popular, &user.claim)
+        .await
+        .expect("add to list should succeed");
+    ctx.list
+        .remove_from_list("favorites", &popular, &user.claim)
+        .await
+        .expect("remove from list should succeed");
+    ctx.list
+        .add_to_list("favorites", &popular, &user.claim)
+        .await
+        .expect("add to list should succeed");
+    for _ in 0..2 {
+        // a chapter is viewed once per hour
+        ctx.reader
+            .pages(&chapter.chapter_version_id, &user.claim)
+            .await
+            .expect("pages should load");
+    }
+    for _ in 0..2 {
+        // only the first time a chapter is finished counts as read
+        ctx.reader
+            .save_progress(1.0, &chapter.chapter_id, &user.claim)
+            .await
+            .expect("progress write should succeed");
+    }
+
+    let score = ctx
+        .db
+        .popularity
+        .get(&popular)
+        .await
+        .expect("popularity should load");
+    assert_eq!(score.popularity, 5.0 + 1.0 + 3.0);
+    assert_eq!(score.trending_7d, 0.0, "trending is only set by the job");
+
+    let updated = ctx
+        .db
+        .popularity
+        .update_trending()
+        .await
+        .expect("trending update should succeed");
+    assert_eq!(updated, 1);
+    let score = ctx
+        .db
+        .popularity
+        .get(&popular)
+        .await
+        .expect("popularity should load");
+    assert!(score.trending_24h > 0.0);
+    assert!(score.trending_7d > 0.0);
+
+    for order in ["trending", "popularity"] {
+        let mut request = search_all();
+        request.order = order.to_owned();
+        let (items, total) = ctx
+            .manga
+            .search(request, &user.claim)
+            .await
+            .expect("search should succeed");
+        assert_eq!(total, 2);
+        assert_eq!(items[0].manga_id, popular, "{order}");
+        assert_eq!(items[1].manga_id, quiet, "{order}");
+    }
+
+    let home = ctx
+        .manga
+        .home(&user.claim)
+        .await
+        .expect("home should succeed");
+    assert_eq!(home.trending[0].manga_id, popular);
+}
+
+#[actix_web::test]
+async fn full_text_search_ranks_and_highlights_titles() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("fulltext", "fulltext@example.com", "password")
+        .await;
+    let title = ctx.create_manga(&user.id, "Pokémon Special", "manga").await;
+    let description = ctx.create_manga(&user.id, "Digimon", "manga").await;
+    ctx.create_manga(&user.id, "Yu-Gi-Oh", "manga").await;
+    ctx.db
+        .session
+        .query("UPDATE type::thing('mangas', $id) SET description = 'a pokemon parody'")
+        .bind(("id", description.clone()))
+        .await
+        .expect("description update should succeed")
+        .check()
+        .expect("description update should succeed");
+
+    // accents & kana are transliterated, incomplete words match by prefix
+    for query in ["pokemon", "POKÉMON", "ポケモン", "poke"] {
+        let (items, total) = ctx
+            .manga
+            .search(search_by_title(query), &user.claim)
+            .await
+            .expect("search should succeed");
+        assert_eq!(total, 1, "{query}");
+        assert_eq!(items[0].manga_id, title, "{query}");
+        let highlight = items[0].highlight.as_deref().expect("title is highlighted");
+        assert!(highlight.contains("<mark>"), "{highlight}");
+        assert!(highlight.contains("Special"), "{highlight}");
+    }
+
+    let mut request = search_all();
+    request.order = "relevance".to_owned();
+    request.query.items = vec![ItemOrArray::Item(Item::new(ItemData::enum_("pokemon")))];
+    let (items, total) = ctx
+        .manga
+        .search(request, &user.claim)
+        .await
+        .expect("search should succeed");
+    assert_eq!(total, 2);
+    assert_eq!(items[0].manga_id, title);
+    assert_eq!(items[1].manga_id, description);
+    assert_eq!(items[1].highlight, None);
+}
+
+#[actix_web::test]
+async fn search_suggest_completes_fields_and_values_at_cursor() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("suggest", "suggest@example.com", "password")
+        .await;
+    ctx.create_manga(&user.id, "Suggest Manga", "manga").await;
+    ctx.list
+        .add("favorites", &user.claim)
+        .await
+        .expect("list add should succeed");
+
+    let suggest = |query: &str, cursor: u32| {
+        ctx.manga.suggest(
+            SuggestRequest {
+                query: query.to_owned(),
+                cursor,
+            },
+            &user.claim,
+        )
+    };
+    let suggestion = |kind, text: &str, start, end| Suggestion {
+        kind,
+        text: text.to_owned(),
+        start,
+        end,
+    };
+
+    let fields = suggest("ta", 2).await.expect("suggest should succeed");
+    assert_eq!(
+        fields,
+        vec![suggestion(SuggestionKind::Field, "tag:", 0, 2)]
+    );
+    let fields = suggest("action next", 11)
+        .await
+        .expect("suggest should succeed");
+    assert_eq!(
+        fields,
+        vec![suggestion(SuggestionKind::Field, "next-available", 7, 11)]
+    );
+
+    let tags = suggest("kind:manga t:adv", 16)
+        .await
+        .expect("suggest should succeed");
+    assert_eq!(
+        tags,
+        vec![suggestion(SuggestionKind::Tag, "adventure", 13, 16)]
+    );
+    let tags = suggest("tag:\"act", 8)
+        .await
+        .expect("suggest should succeed");
+    assert_eq!(tags, vec![suggestion(SuggestionKind::Tag, "action", 4, 8)]);
+    // tags of the manga have no sex
+    assert_eq!(
+        suggest("n!:act", 6)
+            .await
+            .expect("suggest should succeed")
+            .len(),
+        1
+    );
+    assert!(suggest("m:act", 5)
+        .await
+        .expect("suggest should succeed")
+        .is_empty());
+
+    let authors = suggest("a:auth", 6).await.expect("suggest should succeed");
+    assert_eq!(
+        authors,
+        vec![suggestion(SuggestionKind::Author, "author-a", 2, 6)]
+    );
+    let lists = suggest("l: x", 2).await.expect("suggest should succeed");
+    assert_eq!(
+        lists,
+        vec![suggestion(SuggestionKind::List, "favorites", 2, 2)]
+    );
+    let kinds = suggest("kind:MA", 7).await.expect("suggest should succeed");
+    assert_eq!(kinds, vec![suggestion(SuggestionKind::Kind, "manga", 5, 7)]);
+    assert!(suggest("status:", 7)
+        .await
+        .expect("suggest should succeed")
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn saved_searches_are_smart_lists_and_usable_in_queries() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("saved", "saved@example.com", "password")
+        .await;
+    let other = ctx
+        .register_user("saved-other", "saved-other@example.com", "password")
+        .await;
+    let wanted = ctx.create_manga(&user.id, "Saved Wanted", "manga").await;
+    ctx.create_manga(&user.id, "Something Else", "manga").await;
+
+    let mut search = search_by_title("wanted");
+    search.limit = 1;
+    ctx.list
+        .save_search(
+            SaveSearchRequest {
+                name: "wanted titles".to_owned(),
+                search,
+            },
+            &user.claim,
+        )
+        .await
+        .expect("save search should succeed");
+    assert!(matches!(
+        ctx.list
+            .save_search(
+                SaveSearchRequest {
+                    name: "bad/name".to_owned(),
+                    search: search_all(),
+                },
+                &user.claim,
+            )
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+
+    let saved = ctx
+        .list
+        .saved_searches(&user.claim)
+        .await
+        .expect("saved searches should be listed");
+    assert_eq!(saved, vec!["wanted titles".to_owned()]);
+    let lists = ctx
+        .list
+        .list(&user.claim)
+        .await
+        .expect("list list should succeed");
+    assert_eq!(lists.smart, saved);
+    assert!(ctx
+        .list
+        .saved_searches(&other.claim)
+        .await
+        .expect("saved searches should be listed")
+        .is_empty());
+    assert!(ctx
+        .list
+        .list(&other.claim)
+        .await
+        .expect("list list should succeed")
+        .smart
+        .is_empty());
+
+    let (items, total) = ctx
+        .manga
+        .search_saved(
+            SavedSearchPageRequest {
+                name: "wanted titles".to_owned(),
+                page: 1,
+            },
+            &user.claim,
+        )
+        .await
+        .expect("saved search should succeed");
+    assert_eq!(total, 1);
+    assert_eq!(items[0].manga_id, wanted);
+
+    // saved searches can be referenced & negated inside other queries
+    let saved = |name: &str, not: bool| {
+        let mut item = Item::new(ItemData {
+            name: "saved".to_owned(),
+            value: ItemValue::String(name.to_owned()),
+        });
+        item.not = not;
+        ItemOrArray::Item(item)
+    };
+    let mut request = search_all();
+    request.query.items = vec![saved("wanted titles", false)];
+    let (items, total) = ctx
+        .manga
+        .search(request, &user.claim)
+        .await
+        .expect("search should succeed");
+    assert_eq!(total, 1);
+    assert_eq!(items[0].manga_id, wanted);
+
+    let mut request = search_all();
+    request.query.items = vec![saved("wanted titles", true)];
+    let (items, total) = ctx
+        .manga
+        .search(request, &user.claim)
+        .await
+        .expect("search should succeed");
+    assert_eq!(total, 1);
+    assert_ne!(items[0].manga_id, wanted);
+
+    // other users cannot reference the search
+    let mut request = search_all();
+    request.query.items = vec![saved("wanted titles", false)];
+    assert!(matches!(
+        ctx.manga.search(request, &other.claim).await,
+        Err(ApiError::InvalidInput(_))
+    ));
+
+    // saved searches can only reference existing searches & not themselves
+    for (name, reference) in [("missing", "unknown"), ("wanted titles", "wanted titles")] {
+        let mut search = search_all();
+        search.query.items = vec![saved(reference, false)];
+        assert!(matches!(
+            ctx.list
+                .save_search(
+                    SaveSearchRequest {
+                        name: name.to_owned(),
+                        search,
+                    },
+                    &user.claim,
+                )
+                .await,
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+
+    let suggestions = ctx
+        .manga
+        .suggest(
+            SuggestRequest {
+                query: "saved:wa".to_owned(),
+                cursor: 8,
+            },
+            &user.claim,
+        )
+        .await
+        .expect("suggest should succeed");
+    assert_eq!(
+        suggestions,
+        vec![Suggestion {
+            kind: SuggestionKind::SavedSearch,
+            text: "\"wanted titles\"".to_owned(),
+            start: 6,
+            end: 8,
+        }]
+    );
+
+    ctx.list
+        .delete_search("wanted titles", &user.claim)
+        .await
+        .expect("delete search should succeed");
+    assert!(matches!(
+        ctx.manga
+            .search_saved(
+                SavedSearchPageRequest {
+                    name: "wanted titles".to_owned(),
+                    page: 1,
+                },
+                &user.claim,
+            )
+            .await,
+        Err(ApiError::NotFoundInDB)
+    ));
+}
+
+#[actix_web::test]
+async fn new_chapters_notify_favorites_and_readers() {
+    let ctx = TestCtx::new().await;
+    let uploader = ctx
+        .register_user("notify-uploader", "notify-uploader@example.com", "password")
+        .await;
+    let reader = ctx
+        .register_user("notify-reader", "notify-reader@example.com", "password")
+        .await;
+    let manga_id = ctx
+        .create_manga(&uploader.id, "Notify Manga", "manga")
+        .await;
+    ctx.list
+        .add("favorites", &reader.claim)
+        .await
+        .expect("list add should succeed");
+    ctx.list
+        .add_to_list("favorites", &manga_id, &reader.claim)
+        .await
+        .expect("add to list should succeed");
+
+    let chapter = ctx.create_chapter(&manga_id, 1.0, "en", 1).await;
+    let all = NotificationsRequest {
+        page: 1,
+        limit: 10,
+        unread_only: false,
+    };
+    let notifications = ctx
+        .notification
+        .list(all.clone(), &reader.claim)
+        .await
+        .expect("notification list should succeed");
+    assert_eq!(notifications.unread, 1);
+    assert_eq!(notifications.items.len(), 1);
+    assert_eq!(notifications.items[0].manga_id, manga_id);
+    assert_eq!(notifications.items[0].chapter_id, chapter.chapter_id);
+    assert_eq!(notifications.items[0].chapter, 1.0);
+    assert!(ctx
+        .notification
+        .list(all.clone(), &uploader.claim)
+        .await
+        .expect("notification list should succeed")
+        .items
+        .is_empty());
+
+    // push is the default channel
+    assert_eq!(ctx.notification.deliver(100).await.unwrap(), 1);
+    assert_eq!(ctx.pushes.sent().len(), 1);
+    assert_eq!(ctx.notification.deliver(100).await.unwrap(), 0);
+
+    ctx.notification
+        .settings(
+            NotificationSettingsRequest {
+                mode: v1::NotificationMode::Email as i32,
+                webhook: None,
+            },
+            &reader.claim,
+        )
+        .await
+        .expect("settings should succeed");
+    ctx.create_chapter(&manga_id, 2.0, "en", 1).await;
+    assert_eq!(ctx.notification.deliver(100).await.unwrap(), 1);
+    let emails = ctx.emails.sent();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].0.email, "notify-reader@example.com");
+    assert_eq!(emails[0].1.title, "New chapter of Notify Manga");
+    assert_eq!(ctx.pushes.sent().len(), 1);
+
+    // no webhook backend is configured & endpoints must use https
+    assert!(matches!(
+        ctx.notification
+            .settings(
+                NotificationSettingsRequest {
+                    mode: v1::NotificationMode::Webhook as i32,
+                    webhook: Some("https://hook.example".to_owned()),
+                },
+                &reader.claim,
+            )
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        ctx.notification
+            .subscribe(
+                PushSubscriptionRequest {
+                    endpoint: "http://push.example/endpoint".to_owned(),
+                    p256dh: "key".to_owned(),
+                    auth: "auth".to_owned(),
+                },
+                &reader.claim,
+            )
+            .await,
+        Err(ApiError::InvalidInput(_))
+    ));
+
+    ctx.notification
+        .mark_read(MarkNotificationsReadRequest { ids: vec![] }, &reader.claim)
+        .await
+        .expect("mark read should succeed");
+    let notifications = ctx
+        .notification
+        .list(
+            NotificationsRequest {
+                unread_only: true,
+                ..all.clone()
+            },
+            &reader.claim,
+        )
+        .await
+        .expect("notification list should succeed");
+    assert_eq!(notifications.unread, 0);
+    assert!(notifications.items.is_empty());
+
+    // readers who cannot see the manga anymore are not notified
+    ctx.db
+        .mangas
+        .set_visibility(&manga_id, v1::Visibility::Hidden)
+        .await
+        .expect("visibility should be set");
+    ctx.create_chapter(&manga_id, 3.0, "en", 1).await;
+    let notifications = ctx
+        .notification
+        .list(all, &reader.claim)
+        .await
+        .expect("notification list should succeed");
+    assert_eq!(notifications.items.len(), 2);
+    assert_eq!(notifications.unread, 0);
+}
+
 #[actix_web::test]
 async fn list_tag_and_kind_actions_interact_with_manga_and_reader_state() {
     let ctx = TestCtx::new().await;
@@ -1519,9 +2846,13 @@ async fn list_tag_and_kind_actions_interact_with_manga_and_reader_state() {
         .list(&user.claim)
         .await
         .expect("list list should succeed");
-    assert!(lists.contains(&"favorites".to_owned()));
+    assert!(lists.lists.contains(&"favorites".to_owned()));
 
-    let home = ctx.manga.home(&user.id).await.expect("home should succeed");
+    let home = ctx
+        .manga
+        .home(&user.claim)
+        .await
+        .expect("home should succeed");
     assert!(home
         .favorites
         .iter()
@@ -1531,7 +2862,11 @@ async fn list_tag_and_kind_actions_interact_with_manga_and_reader_state() {
         .save_progress(0.5, &chapter.chapter_id, &user.claim)
         .await
         .expect("progress write should succeed");
-    let home_with_progress = ctx.manga.home(&user.id).await.expect("home should succeed");
+    let home_with_progress = ctx
+        .manga
+        .home(&user.claim)
+        .await
+        .expect("home should succeed");
     assert!(home_with_progress
         .reading
         .iter()
@@ -1539,7 +2874,7 @@ async fn list_tag_and_kind_actions_interact_with_manga_and_reader_state() {
 
     let home_other_user = ctx
         .manga
-        .home(&other_user.id)
+        .home(&other_user.claim)
         .await
         .expect("home should succeed");
     assert!(!home_other_user
@@ -1680,7 +3015,7 @@ async fn manga_search_rejects_invalid_order() {
     let mut invalid = search_all();
     invalid.order = "no-such-order".to_owned();
     assert!(matches!(
-        ctx.manga.search(invalid, &user.id).await,
+        ctx.manga.search(invalid, &user.claim).await,
         Err(ApiError::InvalidInput(_))
     ));
 }
@@ -1820,15 +3155,20 @@ async fn auth_refresh_rejects_access_token() {
 
     let issued = ctx
         .auth
-        .login(LoginRequest::Username(LoginWithUsernameAndPassword {
-            username: "refresh-guard".to_owned(),
-            password: "password".to_owned(),
-        }))
+        .login(
+            LoginRequest::Username(LoginWithUsernameAndPassword {
+                username: "refresh-guard".to_owned(),
+                password: "password".to_owned(),
+            }),
+            &ClientInfo::default(),
+        )
         .await
         .expect("login should succeed");
 
     assert!(matches!(
-        ctx.auth.refresh(&issued.access_token).await,
+        ctx.auth
+            .refresh(&issued.access_token, &ClientInfo::default())
+            .await,
         Err(ApiError::InvalidInput(_))
     ));
 }
@@ -1917,7 +3257,7 @@ async fn manga_add_relation_requires_existing_relation_target() {
 
     let info = ctx
         .manga
-        .info(manga_id, &user.id)
+        .info(manga_id, &user.claim)
         .await
         .expect("manga info should still load");
     assert!(info.relations.is_empty());
@@ -1953,6 +3293,7 @@ async fn manga_create_rejects_blank_nested_values() {
                     scrapers: vec![v1::Scraper {
                         channel: "en".to_owned(),
                         url: "https://scraper.example/feed".to_owned(),
+                        auto_approve: false,
                     }],
                 },
                 &user.id,
@@ -2008,3 +3349,541 @@ async fn chapter_add_rejects_empty_titles_and_sources() {
         Err(ApiError::InvalidInput(_))
     ));
 }
+
+struct FakeSite;
+
+impl scraper_module::Register for FakeSite {
+    fn get_used_processor_names(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn url_matches(&self, url: &str) -> bool {
+        url.starts_with("https://scraper.example")
+    }
+
+    fn icon(&self) -> (String, Vec<u8>) {
+        ("image/png".to_owned(), PNG_1X1.to_vec())
+    }
+
+    fn icon_source(&self) -> Option<String> {
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl scraper_module::ReaderScraper for FakeSite {
+    fn multi(&self, _: &str) -> scraper_module::Mode {
+        scraper_module::Mode::Single
+    }
+
+    async fn download_file(&self, _: &str) -> scraper_module::ScraperResult<Vec<u8>> {
+        Ok(PNG_1X1.to_vec())
+    }
+
+    async fn scrape_pages(&self, url: &str) -> scraper_module::ScraperResult<Vec<String>> {
+        Ok(vec![format!("{url}/1.png"), format!("{url}/2.png")])
+    }
+
+    async fn scrape_chapters(
+        &self,
+        _: &str,
+    ) -> scraper_module::ScraperResult<Vec<scraper_module::ScrapedChapter>> {
+        Ok((1..=2)
+            .map(|chapter| scraper_module::ScrapedChapter {
+                names: vec![],
+                chapter: chapter as f64,
+                url: format!("https://scraper.example/chapter/{chapter}"),
+                tags: vec![],
+            })
+            .collect())
+    }
+}
+
+fn fake_scraper_actions(ctx: &TestCtx) -> ScraperActions {
+    let site = Arc::new(FakeSite);
+    ScraperActions {
+        services: Arc::new(manga_scraper::init::Services::new(vec![
+            manga_scraper::init::Service {
+                uri: "fake".to_owned(),
+                register: site.clone(),
+                searchers: None,
+                metadata: None,
+                reader: Some(site),
+            },
+        ])),
+        mangas: ctx.db.mangas.clone(),
+        scraper: ctx.db.scraper.clone(),
+        versions: ctx.db.versions.clone(),
+        chapters: ChapterActions {
+            chapters: ctx.db.chapters.clone(),
+            tags: ctx.db.tags.clone(),
+            versions: ctx.db.versions.clone(),
+            chapter_versions: ctx.db.chapter_versions.clone(),
+            mangas: ctx.db.mangas.clone(),
+            pages: ctx.db.pages.clone(),
+            fs: ctx.storage.clone(),
+        },
+        fs: ctx.storage.clone(),
+    }
+}
+
+#[actix_web::test]
+async fn scraper_actions_refresh_and_download_approved_entries() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("scraper", "scraper@example.com", "password")
+        .await;
+    let manga_id = ctx.create_manga(&user.id, "Scraped Manga", "manga").await;
+
+    let actions = fake_scraper_actions(&ctx);
+    assert!(actions.refresh("unknown", Duration::ZERO).await.is_err());
+
+    actions
+        .refresh("fake", Duration::ZERO)
+        .await
+        .expect("refresh should succeed");
+    actions
+        .refresh("fake", Duration::ZERO)
+        .await
+        .expect("second refresh should succeed");
+    let manga = db::RecordIdType::from((db::manga::Manga::name(), manga_id.as_str()));
+    let version = ctx
+        .db
+        .versions
+        .get("en")
+        .await
+        .expect("version should exist");
+    let entries = ctx
+        .db
+        .scraper
+        .get_items(manga.clone(), version.clone())
+        .await
+        .expect("entries should load");
+    assert_eq!(entries.len(), 2);
+
+    // refreshed recently, so it is skipped
+    actions
+        .refresh("fake", Duration::from_secs(60))
+        .await
+        .expect("refresh should succeed");
+
+    ctx.db
+        .session
+        .query("UPDATE scraper_entry SET state = 'Approved' WHERE data.chapter = 1")
+        .await
+        .expect("entry should be approved");
+    actions
+        .download("fake")
+        .await
+        .expect("download should succeed");
+
+    let chapter = ctx
+        .db
+        .chapters
+        .get(&manga_id, 1.0)
+        .await
+        .expect("chapter should be created");
+    assert_eq!(chapter.data.titles, vec!["Chapter 1".to_owned()]);
+    assert_eq!(
+        chapter.data.sources,
+        vec!["https://scraper.example/chapter/1".to_owned()]
+    );
+    assert_eq!(chapter.data.versions.len(), 1);
+    assert!(ctx.db.chapters.get(&manga_id, 2.0).await.is_err());
+    assert!(ctx
+        .db
+        .scraper
+        .approved()
+        .await
+        .expect("approved entries should load")
+        .is_empty());
+    assert_eq!(
+        ctx.db
+            .scraper
+            .get_items(manga, version)
+            .await
+            .expect("entries should load")
+            .len(),
+        1
+    );
+}
+
+#[actix_web::test]
+async fn scraper_review_lists_groups_and_changes_state() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("reviewer", "reviewer@example.com", "password")
+        .await;
+    let manga_id = ctx.create_manga(&user.id, "Reviewed Manga", "manga").await;
+    let actions = fake_scraper_actions(&ctx);
+    actions
+        .refresh("fake", Duration::ZERO)
+        .await
+        .expect("refresh should succeed");
+
+    let groups = actions.pending(1, 10).await.expect("pending should load");
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].manga_id, manga_id);
+    assert_eq!(groups[0].version, "en");
+    assert_eq!(
+        groups[0].titles.get("en").map(|v| v.items.clone()),
+        Some(vec!["Reviewed Manga".to_owned()])
+    );
+    let entries = &groups[0].entries;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].chapter, 1.0);
+    assert_eq!(entries[1].chapter, 2.0);
+    assert!(actions
+        .pending(2, 10)
+        .await
+        .expect("pending should load")
+        .is_empty());
+
+    assert!(matches!(
+        actions.approve(vec![]).await,
+        Err(ApiError::InvalidInput(_))
+    ));
+    assert_eq!(
+        actions
+            .approve(vec![format!("scraper_entry:{}", entries[0].id)])
+            .await
+            .expect("approve should succeed"),
+        1
+    );
+    assert_eq!(
+        actions
+            .decline(vec![entries[1].id.clone(), "missing".to_owned()])
+            .await
+            .expect("decline should succeed"),
+        1
+    );
+    assert!(actions
+        .pending(1, 10)
+        .await
+        .expect("pending should load")
+        .is_empty());
+    let approved = ctx
+        .db
+        .scraper
+        .approved()
+        .await
+        .expect("approved entries should load");
+    assert_eq!(approved.len(), 1);
+    assert_eq!(approved[0].data.data.chapter, 1.0);
+
+    // declined chapters are not suggested again
+    actions
+        .refresh("fake", Duration::ZERO)
+        .await
+        .expect("refresh should succeed");
+    assert!(actions
+        .pending(1, 10)
+        .await
+        .expect("pending should load")
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn scraper_auto_approve_skips_review() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("trusted", "trusted@example.com", "password")
+        .await;
+    let image_temp_name = ctx.upload_png().await;
+    ctx.manga
+        .create(
+            AddMangaRequest {
+                names: names("Trusted Manga"),
+                kind: "manga".to_owned(),
+                status: Status::Ongoing,
+                description: None,
+                tags: vec![],
+                image_temp_name,
+                authors: vec![],
+                publishers: vec![],
+                artists: vec![],
+                sources: vec![],
+                scrapers: vec![v1::Scraper {
+                    channel: "en".to_owned(),
+                    url: "https://scraper.example/trusted".to_owned(),
+                    auto_approve: true,
+                }],
+            },
+            &user.id,
+        )
+        .await
+        .expect("manga creation should succeed");
+    let actions = fake_scraper_actions(&ctx);
+    actions
+        .refresh("fake", Duration::ZERO)
+        .await
+        .expect("refresh should succeed");
+
+    assert!(actions
+        .pending(1, 10)
+        .await
+        .expect("pending should load")
+        .is_empty());
+    assert_eq!(
+        ctx.db
+            .scraper
+            .approved()
+            .await
+            .expect("approved entries should load")
+            .len(),
+        2
+    );
+}
+
+#[async_trait::async_trait]
+impl scraper_module::SearchScraper for FakeSite {
+    async fn search(
+        &self,
+        query: scraper_module::SearchQuery,
+    ) -> scraper_module::ScraperResult<scraper_module::ExternalSearchResponse> {
+        let query = query.as_simple().expect("simple query");
+        let page = query.get("page").and_then(|v| v.as_int()).unwrap_or(1) as usize;
+        Ok(scraper_module::ExternalSearchResponse {
+            items: vec![scraper_module::ScrapedSearchResponse {
+                title: query
+                    .get("query")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_owned(),
+                url: "https://scraper.example/manga".to_owned(),
+                cover: None,
+                status: None,
+                ty: None,
+            }],
+            next_page: None,
+            prev_page: None,
+            last_page: Some(page),
+            page,
+        })
+    }
+
+    fn query(&self) -> scraper_module::ValidSearches {
+        scraper_module::ValidSearches::QueryOffset
+    }
+}
+
+#[async_trait::async_trait]
+impl scraper_module::MetaDataScraper for FakeSite {
+    async fn scrape_metadata(
+        &self,
+        url: &str,
+    ) -> Result<
+        std::collections::BTreeMap<String, scraper_module::ScrapedData>,
+        scraper_module::ScraperError,
+    > {
+        use scraper_module::ScrapedData::{Arr, Str};
+        Ok([
+            ("url".to_owned(), Str(url.to_owned())),
+            ("title".to_owned(), Str(" Fake Manga ".to_owned())),
+            (
+                "alternative".to_owned(),
+                Arr(vec![Str("Fake Manga".to_owned()), Str("Faux".to_owned())]),
+            ),
+            ("summery".to_owned(), Str("A fake manga".to_owned())),
+            (
+                "genres".to_owned(),
+                Arr(vec![
+                    Str("Action".to_owned()),
+                    Str("".to_owned()),
+                    Str("Action".to_owned()),
+                    Str("Drama".to_owned()),
+                ]),
+            ),
+            (
+                "cover".to_owned(),
+                Str("https://scraper.example/cover.png".to_owned()),
+            ),
+            ("type".to_owned(), Str("Manhwa".to_owned())),
+            ("status".to_owned(), Str("Completed".to_owned())),
+        ]
+        .into())
+    }
+
+    async fn download_file(&self, _: &str) -> scraper_module::ScraperResult<Vec<u8>> {
+        Ok(PNG_1X1.to_vec())
+    }
+}
+
+fn fake_external_actions(ctx: &TestCtx) -> ExternalActions {
+    let site = Arc::new(FakeSite);
+    ExternalActions {
+        services: Arc::new(manga_scraper::init::Services::new(vec![
+            manga_scraper::init::Service {
+                uri: "fake".to_owned(),
+                register: site.clone(),
+                searchers: Some(site.clone()),
+                metadata: Some(site),
+                reader: None,
+            },
+            manga_scraper::init::Service {
+                uri: "reader-only".to_owned(),
+                register: Arc::new(FakeSite),
+                searchers: None,
+                metadata: None,
+                reader: Some(Arc::new(FakeSite)),
+            },
+        ])),
+        fs: ctx.storage.clone(),
+    }
+}
+
+#[actix_web::test]
+async fn external_actions_list_capabilities() {
+    let ctx = TestCtx::new().await;
+    let actions = fake_external_actions(&ctx);
+    let list = actions.list();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].uri, "fake");
+    assert!(matches!(
+        list[0].search,
+        Some(scraper_module::ValidSearches::QueryOffset)
+    ));
+    assert!(list[0].metadata);
+    assert!(!list[0].reader);
+    assert!(list[1].search.is_none());
+    assert!(!list[1].metadata);
+    assert!(list[1].reader);
+
+    let (_, icon) = actions.icon("fake").expect("icon should exist");
+    assert_eq!(icon, PNG_1X1);
+    assert!(actions.icon("unknown").is_err());
+}
+
+#[actix_web::test]
+async fn external_actions_search_validates_query() {
+    let ctx = TestCtx::new().await;
+    let actions = fake_external_actions(&ctx);
+    let query = |items: Vec<(&str, scraper_module::Attribute)>| {
+        scraper_module::SearchQuery::Simple(
+            items.into_iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+        )
+    };
+
+    let result = actions
+        .search(
+            "fake",
+            query(vec![
+                ("query", scraper_module::Attribute::Str("solo".to_owned())),
+                ("page", scraper_module::Attribute::Int(2)),
+            ]),
+        )
+        .await
+        .expect("search should succeed");
+    assert_eq!(result.page, 2);
+    assert_eq!(result.items[0].title, "solo");
+
+    // page is required
+    assert!(actions
+        .search(
+            "fake",
+            query(vec![(
+                "query",
+                scraper_module::Attribute::Str("solo".to_owned())
+            )]),
+        )
+        .await
+        .is_err());
+    // unknown attribute
+    assert!(actions
+        .search(
+            "fake",
+            query(vec![
+                ("query", scraper_module::Attribute::Str("solo".to_owned())),
+                ("page", scraper_module::Attribute::Int(1)),
+                ("sort", scraper_module::Attribute::Str("new".to_owned())),
+            ]),
+        )
+        .await
+        .is_err());
+    // wrong type
+    assert!(actions
+        .search(
+            "fake",
+            query(vec![
+                ("query", scraper_module::Attribute::Int(1)),
+                ("page", scraper_module::Attribute::Int(1)),
+            ]),
+        )
+        .await
+        .is_err());
+    assert!(actions.search("reader-only", query(vec![])).await.is_err());
+    assert!(actions.search("unknown", query(vec![])).await.is_err());
+}
+
+#[actix_web::test]
+async fn external_actions_metadata_uses_matching_site() {
+    let ctx = TestCtx::new().await;
+    let actions = fake_external_actions(&ctx);
+    let url = "https://scraper.example/manga";
+    let metadata = actions
+        .metadata(url)
+        .await
+        .expect("metadata should be scraped");
+    assert_eq!(metadata.uri, "fake");
+    assert_eq!(
+        metadata.data.get("url"),
+        Some(&scraper_module::ScrapedData::Str(url.to_owned()))
+    );
+    assert!(actions.metadata("https://other.example").await.is_err());
+}
+
+#[actix_web::test]
+async fn external_actions_import_prepares_editable_manga() {
+    let ctx = TestCtx::new().await;
+    let user = ctx
+        .register_user("importer", "importer@example.com", "password")
+        .await;
+    let actions = fake_external_actions(&ctx);
+    let url = "https://scraper.example/manga";
+
+    assert!(actions.import(url, "").await.is_err());
+    assert!(actions.import("https://other.example", "en").await.is_err());
+
+    let mut request = actions
+        .import(url, "en")
+        .await
+        .expect("import should succeed");
+    assert_eq!(request.names["en"].items, vec!["Fake Manga".to_owned()]);
+    assert_eq!(request.names["unknown"].items, vec!["Faux".to_owned()]);
+    assert_eq!(request.kind, "manhwa");
+    assert_eq!(request.status, Status::Completed);
+    assert_eq!(request.description.as_deref(), Some("A fake manga"));
+    assert_eq!(
+        request
+            .tags
+            .iter()
+            .map(|v| v.tag.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Action", "Drama"]
+    );
+    assert_eq!(request.sources, vec![url.to_owned()]);
+    assert_eq!(request.scrapers.len(), 1);
+    assert_eq!(request.scrapers[0].url, url);
+    assert_eq!(request.scrapers[0].channel, "en");
+    assert!(!request.image_temp_name.is_empty());
+    let (_, total) = ctx
+        .manga
+        .search(search_all(), &user.claim)
+        .await
+        .expect("search should succeed");
+    assert_eq!(total, 0, "import should not add anything to the db");
+
+    request.names.remove("unknown");
+    let manga_id = ctx
+        .manga
+        .create(request, &user.id)
+        .await
+        .expect("edited import should be created");
+    let info = ctx
+        .manga
+        .info(manga_id, &user.claim)
+        .await
+        .expect("info should load");
+    assert_eq!(info.titles.len(), 1);
+    assert_eq!(info.scrapers.len(), 1);
+}
@@ -21,7 +21,7 @@ impl TokenAction {
                 return Err(ApiError::invalid_input("user_id cannot be empty"));
             }
         }
-        self.token.create(user_id, kind).await?;
+        self.token.create(user_id, kind, None).await?;
         Ok(())
     }
 
@@ -3,11 +3,16 @@ use std::{
     io,
 };
 
-use actix_web::{http::StatusCode, rt::task::JoinError, ResponseError};
+use actix_web::{
+    http::{header::CONTENT_RANGE, StatusCode},
+    rt::task::JoinError,
+    HttpResponse, ResponseError,
+};
 use apistos::ApiErrorComponent;
 use db::error::DbError;
+use scraper_module::ScraperError;
 use serde::{Deserialize, Serialize};
-use storage::StorageError;
+use storage::{RangeNotSatisfiable, StorageError};
 
 #[derive(Serialize, Deserialize, Clone, Debug, ApiErrorComponent)]
 #[openapi_error(status(code = 405, description = "Invalid input"))]
@@ -27,6 +32,10 @@ pub enum ApiError {
     InvalidActivationToken,
     Bcrypt(String),
     FailedToEncodeToken(String),
+    Scraper(String),
+    TooManyRequests,
+    /// Length of the object if known
+    RangeNotSatisfiable(Option<u64>),
 }
 
 impl Drop for ApiError {
@@ -37,7 +46,10 @@ impl Drop for ApiError {
 
 impl From<io::Error> for ApiError {
     fn from(value: io::Error) -> Self {
-        ApiError::WriteError(value.to_string())
+        match RangeNotSatisfiable::find(&value) {
+            Some(range) => ApiError::RangeNotSatisfiable(range.total),
+            None => ApiError::WriteError(value.to_string()),
+        }
     }
 }
 
@@ -95,12 +107,18 @@ impl From<StorageError> for ApiError {
             StorageError::Processing(processing_error) => {
                 ApiError::write_error(processing_error.to_string())
             }
-            StorageError::Io(error) => ApiError::write_error(error.to_string()),
+            StorageError::Io(error) => error.into(),
             StorageError::TempFile(error) => ApiError::write_error(error.to_string()),
         }
     }
 }
 
+impl From<ScraperError> for ApiError {
+    fn from(value: ScraperError) -> Self {
+        ApiError::Scraper(format!("{:?}", value))
+    }
+}
+
 impl From<JoinError> for ApiError {
     fn from(value: JoinError) -> Self {
         ApiError::FailedToEncodeToken(value.to_string())
@@ -122,7 +140,17 @@ impl Display for ApiError {
 impl ResponseError for ApiError {
     fn status_code(&self) -> StatusCode {
         match self {
+            ApiError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut resp = HttpResponse::build(self.status_code());
+        if let ApiError::RangeNotSatisfiable(Some(total)) = self {
+            resp.insert_header((CONTENT_RANGE, format!("bytes */{total}")));
+        }
+        resp.body(self.to_string())
+    }
 }
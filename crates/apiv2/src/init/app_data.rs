@@ -3,25 +3,41 @@ use std::sync::Arc;
 use actix_web::web::Data;
 use apistos::web::{scope, Scope};
 use db::DbHandle;
+use manga_scraper::init::Services;
+use notifier::Notifier;
 use storage::StorageSystem;
 
 use crate::{
     actions::{
-        auth::AuthAction, chapter::ChapterActions, chapter_version::ChapterVersionActions,
-        character::CharacterActions, crytpo::CryptoService, kind::KindActions, lists::ListActions,
-        manga::MangaActions, reader::ReaderActions, tags::TagActions, token::TokenAction,
+        api_key::ApiKeyActions, auth::AuthAction, chapter::ChapterActions,
+        chapter_version::ChapterVersionActions, character::CharacterActions, crytpo::CryptoService,
+        duplicates::DuplicateActions, external::ExternalActions, kind::KindActions,
+        lists::ListActions, mail::Mailer, manga::MangaActions, notification::NotificationActions,
+        reader::ReaderActions, scraper::ScraperActions, tags::TagActions, token::TokenAction,
         user::UserActions,
     },
     init::env::Config,
 };
 
-pub fn init_app_data(config: Arc<Config>, fs: Arc<StorageSystem>, dbs: DbHandle) -> Scope {
-    let crypto = Arc::new(CryptoService::new(config.secret_key.as_bytes().to_vec()));
+pub fn init_app_data(
+    config: Arc<Config>,
+    fs: Arc<StorageSystem>,
+    dbs: DbHandle,
+    services: Arc<Services>,
+    notifier: Arc<Notifier>,
+    mailer: Arc<Mailer>,
+    crypto: Arc<CryptoService>,
+) -> Scope {
+    let api_keys = ApiKeyActions {
+        keys: dbs.api_keys.clone(),
+    };
     let auth = AuthAction {
         users: dbs.users.clone(),
         crypto: crypto.clone(),
         token: dbs.tokens.clone(),
         fs: fs.clone(),
+        mailer,
+        sessions: dbs.sessions.clone(),
     };
     let chapter = ChapterActions {
         chapters: dbs.chapters.clone(),
@@ -49,6 +65,8 @@ pub fn init_app_data(config: Arc<Config>, fs: Arc<StorageSystem>, dbs: DbHandle)
     let lists = ListActions {
         mangas: dbs.mangas.clone(),
         lists: dbs.lists.clone(),
+        popularity: dbs.popularity.clone(),
+        saved_searches: dbs.saved_searches.clone(),
     };
     let manga = MangaActions {
         mangas: dbs.mangas.clone(),
@@ -60,6 +78,41 @@ pub fn init_app_data(config: Arc<Config>, fs: Arc<StorageSystem>, dbs: DbHandle)
         versions: dbs.versions.clone(),
         chapter_versions: dbs.chapter_versions.clone(),
         pages: dbs.pages.clone(),
+        saved_searches: dbs.saved_searches.clone(),
+        fs: fs.clone(),
+    };
+
+    let notification = NotificationActions {
+        notifications: dbs.notifications.clone(),
+        users: dbs.users.clone(),
+        notifier,
+        endpoints: config.notifications.endpoint_policy(),
+    };
+
+    let duplicates = DuplicateActions {
+        pages: dbs.pages.clone(),
+        index: Default::default(),
+    };
+
+    let external = ExternalActions {
+        services: services.clone(),
+        fs: fs.clone(),
+    };
+
+    let scraper = ScraperActions {
+        services,
+        mangas: dbs.mangas.clone(),
+        scraper: dbs.scraper.clone(),
+        versions: dbs.versions.clone(),
+        chapters: ChapterActions {
+            chapters: dbs.chapters.clone(),
+            tags: dbs.tags.clone(),
+            versions: dbs.versions.clone(),
+            chapter_versions: dbs.chapter_versions.clone(),
+            mangas: dbs.mangas.clone(),
+            pages: dbs.pages.clone(),
+            fs: fs.clone(),
+        },
         fs: fs.clone(),
     };
 
@@ -71,6 +124,8 @@ pub fn init_app_data(config: Arc<Config>, fs: Arc<StorageSystem>, dbs: DbHandle)
         mangas: dbs.mangas,
         lists: dbs.lists,
         kinds: dbs.kinds,
+        popularity: dbs.popularity,
+        version_preferences: dbs.version_preferences,
     };
 
     let tags = TagActions {
@@ -88,14 +143,19 @@ pub fn init_app_data(config: Arc<Config>, fs: Arc<StorageSystem>, dbs: DbHandle)
     scope("/api")
         .app_data(Data::from(crypto))
         .app_data(Data::from(fs))
+        .app_data(Data::new(api_keys))
         .app_data(Data::new(auth))
         .app_data(Data::new(chapter))
         .app_data(Data::new(character))
         .app_data(Data::new(cversion))
+        .app_data(Data::new(duplicates))
+        .app_data(Data::new(external))
         .app_data(Data::new(kind))
         .app_data(Data::new(lists))
         .app_data(Data::new(manga))
+        .app_data(Data::new(notification))
         .app_data(Data::new(reader))
+        .app_data(Data::new(scraper))
         .app_data(Data::new(tags))
         .app_data(Data::new(token))
         .app_data(Data::new(user))
@@ -1,5 +1,7 @@
 use helper::random_string;
+use notifier::EndpointPolicy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
@@ -14,6 +16,110 @@ pub struct Config {
     pub spinner: Spinner,
     #[serde(default)]
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub scraper: ScraperConfig,
+    #[serde(default)]
+    pub popularity: PopularityConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MailConfig {
+    /// Where verification, password reset & welcome mails go
+    pub transport: MailTransportConfig,
+    /// Name of the site used in the mails
+    pub site_name: String,
+    /// Public url of the frontend, linked in the mails
+    pub base_url: String,
+    /// Folder with `verification.txt`, `reset_password.txt` and/or `welcome.txt`,
+    /// which replace the built-in templates
+    pub templates: Option<PathBuf>,
+    /// Mails per address & hour
+    pub rate_limit: u32,
+    /// Minutes until an emailed code expires
+    pub code_lifetime: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MailTransportConfig {
+    /// Codes have to be handed out by an admin
+    #[default]
+    Disabled,
+    Smtp(SmtpConfig),
+    /// Writes `.eml` files into the folder instead of sending them
+    File {
+        folder: PathBuf,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Seconds between two deliveries of pending notifications
+    pub interval: u64,
+    /// Seconds until a webhook or push request is aborted
+    pub timeout: u64,
+    /// Accept http urls for webhooks & push endpoints. Only for local testing
+    pub allow_http: bool,
+    /// Accept webhooks & push endpoints on loopback or private addresses. Only for local testing
+    pub allow_private: bool,
+    /// Users can choose to be notified by their own webhook
+    pub webhook: bool,
+    /// Email is only available if this is set
+    pub smtp: Option<SmtpConfig>,
+    /// Web Push is only available if this is set
+    pub push: Option<PushConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub security: notifier::SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender, e.g. `ManRead <noreply@example.com>`
+    pub from: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PushConfig {
+    /// Base64url encoded raw P-256 private key
+    pub vapid_private_key: String,
+    /// Contact of the server, `mailto:` or `https:` url
+    pub subject: String,
+    /// Seconds the push service keeps a message for offline browsers
+    #[serde(default = "default_push_ttl")]
+    pub ttl: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PopularityConfig {
+    /// Seconds between two recomputations of the trending scores
+    pub trending_interval: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScraperConfig {
+    /// Run the background jobs which scrape new chapters
+    pub enabled: bool,
+    /// Seconds between two chapter list refreshes of a site
+    pub refresh_interval: u64,
+    /// Seconds between two downloads of approved chapters of a site
+    pub download_interval: u64,
+    /// Scrapers which were refreshed in the last n seconds are skipped
+    pub recheck_after: u64,
+    /// Map<site uri, group name>. Sites in the same group never run at the same time.
+    /// Sites which are not in here get their own group
+    pub groups: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -66,6 +172,62 @@ impl Default for S3Config {
     }
 }
 
+impl Default for ScraperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            refresh_interval: 60 * 60 * 24,
+            download_interval: 60 * 30,
+            recheck_after: 60 * 60 * 48,
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            interval: 60,
+            timeout: 10,
+            allow_http: false,
+            allow_private: false,
+            webhook: true,
+            smtp: None,
+            push: None,
+        }
+    }
+}
+
+impl NotificationConfig {
+    pub fn endpoint_policy(&self) -> EndpointPolicy {
+        EndpointPolicy {
+            allow_http: self.allow_http,
+            allow_private: self.allow_private,
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransportConfig::Disabled,
+            site_name: "ManRead".to_owned(),
+            base_url: "http://127.0.0.1:3000".to_owned(),
+            templates: None,
+            rate_limit: 5,
+            code_lifetime: 60,
+        }
+    }
+}
+
+impl Default for PopularityConfig {
+    fn default() -> Self {
+        Self {
+            trending_interval: 60 * 15,
+        }
+    }
+}
+
 impl Default for StorageConfig {
     fn default() -> Self {
         Self {
@@ -120,7 +282,35 @@ impl Default for Config {
             secret_key: random_string(64), //2048bit = 256byte = 64 chars
             spinner: Spinner::Pikachu2,
             storage: StorageConfig::default(),
+            scraper: ScraperConfig::default(),
+            popularity: PopularityConfig::default(),
+            notifications: NotificationConfig::default(),
+            mail: MailConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Rejects values which only fail once the server runs
+    fn validate(&self) -> std::io::Result<()> {
+        let intervals = [
+            (
+                "popularity.trending_interval",
+                self.popularity.trending_interval,
+            ),
+            ("notifications.interval", self.notifications.interval),
+            ("scraper.refresh_interval", self.scraper.refresh_interval),
+            ("scraper.download_interval", self.scraper.download_interval),
+        ];
+        for (name, interval) in intervals {
+            if interval == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{name} has to be > 0"),
+                ));
+            }
         }
+        Ok(())
     }
 }
 
@@ -128,14 +318,20 @@ const fn default_true() -> bool {
     true
 }
 
+const fn default_push_ttl() -> u32 {
+    60 * 60 * 24
+}
+
 pub fn get_env() -> std::io::Result<Config> {
     let path = PathBuf::from("Config.toml");
     if path.is_file() {
         let mut file = File::open(path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        toml::from_str(&contents)
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+        let config: Config = toml::from_str(&contents)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+        config.validate()?;
+        Ok(config)
     } else {
         let config = Config::default();
         let serialized =
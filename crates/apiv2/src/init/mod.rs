@@ -3,4 +3,6 @@ pub mod env;
 #[cfg(feature = "https")]
 mod https;
 pub mod logger;
+pub mod notifications;
+pub mod scheduler;
 pub mod server;
@@ -0,0 +1,77 @@
+use std::{sync::Arc, time::Duration};
+
+use notifier::{
+    mail::{FileMailer, MailTransport},
+    Notifier, PushBackend, SmtpBackend, SmtpMailer, SmtpOptions, VapidOptions, WebhookBackend,
+};
+
+use crate::{
+    actions::mail::Mailer,
+    init::env::{MailConfig, MailTransportConfig, NotificationConfig, SmtpConfig},
+};
+
+fn smtp_options(smtp: &SmtpConfig) -> SmtpOptions {
+    SmtpOptions {
+        host: smtp.host.clone(),
+        port: smtp.port,
+        security: smtp.security,
+        username: smtp.username.clone(),
+        password: smtp.password.clone(),
+        from: smtp.from.clone(),
+    }
+}
+
+/// Registers a backend for every configured channel. Backends which fail to build are logged & left out
+pub fn init_notifier(config: &NotificationConfig) -> Notifier {
+    let timeout = Duration::from_secs(config.timeout);
+    let mut notifier = Notifier::new();
+    if config.webhook {
+        match WebhookBackend::new(timeout, config.endpoint_policy()) {
+            Ok(backend) => notifier = notifier.with(backend),
+            Err(e) => log::error!("Failed to init webhook notifications: {}", e),
+        }
+    }
+    if let Some(smtp) = &config.smtp {
+        match SmtpBackend::new(smtp_options(smtp)) {
+            Ok(backend) => notifier = notifier.with(backend),
+            Err(e) => log::error!("Failed to init email notifications: {}", e),
+        }
+    }
+    if let Some(push) = &config.push {
+        match PushBackend::new(
+            VapidOptions {
+                private_key: push.vapid_private_key.clone(),
+                subject: push.subject.clone(),
+            },
+            push.ttl,
+            timeout,
+            config.endpoint_policy(),
+        ) {
+            Ok(backend) => notifier = notifier.with(backend),
+            Err(e) => log::error!("Failed to init push notifications: {}", e),
+        }
+    }
+    notifier
+}
+
+/// Account mails are disabled if the transport fails to build
+pub fn init_mailer(config: &MailConfig) -> std::io::Result<Mailer> {
+    let transport: Option<Arc<dyn MailTransport>> = match &config.transport {
+        MailTransportConfig::Disabled => None,
+        MailTransportConfig::Smtp(smtp) => match SmtpMailer::new(smtp_options(smtp)) {
+            Ok(v) => Some(Arc::new(v)),
+            Err(e) => {
+                log::error!("Failed to init mail transport: {}", e);
+                None
+            }
+        },
+        MailTransportConfig::File { folder } => Some(Arc::new(FileMailer::new(folder))),
+    };
+    let mailer = Mailer::new(transport, config.site_name.clone(), config.base_url.clone())
+        .rate_limit(config.rate_limit, Duration::from_secs(60 * 60))
+        .code_lifetime(Duration::from_secs(config.code_lifetime * 60));
+    match &config.templates {
+        Some(folder) => mailer.load_templates(folder),
+        None => Ok(mailer),
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    future::Future,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use db::{popularity::PopularityDBService, DbHandle};
+use event_runner::{Event, EventStore, GroupId, ProcessType};
+use manga_scraper::init::Services;
+use notifier::Notifier;
+use storage::StorageSystem;
+use tokio::task::JoinHandle;
+
+use crate::{
+    actions::{
+        chapter::ChapterActions, notification::NotificationActions, scraper::ScraperActions,
+    },
+    init::env::{Config, ScraperConfig},
+};
+
+/// Task of the last run of an event
+#[derive(Default)]
+struct TaskHandle(Mutex<Option<JoinHandle<()>>>);
+
+impl TaskHandle {
+    fn cancel(&self) {
+        if let Some(handle) = self.0.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn set(&self, handle: JoinHandle<()>) {
+        *self.0.lock().unwrap() = Some(handle);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Job {
+    /// Scrape chapter lists & add them as pending entries
+    Refresh,
+    /// Download approved entries
+    Download,
+}
+
+struct ScraperEvent {
+    job: Job,
+    uri: String,
+    group: GroupId,
+    interval: Duration,
+    recheck_after: Duration,
+    actions: Arc<ScraperActions>,
+    handle: TaskHandle,
+}
+
+impl Event for ScraperEvent {
+    fn group_id(&self) -> Option<GroupId> {
+        Some(self.group)
+    }
+
+    fn rerun(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn execute(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let actions = self.actions.clone();
+        let uri = self.uri.clone();
+        let job = self.job;
+        let recheck_after = self.recheck_after;
+        Box::pin(async move {
+            let result = match job {
+                Job::Refresh => actions.refresh(&uri, recheck_after).await,
+                Job::Download => actions.download(&uri).await,
+            };
+            if let Err(e) = result {
+                log::error!("Scraper job {:?} for {} failed: {:?}", job, uri, e);
+            }
+        })
+    }
+
+    fn cancel(&self) {
+        self.handle.cancel();
+    }
+
+    fn is_running(&self) -> bool {
+        self.handle.is_running()
+    }
+
+    fn parallel(&self) -> ProcessType {
+        ProcessType::Group(self.group)
+    }
+
+    fn set_handle(&self, handle: JoinHandle<()>) {
+        self.handle.set(handle);
+    }
+}
+
+/// Recomputes the trending scores of all mangas
+struct TrendingEvent {
+    interval: Duration,
+    popularity: Arc<PopularityDBService>,
+    handle: TaskHandle,
+}
+
+impl Event for TrendingEvent {
+    fn rerun(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn execute(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let popularity = self.popularity.clone();
+        Box::pin(async move {
+            if let Err(e) = popularity.update_trending().await {
+                log::error!("Trending update failed: {:?}", e);
+            }
+        })
+    }
+
+    fn cancel(&self) {
+        self.handle.cancel();
+    }
+
+    fn is_running(&self) -> bool {
+        self.handle.is_running()
+    }
+
+    fn parallel(&self) -> ProcessType {
+        ProcessType::Kind
+    }
+
+    fn set_handle(&self, handle: JoinHandle<()>) {
+        self.handle.set(handle);
+    }
+}
+
+/// Sends pending notifications
+struct NotificationEvent {
+    interval: Duration,
+    actions: Arc<NotificationActions>,
+    handle: TaskHandle,
+}
+
+/// Notifications which are sent per run
+const NOTIFICATION_BATCH: u32 = 100;
+
+impl Event for NotificationEvent {
+    fn rerun(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn execute(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let actions = self.actions.clone();
+        Box::pin(async move {
+            if let Err(e) = actions.deliver(NOTIFICATION_BATCH).await {
+                log::error!("Notification delivery failed: {:?}", e);
+            }
+        })
+    }
+
+    fn cancel(&self) {
+        self.handle.cancel();
+    }
+
+    fn is_running(&self) -> bool {
+        self.handle.is_running()
+    }
+
+    fn parallel(&self) -> ProcessType {
+        ProcessType::Kind
+    }
+
+    fn set_handle(&self, handle: JoinHandle<()>) {
+        self.handle.set(handle);
+    }
+}
+
+fn group_id(name: &str) -> GroupId {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Registers the trending & notification jobs & if scraping is enabled a refresh & a download job for every site that can read chapters.
+/// Jobs of the same site(or configured group) never run at the same time
+pub async fn init_scheduler(
+    config: &Config,
+    services: Arc<Services>,
+    fs: Arc<StorageSystem>,
+    notifier: Arc<Notifier>,
+    dbs: &DbHandle,
+) -> EventStore {
+    let store = EventStore::default();
+    store
+        .add(Box::new(TrendingEvent {
+            interval: Duration::from_secs(config.popularity.trending_interval),
+            popularity: dbs.popularity.clone(),
+            handle: TaskHandle::default(),
+        }))
+        .await;
+    store
+        .add(Box::new(NotificationEvent {
+            interval: Duration::from_secs(config.notifications.interval),
+            actions: Arc::new(NotificationActions {
+                notifications: dbs.notifications.clone(),
+                users: dbs.users.clone(),
+                notifier,
+                endpoints: config.notifications.endpoint_policy(),
+            }),
+            handle: TaskHandle::default(),
+        }))
+        .await;
+    if config.scraper.enabled {
+        add_scraper_jobs(&store, &config.scraper, services, fs, dbs).await;
+    }
+    store.start_loop();
+    store
+}
+
+async fn add_scraper_jobs(
+    store: &EventStore,
+    config: &ScraperConfig,
+    services: Arc<Services>,
+    fs: Arc<StorageSystem>,
+    dbs: &DbHandle,
+) {
+    let actions = Arc::new(ScraperActions {
+        services,
+        mangas: dbs.mangas.clone(),
+        scraper: dbs.scraper.clone(),
+        versions: dbs.versions.clone(),
+        chapters: ChapterActions {
+            chapters: dbs.chapters.clone(),
+            tags: dbs.tags.clone(),
+            versions: dbs.versions.clone(),
+            chapter_versions: dbs.chapter_versions.clone(),
+            mangas: dbs.mangas.clone(),
+            pages: dbs.pages.clone(),
+            fs: fs.clone(),
+        },
+        fs,
+    });
+    for service in &actions.services.services {
+        if service.reader.is_none() {
+            continue;
+        }
+        let group = group_id(config.groups.get(&service.uri).unwrap_or(&service.uri));
+        for (job, interval) in [
+            (Job::Refresh, config.refresh_interval),
+            (Job::Download, config.download_interval),
+        ] {
+            store
+                .add(Box::new(ScraperEvent {
+                    job,
+                    uri: service.uri.clone(),
+                    group,
+                    interval: Duration::from_secs(interval),
+                    recheck_after: Duration::from_secs(config.recheck_after),
+                    actions: actions.clone(),
+                    handle: TaskHandle::default(),
+                }))
+                .await;
+        }
+    }
+}
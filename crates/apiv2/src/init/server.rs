@@ -8,9 +8,12 @@ use apistos::{
     web, RapidocConfig, RedocConfig, ScalarConfig, SwaggerUIConfig,
 };
 use db::DbHandle;
+use manga_scraper::init::Services;
+use notifier::Notifier;
 use storage::StorageSystem;
 
 use crate::{
+    actions::{crytpo::CryptoService, mail::Mailer},
     init::{app_data::init_app_data, env::Config, logger::log_url},
     routes,
 };
@@ -35,9 +38,24 @@ pub fn init_server(
     config: Arc<Config>,
     fs: Arc<StorageSystem>,
     dbs: DbHandle,
+    services: Arc<Services>,
+    notifier: Arc<Notifier>,
+    mailer: Arc<Mailer>,
 ) -> std::io::Result<actix_web::dev::Server> {
     log_url(&config);
-    let app_data = move || init_app_data(config.clone(), fs.clone(), dbs.clone());
+    // shared by all workers, so revoked sessions are rejected everywhere
+    let crypto = Arc::new(CryptoService::new(config.secret_key.as_bytes().to_vec()));
+    let app_data = move || {
+        init_app_data(
+            config.clone(),
+            fs.clone(),
+            dbs.clone(),
+            services.clone(),
+            notifier.clone(),
+            mailer.clone(),
+            crypto.clone(),
+        )
+    };
     #[cfg(feature = "https")]
     let ssl_builder = https::init_https(&config.root_folder)?;
     #[cfg(not(feature = "https"))]
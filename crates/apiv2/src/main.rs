@@ -73,13 +73,35 @@ async fn main() -> std::io::Result<()> {
     let storage = storage::StorageSystem::new_with_rw(&config.root_folder, reader, writer, 5)
         .await
         .map_err(|err| std::io::Error::other(err.to_string()))?;
+    let storage = Arc::new(storage);
+
+    let (services, errors) = manga_scraper::init::register(&config.root_folder);
+    for err in errors {
+        log::error!("Failed to load scraper: {}", err);
+    }
+    let services = Arc::new(services);
+
+    let notifier = Arc::new(init::notifications::init_notifier(&config.notifications));
+    let mailer = Arc::new(init::notifications::init_mailer(&config.mail)?);
+
+    let _scheduler = init::scheduler::init_scheduler(
+        &config,
+        services.clone(),
+        storage.clone(),
+        notifier.clone(),
+        &dbs,
+    )
+    .await;
 
     init::server::init_server(
         config.port,
         config.https_port,
         config,
-        Arc::new(storage),
+        storage,
         dbs,
+        services,
+        notifier,
+        mailer,
     )?
     .await
 }
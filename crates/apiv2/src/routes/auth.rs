@@ -1,11 +1,15 @@
-use actix_web::web::{Data, Json, ReqData};
+use actix_web::{
+    http::header,
+    web::{Data, Json, ReqData},
+    HttpRequest,
+};
 use actix_web_grants::AuthorityGuard;
 use actix_web_httpauth::middleware::HttpAuthentication;
 use api_structure::{
     req::LoginRequest,
     v1::{
-        ActivateRequest, Claim, JwTsResponse, JwtType, RegisterRequest,
-        RequestResetPasswordRequest, ResetPasswordRequest, TokenRefreshRequest,
+        ActivateRequest, Claim, IdRequest, JwTsResponse, JwtType, RegisterRequest,
+        RequestResetPasswordRequest, ResetPasswordRequest, SessionsResponse, TokenRefreshRequest,
     },
     Permission,
 };
@@ -18,13 +22,37 @@ use chrono::{DateTime, Utc};
 use storage::FileId;
 
 use crate::{
-    actions::{auth::AuthAction, crytpo::validator},
+    actions::{
+        auth::{AuthAction, ClientInfo},
+        crytpo::validator,
+    },
     error::{ApiError, ApiResult},
 };
 
+/// Device & address of the request, stored with its session
+fn client_info(req: &HttpRequest) -> ClientInfo {
+    ClientInfo {
+        device: req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.chars().take(256).collect())
+            .unwrap_or_else(|| "Unknown device".to_owned()),
+        ip: req.peer_addr().map(|v| v.ip().to_string()),
+    }
+}
+
+pub(crate) fn require_access_token(claim: &Claim) -> ApiResult<()> {
+    if !matches!(claim.r#type, JwtType::AccessToken) || claim.api_key.is_some() {
+        return Err(ApiError::invalid_input("Access token required"));
+    }
+    Ok(())
+}
+
 #[api_operation(tag = "auth", summary = "Registers a user", description = r###""###)]
 async fn signup(
     Json(data): Json<RegisterRequest>,
+    req: HttpRequest,
     service: Data<AuthAction>,
 ) -> ApiResult<CreatedJson<JwTsResponse>> {
     let birthdate = i64::try_from(data.birthdate)
@@ -39,6 +67,7 @@ async fn signup(
             data.gender,
             birthdate,
             data.icon_temp_name.map(|v| FileId::new(v)),
+            &client_info(&req),
         )
         .await
         .map(CreatedJson)
@@ -47,9 +76,13 @@ async fn signup(
 #[api_operation(tag = "auth", summary = "Logs in the user", description = r###""###)]
 pub async fn signin(
     Json(data): Json<LoginRequest>,
+    req: HttpRequest,
     service: Data<AuthAction>,
 ) -> ApiResult<CreatedJson<JwTsResponse>> {
-    service.login(data).await.map(CreatedJson)
+    service
+        .login(data, &client_info(&req))
+        .await
+        .map(CreatedJson)
 }
 
 #[api_operation(
@@ -62,11 +95,7 @@ pub(crate) async fn logout(
     service: Data<AuthAction>,
     claim: ReqData<Claim>,
 ) -> ApiResult<Json<u8>> {
-    if !matches!(claim.r#type, JwtType::AccessToken) {
-        return Err(crate::error::ApiError::invalid_input(
-            "Access token required",
-        ));
-    }
+    require_access_token(&claim)?;
     service.logout(&claim).await?;
     Ok(Json(200))
 }
@@ -78,9 +107,13 @@ pub(crate) async fn logout(
 )]
 pub(crate) async fn verify_reset_password(
     Json(data): Json<ResetPasswordRequest>,
+    req: HttpRequest,
     service: Data<AuthAction>,
 ) -> ApiResult<CreatedJson<JwTsResponse>> {
-    service.reset_password(data).await.map(CreatedJson)
+    service
+        .reset_password(data, &client_info(&req))
+        .await
+        .map(CreatedJson)
 }
 #[api_operation(
     tag = "user",
@@ -98,21 +131,105 @@ pub(crate) async fn request_reset_password(
     Ok(CreatedJson(200))
 }
 
-#[api_operation(tag = "auth", summary = "Refreshes the token", description = r###""###)]
+#[api_operation(
+    tag = "auth",
+    summary = "Refreshes the token",
+    description = r###"Every refresh token can only be used once. Reusing one revokes its session"###
+)]
 pub(crate) async fn refresh(
     Json(data): Json<TokenRefreshRequest>,
+    req: HttpRequest,
     service: Data<AuthAction>,
 ) -> ApiResult<CreatedJson<JwTsResponse>> {
-    service.refresh(&data.refresh_token).await.map(CreatedJson)
+    service
+        .refresh(&data.refresh_token, &client_info(&req))
+        .await
+        .map(CreatedJson)
 }
 
 #[api_operation(tag = "auth", summary = "Verifies the user", description = r###""###)]
 pub(crate) async fn verify(
     Json(data): Json<ActivateRequest>,
+    req: HttpRequest,
     claim: ReqData<Claim>,
     service: Data<AuthAction>,
 ) -> ApiResult<CreatedJson<JwTsResponse>> {
-    service.verify(&data.key, &claim).await.map(CreatedJson)
+    service
+        .verify(&data.key, &claim, &client_info(&req))
+        .await
+        .map(CreatedJson)
+}
+
+#[api_operation(
+    tag = "auth",
+    summary = "Mails a new verification code",
+    description = r###"Only available if mails are configured. Limited per email address"###
+)]
+pub(crate) async fn resend_verification(
+    claim: ReqData<Claim>,
+    service: Data<AuthAction>,
+) -> ApiResult<CreatedJson<u8>> {
+    service.resend_verification(&claim).await?;
+    Ok(CreatedJson(200))
+}
+
+#[api_operation(
+    tag = "auth",
+    summary = "Lists the sessions of the user",
+    description = r###""###
+)]
+pub(crate) async fn sessions(
+    service: Data<AuthAction>,
+    claim: ReqData<Claim>,
+) -> ApiResult<Json<SessionsResponse>> {
+    require_access_token(&claim)?;
+    Ok(Json(SessionsResponse {
+        items: service
+            .sessions(&claim.id, claim.session.as_deref())
+            .await?,
+    }))
+}
+
+#[api_operation(
+    tag = "auth",
+    summary = "Revokes a session of the user",
+    description = r###"Its refresh token stops working & its access tokens are rejected"###
+)]
+pub(crate) async fn revoke_session(
+    Json(data): Json<IdRequest>,
+    service: Data<AuthAction>,
+    claim: ReqData<Claim>,
+) -> ApiResult<Json<u8>> {
+    require_access_token(&claim)?;
+    service.revoke_session(&data.id, Some(&claim.id)).await?;
+    Ok(Json(200))
+}
+
+#[api_operation(
+    tag = "admin",
+    summary = "Lists the sessions of any user",
+    description = r###""###
+)]
+pub(crate) async fn user_sessions(
+    Json(data): Json<IdRequest>,
+    service: Data<AuthAction>,
+) -> ApiResult<Json<SessionsResponse>> {
+    Ok(Json(SessionsResponse {
+        items: service.sessions(&data.id, None).await?,
+    }))
+}
+
+#[api_operation(
+    tag = "admin",
+    summary = "Revokes a session of any user",
+    description = r###""###
+)]
+pub(crate) async fn revoke_user_session(
+    Json(data): Json<IdRequest>,
+    service: Data<AuthAction>,
+) -> ApiResult<Json<u8>> {
+    service.revoke_session(&data.id, None).await?;
+    Ok(Json(200))
 }
 
 pub fn register() -> Scope {
@@ -140,20 +257,48 @@ pub fn register() -> Scope {
                             .to(verify)
                             .guard(AuthorityGuard::new(Permission::Verify)),
                     ),
+                )
+                .service(
+                    apistos::web::resource("/resend-verification").route(
+                        apistos::web::post()
+                            .to(resend_verification)
+                            .guard(AuthorityGuard::new(Permission::Verify)),
+                    ),
+                )
+                .service(
+                    apistos::web::resource("/sessions").route(apistos::web::post().to(sessions)),
+                )
+                .service(
+                    apistos::web::resource("/sessions/revoke")
+                        .route(apistos::web::delete().to(revoke_session)),
+                )
+                .service(
+                    apistos::web::resource("/sessions/user").route(
+                        apistos::web::post()
+                            .to(user_sessions)
+                            .guard(AuthorityGuard::new(Permission::Review)),
+                    ),
+                )
+                .service(
+                    apistos::web::resource("/sessions/user/revoke").route(
+                        apistos::web::delete()
+                            .to(revoke_user_session)
+                            .guard(AuthorityGuard::new(Permission::Review)),
+                    ),
                 ),
         )
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{path::PathBuf, sync::Arc};
+    use std::{path::PathBuf, sync::Arc, time::Duration};
 
     use actix_web::web::{Data, Json};
-    use api_structure::v1::{Gender, RegisterRequest};
+    use api_structure::v1::{Gender, RegisterRequest, Role};
     use db::{init_db, DbConfig, MemoryDbConfig};
     use storage::{MemStorage, StorageSystem};
 
-    use crate::actions::{auth::AuthAction, crytpo::CryptoService};
+    use crate::actions::{auth::AuthAction, crytpo::CryptoService, mail::Mailer};
 
     use super::*;
 
@@ -181,6 +326,8 @@ mod tests {
             crypto: Arc::new(CryptoService::new(b"route-test-secret".to_vec())),
             token: db.tokens,
             fs: storage,
+            mailer: Arc::new(Mailer::new(None, String::new(), String::new())),
+            sessions: db.sessions,
         }
     }
 
@@ -196,10 +343,27 @@ mod tests {
                 birthdate: u64::MAX,
                 icon_temp_name: None,
             }),
+            actix_web::test::TestRequest::default().to_http_request(),
             service,
         )
         .await;
 
         assert!(matches!(response, Err(ApiError::InvalidInput(_))));
     }
+
+    #[test]
+    fn api_keys_are_no_access_tokens() {
+        let mut claim = Claim::new(
+            "user".to_owned(),
+            Role::User,
+            JwtType::AccessToken,
+            Duration::from_secs(60),
+        );
+        assert!(require_access_token(&claim).is_ok());
+        claim.api_key = Some("key".to_owned());
+        assert!(matches!(
+            require_access_token(&claim),
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
 }
@@ -0,0 +1,99 @@
+use actix_web::web::{Data, Json};
+use actix_web_grants::AuthorityGuard;
+use api_structure::{
+    v1::{
+        DuplicatePairsResponse, DuplicatesRequest, InferiorVersionsResponse, SharedPagesResponse,
+    },
+    Permission,
+};
+use apistos::api_operation;
+
+use crate::{actions::duplicates::DuplicateActions, error::ApiResult};
+
+pub fn register() -> apistos::web::Scope {
+    apistos::web::scope("/duplicates")
+        .service(
+            apistos::web::resource("/identical-versions").route(
+                apistos::web::post()
+                    .to(identical_versions)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/repeated-chapters").route(
+                apistos::web::post()
+                    .to(repeated_chapters)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/shared-pages").route(
+                apistos::web::post()
+                    .to(shared_pages)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/inferior-versions").route(
+                apistos::web::post()
+                    .to(inferior_versions)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+}
+
+#[api_operation(
+    tag = "admin",
+    summary = "Lists versions of a chapter which are visually identical scanlations",
+    description = r###"Compares the perceptual hashes of the pages. Shared credit & ad pages are ignored"###
+)]
+pub(crate) async fn identical_versions(
+    Json(data): Json<DuplicatesRequest>,
+    service: Data<DuplicateActions>,
+) -> ApiResult<Json<DuplicatePairsResponse>> {
+    Ok(Json(DuplicatePairsResponse {
+        items: service.report(&data).await?.identical_versions,
+    }))
+}
+
+#[api_operation(
+    tag = "admin",
+    summary = "Lists chapters of a manga which were uploaded twice with different chapter numbers",
+    description = r###"Compares the perceptual hashes of the pages. Shared credit & ad pages are ignored"###
+)]
+pub(crate) async fn repeated_chapters(
+    Json(data): Json<DuplicatesRequest>,
+    service: Data<DuplicateActions>,
+) -> ApiResult<Json<DuplicatePairsResponse>> {
+    Ok(Json(DuplicatePairsResponse {
+        items: service.report(&data).await?.repeated_chapters,
+    }))
+}
+
+#[api_operation(
+    tag = "admin",
+    summary = "Lists credit & ad pages which appear in many chapters",
+    description = r###"Uploaders can compare the hashes to strip these pages"###
+)]
+pub(crate) async fn shared_pages(
+    Json(data): Json<DuplicatesRequest>,
+    service: Data<DuplicateActions>,
+) -> ApiResult<Json<SharedPagesResponse>> {
+    Ok(Json(SharedPagesResponse {
+        items: service.report(&data).await?.shared_pages,
+    }))
+}
+
+#[api_operation(
+    tag = "admin",
+    summary = "Lists versions which have a visually identical version of better quality",
+    description = r###"Versions are scored by resolution, file size, format & how well their pages match the other versions. The inferior versions can be deleted"###
+)]
+pub(crate) async fn inferior_versions(
+    Json(data): Json<DuplicatesRequest>,
+    service: Data<DuplicateActions>,
+) -> ApiResult<Json<InferiorVersionsResponse>> {
+    Ok(Json(InferiorVersionsResponse {
+        items: service.inferior_versions(&data).await?,
+    }))
+}
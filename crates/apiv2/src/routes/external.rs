@@ -0,0 +1,119 @@
+use actix_web::{
+    http::header::CONTENT_TYPE,
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use actix_web_grants::AuthorityGuard;
+use api_structure::{
+    resp::external::ExternalService,
+    v1::{AddMangaRequest, ExternalMetadataRequest, ImportMangaRequest},
+    Permission,
+};
+use apistos::{
+    api_operation,
+    web::{scope, Scope},
+};
+use scraper_module::{ExternalMetadataResponse, ExternalSearchRequest, ExternalSearchResponse};
+
+use crate::{actions::external::ExternalActions, error::ApiResult};
+
+pub fn register() -> Scope {
+    scope("/external")
+        .service(
+            apistos::web::resource("/list").route(
+                apistos::web::post()
+                    .to(list)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/search").route(
+                apistos::web::post()
+                    .to(search)
+                    .guard(AuthorityGuard::new(Permission::Create)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/metadata").route(
+                apistos::web::post()
+                    .to(metadata)
+                    .guard(AuthorityGuard::new(Permission::Create)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/import").route(
+                apistos::web::post()
+                    .to(import)
+                    .guard(AuthorityGuard::new(Permission::Create)),
+            ),
+        )
+}
+
+/// Icons are used in img tags, so they dont need auth
+pub fn register_icon() -> apistos::web::Resource {
+    apistos::web::resource("/external/{uri}").route(apistos::web::get().to(icon))
+}
+
+#[api_operation(
+    tag = "external",
+    summary = "Lists all registered external sites",
+    description = r###"Contains the capabilities of every site. `search` contains the valid search parameters & is missing if the site cannot be searched"###
+)]
+pub(crate) async fn list(external: Data<ExternalActions>) -> Json<Vec<ExternalService>> {
+    Json(external.list())
+}
+
+#[api_operation(
+    tag = "external",
+    summary = "Gets the icon of an external site",
+    description = r###"Doesnt need auth, so it can be used in img tags. The content type is detected from the bytes & falls back to the extension of the icon"###
+)]
+pub(crate) async fn icon(
+    uri: Path<String>,
+    external: Data<ExternalActions>,
+) -> ApiResult<HttpResponse> {
+    let (ext, bytes) = external.icon(&uri)?;
+    let content_type = infer::get(&bytes)
+        .map(|kind| kind.mime_type().to_owned())
+        .unwrap_or_else(|| format!("image/{ext}"));
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, content_type))
+        .body(bytes))
+}
+
+#[api_operation(
+    tag = "external",
+    summary = "Searches for mangas on an external site",
+    description = r###"The query is validated against the search parameters of the site"###
+)]
+pub(crate) async fn search(
+    Json(data): Json<ExternalSearchRequest>,
+    external: Data<ExternalActions>,
+) -> ApiResult<Json<ExternalSearchResponse>> {
+    external.search(&data.uri, data.query).await.map(Json)
+}
+
+#[api_operation(
+    tag = "external",
+    summary = "Gets the metadata of a manga on an external site",
+    description = r###"Uses the first registered site which matches the url"###
+)]
+pub(crate) async fn metadata(
+    Json(data): Json<ExternalMetadataRequest>,
+    external: Data<ExternalActions>,
+) -> ApiResult<Json<ExternalMetadataResponse>> {
+    external.metadata(&data.url).await.map(Json)
+}
+
+#[api_operation(
+    tag = "external",
+    summary = "Prepares a manga from the metadata of an external site",
+    description = r###"Scrapes the url & uploads the cover as temp file. The url is attached as scraper for `channel`.
+Nothing is saved. The result can be edited & passed to `/manga/create`"###
+)]
+pub(crate) async fn import(
+    Json(data): Json<ImportMangaRequest>,
+    external: Data<ExternalActions>,
+) -> ApiResult<Json<AddMangaRequest>> {
+    external.import(&data.url, &data.channel).await.map(Json)
+}
@@ -9,7 +9,10 @@ use storage::StorageSystem;
 
 use crate::{
     error::{ApiError, ApiResult},
-    routes::image::stream::stream,
+    routes::image::{
+        stream::{read_options, stream},
+        variant::{image_variant, vary_accept},
+    },
 };
 
 #[api_operation(
@@ -27,9 +30,12 @@ pub(crate) async fn exec(
         return Err(ApiError::InvalidImageId);
     }
     let key = format!("covers/{}.{}", data.manga_id, data.file_ext);
-    let obj = storage.reader.get(&key, &Default::default()).await?;
+    let variant = image_variant(&req, data.width, data.format.as_deref())?;
+    let obj = storage
+        .get_variant(&key, &variant, &read_options(&req))
+        .await?;
 
-    Ok(stream(&req, obj, true))
+    Ok(vary_accept(stream(&req, obj, true)))
 }
 
 pub fn register() -> apistos::web::Resource {
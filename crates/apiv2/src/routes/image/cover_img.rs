@@ -1,22 +1,27 @@
 use actix_web::{
-    web::{Data, Path},
+    web::{Data, Path, Query},
     HttpRequest, HttpResponse,
 };
+use api_structure::v1::ImageVariantQuery;
 use apistos::api_operation;
 use storage::StorageSystem;
 
 use crate::{
     error::{ApiError, ApiResult},
-    routes::image::stream::stream,
+    routes::image::{
+        stream::{read_options, stream},
+        variant::{image_variant, vary_accept},
+    },
 };
 
 #[api_operation(
     tag = "image",
     summary = "Gets the cover of a manga",
-    description = r###""###
+    description = r###"`w` resizes the cover & `format` re-encodes it. Variants are generated on the first request"###
 )]
 pub(crate) async fn exec(
     filename: Path<String>,
+    query: Query<ImageVariantQuery>,
     req: HttpRequest,
     storage: Data<StorageSystem>,
 ) -> ApiResult<HttpResponse> {
@@ -24,9 +29,12 @@ pub(crate) async fn exec(
         return Err(ApiError::InvalidImageId);
     }
     let key = format!("covers/{filename}");
-    let obj = storage.reader.get(&key, &Default::default()).await?;
+    let variant = image_variant(&req, query.w, query.format.as_deref())?;
+    let obj = storage
+        .get_variant(&key, &variant, &read_options(&req))
+        .await?;
 
-    Ok(stream(&req, obj, true))
+    Ok(vary_accept(stream(&req, obj, true)))
 }
 
 pub fn register() -> apistos::web::Resource {
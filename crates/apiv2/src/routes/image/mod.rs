@@ -3,6 +3,7 @@ pub mod cover_img;
 mod page_image;
 pub mod stream;
 mod upload;
+mod variant;
 
 pub fn register() -> apistos::web::Scope {
     apistos::web::scope("/image")
@@ -9,7 +9,10 @@ use storage::StorageSystem;
 
 use crate::{
     error::{ApiError, ApiResult},
-    routes::image::stream::stream,
+    routes::image::{
+        stream::{read_options, stream},
+        variant::{image_variant, vary_accept},
+    },
 };
 
 #[api_operation(
@@ -31,9 +34,12 @@ pub(crate) async fn exec(
         "mangas/{}/{}/{}/{}.{}",
         data.manga_id, data.chapter_id, data.version_id, data.page, data.file_ext
     );
-    let obj = storage.reader.get(&key, &Default::default()).await?;
+    let variant = image_variant(&req, data.width, data.format.as_deref())?;
+    let obj = storage
+        .get_variant(&key, &variant, &read_options(&req))
+        .await?;
 
-    Ok(stream(&req, obj, false))
+    Ok(vary_accept(stream(&req, obj, false)))
 }
 
 pub fn register() -> apistos::web::Resource {
@@ -4,11 +4,13 @@ use actix_web::{
     error::ErrorInternalServerError,
     http::header::{
         self, CacheControl, ETag, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch,
-        LastModified,
+        LastModified, ACCEPT_RANGES, CONTENT_RANGE, RANGE,
     },
     web, HttpRequest, HttpResponse,
 };
-use storage::{Object, StorageSystem};
+use storage::{ByteRange, Object, Options, RangeNotSatisfiable, StorageSystem};
+
+use crate::error::ApiError;
 
 fn normalize_etag(s: &str) -> String {
     let trimmed = s.trim();
@@ -72,18 +74,65 @@ pub async fn download(
     let key = path.into_inner();
     let obj = storage
         .reader
-        .get(&key, &Default::default())
+        .get(&key, &read_options(req))
         .await
-        .map_err(ErrorInternalServerError)?;
+        .map_err(|err| match RangeNotSatisfiable::find(&err) {
+            Some(_) => actix_web::Error::from(ApiError::from(err)),
+            None => ErrorInternalServerError(err),
+        })?;
 
     Ok(stream(req, obj, true))
 }
 
+/// Parses a single range of the `Range` header. Invalid & multiple ranges are ignored
+fn parse_range(value: &str) -> Option<ByteRange> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    if start.is_empty() {
+        return Some(ByteRange::Suffix(end.parse().ok()?));
+    }
+    let start: u64 = start.parse().ok()?;
+    let end = match end {
+        "" => None,
+        end => Some(end.parse().ok().filter(|end| *end >= start)?),
+    };
+    Some(ByteRange::From { start, end })
+}
+
+/// Reads only the bytes requested by the `Range` header
+pub fn read_options(req: &HttpRequest) -> Options {
+    Options {
+        range: req
+            .headers()
+            .get(RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_range),
+        ..Default::default()
+    }
+}
+
 pub fn stream(req: &HttpRequest, obj: Object, cache: bool) -> HttpResponse {
     if let Some(resp) = maybe_not_modified(req, obj.etag.as_deref(), obj.last_modified) {
         return resp;
     }
-    let mut resp = HttpResponse::Ok();
+    let mut resp = match obj.range {
+        Some(range) => {
+            let total = range
+                .total
+                .map_or_else(|| "*".to_owned(), |v| v.to_string());
+            let mut resp = HttpResponse::PartialContent();
+            resp.insert_header((
+                CONTENT_RANGE,
+                format!("bytes {}-{}/{total}", range.start, range.end),
+            ));
+            resp
+        }
+        None => HttpResponse::Ok(),
+    };
+    resp.insert_header((ACCEPT_RANGES, "bytes"));
     if let Some(ct) = obj.content_type {
         resp.content_type(ct.to_string());
     }
@@ -110,3 +159,37 @@ pub fn stream(req: &HttpRequest, obj: Object, cache: bool) -> HttpResponse {
 
     resp.streaming(obj.stream)
 }
+
+#[cfg(test)]
+mod tests {
+    use storage::ByteRange;
+
+    use super::parse_range;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-9"),
+            Some(ByteRange::From {
+                start: 0,
+                end: Some(9)
+            })
+        );
+        assert_eq!(
+            parse_range("bytes=10-"),
+            Some(ByteRange::From {
+                start: 10,
+                end: None
+            })
+        );
+        assert_eq!(parse_range("bytes=-10"), Some(ByteRange::Suffix(10)));
+    }
+
+    #[test]
+    fn ignores_invalid_ranges() {
+        assert_eq!(parse_range("bytes=9-1"), None);
+        assert_eq!(parse_range("bytes=0-1,4-5"), None);
+        assert_eq!(parse_range("units=0-1"), None);
+        assert_eq!(parse_range("bytes=a-"), None);
+    }
+}
@@ -0,0 +1,50 @@
+use std::str::FromStr as _;
+
+use actix_web::{
+    http::header::{self, HeaderValue},
+    HttpRequest, HttpResponse,
+};
+use storage::{ImageVariant, VariantFormat};
+
+use crate::error::{ApiError, ApiResult};
+
+fn negotiate(req: &HttpRequest) -> VariantFormat {
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let accepts = |mime: &str| {
+        accept
+            .split(',')
+            .filter_map(|v| v.split(';').next())
+            .any(|v| v.trim().eq_ignore_ascii_case(mime))
+    };
+    if accepts("image/avif") {
+        VariantFormat::Avif
+    } else if accepts("image/webp") {
+        VariantFormat::Webp
+    } else {
+        VariantFormat::Original
+    }
+}
+
+/// An explicit `format` wins over the `Accept` header
+pub(crate) fn image_variant(
+    req: &HttpRequest,
+    width: Option<u32>,
+    format: Option<&str>,
+) -> ApiResult<ImageVariant> {
+    let format = match format {
+        Some(v) => VariantFormat::from_str(v).map_err(|e| ApiError::invalid_input(&e))?,
+        None => negotiate(req),
+    };
+    Ok(ImageVariant::new(width, format))
+}
+
+/// The format can depend on the `Accept` header, caches have to keep them apart
+pub(crate) fn vary_accept(mut resp: HttpResponse) -> HttpResponse {
+    resp.headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    resp
+}
@@ -1,8 +1,10 @@
 use actix_web::web::{self, Data, Json, ReqData};
 use actix_web_grants::AuthorityGuard;
 use api_structure::{
+    search::{DeleteSavedSearchRequest, SaveSearchRequest},
     v1::{
-        AddListRequest, AddMangaToListRequest, Claim, DeleteListRequest, RemoveMangaToListRequest,
+        AddListRequest, AddMangaToListRequest, Claim, DeleteListRequest, ListsResponse,
+        RemoveMangaToListRequest,
     },
     Permission,
 };
@@ -37,6 +39,27 @@ pub fn register() -> Scope {
                     .guard(AuthorityGuard::new(Permission::Read)),
             ),
         )
+        .service(
+            apistos::web::resource("/saved-searches").route(
+                apistos::web::post()
+                    .to(saved_searches)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/save-search").route(
+                apistos::web::put()
+                    .to(save_search)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/delete-search").route(
+                apistos::web::delete()
+                    .to(delete_search)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
         .service(
             scope("/{list}")
                 .service(
@@ -111,12 +134,52 @@ pub(crate) async fn delete(
 }
 #[api_operation(
     tag = "list",
-    summary = "Lists all lists for the user",
+    summary = "saves a search as smart list",
+    description = r###"Overwrites the search if the name is already used"###
+)]
+pub(crate) async fn save_search(
+    Json(payload): Json<SaveSearchRequest>,
+    list_service: Data<ListActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<CreatedJson<u8>> {
+    list_service.save_search(payload, &user).await?;
+    Ok(CreatedJson(0))
+}
+
+#[api_operation(
+    tag = "list",
+    summary = "deletes a saved search",
+    description = r###"Queries which reference it with `saved:name` fail afterwards"###
+)]
+pub(crate) async fn delete_search(
+    Json(payload): Json<DeleteSavedSearchRequest>,
+    list_service: Data<ListActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<u8>> {
+    list_service.delete_search(&payload.name, &user).await?;
+    Ok(Json(200))
+}
+
+#[api_operation(
+    tag = "list",
+    summary = "Lists all lists & saved searches for the user",
     description = r###""###
 )]
 pub(crate) async fn list(
     list_service: Data<ListActions>,
     user: ReqData<Claim>,
-) -> ApiResult<Json<Vec<String>>> {
+) -> ApiResult<Json<ListsResponse>> {
     list_service.list(&user).await.map(Json)
 }
+
+#[api_operation(
+    tag = "list",
+    summary = "Lists the saved searches of the user",
+    description = r###"Saved searches are shown as smart lists & can be used in queries with `saved:name`"###
+)]
+pub(crate) async fn saved_searches(
+    list_service: Data<ListActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<Vec<String>>> {
+    list_service.saved_searches(&user).await.map(Json)
+}
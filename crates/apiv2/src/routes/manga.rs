@@ -7,7 +7,10 @@ use actix_web::{
 };
 use actix_web_grants::AuthorityGuard;
 use api_structure::{
-    search::{HomeResponse, SearchRequest, SearchResponse_},
+    search::{
+        HomeResponse, SavedSearchPageRequest, SearchRequest, SearchResponse_, SuggestRequest,
+        Suggestion,
+    },
     v1::{
         AddMangaArtRequest, AddMangaCoverRequest, AddMangaRelationRequest, AddMangaRequest, Claim,
         ConfirmMangaDeleteRequest, EditMangaRequest, IdRequest, MangaInfoResponse,
@@ -142,6 +145,20 @@ pub fn register() -> apistos::web::Scope {
                     .guard(AuthorityGuard::new(Permission::Read)),
             ),
         )
+        .service(
+            apistos::web::resource("/search/saved").route(
+                apistos::web::post()
+                    .to(search_saved)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/search/suggest").route(
+                apistos::web::post()
+                    .to(suggest)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
 }
 
 #[api_operation(
@@ -190,7 +207,7 @@ pub(crate) async fn info(
     manga_service: Data<MangaActions>,
     user: ReqData<Claim>,
 ) -> ApiResult<Json<MangaInfoResponse>> {
-    manga_service.info(data.id, &user.id).await.map(Json)
+    manga_service.info(data.id, &user).await.map(Json)
 }
 
 #[api_operation(skip = true)]
@@ -582,7 +599,7 @@ pub(crate) async fn home(
     manga_service: Data<MangaActions>,
     user: ReqData<Claim>,
 ) -> ApiResult<Json<HomeResponse>> {
-    manga_service.home(&user.id).await.map(Json)
+    manga_service.home(&user).await.map(Json)
 }
 
 #[api_operation(tag = "manga", summary = "Search for manga", description = r###""###)]
@@ -592,7 +609,7 @@ pub(crate) async fn search(
     user: ReqData<Claim>,
 ) -> ApiResult<Json<SearchResponse_>> {
     search_service
-        .search(data, &user.id)
+        .search(data, &user)
         .await
         .map(|v| SearchResponse_ {
             items: v.0,
@@ -600,3 +617,36 @@ pub(crate) async fn search(
         })
         .map(Json)
 }
+
+#[api_operation(
+    tag = "manga",
+    summary = "Runs a saved search",
+    description = r###"Uses the order & limit the search was saved with"###
+)]
+pub(crate) async fn search_saved(
+    Json(data): Json<SavedSearchPageRequest>,
+    search_service: Data<MangaActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<SearchResponse_>> {
+    search_service
+        .search_saved(data, &user)
+        .await
+        .map(|v| SearchResponse_ {
+            items: v.0,
+            max: v.1,
+        })
+        .map(Json)
+}
+
+#[api_operation(
+    tag = "manga",
+    summary = "Suggests completions for a search query",
+    description = r###"Completes the field name, tag, author, list, kind or saved search at the cursor. `cursor`, `start` & `end` are char offsets"###
+)]
+pub(crate) async fn suggest(
+    Json(data): Json<SuggestRequest>,
+    search_service: Data<MangaActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<Vec<Suggestion>>> {
+    search_service.suggest(data, &user).await.map(Json)
+}
@@ -8,11 +8,15 @@ mod auth;
 mod chapter;
 mod chapter_versions;
 mod character;
+mod duplicates;
+mod external;
 mod image;
 mod kind;
 mod lists;
 mod manga;
+mod notification;
 mod reader;
+mod scraper;
 mod tags;
 mod token;
 mod user;
@@ -20,21 +24,28 @@ mod user;
 pub fn register() -> Scope {
     apistos::web::scope("/v1")
         .service(auth::register())
-        .service(scope("/image-no-auth").service(image::cover_img::register()))
+        .service(
+            scope("/image-no-auth")
+                .service(image::cover_img::register())
+                .service(external::register_icon()),
+        )
         .service(
             scope("")
                 .wrap(HttpAuthentication::bearer(validator))
-                // .service(external::register())
+                .service(external::register())
                 .service(chapter::register())
                 .service(character::register())
                 .service(chapter_versions::register())
+                .service(duplicates::register())
                 .service(image::register())
                 .service(token::register())
                 .service(kind::register())
                 .service(reader::register())
+                .service(scraper::register())
                 .service(manga::register())
                 .service(user::register())
                 .service(lists::register())
+                .service(notification::register())
                 .service(tags::register())
                 .default_service(web::route().to(not_found)),
         )
@@ -0,0 +1,126 @@
+use actix_web::web::{Data, Json, ReqData};
+use actix_web_grants::AuthorityGuard;
+use api_structure::{
+    v1::{
+        Claim, MarkNotificationsReadRequest, NotificationSettingsRequest, NotificationsRequest,
+        NotificationsResponse, PushSubscriptionRequest, PushUnsubscribeRequest,
+    },
+    Permission,
+};
+use apistos::{
+    actix::CreatedJson,
+    api_operation,
+    web::{scope, Scope},
+};
+
+use crate::{actions::notification::NotificationActions, error::ApiResult};
+
+pub fn register() -> Scope {
+    scope("/notifications")
+        .service(
+            apistos::web::resource("/list").route(
+                apistos::web::post()
+                    .to(list)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/read").route(
+                apistos::web::put()
+                    .to(mark_read)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/settings").route(
+                apistos::web::put()
+                    .to(settings)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/push/subscribe").route(
+                apistos::web::put()
+                    .to(subscribe)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/push/unsubscribe").route(
+                apistos::web::delete()
+                    .to(unsubscribe)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Lists the notifications of the user",
+    description = r###"Newest first. `unread` counts all unread notifications"###
+)]
+pub(crate) async fn list(
+    Json(data): Json<NotificationsRequest>,
+    notification_service: Data<NotificationActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<NotificationsResponse>> {
+    notification_service.list(data, &user).await.map(Json)
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Marks notifications as read",
+    description = r###"Marks all notifications as read if no ids are given"###
+)]
+pub(crate) async fn mark_read(
+    Json(data): Json<MarkNotificationsReadRequest>,
+    notification_service: Data<NotificationActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<u8>> {
+    notification_service.mark_read(data, &user).await?;
+    Ok(Json(200))
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Sets how the user is notified",
+    description = r###"Only modes which are configured on the server can be chosen"###
+)]
+pub(crate) async fn settings(
+    Json(data): Json<NotificationSettingsRequest>,
+    notification_service: Data<NotificationActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<u8>> {
+    notification_service.settings(data, &user).await?;
+    Ok(Json(200))
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Adds a browser push subscription",
+    description = r###""###
+)]
+pub(crate) async fn subscribe(
+    Json(data): Json<PushSubscriptionRequest>,
+    notification_service: Data<NotificationActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<CreatedJson<u8>> {
+    notification_service.subscribe(data, &user).await?;
+    Ok(CreatedJson(0))
+}
+
+#[api_operation(
+    tag = "notification",
+    summary = "Removes a browser push subscription",
+    description = r###""###
+)]
+pub(crate) async fn unsubscribe(
+    Json(data): Json<PushUnsubscribeRequest>,
+    notification_service: Data<NotificationActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<u8>> {
+    notification_service
+        .unsubscribe(&data.endpoint, &user)
+        .await?;
+    Ok(Json(200))
+}
@@ -3,7 +3,7 @@ use actix_web_grants::AuthorityGuard;
 use api_structure::{
     v1::{
         ChapterVersion, Claim, MangaReaderRequest, MangaReaderResponse, ReadProgressRequest,
-        ReaderPageRequest,
+        ReaderPageRequest, VersionPreferenceRequest,
     },
     Permission,
 };
@@ -37,6 +37,13 @@ pub fn register() -> Scope {
                     .guard(AuthorityGuard::new(Permission::Read)),
             ),
         )
+        .service(
+            apistos::web::resource("/version_preference").route(
+                apistos::web::put()
+                    .to(version_preference)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
 }
 
 #[api_operation(
@@ -75,9 +82,26 @@ pub(crate) async fn info(
 pub(crate) async fn pages_info(
     Json(payload): Json<ReaderPageRequest>,
     reader_service: Data<ReaderActions>,
+    user: ReqData<Claim>,
 ) -> ApiResult<Json<ChapterVersion>> {
     reader_service
-        .pages(&payload.chapter_version_id)
+        .pages(&payload.chapter_version_id, &user)
         .await
         .map(Json)
 }
+
+#[api_operation(
+    tag = "reader",
+    summary = "Sets whether the best scored version of a chapter is opened",
+    description = r###"Without a manga the default of the user is set. Without best_quality the preference for the manga is removed"###
+)]
+pub(crate) async fn version_preference(
+    Json(payload): Json<VersionPreferenceRequest>,
+    reader_service: Data<ReaderActions>,
+    user: ReqData<Claim>,
+) -> ApiResult<Json<u8>> {
+    reader_service
+        .set_version_preference(payload, &user)
+        .await?;
+    Ok(Json(200))
+}
@@ -0,0 +1,73 @@
+use actix_web::web::{Data, Json};
+use actix_web_grants::AuthorityGuard;
+use api_structure::{
+    v1::{PaginationRequest, PendingScraperGroup, ScraperEntriesRequest},
+    Permission,
+};
+use apistos::{
+    api_operation,
+    web::{scope, Scope},
+};
+
+use crate::{actions::scraper::ScraperActions, error::ApiResult};
+
+pub fn register() -> Scope {
+    scope("/scraper")
+        .service(
+            apistos::web::resource("/pending").route(
+                apistos::web::post()
+                    .to(pending)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/approve").route(
+                apistos::web::put()
+                    .to(approve)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/decline").route(
+                apistos::web::put()
+                    .to(decline)
+                    .guard(AuthorityGuard::new(Permission::Review)),
+            ),
+        )
+}
+
+#[api_operation(
+    tag = "scraper",
+    summary = "Lists scraped chapters which need a review",
+    description = r###"Paginates over groups of the same manga & version. Each group contains all of its pending chapters"###
+)]
+pub(crate) async fn pending(
+    Json(data): Json<PaginationRequest>,
+    scraper: Data<ScraperActions>,
+) -> ApiResult<Json<Vec<PendingScraperGroup>>> {
+    scraper.pending(data.page, data.limit).await.map(Json)
+}
+
+#[api_operation(
+    tag = "scraper",
+    summary = "Approves scraped chapters",
+    description = r###"Approved chapters will be downloaded in the background. Returns the number of changed entries"###
+)]
+pub(crate) async fn approve(
+    Json(data): Json<ScraperEntriesRequest>,
+    scraper: Data<ScraperActions>,
+) -> ApiResult<Json<u64>> {
+    scraper.approve(data.ids).await.map(|v| Json(v as u64))
+}
+
+#[api_operation(
+    tag = "scraper",
+    summary = "Declines scraped chapters",
+    description = r###"Declined chapters will not be downloaded or suggested again. Returns the number of changed entries"###
+)]
+pub(crate) async fn decline(
+    Json(data): Json<ScraperEntriesRequest>,
+    scraper: Data<ScraperActions>,
+) -> ApiResult<Json<u64>> {
+    scraper.decline(data.ids).await.map(|v| Json(v as u64))
+}
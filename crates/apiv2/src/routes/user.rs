@@ -1,12 +1,19 @@
 use actix_web::web::{Data, Json, ReqData};
 use actix_web_grants::AuthorityGuard;
 use api_structure::{
-    v1::{Claim, IdRequest, PaginationRequest, SearchRequest, SimpleUser, UpdateUserRequest, User},
+    v1::{
+        ApiKeysResponse, Claim, CreateApiKeyRequest, CreatedApiKey, IdRequest, PaginationRequest,
+        SearchRequest, SimpleUser, UpdateUserRequest, User,
+    },
     Permission,
 };
-use apistos::api_operation;
+use apistos::{actix::CreatedJson, api_operation};
 
-use crate::{actions::user::UserActions, error::ApiResult};
+use crate::{
+    actions::{api_key::ApiKeyActions, user::UserActions},
+    error::ApiResult,
+    routes::auth::require_access_token,
+};
 
 pub fn register() -> apistos::web::Scope {
     apistos::web::scope("/user")
@@ -45,6 +52,27 @@ pub fn register() -> apistos::web::Scope {
                     .guard(AuthorityGuard::new(Permission::Read)),
             ),
         )
+        .service(
+            apistos::web::resource("/api-keys").route(
+                apistos::web::post()
+                    .to(api_keys)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/api-keys/create").route(
+                apistos::web::put()
+                    .to(create_api_key)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
+        .service(
+            apistos::web::resource("/api-keys/revoke").route(
+                apistos::web::delete()
+                    .to(revoke_api_key)
+                    .guard(AuthorityGuard::new(Permission::Read)),
+            ),
+        )
 }
 
 #[api_operation(tag = "user", summary = "Deletes a user", description = r###""###)]
@@ -66,6 +94,8 @@ pub(crate) async fn edit(
     claim: ReqData<Claim>,
     user_service: Data<UserActions>,
 ) -> ApiResult<Json<u8>> {
+    // keys must not change the password or email of their owner
+    require_access_token(&claim)?;
     user_service.edit(data, &claim).await?;
     Ok(Json(200))
 }
@@ -103,3 +133,40 @@ pub(crate) async fn search(
 ) -> ApiResult<Json<Vec<SimpleUser>>> {
     user_service.search(data).await.map(Json)
 }
+
+#[api_operation(
+    tag = "user",
+    summary = "Lists the api keys of the user",
+    description = r###""###
+)]
+pub(crate) async fn api_keys(
+    claim: ReqData<Claim>,
+    service: Data<ApiKeyActions>,
+) -> ApiResult<Json<ApiKeysResponse>> {
+    Ok(Json(ApiKeysResponse {
+        items: service.list(&claim).await?,
+    }))
+}
+
+#[api_operation(
+    tag = "user",
+    summary = "Creates an api key",
+    description = r###"The key is only returned once & is used as bearer token instead of a jwt. It can only have permissions of the role of the user & cannot manage api keys or sessions"###
+)]
+pub(crate) async fn create_api_key(
+    Json(data): Json<CreateApiKeyRequest>,
+    claim: ReqData<Claim>,
+    service: Data<ApiKeyActions>,
+) -> ApiResult<CreatedJson<CreatedApiKey>> {
+    service.create(&claim, data).await.map(CreatedJson)
+}
+
+#[api_operation(tag = "user", summary = "Revokes an api key", description = r###""###)]
+pub(crate) async fn revoke_api_key(
+    Json(data): Json<IdRequest>,
+    claim: ReqData<Claim>,
+    service: Data<ApiKeyActions>,
+) -> ApiResult<Json<u8>> {
+    service.revoke(&claim, &data.id).await?;
+    Ok(Json(200))
+}
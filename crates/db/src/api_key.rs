@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use surrealdb::Datetime;
+use surrealdb_extras::{RecordData, RecordIdType, SurrealTable, SurrealTableInfo};
+
+use crate::{
+    error::{DbError, DbResult},
+    DbSession,
+};
+
+use super::user::User;
+
+/// Long-lived token for scripts. Only the hash of the key is stored
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("api_keys")]
+#[sql(["DEFINE EVENT api_key_updated ON TABLE api_keys WHEN $event = \"UPDATE\" AND $before.updated == $after.updated THEN (UPDATE $after.id SET updated = time::now() );", "DEFINE INDEX IF NOT EXISTS api_key_hash ON TABLE api_keys FIELDS hash UNIQUE;"])]
+pub struct ApiKey {
+    pub user: RecordIdType<User>,
+    pub name: String,
+    /// sha256 of the key as hex
+    pub hash: String,
+    /// Start of the key, so the user can tell keys apart
+    pub prefix: String,
+    /// Names of [api_structure::Permission]s
+    pub permissions: Vec<String>,
+    /// Never expires if not set
+    pub expires: Option<Datetime>,
+    #[opt(exclude = true)]
+    pub updated: Datetime,
+    #[opt(exclude = true)]
+    pub created: Datetime,
+}
+
+/// Everything needed to authenticate a request with a key
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyOwner {
+    pub id: RecordIdType<ApiKey>,
+    pub user: RecordIdType<User>,
+    pub permissions: Vec<String>,
+    pub expires: Option<Datetime>,
+    /// Current role of the owner
+    pub role: Option<u32>,
+    pub disabled: Option<bool>,
+}
+
+#[derive(Clone)]
+pub struct ApiKeyDBService {
+    db: DbSession,
+}
+
+impl ApiKeyDBService {
+    pub fn new(db: DbSession) -> Self {
+        Self { db }
+    }
+
+    /// Returns the id of the key
+    pub async fn create(
+        &self,
+        user: &str,
+        name: String,
+        hash: String,
+        prefix: String,
+        permissions: Vec<String>,
+        expires: Option<Datetime>,
+    ) -> DbResult<String> {
+        let id: RecordIdType<ApiKey> = ApiKey {
+            user: RecordIdType::from((User::name(), user)),
+            name,
+            hash,
+            prefix,
+            permissions,
+            expires,
+            updated: Default::default(),
+            created: Default::default(),
+        }
+        .add(self.db.as_ref())
+        .await?
+        .map(|item| item.id.into())
+        .ok_or(DbError::NotFound)?;
+        Ok(id.id().to_string())
+    }
+
+    /// Newest keys first
+    pub async fn list(&self, user: &str) -> DbResult<Vec<RecordData<ApiKey>>> {
+        Ok(self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE user = $user ORDER BY created DESC",
+                ApiKey::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .take(0)?)
+    }
+
+    /// Only deletes keys of `user`
+    pub async fn revoke(&self, user: &str, id: &str) -> DbResult<()> {
+        let deleted: Vec<RecordData<ApiKey>> = self
+            .db
+            .query("DELETE $id WHERE user = $user RETURN BEFORE")
+            .bind(("id", RecordIdType::<ApiKey>::from((ApiKey::name(), id))))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .take(0)?;
+        if deleted.is_empty() {
+            return Err(DbError::NotFound);
+        }
+        Ok(())
+    }
+
+    pub async fn get_by_hash(&self, hash: &str) -> DbResult<ApiKeyOwner> {
+        let mut owner: Vec<ApiKeyOwner> = self
+            .db
+            .query(format!(
+                "SELECT id, user, permissions, expires, user.role AS role, user.disabled AS disabled FROM {} WHERE hash = $hash LIMIT 1",
+                ApiKey::name()
+            ))
+            .bind(("hash", hash.to_owned()))
+            .await?
+            .take(0)?;
+        owner.pop().ok_or(DbError::NotFound)
+    }
+}
@@ -95,16 +95,23 @@ impl AuthTokenDBService {
         }
         Ok(search.remove(0))
     }
-    pub async fn create(&self, user_id: Option<String>, kind: ActivationTokenKind) -> DbResult<()> {
+    /// Returns the generated token. `active_until_timestamp` is in seconds
+    pub async fn create(
+        &self,
+        user_id: Option<String>,
+        kind: ActivationTokenKind,
+        active_until_timestamp: Option<u64>,
+    ) -> DbResult<String> {
         let user = user_id.map(|v| RecordIdType::from((User::name(), v.as_str())));
+        let token = random_string(6);
         AuthUser {
             user,
-            token: random_string(6),
+            token: token.clone(),
             kind: u32::from(kind),
-            active_until_timestamp: None,
+            active_until_timestamp,
         }
         .add_i(self.db.as_ref())
         .await?;
-        Ok(())
+        Ok(token)
     }
 }
@@ -15,6 +15,7 @@ use crate::{
 use super::{
     character::Character,
     manga::{vec_default, Manga},
+    notification::NotificationDBService,
     page::Page,
     progress::UserProgressDBService,
     tag::{Empty, Tag},
@@ -143,6 +144,26 @@ impl ChapterDBService {
         Ok(v.remove(0).id.id().to_string().to_owned())
     }
 
+    pub async fn get_manga_id_by_version(&self, chapter_version_id: &str) -> DbResult<String> {
+        let mut v: Vec<RecordData<Empty>> = self
+            .db
+            .query(format!(
+                "SELECT id FROM {} WHERE chapters ANYINSIDE (SELECT VALUE id FROM {} WHERE $version INSIDE object::values(versions)) LIMIT 1",
+                Manga::name(),
+                Chapter::name()
+            ))
+            .bind((
+                "version",
+                RecordIdType::<ChapterVersion>::from((ChapterVersion::name(), chapter_version_id)),
+            ))
+            .await?
+            .take(0)?;
+        if v.is_empty() {
+            return Err(DbError::NotFound);
+        }
+        Ok(v.remove(0).id.id().to_string())
+    }
+
     pub async fn delete(&self, chapter_id: &str) -> DbResult<()> {
         let chapter = self.get_by_id(chapter_id).await?;
         let manga_id = self.get_manga_id(chapter_id).await?;
@@ -165,6 +186,9 @@ impl ChapterDBService {
             )
             .await?;
 
+        NotificationDBService::new(self.db.clone())
+            .delete_for_chapter(chapter_id)
+            .await?;
         let chapter_id = RecordIdFunc::from((Chapter::name(), chapter_id));
         chapter_id.delete_s(self.db.as_ref()).await?;
 
@@ -299,6 +323,14 @@ impl ChapterDBService {
         UserProgressDBService::new(self.db.clone())
             .recompute_for_new_chapter(manga_id, &id.id.id().to_string(), chapter)
             .await?;
+        // the chapter exists already, failing here would report an upload error for it
+        let chapter_id = id.id.id().to_string();
+        if let Err(e) = NotificationDBService::new(self.db.clone())
+            .create_for_chapter(manga_id, &chapter_id, chapter)
+            .await
+        {
+            log::warn!("Failed to create notifications for chapter {chapter_id}: {e}");
+        }
         Ok(id.id.into())
     }
     pub async fn add(
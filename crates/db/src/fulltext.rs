@@ -0,0 +1,76 @@
+//! Full text index of mangas. The indexed fields are computed by the db on every write of a manga.
+//! Renaming a tag or character rewrites the mangas which use it, so the names stay in sync too.
+//!
+//! The analyzer transliterates everything to ascii, which removes accents & turns hiragana/katakana
+//! into the same romaji. Words are indexed with their prefixes, so incomplete words still match.
+//! Titles are also indexed as trigrams, which are used to find titles with typos, see
+//! [`crate::search::CompiledQuery::compile_with_typos`].
+
+use surrealdb_extras::SurrealTableInfo as _;
+
+use crate::{character::Character, manga::Manga, tag::Tag, DbClient};
+
+pub const ANALYZER: &str = "manga_text";
+pub const TRIGRAM_ANALYZER: &str = "manga_trigram";
+/// All titles seperated by new lines
+pub const TITLES: &str = "search_titles";
+/// All titles, indexed as trigrams
+pub const TRIGRAMS: &str = "search_trigrams";
+/// Names of characters & tags
+pub const NAMES: &str = "search_names";
+pub const DESCRIPTION: &str = "description";
+
+/// Marks that the mangas which existed before the index were indexed
+const MIGRATION: &str = "migrations:fulltext";
+
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+
+/// Defines the analyzers, fields & indexes. Mangas which were created before are indexed once
+pub async fn define(db: &DbClient) -> Result<(), surrealdb::Error> {
+    let table = Manga::name();
+    let tags = Tag::name();
+    let characters = Character::name();
+    db.query(format!(
+        "DEFINE ANALYZER IF NOT EXISTS {ANALYZER} TOKENIZERS blank,class,punct FILTERS lowercase,ascii,edgengram(2,20);
+        DEFINE ANALYZER IF NOT EXISTS {TRIGRAM_ANALYZER} TOKENIZERS blank,class,punct FILTERS lowercase,ascii,ngram(3,3);
+        DEFINE FIELD IF NOT EXISTS {TITLES} ON {table} VALUE array::join(array::flatten(object::values(titles)), \"\\n\");
+        DEFINE FIELD IF NOT EXISTS {TRIGRAMS} ON {table} VALUE array::join(array::flatten(object::values(titles)), \" \");
+        DEFINE FIELD IF NOT EXISTS {NAMES} ON {table} VALUE array::join(array::concat(array::flatten(characters.names ?? []), generated_tags.tag ?? []), \" \");
+        DEFINE INDEX IF NOT EXISTS manga_titles_search ON {table} FIELDS {TITLES} SEARCH ANALYZER {ANALYZER} BM25 HIGHLIGHTS;
+        DEFINE INDEX IF NOT EXISTS manga_titles_trigram_search ON {table} FIELDS {TRIGRAMS} SEARCH ANALYZER {TRIGRAM_ANALYZER} BM25;
+        DEFINE INDEX IF NOT EXISTS manga_names_search ON {table} FIELDS {NAMES} SEARCH ANALYZER {ANALYZER} BM25;
+        DEFINE INDEX IF NOT EXISTS manga_description_search ON {table} FIELDS {DESCRIPTION} SEARCH ANALYZER {ANALYZER} BM25;
+        DEFINE EVENT IF NOT EXISTS manga_search_tag_renamed ON TABLE {tags} WHEN $event = \"UPDATE\" AND $before.tag != $after.tag THEN (UPDATE {table} WHERE generated_tags CONTAINS $after.id);
+        DEFINE EVENT IF NOT EXISTS manga_search_character_renamed ON TABLE {characters} WHEN $event = \"UPDATE\" AND $before.names != $after.names THEN (UPDATE {table} WHERE characters CONTAINS $after.id);
+        IF {MIGRATION}.id == NONE {{
+            UPDATE {table};
+            CREATE {MIGRATION};
+        }};"
+    ))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+/// Line of the highlighted titles which contains a match
+pub fn highlighted_title(titles: &str) -> Option<String> {
+    titles
+        .lines()
+        .find(|v| v.contains(HIGHLIGHT_START))
+        .map(|v| v.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::highlighted_title;
+
+    #[test]
+    fn highlighted_line() {
+        assert_eq!(
+            highlighted_title("Shingeki no Kyojin\n<mark>Attack</mark> on Titan"),
+            Some("<mark>Attack</mark> on Titan".to_owned())
+        );
+        assert_eq!(highlighted_title("Shingeki no Kyojin"), None);
+    }
+}
@@ -1,18 +1,27 @@
+pub mod api_key;
 pub mod auth;
 pub mod chapter;
 pub mod character;
 pub mod error;
+pub mod fulltext;
 pub mod kind;
 pub mod kv;
 pub mod lists;
 pub mod manga;
+pub mod notification;
 pub mod page;
+pub mod popularity;
 pub mod progress;
+pub mod saved_search;
 pub mod scraper;
+pub mod search;
+pub mod session;
 pub mod tag;
 pub mod user;
 pub mod version;
 pub mod version_link;
+pub mod version_preference;
+pub mod visibility;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::sync::Arc;
@@ -26,6 +35,7 @@ use surrealdb::Surreal;
 use surrealdb::opt::auth::Root;
 pub use surrealdb_extras::{RecordIdFunc, RecordIdType};
 
+use crate::api_key::ApiKeyDBService;
 use crate::auth::AuthTokenDBService;
 use crate::chapter::ChapterDBService;
 use crate::character::CharacterDBService;
@@ -34,13 +44,18 @@ use crate::kind::KindDBService;
 use crate::kv::KeyValueDb;
 use crate::lists::ListDBService;
 use crate::manga::MangaDBService;
+use crate::notification::NotificationDBService;
 use crate::page::PageDBService;
+use crate::popularity::PopularityDBService;
 use crate::progress::UserProgressDBService;
+use crate::saved_search::SavedSearchDBService;
 use crate::scraper::ScraperDbService;
+use crate::session::SessionDBService;
 use crate::tag::TagDBService;
 use crate::user::UserDBService;
 use crate::version::VersionDBService;
 use crate::version_link::ChapterVersionDBService;
+use crate::version_preference::VersionPreferenceDBService;
 
 pub type DbClient = Surreal<Any>;
 pub type DbSession = Arc<DbClient>;
@@ -83,6 +98,7 @@ impl Default for DbConfig {
 #[derive(Clone)]
 pub struct DbHandle {
     pub session: DbSession,
+    pub api_keys: Arc<ApiKeyDBService>,
     pub tokens: Arc<AuthTokenDBService>,
     pub users: Arc<UserDBService>,
     pub characters: Arc<CharacterDBService>,
@@ -90,12 +106,17 @@ pub struct DbHandle {
     pub kinds: Arc<KindDBService>,
     pub lists: Arc<ListDBService>,
     pub mangas: Arc<MangaDBService>,
+    pub notifications: Arc<NotificationDBService>,
     pub pages: Arc<PageDBService>,
+    pub popularity: Arc<PopularityDBService>,
     pub progress: Arc<UserProgressDBService>,
+    pub saved_searches: Arc<SavedSearchDBService>,
     pub scraper: Arc<ScraperDbService>,
+    pub sessions: Arc<SessionDBService>,
     pub tags: Arc<TagDBService>,
     pub versions: Arc<VersionDBService>,
     pub chapter_versions: Arc<ChapterVersionDBService>,
+    pub version_preferences: Arc<VersionPreferenceDBService>,
 }
 
 impl DbHandle {
@@ -121,9 +142,11 @@ pub async fn init_db(config: DbConfig) -> Result<DbHandle, surrealdb::Error> {
             db.use_ns(&cfg.namespace).use_db(&cfg.database).await?;
         }
     }
+    fulltext::define(&db).await?;
 
     Ok(DbHandle {
         session: db.clone(),
+        api_keys: Arc::new(ApiKeyDBService::new(db.clone())),
         users: Arc::new(UserDBService::new(db.clone())),
         tokens: Arc::new(AuthTokenDBService::new(db.clone())),
         characters: Arc::new(CharacterDBService::new(db.clone())),
@@ -131,11 +154,16 @@ pub async fn init_db(config: DbConfig) -> Result<DbHandle, surrealdb::Error> {
         kinds: Arc::new(KindDBService::new(db.clone())),
         lists: Arc::new(ListDBService::new(db.clone())),
         mangas: Arc::new(MangaDBService::new(db.clone())),
+        notifications: Arc::new(NotificationDBService::new(db.clone())),
         pages: Arc::new(PageDBService::new(db.clone())),
+        popularity: Arc::new(PopularityDBService::new(db.clone())),
         progress: Arc::new(UserProgressDBService::new(db.clone())),
+        saved_searches: Arc::new(SavedSearchDBService::new(db.clone())),
         scraper: Arc::new(ScraperDbService::new(db.clone())),
+        sessions: Arc::new(SessionDBService::new(db.clone())),
         tags: Arc::new(TagDBService::new(db.clone())),
         versions: Arc::new(VersionDBService::new(db.clone())),
-        chapter_versions: Arc::new(ChapterVersionDBService::new(db)),
+        chapter_versions: Arc::new(ChapterVersionDBService::new(db.clone())),
+        version_preferences: Arc::new(VersionPreferenceDBService::new(db)),
     })
 }
@@ -1,11 +1,11 @@
 use std::collections::{HashMap, HashSet};
 
 use api_structure::{
-    search::{Array, Item, ItemOrArray, ItemValue, Order, SearchRequest},
-    v1::{Status, TagSex, Visibility},
+    search::{Order, SearchRequest},
+    v1::{Role, Status, Visibility},
 };
 use serde::{Deserialize, Serialize};
-use surrealdb::{opt::PatchOp, Datetime};
+use surrealdb::{opt::PatchOp, Datetime, RecordId};
 use surrealdb_extras::{
     RecordData, RecordIdFunc, RecordIdType, SurrealSelect, SurrealTable, SurrealTableInfo,
     ThingArray,
@@ -14,14 +14,17 @@ use surrealdb_extras::{
 use crate::{
     chapter::ChapterDBService,
     error::{DbError, DbResult},
-    DbSession,
+    fulltext::{self, HIGHLIGHT_END, HIGHLIGHT_START, TITLES},
+    popularity::score_field,
+    saved_search,
+    search::{bind_user, reading_progress, CompiledQuery},
+    visibility, DbSession,
 };
 
 use super::{
     chapter::Chapter,
     character::Character,
     kind::Kind,
-    lists::MangaList,
     tag::{Empty, Tag},
     user::User,
     version::Version,
@@ -36,6 +39,12 @@ pub struct Count {
     count: u64,
 }
 
+#[derive(Deserialize)]
+struct Highlight {
+    id: RecordId,
+    highlight: Option<String>,
+}
+
 #[derive(SurrealSelect, Deserialize)]
 pub struct MangaTitle {
     /// Title map<language, title>
@@ -114,6 +123,9 @@ pub struct Scraper {
     #[serde(default = "true_default")]
     pub enabled: bool,
     pub url: String,
+    /// Scraped chapters are approved without a review
+    #[serde(default)]
+    pub auto_approve: bool,
 }
 
 pub fn vec_default<T>() -> Vec<T> {
@@ -143,272 +155,6 @@ fn get_with_prio(v: HashMap<String, Vec<String>>, prio: &Vec<String>) -> String
         .unwrap_or_default()
 }
 
-fn filter_array_or_item(ior: ItemOrArray) -> Option<ItemOrArray> {
-    match ior {
-        ItemOrArray::Array(array) => filter_array(array).map(ItemOrArray::Array),
-        item => Some(item),
-    }
-}
-
-fn filter_array(mut arr: Array) -> Option<Array> {
-    arr.items = arr
-        .items
-        .into_iter()
-        .filter_map(filter_array_or_item)
-        .collect();
-    if arr.items.is_empty() {
-        return None;
-    }
-    Some(arr)
-}
-
-fn generate_item(
-    item: Item,
-    user_id: &RecordIdType<User>,
-) -> Result<(String, Option<bool>), String> {
-    let not = match item.not {
-        true => "!",
-        false => "",
-    };
-    let not2 = match item.not {
-        true => "not",
-        false => "",
-    };
-    let is_enum = matches!(item.data.value, ItemValue::None);
-    let query = match (is_enum, item.data.name.as_str()) {
-        (true, "next-available") => format!("reading.progress < 1 AND reading.progress != None"),
-        (true, v) => format!(
-            "array::flatten(object::values(titles)).any(|$s|string::contains(string::lowercase($s),'{}'))",
-            v.to_lowercase()
-        ),
-        (false, "") | (false, "title") => format!(
-            "array::flatten(object::values(titles)).any(|$s|string::contains(string::lowercase($s),'{}'))",
-            item.data
-                .value
-                .get_string()
-                .ok_or("title needs to be a string".to_owned())?.to_lowercase()
-        ),
-        (false, "description") => format!(
-            "description {not}~ '{}'",
-            item.data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string".to_owned())?
-        ),
-        (false,"k") |(false,"kind" )=> format!(
-            "kind = (SELECT id FROM kinds WHERE kind {not}= '{}' LIMIT 1)[0].id",
-            item.data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string".to_owned())?
-        ),
-        (false,"male")|(false, "m") => {
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::Male as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"female") | (false,"f") => {
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::Female as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"both" )| (false,"b") => {
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::Both as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"male2female")|(false, "mf") =>{
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::MaleFemale as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"female2male" )| (false,"fm") => {
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::FemaleMale as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"none") | (false,"n") => {
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::None as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"unknown") | (false,"u" )=>{
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            let sex = TagSex::Unknown as u32;
-            format!(
-            "(SELECT id FROM tags WHERE tag = '{tag}' AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
-            match item.not {
-                true => "NOT",
-                false => "",
-            })
-        }
-        (false,"tag") |(false, "t") => {
-            let tag = item
-                .data
-                .value
-                .get_string()
-                .ok_or("description needs to be a string, int".to_owned())?;
-            format!(
-            "generated_tags {} (SELECT id FROM tags WHERE tag = '{tag}').id",
-            match item.not {
-                true => "NONEINSIDE",
-                false => "CONTAINSANY",
-            })
-        }
-        (false,"status") | (false,"s") => format!(
-            "status = {}",
-            item.data
-                .value
-                .get_int()
-                .ok_or("title needs to be a int".to_owned())?
-        ),
-        (false,"uploader") => format!("uploader {not}= (SELECT id FROM users WHERE array::some(names, |$n: string| string::lowercase($n) = '{}') LIMIT 1)[0].id ", item.data
-            .value
-            .get_string()
-            .ok_or("uploader needs to be a string".to_owned())?),
-        (false,"artist") => format!("(SELECT id FROM users WHERE array::some(names, |$n: string| string::lowercase($n) = '{}') LIMIT 1)[0].id {not2} in artists", item.data
-            .value
-            .get_string()
-            .ok_or("artists needs to be a string".to_owned())?),
-        (false,"author") | (false,"a")=> format!("(SELECT id FROM users WHERE array::some(names, |$n: string| string::lowercase($n) = '{}') LIMIT 1)[0].id {not2} in authors", item.data
-            .value
-            .get_string()
-            .ok_or("authors needs to be a string".to_owned())?),
-        (false,"publisher")|(false,"p" )=> format!("(SELECT id FROM users WHERE array::some(names, |$n: string| string::lowercase($n) = '{}') LIMIT 1)[0].id {not2} in publishers", item.data
-            .value
-            .get_string()
-            .ok_or("publishers needs to be a string".to_owned())?),
-       (false, "chapters") |(false,"c")=> {
-            let (mut eq, mut bigger, number) = item.data.value.get_cmp_int()
-                .ok_or("chapters needs to be a eg. >= 10".to_owned())?;
-            if item.not {
-                eq = !eq;
-                bigger = !bigger;
-            }
-            format!("array::len(chapters) {}{} {number}", if bigger { ">" } else { "<" }, if eq { "=" } else { "" })
-        }
-        (false,"list") |(false, "l" )=> format!("id {not2} IN (SELECT mangas FROM {} WHERE name = '{}' AND user = {} LIMIT 1)[0].mangas", MangaList::name(),item.data
-            .value
-            .get_string()
-            .ok_or("publishers needs to be a string".to_owned())?, user_id.to_string()),
-        _ => Err(format!("Unknown item {}", item.data.name))?,
-    };
-    Ok((query, item.or_post))
-}
-
-fn generate_item_or_array(
-    ior: ItemOrArray,
-    user_id: &RecordIdType<User>,
-) -> Result<(String, Option<bool>), String> {
-    match ior {
-        ItemOrArray::Item(item) => generate_item(item, user_id),
-        ItemOrArray::Array(array) => generate_array(array, user_id),
-    }
-}
-
-fn generate_array(
-    arr: Array,
-    user_id: &RecordIdType<User>,
-) -> Result<(String, Option<bool>), String> {
-    let mut queries = vec![];
-    let len = arr.items.len();
-    for (index, (query, or_post)) in arr
-        .items
-        .into_iter()
-        .map(|v| generate_item_or_array(v, user_id))
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .enumerate()
-    {
-        queries.push(query);
-        if index != len - 1 {
-            queries.push(
-                match or_post.unwrap_or(arr.or) {
-                    true => "OR",
-                    false => "AND",
-                }
-                .to_owned(),
-            );
-        }
-    }
-    Ok((
-        format!(
-            "{}({})",
-            match arr.not {
-                true => "!",
-                false => "",
-            },
-            queries.join(" "),
-        ),
-        arr.or_post,
-    ))
-}
-
-fn search_array(q: &str, arr: &Array) -> bool {
-    arr.items.iter().any(|v| match v {
-        ItemOrArray::Item(item) => item.data.name.as_str() == q,
-        ItemOrArray::Array(array) => search_array(q, array),
-    })
-}
-
 impl MangaDBService {
     pub fn new(db: DbSession) -> Self {
         Self { db }
@@ -514,42 +260,80 @@ impl MangaDBService {
             .ok_or(DbError::NotFound)
     }
 
+    /// Searches mangas which are visible for the user.
+    /// The items contain the highlighted title if the query has a title match
     pub async fn search(
         &self,
         data: SearchRequest,
         user: RecordIdType<User>,
+        role: Role,
         count: bool,
-    ) -> DbResult<(u64, Vec<RecordData<Manga>>)> {
-        let mut what = vec!["*"];
-        let mut tb = Manga::name().to_owned();
-        let order =
-            Order::try_from(data.order).map_err(|v| DbError::SearchParseError(v.message()))?;
-        let user_id = user.to_string();
-        if search_array("next-available", &data.query) {
-            tb = format!(
-                "(SELECT *, (select updated, progress from user_progress where user = {user_id} AND manga = $parent.id LIMIT 1)[0] as reading FROM mangas)"
-            );
+    ) -> DbResult<(u64, Vec<(RecordData<Manga>, Option<String>)>)> {
+        let order = Order::try_from(data.order.clone())
+            .map_err(|v| DbError::SearchParseError(v.message()))?;
+        let query = saved_search::expand(&self.db, data.query.clone(), &user).await?;
+        let filter = CompiledQuery::compile(query.clone()).map_err(DbError::SearchParseError)?;
+        let result = self
+            .search_compiled(filter, order, &data, &user, role, count)
+            .await?;
+        // later pages can be empty because the results ended, only a count of 0 means nothing matched
+        if !result.1.is_empty() || (data.page > 1 && !(count && result.0 == 0)) {
+            return Ok(result);
+        }
+        // nothing matched, titles with typos are searched instead
+        match CompiledQuery::compile_with_typos(query).map_err(DbError::SearchParseError)? {
+            Some(filter) => {
+                self.search_compiled(Some(filter), order, &data, &user, role, count)
+                    .await
+            }
+            None => Ok(result),
         }
+    }
 
+    async fn search_compiled(
+        &self,
+        filter: Option<CompiledQuery>,
+        order: Order,
+        data: &SearchRequest,
+        user: &RecordIdType<User>,
+        role: Role,
+        count: bool,
+    ) -> DbResult<(u64, Vec<(RecordData<Manga>, Option<String>)>)> {
+        let mut what = vec!["*".to_owned()];
+        let tb = Manga::name();
         match order {
             Order::ChapterCount => {
-                what.push("array::len(chapters) as chapter_count");
+                what.push("array::len(chapters) as chapter_count".to_owned());
             }
             Order::LastRead => {
-                tb = format!(
-                    "(SELECT *, (select updated, progress from user_progress where user = {user_id} AND manga = $parent.id LIMIT 1)[0] as reading FROM mangas)"
-                );
-                what.push("reading");
+                what.push(format!(
+                    "{} AS reading",
+                    reading_progress("updated, progress")
+                ));
             }
             Order::Alphabetical => {
-                what.push("IF titles.en != NONE THEN titles.en[0] ELSE array::first(object::values(titles))[0] END AS title_en");
+                what.push("IF titles.en != NONE THEN titles.en[0] ELSE array::first(object::values(titles))[0] END AS title_en".to_owned());
+            }
+            Order::Popularity => {
+                what.push(format!("{} AS popularity", score_field("popularity")));
+            }
+            Order::Trending => {
+                what.push(format!("{} AS trending", score_field("trending_7d")));
+            }
+            Order::Relevance => {
+                what.push(format!(
+                    "{} AS relevance",
+                    filter
+                        .as_ref()
+                        .map(|v| v.relevance())
+                        .unwrap_or("0".to_owned())
+                ));
             }
             _ => {}
         }
 
         //TODO:
         // future: sources,relations
-        // custom visibility
         let order_by = format!(
             "ORDER BY {} {}",
             match order {
@@ -557,7 +341,9 @@ impl MangaDBService {
                 Order::Created => "created",
                 Order::Updated => "updated",
                 Order::LastRead => "reading.updated",
-                Order::Popularity => "updated",
+                Order::Popularity => "popularity",
+                Order::Trending => "trending",
+                Order::Relevance => "relevance",
                 Order::Random => "rand()",
                 Order::Status => "status",
                 Order::ChapterCount => "chapter_count",
@@ -571,35 +357,68 @@ impl MangaDBService {
             },
         );
 
-        let limit = format!(
-            "LIMIT {} START {}",
-            data.limit,
-            data.page.saturating_sub(1) * data.limit,
-        );
         let what = what.join(", ");
-        let query_ = match filter_array(data.query) {
-            Some(arr) => format!(
-                "WHERE {}",
-                generate_array(arr, &user)
-                    .map_err(DbError::SearchParseError)?
-                    .0
-            ),
-            None => "".to_owned(),
+        let query_ = match (&filter, visibility::condition(role)) {
+            (Some(filter), Some(visible)) => format!("WHERE ({}) AND {visible}", filter.query),
+            (Some(filter), None) => format!("WHERE {}", filter.query),
+            (None, Some(visible)) => format!("WHERE {visible}"),
+            (None, None) => "".to_owned(),
         };
 
-        let query = format!("SELECT {what} FROM {tb} {} {order_by} {limit}", &query_);
-        println!("{query}");
-        let data: Vec<RecordData<Manga>> = self.db.query(query).await?.take(0)?;
+        let query =
+            format!("SELECT {what} FROM {tb} {query_} {order_by} LIMIT $limit START $start");
+        let mut query = bind_user(self.db.query(query), user)
+            .bind(("limit", data.limit))
+            .bind(("start", data.page.saturating_sub(1) * data.limit));
+        if let Some(filter) = &filter {
+            query = filter.bind(query);
+        }
+        let items: Vec<RecordData<Manga>> = query.await?.take(0)?;
+        let mut highlights = match filter.as_ref().and_then(|v| v.highlight.as_ref()) {
+            Some((reference, param)) if !items.is_empty() => {
+                let query = format!(
+                    "SELECT id, search::highlight('{HIGHLIGHT_START}', '{HIGHLIGHT_END}', {reference}) AS highlight
+                    FROM {tb} WHERE {TITLES} @{reference}@ {param} AND id IN $ids"
+                );
+                let ids = items.iter().map(|v| v.id.clone()).collect::<Vec<_>>();
+                let query = self.db.query(query).bind(("ids", ids));
+                let highlights: Vec<Highlight> = filter
+                    .as_ref()
+                    .map(|v| v.bind(query))
+                    .expect("highlight is part of the filter")
+                    .await?
+                    .take(0)?;
+                highlights
+                    .into_iter()
+                    .filter_map(|v| {
+                        fulltext::highlighted_title(&v.highlight?)
+                            .map(|title| (v.id.id().to_string(), title))
+                    })
+                    .collect()
+            }
+            _ => HashMap::new(),
+        };
+        let items = items
+            .into_iter()
+            .map(|v| {
+                let highlight = highlights.remove(&v.id.id().to_string());
+                (v, highlight)
+            })
+            .collect();
 
         if count {
-            let count: Option<Count> = self
-                .db
-                .query(format!("SELECT count() FROM {tb} {query_} GROUP ALL;"))
-                .await?
-                .take(0)?;
-            Ok((count.map(|v| v.count).unwrap_or_default(), data))
+            let mut query = bind_user(
+                self.db
+                    .query(format!("SELECT count() FROM {tb} {query_} GROUP ALL;")),
+                user,
+            );
+            if let Some(filter) = &filter {
+                query = filter.bind(query);
+            }
+            let count: Option<Count> = query.await?.take(0)?;
+            Ok((count.map(|v| v.count).unwrap_or_default(), items))
         } else {
-            Ok((0, data))
+            Ok((0, items))
         }
     }
 
@@ -768,4 +587,26 @@ impl MangaDBService {
             .await?
             .ok_or(DbError::NotFound)
     }
+
+    /// Like [Self::get], but mangas which cant be seen by `role` are not found
+    pub async fn get_visible(
+        &self,
+        id: &str,
+        user: RecordIdType<User>,
+        role: Role,
+    ) -> DbResult<Manga> {
+        let Some(visible) = visibility::condition(role) else {
+            return self.get(id).await;
+        };
+        let query = self
+            .db
+            .query(format!("SELECT * FROM $id WHERE {visible}"))
+            .bind(("id", RecordIdType::<Manga>::from((Manga::name(), id))));
+        let manga: Vec<RecordData<Manga>> = bind_user(query, &user).await?.take(0)?;
+        manga
+            .into_iter()
+            .next()
+            .map(|v| v.data)
+            .ok_or(DbError::NotFound)
+    }
 }
@@ -0,0 +1,358 @@
+use std::collections::{HashMap, HashSet};
+
+use api_structure::v1::Role;
+use serde::{Deserialize, Serialize};
+use surrealdb::{opt::PatchOp, Datetime};
+use surrealdb_extras::{
+    RecordData, RecordIdFunc, RecordIdType, SurrealSelect, SurrealTable, SurrealTableInfo,
+};
+
+use crate::{
+    error::{DbError, DbResult},
+    lists::MangaList,
+    progress::UserProgress,
+    tag::Empty,
+    user::NotificationMode,
+    visibility, DbSession,
+};
+
+use super::{chapter::Chapter, manga::Manga, user::User};
+
+/// Failed deliveries are retried until this many attempts were made
+pub const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("notifications")]
+#[sql(["DEFINE EVENT notification_updated ON TABLE notifications WHEN $event = \"UPDATE\" AND $before.updated == $after.updated THEN (UPDATE $after.id SET updated = time::now() );"])]
+pub struct Notification {
+    /// User who is notified
+    pub user: RecordIdType<User>,
+    /// Manga which got a new chapter
+    pub manga: RecordIdType<Manga>,
+    /// The new chapter
+    pub chapter: RecordIdType<Chapter>,
+    /// Chapter number, so listing does not need to fetch the chapter
+    pub number: f64,
+    /// Seen by the user
+    pub read: bool,
+    /// Sent by the channel of the user
+    pub delivered: bool,
+    /// Failed deliveries
+    pub attempts: u32,
+    #[opt(exclude = true)]
+    pub updated: Datetime,
+    #[opt(exclude = true)]
+    pub created: Datetime,
+}
+
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("push_subscriptions")]
+pub struct PushSubscription {
+    /// User who subscribed
+    pub user: RecordIdType<User>,
+    /// Url of the push service
+    pub endpoint: String,
+    /// Public key of the browser
+    pub p256dh: String,
+    /// Auth secret of the browser
+    pub auth: String,
+    #[opt(exclude = true)]
+    pub created: Datetime,
+}
+
+/// Notification with the titles of the manga
+#[derive(SurrealSelect, Deserialize)]
+pub struct NotificationEntry {
+    pub manga: RecordIdType<Manga>,
+    pub chapter: RecordIdType<Chapter>,
+    pub number: f64,
+    pub read: bool,
+    pub created: Datetime,
+    pub titles: HashMap<String, Vec<String>>,
+}
+
+/// Undelivered notification with everything needed to send it
+#[derive(SurrealSelect, Deserialize)]
+pub struct PendingNotification {
+    pub user: RecordIdType<User>,
+    pub manga: RecordIdType<Manga>,
+    pub chapter: RecordIdType<Chapter>,
+    pub number: f64,
+    pub attempts: u32,
+    pub titles: HashMap<String, Vec<String>>,
+    pub email: String,
+    pub mode: Option<NotificationMode>,
+    pub webhook: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Count {
+    count: u64,
+}
+
+#[derive(SurrealSelect, Deserialize)]
+struct MangaAccess {
+    visibility: u64,
+    uploader: RecordIdType<User>,
+}
+
+#[derive(SurrealSelect, Deserialize)]
+struct UserRole {
+    role: u32,
+}
+
+#[derive(Clone)]
+pub struct NotificationDBService {
+    db: DbSession,
+}
+
+impl NotificationDBService {
+    pub fn new(db: DbSession) -> Self {
+        Self { db }
+    }
+
+    /// Notifies everyone who has the manga in their favorites or is reading it & can see it.
+    /// Returns the number of created notifications
+    pub async fn create_for_chapter(
+        &self,
+        manga_id: &str,
+        chapter_id: &str,
+        number: f64,
+    ) -> DbResult<usize> {
+        let manga = RecordIdType::<Manga>::from((Manga::name(), manga_id));
+        let mut response = self
+            .db
+            .query(format!(
+                "SELECT VALUE user FROM {} WHERE name = 'favorites' AND $manga INSIDE mangas;",
+                MangaList::name()
+            ))
+            .query(format!(
+                "SELECT VALUE user FROM {} WHERE manga = $manga;",
+                UserProgress::name()
+            ))
+            .bind(("manga", manga.clone()))
+            .await?;
+        let favorites: Vec<RecordIdType<User>> = response.take(0)?;
+        let reading: Vec<RecordIdType<User>> = response.take(1)?;
+        let users = favorites
+            .into_iter()
+            .chain(reading)
+            .map(|v| v.id().to_string())
+            .collect::<HashSet<_>>();
+        if users.is_empty() {
+            return Ok(0);
+        }
+
+        let access: RecordData<MangaAccess> = RecordIdFunc::from((Manga::name(), manga_id))
+            .get_part(self.db.as_ref())
+            .await?
+            .ok_or(DbError::NotFound)?;
+        let uploader = access.data.uploader.id().to_string();
+        let roles: Vec<RecordData<UserRole>> = self
+            .db
+            .query(format!(
+                "SELECT id, role FROM {} WHERE id INSIDE $users AND disabled = false",
+                User::name()
+            ))
+            .bind((
+                "users",
+                users
+                    .iter()
+                    .map(|v| RecordIdType::<User>::from((User::name(), v.as_str())))
+                    .collect::<Vec<_>>(),
+            ))
+            .await?
+            .take(0)?;
+
+        let mut created = 0;
+        for user in roles {
+            let uid = user.id.id().to_string();
+            let Ok(role) = Role::try_from(user.data.role) else {
+                continue;
+            };
+            if !visibility::can_access(role, &uid, access.data.visibility, &uploader) {
+                continue;
+            }
+            Notification {
+                user: RecordIdType::from((User::name(), uid.as_str())),
+                manga: manga.clone(),
+                chapter: RecordIdType::from((Chapter::name(), chapter_id)),
+                number,
+                read: false,
+                delivered: false,
+                attempts: 0,
+                updated: Default::default(),
+                created: Default::default(),
+            }
+            .add_i(self.db.as_ref())
+            .await?;
+            created += 1;
+        }
+        Ok(created)
+    }
+
+    /// Removes the notifications of a deleted chapter
+    pub async fn delete_for_chapter(&self, chapter_id: &str) -> DbResult<()> {
+        self.db
+            .query(format!(
+                "DELETE FROM {} WHERE chapter = $chapter",
+                Notification::name()
+            ))
+            .bind((
+                "chapter",
+                RecordIdType::<Chapter>::from((Chapter::name(), chapter_id)),
+            ))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Newest first. Returns the notifications & the number of unread notifications
+    pub async fn list(
+        &self,
+        user: &str,
+        page: u32,
+        limit: u32,
+        unread_only: bool,
+    ) -> DbResult<(Vec<RecordData<NotificationEntry>>, u64)> {
+        let filter = match unread_only {
+            true => " AND read = false",
+            false => "",
+        };
+        let mut response = self
+            .db
+            .query(format!(
+                "SELECT id, manga, chapter, number, read, created, manga.titles AS titles FROM {} WHERE user = $user{filter} ORDER BY created DESC LIMIT $limit START $start;",
+                Notification::name()
+            ))
+            .query(format!(
+                "SELECT count() FROM {} WHERE user = $user AND read = false GROUP ALL;",
+                Notification::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind(("limit", limit))
+            .bind(("start", page.saturating_sub(1) * limit))
+            .await?;
+        let items = response.take(0)?;
+        let unread: Option<Count> = response.take(1)?;
+        Ok((items, unread.map(|v| v.count).unwrap_or_default()))
+    }
+
+    /// Marks the notifications as read. Ids of other users are ignored
+    pub async fn mark_read(&self, user: &str, ids: Vec<String>) -> DbResult<()> {
+        let ids = ids
+            .iter()
+            .map(|v| RecordIdType::<Notification>::from((Notification::name(), v.as_str())))
+            .collect::<Vec<_>>();
+        self.db
+            .query(format!(
+                "UPDATE {} SET read = true WHERE user = $user AND id INSIDE $ids",
+                Notification::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind(("ids", ids))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    pub async fn mark_all_read(&self, user: &str) -> DbResult<()> {
+        self.db
+            .query(format!(
+                "UPDATE {} SET read = true WHERE user = $user AND read = false",
+                Notification::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Oldest first
+    pub async fn pending(&self, limit: u32) -> DbResult<Vec<RecordData<PendingNotification>>> {
+        Ok(self
+            .db
+            .query(format!(
+                "SELECT id, user, manga, chapter, number, attempts, manga.titles AS titles, user.email AS email, user.notification_mode AS mode, user.webhook AS webhook FROM {} WHERE delivered = false AND attempts < $max ORDER BY created LIMIT $limit",
+                Notification::name()
+            ))
+            .bind(("max", MAX_ATTEMPTS))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?)
+    }
+
+    pub async fn set_delivered(&self, id: &str) -> DbResult<()> {
+        let _: Option<RecordData<Empty>> = RecordIdFunc::from((Notification::name(), id))
+            .patch(self.db.as_ref(), PatchOp::replace("/delivered", true))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_failed(&self, id: &str, attempts: u32) -> DbResult<()> {
+        let _: Option<RecordData<Empty>> = RecordIdFunc::from((Notification::name(), id))
+            .patch(self.db.as_ref(), PatchOp::replace("/attempts", attempts))
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces the keys if the endpoint is already subscribed
+    pub async fn subscribe(
+        &self,
+        user: &str,
+        endpoint: String,
+        p256dh: String,
+        auth: String,
+    ) -> DbResult<()> {
+        self.unsubscribe(user, &endpoint).await?;
+        PushSubscription {
+            user: RecordIdType::from((User::name(), user)),
+            endpoint,
+            p256dh,
+            auth,
+            created: Default::default(),
+        }
+        .add_i(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&self, user: &str, endpoint: &str) -> DbResult<()> {
+        self.db
+            .query(format!(
+                "DELETE FROM {} WHERE user = $user AND endpoint = $endpoint",
+                PushSubscription::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind(("endpoint", endpoint.to_owned()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Removes the endpoint of every user, e.g. when the push service reports that it expired
+    pub async fn remove_subscription(&self, endpoint: &str) -> DbResult<()> {
+        self.db
+            .query(format!(
+                "DELETE FROM {} WHERE endpoint = $endpoint",
+                PushSubscription::name()
+            ))
+            .bind(("endpoint", endpoint.to_owned()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    pub async fn subscriptions(&self, user: &str) -> DbResult<Vec<PushSubscription>> {
+        let v: Vec<RecordData<PushSubscription>> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE user = $user",
+                PushSubscription::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .take(0)?;
+        Ok(v.into_iter().map(|v| v.data).collect())
+    }
+}
@@ -1,13 +1,23 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::{Arc, Mutex},
+};
+
 use serde::{Deserialize, Serialize};
-use storage::{FileBuilderExt as _, MangaPageFileBuilder};
-use surrealdb::Datetime;
-use surrealdb_extras::{RecordData, RecordIdFunc, RecordIdType, SurrealTable, ThingArray};
+use storage::{FileBuilderExt as _, HashSink, HashSinkError, MangaPageFileBuilder};
+use surrealdb::{opt::PatchOp, Datetime};
+use surrealdb_extras::{
+    RecordData, RecordIdFunc, RecordIdType, SurrealTable, SurrealTableInfo, ThingArray,
+};
 
 use crate::{
     error::{DbError, DbResult},
     DbSession,
 };
 
+use super::{chapter::Chapter, manga::Manga, version::Version, version_link::ChapterVersion};
+
 #[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
 #[db("manga_pages")]
 #[sql(["DEFINE EVENT manga_page_updated ON TABLE manga_pages WHEN $event = \"UPDATE\" AND $before.updated == $after.updated THEN (UPDATE $after.id SET updated = time::now() );"])]
@@ -20,7 +30,9 @@ pub struct Page {
     pub height: u32,
     /// Extension of the page
     pub ext: String,
-    /// Hash of the page
+    /// Bytes of the file. Not known for pages added before it was recorded
+    pub size: Option<u64>,
+    /// Perceptual hash of the page as base64. Set in the background after the page was added
     pub hash: Option<String>,
     #[opt(exclude = true)]
     pub updated: Datetime,
@@ -28,14 +40,84 @@ pub struct Page {
     pub created: Datetime,
 }
 
+/// Pages of a chapter version with their perceptual hashes
+#[derive(Debug, Clone)]
+pub struct HashedVersion {
+    pub manga: String,
+    pub chapter: String,
+    /// Chapter number
+    pub episode: f64,
+    pub version: String,
+    pub chapter_version: String,
+    /// Page ids & hashes in reading order. Pages which are not hashed yet are skipped
+    pub pages: Vec<(String, String)>,
+}
+
+#[derive(Deserialize)]
+struct VersionHashes {
+    id: RecordIdType<ChapterVersion>,
+    version: RecordIdType<Version>,
+    pages: Vec<RecordIdType<Page>>,
+    hashes: Vec<Option<String>>,
+}
+
+/// Pages of a chapter version, used to compare the quality of versions
+#[derive(Deserialize, Debug)]
+pub struct VersionPages {
+    pub id: RecordIdType<ChapterVersion>,
+    pub pages: Vec<Page>,
+}
+
+#[derive(Deserialize)]
+struct ChapterVersions {
+    id: RecordIdType<Chapter>,
+    chapter: f64,
+    versions: HashMap<String, RecordIdType<ChapterVersion>>,
+}
+
+#[derive(Deserialize)]
+struct MangaChapters {
+    id: RecordIdType<Manga>,
+    chapters: Vec<RecordIdType<Chapter>>,
+}
+
 #[derive(Clone)]
 pub struct PageDBService {
     db: DbSession,
+    /// Pages whose hash was stored or which were deleted. `None` until [`Self::track_changes`]
+    changed: Arc<Mutex<Option<HashSet<String>>>>,
 }
 
 impl PageDBService {
     pub fn new(db: DbSession) -> Self {
-        Self { db }
+        Self {
+            db,
+            changed: Arc::default(),
+        }
+    }
+
+    /// Starts recording the pages which change, so [`Self::take_changes`] returns them
+    pub fn track_changes(&self) {
+        self.changed
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashSet::new);
+    }
+
+    /// Pages whose hash was stored or which were deleted since the last call
+    pub fn take_changes(&self) -> HashSet<String> {
+        self.changed
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(mem::take)
+            .unwrap_or_default()
+    }
+
+    fn changed(&self, page: String) {
+        if let Some(changed) = self.changed.lock().unwrap().as_mut() {
+            changed.insert(page);
+        }
     }
 
     pub async fn get(&self, ids: Vec<RecordIdType<Page>>) -> DbResult<Vec<RecordData<Page>>> {
@@ -44,13 +126,14 @@ impl PageDBService {
     }
     pub async fn add(&self, pages: Vec<MangaPageFileBuilder>) -> DbResult<Vec<RecordIdType<Page>>> {
         let mut out = vec![];
-        for (index, page) in pages.into_iter().enumerate() {
+        for (index, mut page) in pages.into_iter().enumerate() {
             let ext = page.ext().map_err(|_| DbError::NoExtension)?.to_owned();
             let p = Page {
                 page: index as u32 + 1,
                 width: page.width().ok_or(DbError::NoImage)?,
                 height: page.height().ok_or(DbError::NoImage)?,
                 ext: ext.clone(),
+                size: page.size(),
                 hash: None,
                 updated: Default::default(),
                 created: Default::default(),
@@ -59,16 +142,135 @@ impl PageDBService {
             .await?;
             let p = p.ok_or(DbError::NotFound)?;
 
+            page.attach_hash(p.id.id().to_string(), Arc::new(self.clone()));
             page.build(index + 1).await?;
             out.push(p);
         }
         Ok(out.into_iter().map(Into::into).collect())
     }
 
+    /// Pages of each of the chapter versions in reading order
+    pub async fn version_pages(
+        &self,
+        chapter_versions: Vec<String>,
+    ) -> DbResult<Vec<VersionPages>> {
+        if chapter_versions.is_empty() {
+            return Ok(vec![]);
+        }
+        let ids: Vec<RecordIdType<ChapterVersion>> = chapter_versions
+            .iter()
+            .map(|v| RecordIdType::from((ChapterVersion::name(), v.as_str())))
+            .collect();
+        Ok(self
+            .db
+            .query("SELECT id, pages.* AS pages FROM $ids")
+            .bind(("ids", ids))
+            .await?
+            .take(0)?)
+    }
+
+    /// Every chapter version of the library which belongs to a manga
+    pub async fn hashed_versions(&self) -> DbResult<Vec<HashedVersion>> {
+        self.load_hashed_versions(None).await
+    }
+
+    /// Chapter versions which contain one of `pages`
+    pub async fn hashed_versions_of(&self, pages: Vec<String>) -> DbResult<Vec<HashedVersion>> {
+        let pages = pages
+            .iter()
+            .map(|v| RecordIdType::from((Page::name(), v.as_str())))
+            .collect();
+        self.load_hashed_versions(Some(pages)).await
+    }
+
+    async fn load_hashed_versions(
+        &self,
+        pages: Option<Vec<RecordIdType<Page>>>,
+    ) -> DbResult<Vec<HashedVersion>> {
+        let filter = match pages {
+            Some(_) => "WHERE pages CONTAINSANY $pages",
+            None => "",
+        };
+        let mut res = self
+            .db
+            .query(format!(
+                "SELECT id, version, pages, pages.hash AS hashes FROM {} {filter};",
+                ChapterVersion::name()
+            ))
+            .query(format!(
+                "SELECT id, chapter, versions FROM {};",
+                Chapter::name()
+            ))
+            .query(format!("SELECT id, chapters FROM {};", Manga::name()))
+            .bind(("pages", pages))
+            .await?;
+        let versions: Vec<VersionHashes> = res.take(0)?;
+        let chapters: Vec<ChapterVersions> = res.take(1)?;
+        let mangas: Vec<MangaChapters> = res.take(2)?;
+
+        let manga_of: HashMap<String, String> = mangas
+            .into_iter()
+            .flat_map(|manga| {
+                let id = manga.id.id().to_string();
+                manga
+                    .chapters
+                    .into_iter()
+                    .map(move |chapter| (chapter.id().to_string(), id.clone()))
+            })
+            .collect();
+        let mut chapter_of = HashMap::new();
+        for chapter in chapters {
+            let chapter_id = chapter.id.id().to_string();
+            let Some(manga) = manga_of.get(&chapter_id) else {
+                continue;
+            };
+            for chapter_version in chapter.versions.into_values() {
+                chapter_of.insert(
+                    chapter_version.id().to_string(),
+                    (manga.clone(), chapter_id.clone(), chapter.chapter),
+                );
+            }
+        }
+
+        Ok(versions
+            .into_iter()
+            .filter_map(|v| {
+                let chapter_version = v.id.id().to_string();
+                let (manga, chapter, episode) = chapter_of.remove(&chapter_version)?;
+                Some(HashedVersion {
+                    manga,
+                    chapter,
+                    episode,
+                    version: v.version.id().to_string(),
+                    chapter_version,
+                    pages: v
+                        .pages
+                        .into_iter()
+                        .zip(v.hashes)
+                        .filter_map(|(page, hash)| Some((page.id().to_string(), hash?)))
+                        .collect(),
+                })
+            })
+            .collect())
+    }
+
     pub async fn delete(&self, pages: Vec<RecordIdType<Page>>) -> DbResult<()> {
         for page in pages {
+            let id = page.id().to_string();
             RecordIdFunc::from(page).delete_s(self.db.as_ref()).await?;
+            self.changed(id);
         }
         Ok(())
     }
 }
+
+#[async_trait::async_trait]
+impl HashSink for PageDBService {
+    async fn store_hash(&self, target: &str, hash: String) -> Result<(), HashSinkError> {
+        let _: Option<RecordData<Page>> = RecordIdFunc::from((Page::name(), target))
+            .patch(self.db.as_ref(), PatchOp::replace("/hash", hash))
+            .await?;
+        self.changed(target.to_owned());
+        Ok(())
+    }
+}
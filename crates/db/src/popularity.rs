@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::Datetime;
+use surrealdb_extras::{RecordIdType, SurrealTable, SurrealTableInfo};
+
+use crate::{error::DbResult, DbSession};
+
+use super::{chapter::Chapter, manga::Manga, user::User, version_link::ChapterVersion};
+
+/// Trending windows in hours
+pub const WINDOWS: [f64; 3] = [24.0, 24.0 * 7.0, 24.0 * 30.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    /// A chapter was finished
+    Read,
+    /// The manga was added to a list
+    ListAdd,
+    /// The pages of a chapter were loaded
+    PageView,
+}
+
+impl Activity {
+    fn field(self) -> &'static str {
+        match self {
+            Activity::Read => "reads",
+            Activity::ListAdd => "list_adds",
+            Activity::PageView => "views",
+        }
+    }
+
+    pub fn weight(self) -> f64 {
+        match self {
+            Activity::Read => 3.0,
+            Activity::ListAdd => 5.0,
+            Activity::PageView => 1.0,
+        }
+    }
+}
+
+/// Counters of a manga in one hour. Removed after the longest trending window
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("manga_activity")]
+pub struct MangaActivity {
+    pub manga: RecordIdType<Manga>,
+    /// Start of the hour
+    pub hour: Datetime,
+    pub reads: u64,
+    pub list_adds: u64,
+    pub views: u64,
+}
+
+/// A user viewed a chapter in an hour. Their views of the chapter in that hour are counted once.
+/// Removed when the trending scores are updated after the hour
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("manga_page_views")]
+pub struct PageViewMarker {
+    /// Start of the hour
+    pub hour: Datetime,
+}
+
+/// A user added a manga to a list. Further additions of the manga by the user are not counted
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("manga_list_adds")]
+pub struct ListAddMarker {
+    pub added: Datetime,
+}
+
+/// Scores of a manga. The id is the same as the one of the manga, so sorting doesnt need a join.
+/// Stored seperately, because updating the manga would change `updated`
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone, Default)]
+#[db("manga_popularity")]
+pub struct MangaPopularity {
+    /// Weighted activity since counting started
+    pub popularity: f64,
+    pub trending_24h: f64,
+    pub trending_7d: f64,
+    pub trending_30d: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ActivityBucket {
+    manga: RecordIdType<Manga>,
+    /// Hours since the start of the bucket
+    age: f64,
+    reads: u64,
+    list_adds: u64,
+    views: u64,
+}
+
+impl ActivityBucket {
+    fn weighted(&self) -> f64 {
+        self.reads as f64 * Activity::Read.weight()
+            + self.list_adds as f64 * Activity::ListAdd.weight()
+            + self.views as f64 * Activity::PageView.weight()
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Score {
+    id: String,
+    trending_24h: f64,
+    trending_7d: f64,
+    trending_30d: f64,
+}
+
+/// Sums the weighted activity of every window. Activity loses half of its value every half window
+fn scores(buckets: &[ActivityBucket]) -> Vec<Score> {
+    let mut scores: HashMap<String, [f64; 3]> = HashMap::new();
+    for bucket in buckets {
+        let score = scores.entry(bucket.manga.id().to_string()).or_default();
+        let age = bucket.age.max(0.0);
+        for (score, window) in score.iter_mut().zip(WINDOWS) {
+            if age < window {
+                *score += bucket.weighted() * 0.5f64.powf(age / (window / 2.0));
+            }
+        }
+    }
+    let mut scores = scores
+        .into_iter()
+        .map(|(id, [trending_24h, trending_7d, trending_30d])| Score {
+            id,
+            trending_24h,
+            trending_7d,
+            trending_30d,
+        })
+        .collect::<Vec<_>>();
+    scores.sort_by(|a, b| a.id.cmp(&b.id));
+    scores
+}
+
+/// Field of a manga which contains its score, used by search
+pub fn score_field(field: &str) -> String {
+    format!(
+        "(type::thing(\"{}\", record::id(id)).{field} ?? 0)",
+        MangaPopularity::name()
+    )
+}
+
+#[derive(Clone)]
+pub struct PopularityDBService {
+    db: DbSession,
+}
+
+impl PopularityDBService {
+    pub fn new(db: DbSession) -> Self {
+        Self { db }
+    }
+
+    /// Counts the activity in the bucket of the current hour & adds it to the popularity
+    pub async fn record(&self, manga_id: &str, activity: Activity) -> DbResult<()> {
+        let counters = [Activity::Read, Activity::ListAdd, Activity::PageView]
+            .into_iter()
+            .map(|v| match v == activity {
+                true => format!("{0} = ({0} ?? 0) + 1", v.field()),
+                false => format!("{0} = {0} ?? 0", v.field()),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.db
+            .query(format!(
+                "LET $hour = time::floor(time::now(), 1h);
+                UPSERT type::thing($activity, [$manga, $hour]) SET manga = $manga, hour = $hour, {counters};
+                UPSERT type::thing($popularity, record::id($manga)) SET popularity = (popularity ?? 0) + $weight,
+                    trending_24h = trending_24h ?? 0, trending_7d = trending_7d ?? 0, trending_30d = trending_30d ?? 0;"
+            ))
+            .bind(("activity", MangaActivity::name()))
+            .bind(("popularity", MangaPopularity::name()))
+            .bind(("manga", RecordIdType::<Manga>::from((Manga::name(), manga_id))))
+            .bind(("weight", activity.weight()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Counts a page view, unless the user viewed the chapter of the version in the current hour
+    pub async fn record_view(
+        &self,
+        manga_id: &str,
+        user: &str,
+        chapter_version_id: &str,
+    ) -> DbResult<()> {
+        let mut res = self
+            .db
+            .query(format!(
+                "LET $hour = time::floor(time::now(), 1h);
+                LET $chapter = (SELECT VALUE id FROM {} WHERE $version INSIDE object::values(versions) LIMIT 1)[0];
+                INSERT IGNORE INTO {} {{ id: [$user, $chapter, $hour], hour: $hour }};",
+                Chapter::name(),
+                PageViewMarker::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind((
+                "version",
+                RecordIdType::<ChapterVersion>::from((ChapterVersion::name(), chapter_version_id)),
+            ))
+            .await?;
+        let created: Vec<PageViewMarker> = res.take(2)?;
+        if created.is_empty() {
+            return Ok(());
+        }
+        self.record(manga_id, Activity::PageView).await
+    }
+
+    /// Counts a list addition, unless the user already added the manga to a list
+    pub async fn record_list_add(&self, manga_id: &str, user: &str) -> DbResult<()> {
+        let created: Vec<ListAddMarker> = self
+            .db
+            .query(format!(
+                "INSERT IGNORE INTO {} {{ id: [$user, $manga], added: time::now() }};",
+                ListAddMarker::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind((
+                "manga",
+                RecordIdType::<Manga>::from((Manga::name(), manga_id)),
+            ))
+            .await?
+            .take(0)?;
+        if created.is_empty() {
+            return Ok(());
+        }
+        self.record(manga_id, Activity::ListAdd).await
+    }
+
+    pub async fn get(&self, manga_id: &str) -> DbResult<MangaPopularity> {
+        let score: Option<MangaPopularity> =
+            self.db.select((MangaPopularity::name(), manga_id)).await?;
+        Ok(score.unwrap_or_default())
+    }
+
+    /// Recomputes the trending scores of every manga & removes buckets which are older than every window.
+    /// Returns the number of mangas with activity
+    pub async fn update_trending(&self) -> DbResult<usize> {
+        let oldest = format!("{}h", WINDOWS[WINDOWS.len() - 1]);
+        let buckets: Vec<ActivityBucket> = self
+            .db
+            .query(format!(
+                "SELECT manga, reads, list_adds, views, duration::mins(time::now() - hour) / 60f AS age
+                FROM {} WHERE hour > time::now() - {oldest}",
+                MangaActivity::name()
+            ))
+            .await?
+            .take(0)?;
+        let scores = scores(&buckets);
+        let len = scores.len();
+        self.db
+            .query(format!(
+                "BEGIN TRANSACTION;
+                UPDATE {popularity} SET trending_24h = 0, trending_7d = 0, trending_30d = 0;
+                FOR $score IN $scores {{
+                    UPSERT type::thing(\"{popularity}\", $score.id) SET popularity = popularity ?? 0,
+                        trending_24h = $score.trending_24h, trending_7d = $score.trending_7d, trending_30d = $score.trending_30d;
+                }};
+                DELETE {activity} WHERE hour <= time::now() - {oldest};
+                DELETE {views} WHERE hour < time::floor(time::now(), 1h);
+                COMMIT TRANSACTION;",
+                popularity = MangaPopularity::name(),
+                activity = MangaActivity::name(),
+                views = PageViewMarker::name(),
+            ))
+            .bind(("scores", scores))
+            .await?
+            .check()?;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb_extras::RecordIdType;
+
+    use super::{scores, ActivityBucket, Score};
+
+    fn bucket(manga: &str, age: f64, reads: u64, list_adds: u64, views: u64) -> ActivityBucket {
+        ActivityBucket {
+            manga: RecordIdType::from(("mangas", manga)),
+            age,
+            reads,
+            list_adds,
+            views,
+        }
+    }
+
+    #[test]
+    fn windows_only_count_their_buckets() {
+        let scores = scores(&[
+            bucket("a", 0.0, 1, 0, 0),
+            bucket("a", 48.0, 0, 1, 0),
+            bucket("b", 24.0 * 10.0, 0, 0, 2),
+        ]);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].id, "a");
+        assert_eq!(scores[0].trending_24h, 3.0);
+        let decay_7d = 0.5f64.powf(48.0 / 84.0);
+        assert!((scores[0].trending_7d - (3.0 + 5.0 * decay_7d)).abs() < 1e-9);
+        assert!(scores[0].trending_30d > scores[0].trending_7d);
+        assert_eq!(
+            scores[1],
+            Score {
+                id: "b".to_owned(),
+                trending_24h: 0.0,
+                trending_7d: 0.0,
+                trending_30d: 2.0 * 0.5f64.powf(240.0 / 360.0),
+            }
+        );
+    }
+
+    #[test]
+    fn recent_activity_is_worth_more() {
+        let scores = scores(&[bucket("old", 100.0, 10, 0, 0), bucket("new", 1.0, 10, 0, 0)]);
+        let old = scores.iter().find(|v| v.id == "old").unwrap();
+        let new = scores.iter().find(|v| v.id == "new").unwrap();
+        assert!(new.trending_7d > old.trending_7d);
+        assert!(new.trending_30d > old.trending_30d);
+    }
+}
@@ -24,6 +24,11 @@ pub struct UserProgress {
     pub updated: Datetime,
 }
 
+#[derive(Deserialize, SurrealSelect)]
+struct ProgressValue {
+    progress: f64,
+}
+
 #[derive(Deserialize, SurrealSelect)]
 struct ProgressUser {
     user: RecordIdType<User>,
@@ -61,14 +66,15 @@ impl UserProgressDBService {
         }
     }
 
+    /// Returns the previous progress of the chapter
     pub async fn update(
         &self,
         user_id: &str,
         manga_id: &str,
         chapter_id: &str,
         progress: f64,
-    ) -> DbResult<()> {
-        let mut record: Vec<RecordData<Empty>> = UserProgress::search(
+    ) -> DbResult<Option<f64>> {
+        let mut record: Vec<RecordData<ProgressValue>> = UserProgress::search(
             self.db.as_ref(),
             Some(format!(
                 "WHERE user = {} AND manga = {} AND chapter = {} LIMIT 1",
@@ -88,14 +94,15 @@ impl UserProgressDBService {
             }
             .add(self.db.as_ref())
             .await?;
+            Ok(None)
         } else {
+            let record = record.remove(0);
             let _: Option<Empty> = record
-                .remove(0)
                 .id
                 .patch(self.db.as_ref(), PatchOp::replace("/progress", progress))
                 .await?;
+            Ok(Some(record.data.progress))
         }
-        Ok(())
     }
 
     pub async fn load_next_chapter(
@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+
+use api_structure::search::{Array, Item, ItemOrArray, SearchRequest};
+use serde::{Deserialize, Serialize};
+use surrealdb::{opt::PatchOp, Datetime};
+use surrealdb_extras::{RecordData, RecordIdType, SurrealSelect, SurrealTable, SurrealTableInfo};
+
+use crate::{
+    error::{DbError, DbResult},
+    DbSession,
+};
+
+use super::{tag::Empty, user::User};
+
+/// Search item which is replaced by the query of a saved search
+pub const FIELD: &str = "saved";
+/// Saved searches which can be nested in each other
+const MAX_DEPTH: usize = 8;
+/// Items of a query after every saved search is expanded
+const MAX_ITEMS: usize = 1024;
+
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("saved_searches")]
+#[sql(["DEFINE EVENT saved_search_updated ON TABLE saved_searches WHEN $event = \"UPDATE\" AND $before.updated == $after.updated THEN (UPDATE $after.id SET updated = time::now() );"])]
+pub struct SavedSearch {
+    /// Name which is used by `saved:name`
+    pub name: String,
+    /// User who saved the search
+    pub user: RecordIdType<User>,
+    /// Query, order & page size
+    pub search: SearchRequest,
+    #[opt(exclude = true)]
+    pub updated: Datetime,
+    #[opt(exclude = true)]
+    pub created: Datetime,
+}
+
+#[derive(SurrealSelect, Deserialize)]
+pub struct SavedSearchName {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct NamedQuery {
+    name: String,
+    query: Array,
+}
+
+#[derive(Clone)]
+pub struct SavedSearchDBService {
+    db: DbSession,
+}
+
+async fn get_saved(db: &DbSession, name: &str, user: &str) -> DbResult<RecordData<SavedSearch>> {
+    let mut v: Vec<RecordData<SavedSearch>> = db
+        .query(format!(
+            "SELECT * FROM {} WHERE name = $name AND user = $user LIMIT 1",
+            SavedSearch::name()
+        ))
+        .bind(("name", name.to_owned()))
+        .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+        .await?
+        .take(0)?;
+    if v.is_empty() {
+        return Err(DbError::NotFound);
+    }
+    Ok(v.remove(0))
+}
+
+/// Names of the saved searches which are referenced by the query
+fn referenced(arr: &Array, out: &mut HashSet<String>) {
+    for item in &arr.items {
+        match item {
+            ItemOrArray::Item(item) if item.data.name == FIELD => {
+                if let Some(name) = item.data.value.get_string() {
+                    out.insert(name);
+                }
+            }
+            ItemOrArray::Item(_) => {}
+            ItemOrArray::Array(array) => referenced(array, out),
+        }
+    }
+}
+
+/// Replaces `saved:name` items with a group containing the saved query.
+/// `stack` contains the saved searches which are currently expanded, to detect cycles.
+/// `len` counts the items of the expanded query
+fn substitute(
+    arr: Array,
+    saved: &HashMap<String, Array>,
+    stack: &mut Vec<String>,
+    len: &mut usize,
+) -> Result<Array, String> {
+    let mut items = Vec::with_capacity(arr.items.len());
+    for item in arr.items {
+        *len += 1;
+        if *len > MAX_ITEMS {
+            return Err(format!(
+                "query has more than {MAX_ITEMS} items after expanding saved searches"
+            ));
+        }
+        items.push(match item {
+            ItemOrArray::Item(Item { not, or_post, data }) if data.name == FIELD => {
+                let name = data
+                    .value
+                    .get_string()
+                    .ok_or("saved needs to be a string".to_owned())?;
+                if stack.contains(&name) {
+                    return Err(format!("saved search {name} references itself"));
+                }
+                if stack.len() >= MAX_DEPTH {
+                    return Err(format!(
+                        "saved searches are nested more than {MAX_DEPTH} levels deep"
+                    ));
+                }
+                let query = saved
+                    .get(&name)
+                    .cloned()
+                    .ok_or(format!("unknown saved search {name}"))?;
+                stack.push(name);
+                let query = substitute(query, saved, stack, len)?;
+                stack.pop();
+                ItemOrArray::Array(Array {
+                    or: false,
+                    not,
+                    or_post,
+                    items: vec![ItemOrArray::Array(query)],
+                })
+            }
+            ItemOrArray::Array(array) => ItemOrArray::Array(substitute(array, saved, stack, len)?),
+            item => item,
+        });
+    }
+    Ok(Array { items, ..arr })
+}
+
+impl SavedSearchDBService {
+    pub fn new(db: DbSession) -> Self {
+        Self { db }
+    }
+
+    /// Saves the search or replaces the one with the same name.
+    /// Referenced saved searches have to exist & must not reference this one
+    pub async fn save(&self, name: &str, user: &str, search: SearchRequest) -> DbResult<()> {
+        let user_id = RecordIdType::from((User::name(), user));
+        expand_within(
+            &self.db,
+            search.query.clone(),
+            &user_id,
+            vec![name.to_owned()],
+        )
+        .await?;
+        match get_saved(&self.db, name, user).await {
+            Ok(saved) => {
+                let _: Option<RecordData<Empty>> = saved
+                    .patch(self.db.as_ref(), PatchOp::replace("/search", search))
+                    .await?;
+            }
+            Err(DbError::NotFound) => {
+                SavedSearch {
+                    name: name.to_owned(),
+                    user: user_id,
+                    search,
+                    updated: Default::default(),
+                    created: Default::default(),
+                }
+                .add_i(self.db.as_ref())
+                .await?;
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    pub async fn delete(&self, name: &str, user: &str) -> DbResult<()> {
+        get_saved(&self.db, name, user)
+            .await?
+            .delete_s(self.db.as_ref())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, name: &str, user: &str) -> DbResult<SearchRequest> {
+        Ok(get_saved(&self.db, name, user).await?.data.search)
+    }
+
+    pub async fn names(&self, user: &str) -> DbResult<Vec<String>> {
+        let v: Vec<RecordData<SavedSearchName>> = self
+            .db
+            .query(format!(
+                "SELECT name, id FROM {} WHERE user = $user ORDER BY name",
+                SavedSearch::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .take(0)?;
+        Ok(v.into_iter().map(|v| v.data.name).collect())
+    }
+}
+
+/// Replaces every `saved:name` item with the query of the saved search of the user.
+/// Saved searches can reference other saved searches, but not themselves
+pub async fn expand(db: &DbSession, query: Array, user: &RecordIdType<User>) -> DbResult<Array> {
+    expand_within(db, query, user, vec![]).await
+}
+
+/// [`expand`] for a query which is part of the saved searches in `stack`
+async fn expand_within(
+    db: &DbSession,
+    query: Array,
+    user: &RecordIdType<User>,
+    mut stack: Vec<String>,
+) -> DbResult<Array> {
+    let mut missing = HashSet::new();
+    referenced(&query, &mut missing);
+    if missing.is_empty() {
+        return Ok(query);
+    }
+    let mut saved = HashMap::new();
+    for depth in 0.. {
+        if missing.is_empty() {
+            break;
+        }
+        if depth >= MAX_DEPTH {
+            return Err(DbError::SearchParseError(format!(
+                "saved searches are nested more than {MAX_DEPTH} levels deep"
+            )));
+        }
+        let names = missing.drain().collect::<Vec<_>>();
+        let found: Vec<NamedQuery> = db
+            .query(format!(
+                "SELECT name, search.query AS query FROM {} WHERE user = $user AND name IN $names",
+                SavedSearch::name()
+            ))
+            .bind(("user", user.clone()))
+            .bind(("names", names.clone()))
+            .await?
+            .take(0)?;
+        if let Some(name) = names.iter().find(|v| !found.iter().any(|f| &f.name == *v)) {
+            return Err(DbError::SearchParseError(format!(
+                "unknown saved search {name}"
+            )));
+        }
+        for v in found {
+            referenced(&v.query, &mut missing);
+            saved.insert(v.name, v.query);
+        }
+        missing.retain(|v| !saved.contains_key(v));
+    }
+    substitute(query, &saved, &mut stack, &mut 0).map_err(DbError::SearchParseError)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use api_structure::search::{Array, Item, ItemData, ItemOrArray, ItemValue};
+
+    use super::{substitute, MAX_DEPTH, MAX_ITEMS};
+
+    fn array(items: Vec<ItemOrArray>) -> Array {
+        Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items,
+        }
+    }
+
+    fn item(name: &str, value: &str) -> ItemOrArray {
+        ItemOrArray::Item(Item::new(ItemData {
+            name: name.to_owned(),
+            value: ItemValue::String(value.to_owned()),
+        }))
+    }
+
+    #[test]
+    fn saved_items_are_replaced_by_groups() {
+        let saved = HashMap::from([
+            ("isekai".to_owned(), array(vec![item("tag", "isekai")])),
+            (
+                "long isekai".to_owned(),
+                array(vec![item("saved", "isekai"), item("c", ">=50")]),
+            ),
+        ]);
+        let mut query = array(vec![item("saved", "long isekai")]);
+        if let ItemOrArray::Item(item) = &mut query.items[0] {
+            item.not = true;
+            item.or_post = Some(true);
+        }
+        let expanded = substitute(query, &saved, &mut vec![], &mut 0).unwrap();
+        let expected = array(vec![ItemOrArray::Array(Array {
+            or: false,
+            not: true,
+            or_post: Some(true),
+            items: vec![ItemOrArray::Array(array(vec![
+                ItemOrArray::Array(array(vec![ItemOrArray::Array(array(vec![item(
+                    "tag", "isekai",
+                )]))])),
+                item("c", ">=50"),
+            ]))],
+        })]);
+        assert_eq!(expanded, expected);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let saved = HashMap::from([
+            ("a".to_owned(), array(vec![item("saved", "b")])),
+            ("b".to_owned(), array(vec![item("saved", "a")])),
+        ]);
+        assert_eq!(
+            substitute(array(vec![item("saved", "a")]), &saved, &mut vec![], &mut 0),
+            Err("saved search a references itself".to_owned())
+        );
+        assert_eq!(
+            substitute(array(vec![item("saved", "c")]), &saved, &mut vec![], &mut 0),
+            Err("unknown saved search c".to_owned())
+        );
+    }
+
+    #[test]
+    fn expansion_is_limited() {
+        // every saved search references the next one
+        let saved = (0..=MAX_DEPTH)
+            .map(|i| {
+                (
+                    i.to_string(),
+                    array(vec![item("saved", &(i + 1).to_string())]),
+                )
+            })
+            .chain([((MAX_DEPTH + 1).to_string(), array(vec![]))])
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            substitute(array(vec![item("saved", "0")]), &saved, &mut vec![], &mut 0),
+            Err(format!(
+                "saved searches are nested more than {MAX_DEPTH} levels deep"
+            ))
+        );
+
+        // each level has four times the items of the previous one
+        let saved = (0..MAX_DEPTH)
+            .map(|i| {
+                let next = (i + 1).to_string();
+                (
+                    i.to_string(),
+                    array((0..4).map(|_| item("saved", &next)).collect()),
+                )
+            })
+            .chain([(MAX_DEPTH.to_string(), array(vec![item("tag", "a")]))])
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            substitute(array(vec![item("saved", "0")]), &saved, &mut vec![], &mut 0),
+            Err(format!(
+                "query has more than {MAX_ITEMS} items after expanding saved searches"
+            ))
+        );
+    }
+}
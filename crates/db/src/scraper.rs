@@ -52,6 +52,25 @@ impl ScraperDbService {
         Self { db }
     }
 
+    /// All entries which were approved, but not downloaded yet
+    pub async fn approved(&self) -> DbResult<Vec<RecordData<ScraperEntry>>> {
+        let v: Vec<RecordData<ScraperEntry>> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE state == 'Approved'",
+                ScraperEntry::name()
+            ))
+            .await?
+            .take(0)?;
+        Ok(v)
+    }
+
+    /// Removes an entry after it was turned into a chapter
+    pub async fn remove(&self, entry: RecordData<ScraperEntry>) -> DbResult<()> {
+        entry.delete_s(self.db.as_ref()).await?;
+        Ok(())
+    }
+
     pub async fn get_items(
         &self,
         manga: RecordIdType<Manga>,
@@ -70,6 +89,60 @@ impl ScraperDbService {
         Ok(v)
     }
 
+    /// Groups of pending entries. Paginates over the groups, not the entries
+    pub async fn pending(&self, page: u32, limit: u32) -> DbResult<Vec<MangaVersion>> {
+        let data: Vec<MangaVersion> = self
+            .db
+            .query(format!(
+                "SELECT manga,version FROM {} WHERE state = 'Pending' GROUP BY manga,version LIMIT $limit START $start",
+                ScraperEntry::name()
+            ))
+            .bind(("limit", limit))
+            .bind(("start", page.saturating_sub(1) * limit))
+            .await?
+            .take(0)?;
+        Ok(data)
+    }
+
+    pub async fn get_pending_items(
+        &self,
+        manga: RecordIdType<Manga>,
+        version: RecordIdType<Version>,
+    ) -> DbResult<Vec<RecordData<ScraperEntry>>> {
+        let v: Vec<RecordData<ScraperEntry>> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE manga = $manga AND version = $version AND state = 'Pending' ORDER BY data.chapter",
+                ScraperEntry::name()
+            ))
+            .bind(("manga", manga))
+            .bind(("version", version))
+            .await?
+            .take(0)?;
+        Ok(v)
+    }
+
+    /// Changes the state of multiple entries. Returns the number of changed entries
+    pub async fn set_state(&self, ids: Vec<String>, state: State) -> DbResult<usize> {
+        let ids = ids
+            .iter()
+            .map(|id| {
+                let id = id
+                    .strip_prefix(&format!("{}:", ScraperEntry::name()))
+                    .unwrap_or(id);
+                RecordIdType::<ScraperEntry>::from((ScraperEntry::name(), id))
+            })
+            .collect::<Vec<_>>();
+        let v: Vec<Empty> = self
+            .db
+            .query("UPDATE $ids SET state = $state")
+            .bind(("ids", ids))
+            .bind(("state", state))
+            .await?
+            .take(0)?;
+        Ok(v.len())
+    }
+
     pub async fn process_items(
         &self,
     ) -> DbResult<Vec<(RecordIdType<Manga>, RecordIdType<Version>)>> {
@@ -87,6 +160,7 @@ impl ScraperDbService {
         manga: RecordIdType<Manga>,
         version: RecordIdType<Version>,
         chapters: Vec<ScrapedChapter>,
+        auto_approve: bool,
     ) -> DbResult<()> {
         //TODO: dont load all into memory
 
@@ -108,7 +182,10 @@ impl ScraperDbService {
             .map(|v| ScraperEntry {
                 manga: manga.clone(),
                 version: version.clone(),
-                state: State::default(),
+                state: match auto_approve {
+                    true => State::Approved,
+                    false => State::default(),
+                },
                 data: v,
             })
             .collect::<Vec<_>>();
@@ -0,0 +1,797 @@
+use api_structure::{
+    search::{Array, Item, ItemOrArray, ItemValue},
+    v1::TagSex,
+};
+use surrealdb::{engine::any::Any, method::Query};
+use surrealdb_extras::{RecordIdType, SurrealTableInfo};
+
+use crate::{
+    fulltext::{DESCRIPTION, NAMES, TITLES, TRIGRAMS},
+    lists::MangaList,
+    progress::UserProgress,
+    user::User,
+};
+
+/// Value that is passed to surrealdb via `.bind()`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    String(String),
+    Int(i64),
+}
+
+/// Where clause compiled from a search [`Array`].
+/// User input never ends up in `query`, it is only referenced by `$p{n}` and stored in `bindings`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CompiledQuery {
+    pub query: String,
+    /// (name without `$`, value)
+    pub bindings: Vec<(String, Binding)>,
+    /// Number of full text matches, used as reference `@n@`
+    matches: usize,
+    /// (reference, weight) of the full text matches which are not negated
+    scores: Vec<(usize, f64)>,
+    /// Reference & parameter of the first title match which is not negated
+    pub highlight: Option<(usize, String)>,
+    /// Title matches also match the trigrams of their words
+    typos: bool,
+    /// At least one title match was extended with trigrams
+    has_trigrams: bool,
+}
+
+impl CompiledQuery {
+    /// Compiles the search tree. Returns `Ok(None)` if the tree has no items
+    pub fn compile(arr: Array) -> Result<Option<Self>, String> {
+        let arr = match filter_array(arr) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let mut out = Self::default();
+        let (query, _) = out.generate_array(arr)?;
+        out.query = query;
+        Ok(Some(out))
+    }
+
+    /// Like [`Self::compile`], but titles also match if they share a trigram with a word of the
+    /// search, so titles with typos are found. It matches a lot more & is meant as a fallback
+    /// when the normal query finds nothing. Returns `Ok(None)` if no title is searched
+    pub fn compile_with_typos(arr: Array) -> Result<Option<Self>, String> {
+        let arr = match filter_array(arr) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let mut out = Self {
+            typos: true,
+            ..Default::default()
+        };
+        let (query, _) = out.generate_array(arr)?;
+        out.query = query;
+        Ok(out.has_trigrams.then_some(out))
+    }
+
+    /// Adds all bindings to the query. `$user` needs to be bound by the caller
+    pub fn bind<'a>(&self, mut query: Query<'a, Any>) -> Query<'a, Any> {
+        for (name, value) in &self.bindings {
+            query = match value {
+                Binding::String(s) => query.bind((name.clone(), s.clone())),
+                Binding::Int(i) => query.bind((name.clone(), *i)),
+            };
+        }
+        query
+    }
+
+    fn push(&mut self, value: Binding) -> String {
+        let name = format!("p{}", self.bindings.len());
+        let param = format!("${name}");
+        self.bindings.push((name, value));
+        param
+    }
+
+    fn push_str(&mut self, value: Option<String>, err: &str) -> Result<String, String> {
+        Ok(self.push(Binding::String(value.ok_or(err.to_owned())?)))
+    }
+
+    fn full_text_match(&mut self, field: &str, param: &str, not: bool, weight: f64) -> String {
+        let reference = self.matches;
+        self.matches += 1;
+        if !not {
+            self.scores.push((reference, weight));
+            if field == TITLES && self.highlight.is_none() {
+                self.highlight = Some((reference, param.to_owned()));
+            }
+        }
+        format!("{field} @{reference}@ {param}")
+    }
+
+    /// Matches `text` against the full text indexes of `fields` (field, weight for relevance)
+    fn full_text(&mut self, text: String, not: bool, fields: &[(&str, f64)]) -> String {
+        let trigrams = trigrams(&text);
+        let param = self.push(Binding::String(text));
+        let mut matches = vec![];
+        for (field, weight) in fields {
+            matches.push(self.full_text_match(field, &param, not, *weight));
+            // negated matches stay exact, so they dont exclude more than asked for
+            if *field == TITLES && self.typos && !not && !trigrams.is_empty() {
+                self.has_trigrams = true;
+                // every trigram is matched on its own, the index requires all words of a match
+                let weight = weight / trigrams.len() as f64;
+                for trigram in &trigrams {
+                    let trigram = self.push(Binding::String(trigram.clone()));
+                    matches.push(self.full_text_match(TRIGRAMS, &trigram, not, weight));
+                }
+            }
+        }
+        format!(
+            "{}({})",
+            match not {
+                true => "!",
+                false => "",
+            },
+            matches.join(" OR ")
+        )
+    }
+
+    /// Weighted sum of the scores of all full text matches. `0` if nothing was matched
+    pub fn relevance(&self) -> String {
+        match self.scores.is_empty() {
+            true => "0".to_owned(),
+            false => self
+                .scores
+                .iter()
+                .map(|(reference, weight)| format!("(search::score({reference}) ?? 0) * {weight}"))
+                .collect::<Vec<_>>()
+                .join(" + "),
+        }
+    }
+
+    fn tag_with_sex(&mut self, item: &Item, sex: TagSex) -> Result<String, String> {
+        let tag = self.push_str(
+            item.data.value.get_string(),
+            "description needs to be a string, int",
+        )?;
+        let sex = sex as u32;
+        Ok(format!(
+            "(SELECT id FROM tags WHERE tag = {tag} AND sex = {sex} LIMIT 1)[0].id {} in generated_tags",
+            match item.not {
+                true => "NOT",
+                false => "",
+            }
+        ))
+    }
+
+    fn user_by_name(&mut self, item: &Item, err: &str) -> Result<String, String> {
+        let name = self.push_str(item.data.value.get_string(), err)?;
+        Ok(format!(
+            "(SELECT id FROM users WHERE array::some(names, |$n: string| string::lowercase($n) = {name}) LIMIT 1)[0].id"
+        ))
+    }
+
+    fn generate_item(&mut self, item: Item) -> Result<(String, Option<bool>), String> {
+        let not = match item.not {
+            true => "!",
+            false => "",
+        };
+        let not2 = match item.not {
+            true => "not",
+            false => "",
+        };
+        let is_enum = matches!(item.data.value, ItemValue::None);
+        let query = match (is_enum, item.data.name.as_str()) {
+            (true, "next-available") => {
+                format!("({} ?? 1) < 1", reading_progress("VALUE progress"))
+            }
+            (true, v) => self.full_text(
+                v.to_lowercase(),
+                item.not,
+                &[(TITLES, 3.0), (NAMES, 1.0), (DESCRIPTION, 0.5)],
+            ),
+            (false, "") | (false, "title") => {
+                let title = item
+                    .data
+                    .value
+                    .get_string()
+                    .ok_or("title needs to be a string".to_owned())?;
+                self.full_text(title.to_lowercase(), item.not, &[(TITLES, 1.0)])
+            }
+            (false, "description") => {
+                let description = item
+                    .data
+                    .value
+                    .get_string()
+                    .ok_or("description needs to be a string".to_owned())?;
+                self.full_text(description, item.not, &[(DESCRIPTION, 1.0)])
+            }
+            (false, "k") | (false, "kind") => {
+                let kind =
+                    self.push_str(item.data.value.get_string(), "kind needs to be a string")?;
+                format!("kind = (SELECT id FROM kinds WHERE kind {not}= {kind} LIMIT 1)[0].id")
+            }
+            (false, "male") | (false, "m") => self.tag_with_sex(&item, TagSex::Male)?,
+            (false, "female") | (false, "f") => self.tag_with_sex(&item, TagSex::Female)?,
+            (false, "both") | (false, "b") => self.tag_with_sex(&item, TagSex::Both)?,
+            (false, "male2female") | (false, "mf") => {
+                self.tag_with_sex(&item, TagSex::MaleFemale)?
+            }
+            (false, "female2male") | (false, "fm") => {
+                self.tag_with_sex(&item, TagSex::FemaleMale)?
+            }
+            (false, "none") | (false, "n") => self.tag_with_sex(&item, TagSex::None)?,
+            (false, "unknown") | (false, "u") => self.tag_with_sex(&item, TagSex::Unknown)?,
+            (false, "tag") | (false, "t") => {
+                let tag = self.push_str(
+                    item.data.value.get_string(),
+                    "description needs to be a string, int",
+                )?;
+                format!(
+                    "generated_tags {} (SELECT id FROM tags WHERE tag = {tag}).id",
+                    match item.not {
+                        true => "NONEINSIDE",
+                        false => "CONTAINSANY",
+                    }
+                )
+            }
+            (false, "status") | (false, "s") => {
+                let status = item
+                    .data
+                    .value
+                    .get_int()
+                    .ok_or("title needs to be a int".to_owned())?;
+                let status = self.push(Binding::Int(status));
+                format!("status {not}= {status}")
+            }
+            (false, "uploader") => {
+                let user = self.user_by_name(&item, "uploader needs to be a string")?;
+                format!("uploader {not}= {user}")
+            }
+            (false, "artist") => {
+                let user = self.user_by_name(&item, "artists needs to be a string")?;
+                format!("{user} {not2} in artists")
+            }
+            (false, "author") | (false, "a") => {
+                let user = self.user_by_name(&item, "authors needs to be a string")?;
+                format!("{user} {not2} in authors")
+            }
+            (false, "publisher") | (false, "p") => {
+                let user = self.user_by_name(&item, "publishers needs to be a string")?;
+                format!("{user} {not2} in publishers")
+            }
+            (false, "chapters") | (false, "c") => {
+                let (mut eq, mut bigger, number) = item
+                    .data
+                    .value
+                    .get_cmp_int()
+                    .ok_or("chapters needs to be a eg. >= 10".to_owned())?;
+                if item.not {
+                    eq = !eq;
+                    bigger = !bigger;
+                }
+                let number = self.push(Binding::Int(number));
+                format!(
+                    "array::len(chapters) {}{} {number}",
+                    if bigger { ">" } else { "<" },
+                    if eq { "=" } else { "" }
+                )
+            }
+            (false, "list") | (false, "l") => {
+                let list =
+                    self.push_str(item.data.value.get_string(), "list needs to be a string")?;
+                format!(
+                    "id {not2} IN (SELECT mangas FROM {} WHERE name = {list} AND user = $user LIMIT 1)[0].mangas",
+                    MangaList::name()
+                )
+            }
+            _ => Err(format!("Unknown item {}", item.data.name))?,
+        };
+        Ok((query, item.or_post))
+    }
+
+    fn generate_item_or_array(
+        &mut self,
+        ior: ItemOrArray,
+    ) -> Result<(String, Option<bool>), String> {
+        match ior {
+            ItemOrArray::Item(item) => self.generate_item(item),
+            ItemOrArray::Array(array) => self.generate_array(array),
+        }
+    }
+
+    fn generate_array(&mut self, arr: Array) -> Result<(String, Option<bool>), String> {
+        let mut queries = vec![];
+        let len = arr.items.len();
+        for (index, item) in arr.items.into_iter().enumerate() {
+            let (query, or_post) = self.generate_item_or_array(item)?;
+            queries.push(query);
+            if index != len - 1 {
+                queries.push(
+                    match or_post.unwrap_or(arr.or) {
+                        true => "OR",
+                        false => "AND",
+                    }
+                    .to_owned(),
+                );
+            }
+        }
+        Ok((
+            format!(
+                "{}({})",
+                match arr.not {
+                    true => "!",
+                    false => "",
+                },
+                queries.join(" "),
+            ),
+            arr.or_post,
+        ))
+    }
+}
+
+/// Reading progress of `$user` for the manga which is currently selected
+pub fn reading_progress(what: &str) -> String {
+    format!(
+        "(SELECT {what} FROM {} WHERE user = $user AND manga = $parent.id LIMIT 1)[0]",
+        UserProgress::name()
+    )
+}
+
+/// Binds the user which is used by `list` items & [`reading_progress`]
+pub fn bind_user<'a>(query: Query<'a, Any>, user: &RecordIdType<User>) -> Query<'a, Any> {
+    query.bind(("user", user.clone()))
+}
+
+/// Trigrams of the words in `text`, at most 32 so long searches dont create huge queries
+fn trigrams(text: &str) -> Vec<String> {
+    let mut out: Vec<String> = vec![];
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars = word.chars().collect::<Vec<_>>();
+        for trigram in chars.windows(3) {
+            let trigram = trigram.iter().collect::<String>();
+            if !out.contains(&trigram) {
+                out.push(trigram);
+            }
+        }
+    }
+    out.truncate(32);
+    out
+}
+
+fn filter_array_or_item(ior: ItemOrArray) -> Option<ItemOrArray> {
+    match ior {
+        ItemOrArray::Array(array) => filter_array(array).map(ItemOrArray::Array),
+        item => Some(item),
+    }
+}
+
+fn filter_array(mut arr: Array) -> Option<Array> {
+    arr.items = arr
+        .items
+        .into_iter()
+        .filter_map(filter_array_or_item)
+        .collect();
+    if arr.items.is_empty() {
+        return None;
+    }
+    Some(arr)
+}
+
+pub fn search_array(q: &str, arr: &Array) -> bool {
+    arr.items.iter().any(|v| match v {
+        ItemOrArray::Item(item) => item.data.name.as_str() == q,
+        ItemOrArray::Array(array) => search_array(q, array),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use api_structure::search::{Array, Item, ItemData, ItemOrArray, ItemValue, SearchRequest};
+
+    use super::{Binding, CompiledQuery};
+
+    /// xorshift, so the suite doesnt need a rand dependency & failures are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        fn bool(&mut self) -> bool {
+            self.below(2) == 0
+        }
+
+        fn string(&mut self) -> String {
+            const PARTS: &[&str] = &[
+                "'",
+                "\"",
+                "`",
+                "\\",
+                ";",
+                "--",
+                "/*",
+                "*/",
+                "$p0",
+                "$user",
+                "$parent",
+                "(",
+                ")",
+                "[",
+                "]",
+                "{",
+                "}",
+                "|",
+                "!",
+                "~",
+                "=",
+                " OR 1=1",
+                " AND ",
+                "NONE",
+                "⟨",
+                "⟩",
+                "DELETE mangas;",
+                "\n",
+                "\0",
+                "ä",
+                "a",
+                "B",
+                "z",
+                "1",
+                " ",
+            ];
+            let len = self.below(8);
+            (0..len)
+                .map(|_| PARTS[self.below(PARTS.len() as u64) as usize])
+                .collect()
+        }
+    }
+
+    const STRING_FIELDS: &[&str] = &[
+        "",
+        "title",
+        "description",
+        "k",
+        "kind",
+        "m",
+        "male",
+        "f",
+        "female",
+        "b",
+        "both",
+        "mf",
+        "male2female",
+        "fm",
+        "female2male",
+        "n",
+        "none",
+        "u",
+        "unknown",
+        "t",
+        "tag",
+        "uploader",
+        "artist",
+        "a",
+        "author",
+        "p",
+        "publisher",
+        "l",
+        "list",
+    ];
+
+    /// Field which decides the structure of a generated item. Values are filled in later
+    #[derive(Clone, Copy)]
+    enum Slot {
+        String(&'static str),
+        Status,
+        Chapters {
+            eq: bool,
+            bigger: bool,
+        },
+        /// Free text enum, which gets used as title
+        Enum,
+        NextAvailable,
+    }
+
+    fn shape_slot(rng: &mut Rng) -> Slot {
+        match rng.below(5) {
+            0 => Slot::Status,
+            1 => Slot::Chapters {
+                eq: rng.bool(),
+                bigger: rng.bool(),
+            },
+            2 => Slot::Enum,
+            3 => Slot::NextAvailable,
+            _ => Slot::String(STRING_FIELDS[rng.below(STRING_FIELDS.len() as u64) as usize]),
+        }
+    }
+
+    fn fill_slot(slot: Slot, values: &mut Rng) -> ItemData {
+        match slot {
+            Slot::String(name) => ItemData {
+                name: name.to_owned(),
+                value: ItemValue::String(values.string()),
+            },
+            Slot::Status => ItemData {
+                name: "status".to_owned(),
+                value: ItemValue::Int(values.next() as i64),
+            },
+            Slot::Chapters { eq, bigger } => ItemData {
+                name: "chapters".to_owned(),
+                value: ItemValue::CmpInt {
+                    eq,
+                    bigger,
+                    value: values.next() as i64,
+                },
+            },
+            Slot::Enum => {
+                let mut name = values.string();
+                while name == "next-available" {
+                    name = values.string();
+                }
+                ItemData::enum_(name)
+            }
+            Slot::NextAvailable => ItemData::enum_("next-available"),
+        }
+    }
+
+    /// Same `shape` seed => same tree structure, `values` only changes user supplied data
+    fn gen_array(shape: &mut Rng, values: &mut Rng, depth: u32) -> Array {
+        let len = 1 + shape.below(4);
+        let items = (0..len)
+            .map(|_| match depth > 0 && shape.below(3) == 0 {
+                true => ItemOrArray::Array(gen_array(shape, values, depth - 1)),
+                false => ItemOrArray::Item(Item {
+                    not: shape.bool(),
+                    or_post: match shape.below(3) {
+                        0 => None,
+                        1 => Some(true),
+                        _ => Some(false),
+                    },
+                    data: {
+                        let slot = shape_slot(shape);
+                        fill_slot(slot, values)
+                    },
+                }),
+            })
+            .collect();
+        Array {
+            or: shape.bool(),
+            not: shape.bool(),
+            or_post: match shape.below(3) {
+                0 => None,
+                1 => Some(true),
+                _ => Some(false),
+            },
+            items,
+        }
+    }
+
+    fn gen_request(shape_seed: u64, value_seed: u64) -> SearchRequest {
+        let mut shape = Rng(shape_seed);
+        let mut values = Rng(value_seed);
+        SearchRequest {
+            order: "created".to_owned(),
+            desc: shape.bool(),
+            limit: 20,
+            page: 1,
+            query: gen_array(&mut shape, &mut values, 3),
+        }
+    }
+
+    fn collect_values(arr: &Array, out: &mut Vec<Binding>) {
+        for item in &arr.items {
+            match item {
+                ItemOrArray::Array(array) => collect_values(array, out),
+                ItemOrArray::Item(item) => match (&item.data.value, item.data.name.as_str()) {
+                    (ItemValue::None, "next-available") => {}
+                    (ItemValue::None, name) => out.push(Binding::String(name.to_lowercase())),
+                    (ItemValue::String(s), "" | "title") => {
+                        out.push(Binding::String(s.to_lowercase()))
+                    }
+                    (ItemValue::String(s), _) => out.push(Binding::String(s.clone())),
+                    (ItemValue::Int(i), _) => out.push(Binding::Int(*i)),
+                    (ItemValue::CmpInt { value, .. }, _) => out.push(Binding::Int(*value)),
+                    _ => unreachable!(),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn values_dont_change_query_structure() {
+        for seed in 1..2000u64 {
+            let shape_seed = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+            let a = gen_request(shape_seed, seed * 31 + 7).query;
+            let b = gen_request(shape_seed, seed * 97 + 3).query;
+            let qa = CompiledQuery::compile(a).unwrap().unwrap();
+            let qb = CompiledQuery::compile(b).unwrap().unwrap();
+            assert_eq!(qa.query, qb.query, "seed {seed}");
+            assert_eq!(qa.bindings.len(), qb.bindings.len(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn every_value_is_bound() {
+        for seed in 1..2000u64 {
+            let arr = gen_request(seed.wrapping_mul(0xBF58_476D_1CE4_E5B9) | 1, seed).query;
+            let mut expected = vec![];
+            collect_values(&arr, &mut expected);
+            let compiled = CompiledQuery::compile(arr).unwrap().unwrap();
+            let bound = compiled
+                .bindings
+                .iter()
+                .map(|v| v.1.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(bound, expected, "seed {seed}");
+            for (index, (name, _)) in compiled.bindings.iter().enumerate() {
+                assert_eq!(name, &format!("p{index}"));
+                assert!(compiled.query.contains(&format!("${name}")));
+            }
+            assert!(!compiled.query.contains('\''), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn injection_is_bound() {
+        let arr = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![ItemOrArray::Item(Item::new(ItemData {
+                name: "t".to_owned(),
+                value: ItemValue::String("x' OR 1=1".to_owned()),
+            }))],
+        };
+        let compiled = CompiledQuery::compile(arr).unwrap().unwrap();
+        assert_eq!(
+            compiled.query,
+            "(generated_tags CONTAINSANY (SELECT id FROM tags WHERE tag = $p0).id)"
+        );
+        assert_eq!(
+            compiled.bindings,
+            vec![("p0".to_owned(), Binding::String("x' OR 1=1".to_owned()))]
+        );
+    }
+
+    #[test]
+    fn full_text_matches() {
+        let arr = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![
+                ItemOrArray::Item(Item::new(ItemData::enum_("Naruto"))),
+                ItemOrArray::Item(Item {
+                    not: true,
+                    or_post: None,
+                    data: ItemData {
+                        name: "title".to_owned(),
+                        value: ItemValue::String("Boruto".to_owned()),
+                    },
+                }),
+            ],
+        };
+        let compiled = CompiledQuery::compile(arr).unwrap().unwrap();
+        assert_eq!(
+            compiled.query,
+            "((search_titles @0@ $p0 OR search_names @1@ $p0 OR description @2@ $p0) AND !(search_titles @3@ $p1))"
+        );
+        assert_eq!(
+            compiled.bindings,
+            vec![
+                ("p0".to_owned(), Binding::String("naruto".to_owned())),
+                ("p1".to_owned(), Binding::String("boruto".to_owned()))
+            ]
+        );
+        // negated matches dont add to the relevance & cant be highlighted
+        assert_eq!(
+            compiled.relevance(),
+            "(search::score(0) ?? 0) * 3 + (search::score(1) ?? 0) * 1 + (search::score(2) ?? 0) * 0.5"
+        );
+        assert_eq!(compiled.highlight, Some((0, "$p0".to_owned())));
+    }
+
+    #[test]
+    fn relevance_without_matches() {
+        let arr = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![ItemOrArray::Item(Item::new(ItemData {
+                name: "status".to_owned(),
+                value: ItemValue::Int(1),
+            }))],
+        };
+        let compiled = CompiledQuery::compile(arr).unwrap().unwrap();
+        assert_eq!(compiled.relevance(), "0");
+        assert_eq!(compiled.highlight, None);
+    }
+
+    #[test]
+    fn typo_tolerant_titles() {
+        let arr = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![
+                ItemOrArray::Item(Item::new(ItemData {
+                    name: "title".to_owned(),
+                    value: ItemValue::String("Atack".to_owned()),
+                })),
+                ItemOrArray::Item(Item {
+                    not: true,
+                    or_post: None,
+                    data: ItemData {
+                        name: "title".to_owned(),
+                        value: ItemValue::String("Boruto".to_owned()),
+                    },
+                }),
+            ],
+        };
+        let compiled = CompiledQuery::compile_with_typos(arr.clone())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            compiled.query,
+            "((search_titles @0@ $p0 OR search_trigrams @1@ $p1 OR search_trigrams @2@ $p2 OR search_trigrams @3@ $p3) AND !(search_titles @4@ $p4))"
+        );
+        assert_eq!(
+            compiled.bindings[1..4],
+            [
+                ("p1".to_owned(), Binding::String("ata".to_owned())),
+                ("p2".to_owned(), Binding::String("tac".to_owned())),
+                ("p3".to_owned(), Binding::String("ack".to_owned()))
+            ]
+        );
+        assert_eq!(compiled.highlight, Some((0, "$p0".to_owned())));
+        // the normal query stays exact
+        assert_eq!(
+            CompiledQuery::compile(arr).unwrap().unwrap().query,
+            "((search_titles @0@ $p0) AND !(search_titles @1@ $p1))"
+        );
+
+        let status = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![ItemOrArray::Item(Item::new(ItemData {
+                name: "status".to_owned(),
+                value: ItemValue::Int(1),
+            }))],
+        };
+        assert_eq!(CompiledQuery::compile_with_typos(status), Ok(None));
+    }
+
+    #[test]
+    fn empty_tree() {
+        let arr = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![ItemOrArray::Array(Array {
+                or: true,
+                not: false,
+                or_post: None,
+                items: vec![],
+            })],
+        };
+        assert_eq!(CompiledQuery::compile(arr).unwrap(), None);
+    }
+
+    #[test]
+    fn unknown_field() {
+        let arr = Array {
+            or: false,
+            not: false,
+            or_post: None,
+            items: vec![ItemOrArray::Item(Item::new(ItemData {
+                name: "x' OR 1=1".to_owned(),
+                value: ItemValue::String("a".to_owned()),
+            }))],
+        };
+        assert!(CompiledQuery::compile(arr).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use api_structure::REFRESH_SECS;
+use helper::random_string;
+use serde::{Deserialize, Serialize};
+use surrealdb::Datetime;
+use surrealdb_extras::{RecordData, RecordIdFunc, RecordIdType, SurrealTable, SurrealTableInfo};
+
+use crate::{
+    error::{DbError, DbResult},
+    DbSession,
+};
+
+use super::user::User;
+
+/// A login on one device. Every refresh token belongs to a session & only the newest one is valid
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("sessions")]
+#[sql(["DEFINE EVENT session_updated ON TABLE sessions WHEN $event = \"UPDATE\" AND $before.updated == $after.updated THEN (UPDATE $after.id SET updated = time::now() );"])]
+pub struct Session {
+    pub user: RecordIdType<User>,
+    /// Id of the newest refresh token. Presenting an older one means it was stolen
+    pub jti: String,
+    /// Usually the user agent
+    pub device: String,
+    /// Address of the last login or refresh
+    pub ip: Option<String>,
+    pub last_used: Datetime,
+    /// Revoked sessions cannot be refreshed anymore
+    pub revoked: bool,
+    #[opt(exclude = true)]
+    pub updated: Datetime,
+    #[opt(exclude = true)]
+    pub created: Datetime,
+}
+
+pub enum Rotation {
+    /// Id of the new refresh token
+    Rotated(String),
+    /// The token was already replaced, so the session got revoked
+    Reused,
+    /// The session was revoked before or does not exist
+    Revoked,
+}
+
+#[derive(Clone)]
+pub struct SessionDBService {
+    db: DbSession,
+}
+
+impl SessionDBService {
+    pub fn new(db: DbSession) -> Self {
+        Self { db }
+    }
+
+    /// Returns the id of the session & of its first refresh token
+    pub async fn create(
+        &self,
+        user: &str,
+        device: String,
+        ip: Option<String>,
+    ) -> DbResult<(String, String)> {
+        let jti = random_string(32);
+        let id: RecordIdType<Session> = Session {
+            user: RecordIdType::from((User::name(), user)),
+            jti: jti.clone(),
+            device,
+            ip,
+            last_used: Default::default(),
+            revoked: false,
+            updated: Default::default(),
+            created: Default::default(),
+        }
+        .add(self.db.as_ref())
+        .await?
+        .map(|item| item.id.into())
+        .ok_or(DbError::NotFound)?;
+        Ok((id.id().to_string(), jti))
+    }
+
+    /// Replaces the refresh token `jti` with a new one.
+    /// Reusing a replaced token revokes the session, because one of the two users stole it
+    pub async fn rotate(&self, id: &str, jti: &str, ip: Option<String>) -> DbResult<Rotation> {
+        let new_jti = random_string(32);
+        let rotated: Vec<RecordData<Session>> = self
+            .db
+            .query("UPDATE $id SET jti = $new, ip = $ip, last_used = time::now() WHERE jti = $jti AND revoked = false")
+            .bind(("id", RecordIdType::<Session>::from((Session::name(), id))))
+            .bind(("new", new_jti.clone()))
+            .bind(("jti", jti.to_owned()))
+            .bind(("ip", ip))
+            .await?
+            .take(0)?;
+        if !rotated.is_empty() {
+            return Ok(Rotation::Rotated(new_jti));
+        }
+        let session: Option<Session> = RecordIdFunc::from((Session::name(), id))
+            .get(self.db.as_ref())
+            .await?;
+        if session.is_some_and(|v| !v.revoked) {
+            self.revoke(None, id).await?;
+            return Ok(Rotation::Reused);
+        }
+        Ok(Rotation::Revoked)
+    }
+
+    /// Sessions which were used in the last `REFRESH_SECS`, recently used first
+    pub async fn list(&self, user: &str) -> DbResult<Vec<RecordData<Session>>> {
+        Ok(self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE user = $user AND revoked = false AND last_used > time::now() - {REFRESH_SECS}s ORDER BY last_used DESC",
+                Session::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .take(0)?)
+    }
+
+    /// Only revokes sessions of `user` if set
+    pub async fn revoke(&self, user: Option<&str>, id: &str) -> DbResult<()> {
+        let filter = match user {
+            Some(_) => " AND user = $user",
+            None => "",
+        };
+        let revoked: Vec<RecordData<Session>> = self
+            .db
+            .query(format!(
+                "UPDATE $id SET revoked = true WHERE revoked = false{filter}"
+            ))
+            .bind(("id", RecordIdType::<Session>::from((Session::name(), id))))
+            .bind((
+                "user",
+                user.map(|v| RecordIdType::<User>::from((User::name(), v))),
+            ))
+            .await?
+            .take(0)?;
+        if revoked.is_empty() {
+            return Err(DbError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Returns the ids of the revoked sessions
+    pub async fn revoke_all(&self, user: &str) -> DbResult<Vec<String>> {
+        let revoked: Vec<RecordData<Session>> = self
+            .db
+            .query(format!(
+                "UPDATE {} SET revoked = true WHERE user = $user AND revoked = false",
+                Session::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .await?
+            .take(0)?;
+        Ok(revoked.into_iter().map(|v| v.id.id().to_string()).collect())
+    }
+}
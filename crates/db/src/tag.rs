@@ -32,11 +32,45 @@ impl TagDBService {
     }
 
     pub async fn search(&self, query: &str) -> DbResult<Vec<GlobalTag>> {
-        let v: Vec<RecordData<Tag>> = Tag::search(
-            self.db.as_ref(),
-            Some(format!("WHERE string::contains(tag, \"{}\")", query)),
-        )
-        .await?;
+        let v: Vec<RecordData<Tag>> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE string::contains(tag, $query)",
+                Tag::name()
+            ))
+            .bind(("query", query.to_owned()))
+            .await?
+            .take(0)?;
+        Ok(v.into_iter()
+            .map(|v| GlobalTag {
+                tag: v.data.tag,
+                description: v.data.description,
+                sex: TagSex::try_from(v.data.sex).unwrap_or(TagSex::Unknown),
+            })
+            .collect())
+    }
+    /// Tags starting with `prefix` (case insensitive), optionally only the ones with `sex`
+    pub async fn suggest(
+        &self,
+        prefix: &str,
+        sex: Option<TagSex>,
+        limit: u32,
+    ) -> DbResult<Vec<GlobalTag>> {
+        let sex_filter = match sex {
+            Some(_) => "AND sex = $sex",
+            None => "",
+        };
+        let v: Vec<RecordData<Tag>> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE string::starts_with(string::lowercase(tag), $prefix) {sex_filter} LIMIT $limit",
+                Tag::name()
+            ))
+            .bind(("prefix", prefix.to_lowercase()))
+            .bind(("sex", sex.map(|v| v as u64)))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?;
         Ok(v.into_iter()
             .map(|v| GlobalTag {
                 tag: v.data.tag,
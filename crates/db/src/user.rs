@@ -27,6 +27,7 @@ pub struct UserDBService {
 pub enum NotificationMode {
     Email,
     Push,
+    Webhook,
 }
 
 fn notification_mode_default() -> NotificationMode {
@@ -120,6 +121,9 @@ pub struct User {
     /// How the user wants to be notified
     #[serde(default = "notification_mode_default")]
     pub notification_mode: NotificationMode,
+    /// Url which is called for [NotificationMode::Webhook]
+    #[serde(default)]
+    pub webhook: Option<String>,
     /// More information about the user
     pub bio: Option<String>,
     /// More information about the user(location)
@@ -183,16 +187,17 @@ impl UserDBService {
         page: u32,
         limit: u32,
     ) -> DbResult<Vec<RecordData<SimpleUser>>> {
-        let user: Vec<RecordData<SimpleUser>> = User::search(
-            self.db.as_ref(),
-            Some(format!(
-                "WHERE disabled = false AND names.any(|$s| string::contains(string::lowercase($s), \"{}\")) LIMIT {} START {}",
-                query.to_lowercase(),
-                limit,
-                page.saturating_sub(1) * limit
-            )),
-        )
-        .await?;
+        let user: Vec<RecordData<SimpleUser>> = self
+            .db
+            .query(format!(
+                "SELECT names, icon_ext, gender, id FROM {} WHERE disabled = false AND names.any(|$s| string::contains(string::lowercase($s), $query)) LIMIT $limit START $start",
+                User::name()
+            ))
+            .bind(("query", query.to_lowercase()))
+            .bind(("limit", limit))
+            .bind(("start", page.saturating_sub(1) * limit))
+            .await?
+            .take(0)?;
         Ok(user)
     }
 
@@ -213,6 +218,7 @@ impl UserDBService {
             tags: vec![],
             achievements: vec![],
             notification_mode: NotificationMode::Push,
+            webhook: None,
             bio: None,
             location: None,
             links: vec![],
@@ -285,6 +291,26 @@ impl UserDBService {
         Ok(())
     }
 
+    pub async fn set_notification_mode(
+        &self,
+        id: &str,
+        mode: NotificationMode,
+        webhook: Option<String>,
+    ) -> DbResult<()> {
+        let id = RecordIdFunc::from((User::name(), id));
+        let _: Option<RecordData<Empty>> = id
+            .clone()
+            .patch(
+                self.db.as_ref(),
+                PatchOp::replace("/notification_mode", mode),
+            )
+            .await?;
+        let _: Option<RecordData<Empty>> = id
+            .patch(self.db.as_ref(), PatchOp::replace("/webhook", webhook))
+            .await?;
+        Ok(())
+    }
+
     pub async fn replace_description(&self, id: &str, description: String) -> DbResult<()> {
         let _: Option<RecordData<Empty>> = RecordIdFunc::from((User::name(), id))
             .patch(self.db.as_ref(), PatchOp::replace("/bio", description))
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use surrealdb::{opt::PatchOp, Datetime};
+use surrealdb_extras::{RecordData, RecordIdType, SurrealTable, SurrealTableInfo};
+
+use crate::{error::DbResult, DbSession};
+
+use super::{manga::Manga, tag::Empty, user::User};
+
+/// Whether the reader opens the best scored version of a chapter
+#[derive(SurrealTable, Serialize, Deserialize, Debug, Clone)]
+#[db("version_preferences")]
+#[sql(["DEFINE EVENT version_preference_updated ON TABLE version_preferences WHEN $event = \"UPDATE\" AND $before.updated == $after.updated THEN (UPDATE $after.id SET updated = time::now() );"])]
+pub struct VersionPreference {
+    pub user: RecordIdType<User>,
+    /// Default of the user if not set
+    pub manga: Option<RecordIdType<Manga>>,
+    pub best_quality: bool,
+    #[opt(exclude = true)]
+    pub updated: Datetime,
+}
+
+#[derive(Clone)]
+pub struct VersionPreferenceDBService {
+    db: DbSession,
+}
+
+impl VersionPreferenceDBService {
+    pub fn new(db: DbSession) -> Self {
+        Self { db }
+    }
+
+    fn manga_id(manga: Option<&str>) -> Option<RecordIdType<Manga>> {
+        manga.map(|v| RecordIdType::from((Manga::name(), v)))
+    }
+
+    pub async fn set(&self, user: &str, manga: Option<&str>, best_quality: bool) -> DbResult<()> {
+        let mut existing: Vec<RecordData<VersionPreference>> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE user = $user AND manga = $manga LIMIT 1",
+                VersionPreference::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind(("manga", Self::manga_id(manga)))
+            .await?
+            .take(0)?;
+        match existing.pop() {
+            Some(v) => {
+                let _: Option<Empty> =
+                    v.id.patch(
+                        self.db.as_ref(),
+                        PatchOp::replace("/best_quality", best_quality),
+                    )
+                    .await?;
+            }
+            None => {
+                VersionPreference {
+                    user: RecordIdType::from((User::name(), user)),
+                    manga: Self::manga_id(manga),
+                    best_quality,
+                    updated: Default::default(),
+                }
+                .add(self.db.as_ref())
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the preference for `manga`, so the default of the user applies again
+    pub async fn clear(&self, user: &str, manga: &str) -> DbResult<()> {
+        self.db
+            .query(format!(
+                "DELETE FROM {} WHERE user = $user AND manga = $manga",
+                VersionPreference::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind(("manga", Self::manga_id(Some(manga))))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    /// Preference for `manga`, falls back to the default of the user
+    pub async fn get(&self, user: &str, manga: &str) -> DbResult<Option<bool>> {
+        let found: Vec<VersionPreference> = self
+            .db
+            .query(format!(
+                "SELECT * FROM {} WHERE user = $user AND (manga = $manga OR manga = NONE) ORDER BY manga DESC LIMIT 2",
+                VersionPreference::name()
+            ))
+            .bind(("user", RecordIdType::<User>::from((User::name(), user))))
+            .bind(("manga", Self::manga_id(Some(manga))))
+            .await?
+            .take(0)?;
+        Ok(found
+            .iter()
+            .find(|v| v.manga.is_some())
+            .or_else(|| found.first())
+            .map(|v| v.best_quality))
+    }
+}
@@ -0,0 +1,130 @@
+//! Which mangas a user can see. Used inside of queries & for single mangas,
+//! so lists, search & info cannot disagree
+
+use api_structure::v1::{Role, Visibility};
+
+const VISIBILITIES: [Visibility; 3] = [
+    Visibility::Visible,
+    Visibility::Hidden,
+    Visibility::AdminReview,
+];
+
+/// Which mangas with a visibility can be seen by a role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    All,
+    /// Only mangas uploaded by the user
+    Uploaded,
+    None,
+}
+
+pub fn access(role: Role, visibility: Visibility) -> Access {
+    let staff = matches!(role, Role::Admin | Role::CoAdmin | Role::Moderator);
+    match visibility {
+        Visibility::Visible => Access::All,
+        Visibility::Hidden | Visibility::AdminReview if staff => Access::All,
+        Visibility::Hidden if role == Role::Author => Access::Uploaded,
+        Visibility::Hidden | Visibility::AdminReview => Access::None,
+    }
+}
+
+/// `visibility` is the raw db value, unknown values are never accessible
+pub fn can_access(role: Role, uid: &str, visibility: u64, uploader: &str) -> bool {
+    let Ok(visibility) = Visibility::try_from(visibility) else {
+        return false;
+    };
+    match access(role, visibility) {
+        Access::All => true,
+        Access::Uploaded => uid == uploader,
+        Access::None => false,
+    }
+}
+
+/// Condition for the mangas `role` can see, `$user` has to be bound with [crate::search::bind_user].
+/// None if nothing needs to be filtered
+pub fn condition(role: Role) -> Option<String> {
+    if VISIBILITIES.iter().all(|v| access(role, *v) == Access::All) {
+        return None;
+    }
+    let parts = VISIBILITIES
+        .into_iter()
+        .filter_map(|v| match access(role, v) {
+            Access::All => Some(format!("visibility = {}", v as u64)),
+            Access::Uploaded => Some(format!("(visibility = {} AND uploader = $user)", v as u64)),
+            Access::None => None,
+        })
+        .collect::<Vec<_>>();
+    Some(format!("({})", parts.join(" OR ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use api_structure::v1::{Role, Visibility};
+
+    use super::{access, can_access, condition, Access};
+
+    const ROLES: [Role; 6] = [
+        Role::NotVerified,
+        Role::User,
+        Role::Author,
+        Role::Moderator,
+        Role::CoAdmin,
+        Role::Admin,
+    ];
+
+    fn expected(role: Role, visibility: Visibility) -> Access {
+        match (role, visibility) {
+            (_, Visibility::Visible) => Access::All,
+            (Role::NotVerified | Role::User, Visibility::Hidden) => Access::None,
+            (Role::Author, Visibility::Hidden) => Access::Uploaded,
+            (Role::Moderator | Role::CoAdmin | Role::Admin, Visibility::Hidden) => Access::All,
+            (Role::NotVerified | Role::User | Role::Author, Visibility::AdminReview) => {
+                Access::None
+            }
+            (Role::Moderator | Role::CoAdmin | Role::Admin, Visibility::AdminReview) => Access::All,
+        }
+    }
+
+    #[test]
+    fn every_role_visibility_pair() {
+        for role in ROLES {
+            for visibility in super::VISIBILITIES {
+                assert_eq!(
+                    access(role, visibility),
+                    expected(role, visibility),
+                    "{role:?} {visibility:?}"
+                );
+                let own = can_access(role, "me", visibility as u64, "me");
+                let other = can_access(role, "me", visibility as u64, "other");
+                match expected(role, visibility) {
+                    Access::All => assert!(own && other, "{role:?} {visibility:?}"),
+                    Access::Uploaded => assert!(own && !other, "{role:?} {visibility:?}"),
+                    Access::None => assert!(!own && !other, "{role:?} {visibility:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_visibility_is_never_accessible() {
+        for role in ROLES {
+            assert!(!can_access(role, "me", 99, "me"));
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(condition(Role::Admin), None);
+        assert_eq!(condition(Role::CoAdmin), None);
+        assert_eq!(condition(Role::Moderator), None);
+        assert_eq!(
+            condition(Role::Author).as_deref(),
+            Some("(visibility = 0 OR (visibility = 1 AND uploader = $user))")
+        );
+        assert_eq!(condition(Role::User).as_deref(), Some("(visibility = 0)"));
+        assert_eq!(
+            condition(Role::NotVerified).as_deref(),
+            Some("(visibility = 0)")
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Validates the hand written scraper definitions without starting the server.
+//!
+//! ```text
+//! manga-scraper-check [root]
+//! manga-scraper-check --fixture <root> <uri> <metadata|chapters|pages|search> <html file> <url|query>
+//! ```
+//!
+//! The first mode parses every file in `{root}/external` & reports errors, missing icons & unused processors.
+//! The second mode serves the html file for every request of the site & prints the scraped data,
+//! so selectors can be tuned without requesting the live site.
+//! Needs the `testing` feature, which enables answering requests without the network.
+
+use std::{collections::HashMap, path::Path, process::ExitCode};
+
+use manga_scraper::{
+    init::{self, parse, scraper::set_response_source},
+    InitError,
+};
+use scraper_module::{Attribute, SearchQuery};
+
+const USAGE: &str = "usage:
+    manga-scraper-check [root]
+    manga-scraper-check --fixture <root> <uri> <metadata|chapters|pages|search> <html file> <url|query>";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(|v| v.as_str()) {
+        Some("--fixture") => fixture(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Some(root) if args.len() == 1 => check(Path::new(root)),
+        None => check(Path::new("data")),
+        Some(_) => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn check(root: &Path) -> ExitCode {
+    let (registers, searches, metadata, readers, errors) = parse::parse(root);
+
+    let mut exts: HashMap<&str, Vec<&str>> = HashMap::new();
+    let files = parse::files(root);
+    for (ext, paths) in &files {
+        for uri in paths
+            .iter()
+            .filter_map(|v| v.file_stem().and_then(|v| v.to_str()))
+        {
+            exts.entry(uri).or_default().push(*ext);
+        }
+    }
+
+    let (missing_icons, errors): (Vec<_>, Vec<_>) = errors
+        .iter()
+        .partition(|v| matches!(v.inner(), InitError::MissingIcon));
+    for error in &errors {
+        println!("error: {error}");
+    }
+    for error in &missing_icons {
+        match error {
+            InitError::Located {
+                file: Some(file), ..
+            } => println!("missing icon: {}", file.display()),
+            error => println!("missing icon: {error}"),
+        }
+    }
+    let mut warnings = 0;
+    for (uri, register) in &registers {
+        let names = register.get_used_processor_names();
+        let exts = exts.get(uri.as_str()).map(|v| v.as_slice()).unwrap_or(&[]);
+        for name in parse::unused_processors(&names, exts) {
+            println!("warning: {uri}.filter: unused processor `{name}`");
+            warnings += 1;
+        }
+    }
+
+    println!(
+        "{} sites, {} searches, {} metadata, {} readers: {} errors, {} missing icons, {} warnings",
+        registers.len(),
+        searches.len(),
+        metadata.len(),
+        readers.len(),
+        errors.len(),
+        missing_icons.len(),
+        warnings
+    );
+    match errors.is_empty() && missing_icons.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+fn fixture(args: &[String]) -> ExitCode {
+    let [root, uri, kind, html, target] = args else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let body = match std::fs::read(html) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("failed to read {html}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // every request of the site gets the same page
+    set_response_source(move |_| Some(body.clone()));
+
+    let (services, errors) = init::register(Path::new(root));
+    for error in &errors {
+        eprintln!("error: {error}");
+    }
+    let Some(service) = services.get_by_uri(uri) else {
+        eprintln!("unknown site {uri}");
+        return ExitCode::FAILURE;
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start runtime");
+    let out = runtime.block_on(async {
+        let missing = || format!("{uri} has no .{kind} file");
+        let out = match kind.as_str() {
+            "metadata" => {
+                let metadata = service.metadata.as_ref().ok_or_else(missing)?;
+                serde_json::to_string_pretty(
+                    &metadata
+                        .scrape_metadata(target)
+                        .await
+                        .map_err(|e| format!("{e:?}"))?,
+                )
+            }
+            "chapters" => {
+                let reader = service.reader.as_ref().ok_or_else(missing)?;
+                serde_json::to_string_pretty(
+                    &reader
+                        .scrape_chapters(target)
+                        .await
+                        .map_err(|e| format!("{e:?}"))?,
+                )
+            }
+            "pages" => {
+                let reader = service.reader.as_ref().ok_or_else(missing)?;
+                serde_json::to_string_pretty(
+                    &reader
+                        .scrape_pages(target)
+                        .await
+                        .map_err(|e| format!("{e:?}"))?,
+                )
+            }
+            "search" => {
+                let search = service.searchers.as_ref().ok_or_else(missing)?;
+                let query = SearchQuery::Simple(HashMap::from([
+                    ("query".to_owned(), Attribute::Str(target.to_owned())),
+                    ("page".to_owned(), Attribute::Int(1)),
+                ]));
+                serde_json::to_string_pretty(
+                    &search.search(query).await.map_err(|e| format!("{e:?}"))?,
+                )
+            }
+            kind => return Err(format!("unknown kind {kind}\n{USAGE}")),
+        };
+        out.map_err(|e| e.to_string())
+    });
+    match out {
+        Ok(out) => {
+            println!("{out}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
 use reqwest::header::{InvalidHeaderName, InvalidHeaderValue};
 use scraper::error::SelectorErrorKind;
 
@@ -15,8 +20,160 @@ pub enum InitError {
     Toml(toml::de::Error),
     SelectorError(String),
     Reqwest(reqwest::Error),
+    SerdeJson(String),
+    /// Library in `external/` is not a valid plugin
+    InvalidPlugin(String),
+    /// Plugin was built for another [scraper_module::plugin::ABI_VERSION]
+    PluginAbiMismatch {
+        expected: u32,
+        found: u32,
+    },
+    /// Plugin without [scraper_module::plugin::capability::FILTER] & without a `.filter` file
+    PluginWithoutSite(String),
+    /// Arguments of a processor in a `use` line are invalid
+    InvalidProcessorArgs(String),
+    /// `.filter` without an icon with the same uri
+    MissingIcon,
+    /// Query is missing the `[kind]` part
+    MissingKind,
+    /// `[` or `![` was opened but never closed
+    UnclosedKind,
+    /// `error` was caused by `file`. line & column start at 1 & are 0 if the whole file is the cause.
+    /// `file` is only missing while the error bubbles up from parsing a str
+    Located {
+        file: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        error: Box<InitError>,
+    },
 }
 
+impl InitError {
+    /// Marks `column` of the current line as cause.
+    /// If the error already has a column, it is treated as relative to `column`
+    pub fn at_column(self, column: usize) -> Self {
+        match self {
+            InitError::Located {
+                file,
+                line,
+                column: c,
+                error,
+            } => InitError::Located {
+                file,
+                line,
+                column: column + c.max(1) - 1,
+                error,
+            },
+            error => InitError::Located {
+                file: None,
+                line: 0,
+                column,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Marks `line` as cause. Keeps the column if there is one
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            InitError::Located {
+                file,
+                column,
+                error,
+                ..
+            } => InitError::Located {
+                file,
+                line,
+                column,
+                error,
+            },
+            error => InitError::Located {
+                file: None,
+                line,
+                column: 1,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Adds `file` to the error. Errors without a position point at the whole file
+    pub fn in_file(self, file: &Path) -> Self {
+        match self {
+            InitError::Located {
+                file: None,
+                line,
+                column,
+                error,
+            } => InitError::Located {
+                file: Some(file.to_path_buf()),
+                line,
+                column,
+                error,
+            },
+            error @ InitError::Located { .. } => error,
+            error => InitError::Located {
+                file: Some(file.to_path_buf()),
+                line: 0,
+                column: 0,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Toml errors only contain the byte offset, so the content is needed for the line
+    pub fn toml(err: toml::de::Error, content: &str) -> Self {
+        match err.span() {
+            Some(span) => {
+                let before = &content[..span.start.min(content.len())];
+                let line = before.matches('\n').count() + 1;
+                let column = before
+                    .rsplit_once('\n')
+                    .map(|v| v.1)
+                    .unwrap_or(before)
+                    .chars()
+                    .count()
+                    + 1;
+                InitError::Toml(err).at_column(column).at_line(line)
+            }
+            None => InitError::Toml(err),
+        }
+    }
+
+    /// The error without its location
+    pub fn inner(&self) -> &InitError {
+        match self {
+            InitError::Located { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Located {
+                file,
+                line,
+                column,
+                error,
+            } => {
+                let file = file
+                    .as_ref()
+                    .map(|v| v.display().to_string())
+                    .unwrap_or_else(|| "<str>".to_owned());
+                match line {
+                    0 => write!(f, "{file}: {error}"),
+                    line => write!(f, "{file}:{line}:{column}: {error}"),
+                }
+            }
+            InitError::Toml(err) => write!(f, "Toml({})", err.message()),
+            error => write!(f, "{error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
 impl From<toml::de::Error> for InitError {
     fn from(err: toml::de::Error) -> Self {
         InitError::Toml(err)
@@ -51,6 +208,7 @@ pub enum ScrapeError {
     Cloudflare,
     UrlParseError(String),
     Reqwest(reqwest::Error),
+    Curl(String),
 }
 
 impl From<reqwest::Error> for ScrapeError {
@@ -59,6 +217,12 @@ impl From<reqwest::Error> for ScrapeError {
     }
 }
 
+impl From<curl::Error> for ScrapeError {
+    fn from(err: curl::Error) -> Self {
+        ScrapeError::Curl(err.to_string())
+    }
+}
+
 impl From<url::ParseError> for ScrapeError {
     fn from(err: url::ParseError) -> Self {
         ScrapeError::UrlParseError(err.to_string())
@@ -79,8 +243,7 @@ impl From<regex::Error> for InitError {
 
 impl From<serde_json::Error> for InitError {
     fn from(err: serde_json::Error) -> Self {
-        println!("{err}");
-        todo!()
+        InitError::SerdeJson(err.to_string())
     }
 }
 
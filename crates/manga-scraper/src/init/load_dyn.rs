@@ -1,17 +1,34 @@
-use std::{fs, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    ffi::c_void,
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use async_trait::async_trait;
 use libloading::Library;
-use scraper_module::{req::ClientT, Functions, RegisterOverride};
+use scraper_module::{
+    plugin::{
+        capability, free_buffer, method, Buffer, HostRequest, HostResponse, HostVTable, Manifest,
+        PluginVTable, Str, ABI_VERSION, INIT_SYMBOL, MANIFEST_SYMBOL,
+    },
+    ExternalSearchResponse, MetaDataScraper, Mode, ReaderScraper, Register, RegisterOverride,
+    ScrapedChapter, ScrapedData, ScraperError, ScraperResult, SearchQuery, SearchScraper,
+    ValidSearches,
+};
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::runtime::{fetch::ReqwestClient, files::guess_episode};
+use crate::InitError;
 
-type LoadFn = unsafe fn(client: Arc<dyn ClientT>, functions: Functions) -> RegisterOverride;
+type ManifestFn = unsafe extern "C" fn() -> Manifest;
+type InitFn = unsafe extern "C" fn(HostVTable) -> PluginVTable;
 
-pub fn load_dyn(dir: &std::path::Path) -> Vec<RegisterOverride> {
+/// Loads every plugin in `dir/external`. Plugins with another abi version are refused
+pub fn load_dyn(dir: &Path) -> (Vec<(PathBuf, RegisterOverride)>, Vec<InitError>) {
     let mut overrides = Vec::new();
-    let functions = Functions {
-        guess_episode: guess_episode,
-    };
+    let mut errors = Vec::new();
     for path in fs::read_dir(dir.join("external"))
         .map(|v| {
             v.filter_map(|v| v.ok().map(|v| v.path()))
@@ -23,15 +40,301 @@ pub fn load_dyn(dir: &std::path::Path) -> Vec<RegisterOverride> {
             .extension()
             .map_or(false, |ext| ext == "dll" || ext == "dylib" || ext == "so")
         {
-            unsafe {
-                if let Ok(lib) = Library::new(&path) {
-                    if let Ok(load_fn) = lib.get::<LoadFn>(b"load") {
-                        overrides.push(load_fn(Arc::new(ReqwestClient::new()), functions.clone()));
-                    }
-                }
+            match unsafe { load(&path) } {
+                Ok(v) => overrides.push((path, v)),
+                Err(e) => errors.push(e.in_file(&path)),
             }
         }
     }
-    //TODO: overrides
-    overrides
+    (overrides, errors)
+}
+
+unsafe fn load(path: &Path) -> crate::Result<RegisterOverride> {
+    let lib = Library::new(path).map_err(|e| InitError::InvalidPlugin(e.to_string()))?;
+    let manifest_fn = lib
+        .get::<ManifestFn>(MANIFEST_SYMBOL)
+        .map_err(|e| InitError::InvalidPlugin(e.to_string()))?;
+    let manifest = manifest_fn();
+    let (name, version) = read_manifest(&manifest)?;
+    let init = lib
+        .get::<InitFn>(INIT_SYMBOL)
+        .map_err(|e| InitError::InvalidPlugin(e.to_string()))?;
+
+    let host = Box::new(PluginHost::new()?);
+    let vtable = init(HostVTable {
+        abi_version: ABI_VERSION,
+        host: host.as_ref() as *const PluginHost as *const c_void,
+        fetch,
+        free: free_buffer,
+    });
+    let plugin = Arc::new(DynPlugin {
+        vtable,
+        _host: host,
+        _lib: lib,
+    });
+    log::info!("Loaded plugin {name} {version}");
+
+    let has = |c: u32| manifest.capabilities & c != 0;
+    let register = match has(capability::FILTER) {
+        true => {
+            let icon = plugin
+                .call(method::ICON, &())
+                .map_err(|e| InitError::InvalidPlugin(format!("{e:?}")))?;
+            Some(Arc::new(PluginRegister {
+                plugin: plugin.clone(),
+                icon,
+            }) as Arc<dyn Register>)
+        }
+        false => None,
+    };
+    let scraper = Arc::new(PluginScraper(plugin));
+    Ok(RegisterOverride {
+        uri: name,
+        register,
+        metadata: has(capability::METADATA).then(|| scraper.clone() as Arc<dyn MetaDataScraper>),
+        search: has(capability::SEARCH).then(|| scraper.clone() as Arc<dyn SearchScraper>),
+        reader: has(capability::READER).then(|| scraper.clone() as Arc<dyn ReaderScraper>),
+    })
+}
+
+/// Returns the name & version of the plugin, if it was built for [ABI_VERSION]
+unsafe fn read_manifest(manifest: &Manifest) -> crate::Result<(String, String)> {
+    if manifest.abi_version != ABI_VERSION {
+        return Err(InitError::PluginAbiMismatch {
+            expected: ABI_VERSION,
+            found: manifest.abi_version,
+        });
+    }
+    let name = String::from_utf8(manifest.name.as_bytes().to_vec())
+        .map_err(|e| InitError::InvalidPlugin(e.to_string()))?;
+    let version = String::from_utf8_lossy(manifest.version.as_bytes()).into_owned();
+    Ok((name, version))
+}
+
+struct PluginHost {
+    client: reqwest::Client,
+    /// Plugins are called from blocking threads and from async code (`url_matches`, `multi`),
+    /// so the requests are driven by a runtime owned by the host
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl PluginHost {
+    fn new() -> crate::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|e| InitError::InvalidPlugin(e.to_string()))?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            runtime: Some(runtime),
+        })
+    }
+
+    fn fetch(&self, request: HostRequest) -> Result<HostResponse, String> {
+        let runtime = self
+            .runtime
+            .as_ref()
+            .ok_or_else(|| "host is shutting down".to_owned())?;
+        match tokio::runtime::Handle::try_current() {
+            // block_on panics on a thread of another runtime
+            Ok(_) => std::thread::scope(|s| {
+                s.spawn(|| runtime.block_on(self.send(request)))
+                    .join()
+                    .unwrap_or_else(|_| Err("host panicked".to_owned()))
+            }),
+            Err(_) => runtime.block_on(self.send(request)),
+        }
+    }
+
+    async fn send(&self, request: HostRequest) -> Result<HostResponse, String> {
+        let method =
+            reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+        let mut builder = self.client.request(method, &request.url);
+        for (key, value) in request.headers {
+            builder = builder.header(key, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let resp = builder.send().await.map_err(|e| e.to_string())?;
+        Ok(HostResponse {
+            status: resp.status().as_u16(),
+            headers: resp
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_owned())))
+                .collect(),
+            body: resp.bytes().await.map_err(|e| e.to_string())?.to_vec(),
+        })
+    }
+}
+
+impl Drop for PluginHost {
+    fn drop(&mut self) {
+        // dropping a runtime inside of async code panics
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+extern "C" fn fetch(host: *const c_void, request: Str) -> Buffer {
+    let host = unsafe { &*(host as *const PluginHost) };
+    let request = unsafe { request.as_bytes() };
+    // unwinding into the plugin would abort the server
+    let out = catch_unwind(AssertUnwindSafe(|| {
+        serde_json::from_slice::<HostRequest>(request)
+            .map_err(|e| e.to_string())
+            .and_then(|request| host.fetch(request))
+    }))
+    .unwrap_or_else(|_| Err("host panicked".to_owned()));
+    Buffer::from_vec(serde_json::to_vec(&out).unwrap_or_default())
+}
+
+struct DynPlugin {
+    vtable: PluginVTable,
+    // dropped after the plugin, because it is used by it
+    _host: Box<PluginHost>,
+    _lib: Library,
+}
+
+// plugins have to implement `Plugin`, which is Send + Sync
+unsafe impl Send for DynPlugin {}
+unsafe impl Sync for DynPlugin {}
+
+impl Drop for DynPlugin {
+    fn drop(&mut self) {
+        (self.vtable.drop)(self.vtable.plugin)
+    }
+}
+
+impl DynPlugin {
+    fn call<O: DeserializeOwned>(&self, method: u32, input: &impl Serialize) -> ScraperResult<O> {
+        let input = serde_json::to_vec(input)?;
+        let out = (self.vtable.call)(self.vtable.plugin, method, Str::from_bytes(&input));
+        let bytes = unsafe { out.to_vec() };
+        (self.vtable.free)(out);
+        serde_json::from_slice::<Result<O, String>>(&bytes)?.map_err(ScraperError::Plugin)
+    }
+
+    async fn call_blocking<O: DeserializeOwned + Send + 'static>(
+        self: &Arc<Self>,
+        method: u32,
+        input: impl Serialize + Send + 'static,
+    ) -> ScraperResult<O> {
+        let plugin = self.clone();
+        tokio::task::spawn_blocking(move || plugin.call(method, &input))
+            .await
+            .map_err(|e| ScraperError::Plugin(e.to_string()))?
+    }
+}
+
+struct PluginRegister {
+    plugin: Arc<DynPlugin>,
+    icon: (String, Vec<u8>),
+}
+
+impl Register for PluginRegister {
+    fn get_used_processor_names(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn url_matches(&self, url: &str) -> bool {
+        self.plugin.call(method::URL_MATCHES, &url).unwrap_or(false)
+    }
+
+    fn icon(&self) -> (String, Vec<u8>) {
+        self.icon.clone()
+    }
+
+    fn icon_source(&self) -> Option<String> {
+        None
+    }
+}
+
+struct PluginScraper(Arc<DynPlugin>);
+
+#[async_trait]
+impl SearchScraper for PluginScraper {
+    async fn search(&self, query: SearchQuery) -> ScraperResult<ExternalSearchResponse> {
+        self.0.call_blocking(method::SEARCH, query).await
+    }
+
+    fn query(&self) -> ValidSearches {
+        self.0
+            .call(method::QUERY, &())
+            .unwrap_or(ValidSearches::QueryOffset)
+    }
+}
+
+#[async_trait]
+impl MetaDataScraper for PluginScraper {
+    async fn scrape_metadata(&self, url: &str) -> ScraperResult<BTreeMap<String, ScrapedData>> {
+        self.0.call_blocking(method::METADATA, url.to_owned()).await
+    }
+
+    async fn download_file(&self, url: &str) -> ScraperResult<Vec<u8>> {
+        self.0.call_blocking(method::DOWNLOAD, url.to_owned()).await
+    }
+}
+
+#[async_trait]
+impl ReaderScraper for PluginScraper {
+    fn multi(&self, url: &str) -> Mode {
+        self.0.call(method::MULTI, &url).unwrap_or(Mode::Single)
+    }
+
+    async fn download_file(&self, url: &str) -> ScraperResult<Vec<u8>> {
+        self.0.call_blocking(method::DOWNLOAD, url.to_owned()).await
+    }
+
+    async fn scrape_pages(&self, url: &str) -> ScraperResult<Vec<String>> {
+        self.0.call_blocking(method::PAGES, url.to_owned()).await
+    }
+
+    async fn scrape_chapters(&self, url: &str) -> ScraperResult<Vec<ScrapedChapter>> {
+        self.0.call_blocking(method::CHAPTERS, url.to_owned()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scraper_module::plugin::{Manifest, Str, ABI_VERSION};
+
+    use super::{read_manifest, HostRequest, PluginHost};
+    use crate::InitError;
+
+    fn manifest(abi_version: u32) -> Manifest {
+        Manifest {
+            abi_version,
+            name: Str::new("plugin"),
+            version: Str::new("1.0.0"),
+            capabilities: 0,
+        }
+    }
+
+    #[test]
+    fn test_abi_mismatch_is_refused() {
+        assert!(matches!(
+            unsafe { read_manifest(&manifest(ABI_VERSION + 1)) },
+            Err(InitError::PluginAbiMismatch { expected, found })
+                if expected == ABI_VERSION && found == ABI_VERSION + 1
+        ));
+        let (name, version) = unsafe { read_manifest(&manifest(ABI_VERSION)) }.unwrap();
+        assert_eq!((name.as_str(), version.as_str()), ("plugin", "1.0.0"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_inside_a_runtime_does_not_panic() {
+        let host = PluginHost::new().unwrap();
+        let resp = host.fetch(HostRequest {
+            method: "GET".to_owned(),
+            url: "http://127.0.0.1:9".to_owned(),
+            headers: vec![],
+            body: None,
+        });
+        assert!(resp.is_err());
+        drop(host);
+    }
 }
@@ -1,6 +1,12 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use scraper_module::{MetaDataScraper, ReaderScraper, Register, SearchScraper};
+use scraper_module::{MetaDataScraper, ReaderScraper, Register, RegisterOverride, SearchScraper};
+
+use crate::InitError;
 
 mod load_dyn;
 pub mod parse;
@@ -34,13 +40,37 @@ impl Services {
     }
 }
 
-pub fn register(root: &Path) -> crate::Result<Services> {
-    let (registers, searchers, metadata, reader) = parse::parse(root)?;
+/// Loads all sites in `root/external`.
+/// Sites with invalid files are skipped, the errors are returned next to the valid sites
+pub fn register(root: &Path) -> (Services, Vec<InitError>) {
+    let (dylibs, dylib_errors) = load_dyn::load_dyn(root);
+    let (services, mut errors) = register_with_overrides(root, dylibs);
+    errors.extend(dylib_errors);
+    (services, errors)
+}
+
+/// Loads all sites in `root/external` and applies `dylibs` on top of them.
+/// The `register` of an override replaces the `.filter` of a site with the same uri
+pub fn register_with_overrides(
+    root: &Path,
+    dylibs: Vec<(PathBuf, RegisterOverride)>,
+) -> (Services, Vec<InitError>) {
+    let (mut registers, searchers, metadata, reader, mut errors) = parse::parse(root);
     let mut searchers = searchers.into_iter().collect::<HashMap<_, _>>();
     let mut metadata = metadata.into_iter().collect::<HashMap<_, _>>();
     let mut reader = reader.into_iter().collect::<HashMap<_, _>>();
-    let dylibs = load_dyn::load_dyn(root);
-    for dylib in dylibs {
+    for (file, dylib) in dylibs {
+        match dylib.register {
+            Some(register) => match registers.iter_mut().find(|v| v.0 == dylib.uri) {
+                Some(v) => v.1 = register,
+                None => registers.push((dylib.uri.clone(), register)),
+            },
+            None if !registers.iter().any(|v| v.0 == dylib.uri) => {
+                errors.push(InitError::PluginWithoutSite(dylib.uri).in_file(&file));
+                continue;
+            }
+            None => {}
+        }
         if let Some(search) = dylib.search {
             searchers.insert(dylib.uri.to_owned(), search);
         }
@@ -62,5 +92,5 @@ pub fn register(root: &Path) -> crate::Result<Services> {
             uri,
         })
     }
-    Ok(Services::new(services))
+    (Services::new(services), errors)
 }
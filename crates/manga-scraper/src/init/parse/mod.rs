@@ -14,16 +14,23 @@ use std::{
 
 use scraper_module::{MetaDataScraper, ReaderScraper, Register, SearchScraper};
 
-use crate::processors::{Processors, Target};
+use crate::{
+    processors::{PreProcessor, Processors, Target},
+    InitError,
+};
 
+/// Parses every file in `root/external`.
+/// Invalid files are skipped & their errors are returned, so one broken site doesnt affect the others
 pub fn parse(
     root: &Path,
-) -> crate::Result<(
+) -> (
     Vec<(String, Arc<dyn Register>)>,
     Vec<(String, Arc<dyn SearchScraper>)>,
     Vec<(String, Arc<dyn MetaDataScraper>)>,
     Vec<(String, Arc<dyn ReaderScraper>)>,
-)> {
+    Vec<InitError>,
+) {
+    let mut errors = vec![];
     let mut files = files(root);
     let filter = files.remove("filter").unwrap_or_default();
     let search = files.remove("search").unwrap_or_default();
@@ -34,121 +41,142 @@ pub fn parse(
     let mut imgs = files
         .into_iter()
         .flat_map(|(ty, v)| v.into_iter().map(move |v| (ty, v)))
-        .map(|v| {
-            let name = v.1.file_name().and_then(|v| v.to_str()).unwrap().to_owned();
-            (
-                name.strip_suffix(format!(".{}", v.0).as_str())
-                    .unwrap_or(&name)
-                    .to_owned(),
-                (v.0, v.1),
-            )
-        })
+        .filter_map(|v| Some((uri(&v.1).ok()?.to_owned(), (v.0, v.1))))
         .collect::<HashMap<_, _>>();
     let mut scrapers = vec![];
     for filter in filter {
-        let uri = filter
-            .file_name()
-            .and_then(|v| v.to_str())
-            .unwrap_or_default()
-            .rsplit_once(".")
-            .unwrap()
-            .0;
-        let scraper = register::parse(&filter, uri, &mut imgs)?;
-        scrapers.push((uri.to_owned(), scraper));
+        let scraper =
+            uri(&filter).and_then(|uri| Ok((uri, register::parse(&filter, uri, &mut imgs)?)));
+        match scraper {
+            Ok((uri, scraper)) => scrapers.push((uri.to_owned(), scraper)),
+            Err(e) => errors.push(e.in_file(&filter)),
+        }
     }
     let mut searches = vec![];
     let processors = Processors::new();
 
     let mut metadatas = vec![];
     for search in search {
-        let uri = search
-            .file_name()
-            .and_then(|v| v.to_str())
-            .unwrap_or_default()
-            .rsplit_once(".")
-            .unwrap()
-            .0;
-        let item = scrapers
-            .iter()
-            .find(|v| v.0 == uri)
-            .map(|b| b.1.get_used_processor_names())
-            .unwrap_or_default();
-        let post_search = item
-            .iter()
-            .filter_map(|v| processors.get_post_processor_search(v))
-            .collect::<Vec<_>>();
-
-        searches.push((uri.to_owned(), search::parse(&search, item, post_search)?));
+        if !registered(&scrapers, &search) {
+            continue;
+        }
+        let item = uri(&search).and_then(|uri| {
+            let item = used_processors(&scrapers, uri);
+            let post_search = item
+                .iter()
+                .filter_map(|v| processors.get_post_processor_search(v))
+                .collect::<Vec<_>>();
+            Ok((uri, search::parse(&search, item, post_search)?))
+        });
+        match item {
+            Ok((uri, item)) => searches.push((uri.to_owned(), item)),
+            Err(e) => errors.push(e.in_file(&search)),
+        }
     }
 
     let mut readers = vec![];
 
     for scraper in scraper {
-        let uri = scraper
-            .file_name()
-            .and_then(|v| v.to_str())
-            .unwrap_or_default()
-            .rsplit_once(".")
-            .unwrap()
-            .0;
-        let item = scrapers
-            .iter()
-            .find(|v| v.0 == uri)
-            .map(|b| b.1.get_used_processor_names())
-            .unwrap_or_default();
-        let pre = item
-            .iter()
-            .filter_map(|v| processors.get_pre_processor(v))
-            .collect::<Vec<_>>();
-        let pre_meta = pre
-            .iter()
-            .filter_map(|(tr, t)| match t {
-                Target::Metadata => Some(tr.clone()),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
-        let post_scrape = item
-            .iter()
-            .filter_map(|v| processors.get_post_processor_scraper(v))
-            .collect::<Vec<_>>();
-        readers.push((
-            uri.to_owned(),
-            scraper::parse(&scraper, item, pre_meta, post_scrape)?,
-        ));
+        if !registered(&scrapers, &scraper) {
+            continue;
+        }
+        let item = uri(&scraper).and_then(|uri| {
+            let item = used_processors(&scrapers, uri);
+            let pre_meta = pre_processors(&processors, &item);
+            let post_scrape = item
+                .iter()
+                .filter_map(|v| processors.get_post_processor_scraper(v))
+                .collect::<Vec<_>>();
+            Ok((uri, scraper::parse(&scraper, item, pre_meta, post_scrape)?))
+        });
+        match item {
+            Ok((uri, item)) => readers.push((uri.to_owned(), item)),
+            Err(e) => errors.push(e.in_file(&scraper)),
+        }
     }
 
     for metadata in metadata {
-        let uri = metadata
-            .file_name()
-            .and_then(|v| v.to_str())
-            .unwrap_or_default()
-            .rsplit_once(".")
-            .unwrap()
-            .0;
-        let item = scrapers
-            .iter()
-            .find(|v| v.0 == uri)
-            .map(|b| b.1.get_used_processor_names())
-            .unwrap_or_default();
-        let post_meta = item
-            .iter()
-            .filter_map(|v| processors.get_post_processor_meta(v))
-            .collect::<Vec<_>>();
-        let pre = item
-            .iter()
-            .filter_map(|v| processors.get_pre_processor(v))
-            .collect::<Vec<_>>();
-        let pre_meta = pre
-            .iter()
-            .filter_map(|(tr, t)| match t {
-                Target::Metadata => Some(tr.clone()),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
-        let metadata = metadata::parse(&metadata, item, pre_meta, post_meta)?;
-        metadatas.push((uri.to_owned(), metadata));
+        if !registered(&scrapers, &metadata) {
+            continue;
+        }
+        let item = uri(&metadata).and_then(|uri| {
+            let item = used_processors(&scrapers, uri);
+            let post_meta = item
+                .iter()
+                .filter_map(|v| processors.get_post_processor_meta(v))
+                .collect::<Vec<_>>();
+            let pre_meta = pre_processors(&processors, &item);
+            Ok((uri, metadata::parse(&metadata, item, pre_meta, post_meta)?))
+        });
+        match item {
+            Ok((uri, item)) => metadatas.push((uri.to_owned(), item)),
+            Err(e) => errors.push(e.in_file(&metadata)),
+        }
     }
-    Ok((scrapers, searches, metadatas, readers))
+    (scrapers, searches, metadatas, readers, errors)
+}
+
+/// Files are named `{uri}.{ext}`
+fn uri(file: &Path) -> crate::Result<&str> {
+    file.file_stem()
+        .and_then(|v| v.to_str())
+        .ok_or_else(|| InitError::InitParseError("file name is not valid utf8".to_owned()))
+}
+
+/// Only sites with a valid `.filter` file are used, so the other files dont need to be parsed
+fn registered(scrapers: &[(String, Arc<dyn Register>)], file: &Path) -> bool {
+    uri(file).is_ok_and(|uri| scrapers.iter().any(|v| v.0 == uri))
+}
+
+fn used_processors<'a>(scrapers: &'a [(String, Arc<dyn Register>)], uri: &str) -> Vec<&'a str> {
+    scrapers
+        .iter()
+        .find(|v| v.0 == uri)
+        .map(|b| b.1.get_used_processor_names())
+        .unwrap_or_default()
+}
+
+fn pre_processors(
+    processors: &Processors,
+    item: &[&str],
+) -> Vec<Arc<dyn PreProcessor + Sync + Send>> {
+    item.iter()
+        .filter_map(|v| processors.get_pre_processor(v))
+        .filter_map(|(tr, t)| match t {
+            Target::Metadata => Some(tr),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `use` lines of a `.filter` which dont affect any file of the site.
+/// `exts` are the extensions of all files of the site
+pub fn unused_processors<'a>(names: &[&'a str], exts: &[&str]) -> Vec<&'a str> {
+    let processors = Processors::new();
+    let has = |ext: &str| exts.contains(&ext);
+    names
+        .iter()
+        .copied()
+        .filter(|name| {
+            let used = if ["reqwest", "reqwest_rustls", "curl"].contains(name) {
+                has("search") || has("metadata") || has("scraper")
+            } else if name.starts_with("post scraper-chapter") {
+                has("scraper")
+            } else if let Some((_, target)) = processors.get_pre_processor(name) {
+                // only metadata preprocessors are passed to .metadata & .scraper
+                matches!(target, Target::Metadata) && (has("metadata") || has("scraper"))
+            } else if processors.get_post_processor_meta(name).is_some() {
+                has("metadata")
+            } else if processors.get_post_processor_search(name).is_some() {
+                has("search")
+            } else if processors.get_post_processor_scraper(name).is_some() {
+                has("scraper")
+            } else {
+                false
+            };
+            !used
+        })
+        .collect()
 }
 
 pub fn files(root: &Path) -> HashMap<&'static str, Vec<PathBuf>> {
@@ -8,10 +8,12 @@ use std::{
 use regex::bytes::Regex;
 use scraper_module::Register;
 
+use crate::processors::Processors;
+
 pub struct InterpretedRegister {
     urls: Vec<UrlFilter>,
     pub processors: Vec<String>,
-    img_path: PathBuf,
+    icon: Vec<u8>,
     extension: String,
     img_source: Option<String>,
 }
@@ -38,8 +40,7 @@ impl Register for InterpretedRegister {
     }
 
     fn icon(&self) -> (String, Vec<u8>) {
-        let file_bytes = fs::read(&self.img_path).unwrap();
-        (self.extension.clone(), file_bytes)
+        (self.extension.clone(), self.icon.clone())
     }
 
     fn get_used_processor_names(&self) -> Vec<&str> {
@@ -60,60 +61,70 @@ pub fn parse(
     let prefixes = ["regex ", "starts_with ", "use ", "contains ", "source "];
     let mut items = text
         .lines()
-        .filter_map(|v| {
-            let prefix = prefixes.into_iter().find(|prefix| v.starts_with(prefix));
-            prefix.map(|pre| (pre, v.strip_prefix(pre).unwrap()))
+        .enumerate()
+        .filter_map(|(i, v)| {
+            prefixes
+                .into_iter()
+                .find_map(|prefix| Some((prefix.trim_end(), (i + 1, v.strip_prefix(prefix)?))))
         })
         .fold(
             HashMap::new(),
-            |mut acc: HashMap<&str, Vec<&str>>, (pre, v)| {
-                acc.entry(pre.strip_suffix(" ").unwrap())
-                    .or_default()
-                    .push(v);
+            |mut acc: HashMap<&str, Vec<(usize, &str)>>, (pre, v)| {
+                acc.entry(pre).or_default().push(v);
                 acc
             },
         );
+    let known = Processors::new();
     let processors = items
         .remove("use")
         .unwrap_or_default()
         .into_iter()
-        .map(|v| v.to_owned())
-        .collect::<Vec<String>>();
+        .map(|(line, v)| {
+            known.validate(v).map(|_| v.to_owned()).map_err(|e| {
+                crate::InitError::InvalidProcessorArgs(e)
+                    .at_column("use ".len() + 1)
+                    .at_line(line)
+            })
+        })
+        .collect::<crate::Result<Vec<String>>>()?;
     let mut starts_with = items
         .remove("starts_with")
         .unwrap_or_default()
         .into_iter()
-        .map(|v| UrlFilter::StartsWith(v.trim().to_owned()))
+        .map(|(_, v)| UrlFilter::StartsWith(v.trim().to_owned()))
         .collect::<Vec<_>>();
     let contains = items
         .remove("contains")
         .unwrap_or_default()
         .into_iter()
-        .map(|v| UrlFilter::Contains(v.trim().to_owned()))
+        .map(|(_, v)| UrlFilter::Contains(v.trim().to_owned()))
         .collect::<Vec<_>>();
     let regex = items
         .remove("regex")
         .unwrap_or_default()
         .into_iter()
-        .map(|v| Ok(UrlFilter::Regex(Regex::new(v)?)))
-        .collect::<Result<Vec<_>, regex::Error>>()?;
+        .map(|(line, v)| {
+            Regex::new(v).map(UrlFilter::Regex).map_err(|e| {
+                crate::InitError::from(e)
+                    .at_column("regex ".len() + 1)
+                    .at_line(line)
+            })
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
     starts_with.extend(regex);
     starts_with.extend(contains);
     let mut source = items.remove("source").unwrap_or_default();
     let img_source = match source.is_empty() {
         true => None,
-        false => Some(source.remove(0).to_owned()),
+        false => Some(source.remove(0).1.to_owned()),
     };
-    let img = img_map
-        .remove(uri)
-        .ok_or(crate::InitError::InitParseError(format!(
-            "couldnt find icon: {uri}"
-        )))?;
+    let img = img_map.remove(uri).ok_or(crate::InitError::MissingIcon)?;
+    let icon = fs::read(&img.1).map_err(|e| crate::InitError::from(e).in_file(&img.1))?;
     Ok(Arc::new(InterpretedRegister {
         urls: starts_with,
         processors,
         extension: img.0.to_owned(),
-        img_path: img.1,
+        icon,
         img_source,
     }))
 }
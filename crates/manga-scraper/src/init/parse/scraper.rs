@@ -66,7 +66,7 @@ impl InterpretedFileScraper {
         post_processors: Vec<(Arc<dyn PostScraperProcessor + Sync + Send>, String)>,
     ) -> crate::Result<Self> {
         let content = read_to_string(file)?;
-        let data: Scrape = toml::from_str(&content)?;
+        let data: Scrape = toml::from_str(&content).map_err(|e| InitError::toml(e, &content))?;
 
         let post_suffix = processors
             .iter()
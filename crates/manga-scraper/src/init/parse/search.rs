@@ -94,7 +94,7 @@ impl InterpretedSearch {
         post_search: Vec<(Arc<dyn PostSearchProcessor + Send + Sync>, String)>,
     ) -> crate::Result<Self> {
         let content = read_to_string(file)?;
-        let data: Search = toml::from_str(&content)?;
+        let data: Search = toml::from_str(&content).map_err(|e| InitError::toml(e, &content))?;
 
         let query_data = selectors(merge(data.query, data.general.clone()))?;
 
@@ -14,48 +14,39 @@ pub struct Query {
 impl TryFrom<&str> for Query {
     type Error = InitError;
 
+    /// Errors contain the column inside of `s`
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        let mut chars = s.chars();
-        let mut name = vec![];
-        loop {
-            let c = match chars.next() {
-                Some(c) => c,
-                None => todo!("add error for this case"),
-            };
-
-            if c == '[' {
-                break;
-            }
-            name.push(c);
-        }
+        let end = s.chars().count() + 1;
+        let (name, rest) = s
+            .split_once('[')
+            .ok_or_else(|| InitError::MissingKind.at_column(end))?;
+        let (kind, str) = rest
+            .split_once(']')
+            .ok_or_else(|| InitError::UnclosedKind.at_column(end))?;
+        let kind_column = name.chars().count() + 2;
+        let query_column = kind_column + kind.chars().count() + 1;
 
-        let mut kind = vec![];
-        loop {
-            let c = match chars.next() {
-                Some(c) => c,
-                None => todo!("add error for this case"),
-            };
-
-            if c == ']' {
-                break;
-            }
-            kind.push(c);
-        }
-
-        let str = chars.collect::<String>();
         let (left, right) = str
             .split_once("=>")
-            .map(|v| (v.0, Some(v.1)))
-            .unwrap_or((&str, None));
-        let right = right.map(Query::try_from).transpose()?.map(Box::new);
+            .map(|v| (v.0, Some((v.1, query_column + v.0.chars().count() + 2))))
+            .unwrap_or((str, None));
+        let right = right
+            .map(|(right, column)| Query::try_from(right).map_err(|e| e.at_column(column)))
+            .transpose()?
+            .map(Box::new);
 
         let mut seperator;
         let mut builder = vec![];
+        // column of the first char in builder
+        let mut builder_column = query_column;
         let mut items = vec![];
-        let mut kind = KindBuilder::try_from(kind.into_iter().collect::<String>().as_str())?;
+        let mut kind = KindBuilder::try_from(kind).map_err(|e| e.at_column(kind_column))?;
         let mut kind_builder = false;
-        let mut left = left.chars();
-        while let Some(c) = left.next() {
+        for (i, c) in left.chars().enumerate() {
+            let column = query_column + i;
+            if builder.is_empty() {
+                builder_column = column;
+            }
             if matches!(kind, KindBuilder::Regex(_)) {
                 builder.push(c);
                 continue;
@@ -63,7 +54,8 @@ impl TryFrom<&str> for Query {
             if kind_builder {
                 if builder.is_empty() && c == '[' {
                 } else if c == ']' {
-                    kind = KindBuilder::try_from(builder.drain(..).collect::<String>().as_str())?;
+                    kind = KindBuilder::try_from(builder.drain(..).collect::<String>().as_str())
+                        .map_err(|e| e.at_column(builder_column))?;
                     kind_builder = false;
                 } else {
                     builder.push(c);
@@ -82,7 +74,8 @@ impl TryFrom<&str> for Query {
                 let kinds = str
                     .split("<-")
                     .map(|s| Kind::new(kind.clone(), s.to_owned()))
-                    .collect::<Result<Vec<_>, _>>()?;
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| e.at_column(builder_column))?;
                 items.push((kinds, seperator == Some('|')));
             } else {
                 if builder.is_empty() && c == '!' {
@@ -94,19 +87,19 @@ impl TryFrom<&str> for Query {
             }
         }
 
+        if kind_builder {
+            return Err(InitError::UnclosedKind.at_column(builder_column));
+        }
         if !builder.is_empty() {
-            if kind_builder {
-                todo!("failed to close kind builder");
-            }
             let kinds = builder
                 .into_iter()
                 .collect::<String>()
                 .split("<-")
                 .map(|s| Kind::new(kind.clone(), s.to_owned()))
-                .collect::<Result<Vec<_>, _>>()?;
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.at_column(builder_column))?;
             items.push((kinds, false));
         }
-        let name = name.into_iter().collect::<String>();
 
         Ok(Self {
             name: match name.trim().is_empty() {
@@ -195,10 +188,11 @@ pub struct MySelector {
 }
 
 impl MySelector {
+    /// Errors contain the column inside of `line`
     pub fn parse(line: &str) -> crate::Result<(usize, MySelector)> {
         let (line, spaces) = strip_spaces_and_count(line);
 
-        let query = Query::try_from(line.as_str())?;
+        let query = Query::try_from(line.as_str()).map_err(|e| e.at_column(spaces + 1))?;
 
         Ok((
             spaces + 1,
@@ -220,8 +214,13 @@ fn strip_spaces_and_count(s: &str) -> (String, usize) {
     (s.to_owned(), count)
 }
 
+/// Errors contain the line & column inside of `s`
 pub fn parse(s: &str) -> crate::Result<Vec<MySelector>> {
-    let mut lines = s.lines().filter(|v| !v.trim().is_empty());
+    let mut lines = s
+        .lines()
+        .enumerate()
+        .filter(|(_, v)| !v.trim().is_empty())
+        .map(|(i, v)| (i + 1, v));
     let mut default = MySelector {
         query: Query {
             name: None,
@@ -237,10 +236,10 @@ pub fn parse(s: &str) -> crate::Result<Vec<MySelector>> {
 fn parse_<'a>(
     parent: &mut MySelector,
     pindent: usize,
-    lines: &mut impl Iterator<Item = &'a str>,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
 ) -> crate::Result<Option<(usize, MySelector)>> {
-    while let Some(line) = lines.next() {
-        let (mut indent, mut tag) = MySelector::parse(line)?;
+    while let Some((n, line)) = lines.next() {
+        let (mut indent, mut tag) = MySelector::parse(line).map_err(|e| e.at_line(n))?;
         loop {
             if indent > pindent {
                 parent.children.push(tag);
@@ -1,9 +1,11 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
+use curl::easy::{Easy, List};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use reqwest::{
-    header::{HeaderMap, HeaderName, HeaderValue, COOKIE, REFERER, USER_AGENT},
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, COOKIE, REFERER, USER_AGENT},
+    redirect::Policy,
     ClientBuilder, IntoUrl,
 };
 use serde::Deserialize;
@@ -12,6 +14,11 @@ use url::Url;
 
 use crate::{InitError, ScrapeError};
 
+/// Limits of every engine, so a slow or looping site cannot hold a request forever
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_REDIRECTS: usize = 10;
+
 pub struct Engine {
     kind: EngineKind,
     headers: HeaderMap,
@@ -41,15 +48,24 @@ impl Engine {
                     ClientBuilder::new()
                         .danger_accept_invalid_certs(true)
                         .use_native_tls()
+                        .connect_timeout(CONNECT_TIMEOUT)
+                        .timeout(TIMEOUT)
+                        .redirect(Policy::limited(MAX_REDIRECTS))
                         .build()?,
                 ),
                 EngineMode::ReqwestRustlsTls => EngineKind::Reqwest(
                     ClientBuilder::new()
                         .danger_accept_invalid_certs(true)
                         .use_rustls_tls()
+                        .connect_timeout(CONNECT_TIMEOUT)
+                        .timeout(TIMEOUT)
+                        .redirect(Policy::limited(MAX_REDIRECTS))
                         .build()?,
                 ),
-                EngineMode::Curl => todo!(),
+                EngineMode::Curl => {
+                    curl::init();
+                    EngineKind::Curl
+                }
             },
             headers: map,
         })
@@ -57,11 +73,16 @@ impl Engine {
 }
 pub enum EngineKind {
     Reqwest(reqwest::Client),
+    /// libcurl has a different tls fingerprint than reqwest, which is blocked by less sites
+    Curl,
 }
 
 static SHARED_COOKIES: Lazy<Arc<DashMap<String, String>>> = Lazy::new(|| Arc::new(DashMap::new()));
 static SHARED_USER_AGENTS: Lazy<Arc<DashMap<String, String>>> =
     Lazy::new(|| Arc::new(DashMap::new()));
+#[cfg(feature = "testing")]
+static RESPONSE_SOURCE: once_cell::sync::OnceCell<ResponseSource> =
+    once_cell::sync::OnceCell::new();
 
 pub fn set_cookie(url: &Url, cookie: String) {
     if let Some(domain) = url.host_str() {
@@ -90,6 +111,17 @@ pub fn get_user_agent(url: impl IntoUrl) -> Option<String> {
         .and_then(|domain| SHARED_USER_AGENTS.get(domain).map(|ua| ua.value().clone()))
 }
 
+/// Returns the body for a url or `None` to fetch it
+#[cfg(feature = "testing")]
+pub type ResponseSource = Box<dyn Fn(&str) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Answers requests with `source` instead of fetching them.
+/// Used to run scrapers against recorded html, only the first source is kept
+#[cfg(feature = "testing")]
+pub fn set_response_source(source: impl Fn(&str) -> Option<Vec<u8>> + Send + Sync + 'static) {
+    let _ = RESPONSE_SOURCE.set(Box::new(source));
+}
+
 #[derive(Deserialize)]
 struct CloudflareBypass {
     user_agent: String,
@@ -111,6 +143,10 @@ impl Engine {
             .replace("</noscript>", ""))
     }
     pub async fn request(&self, get: bool, url: &str) -> Result<Vec<u8>, ScrapeError> {
+        #[cfg(feature = "testing")]
+        if let Some(body) = RESPONSE_SOURCE.get().and_then(|source| source(url)) {
+            return Ok(body);
+        }
         let mut err = Ok(vec![]);
         for _ in 0..3 {
             match self.request_single(get, url).await {
@@ -121,42 +157,55 @@ impl Engine {
                         .any(|window| window == cloudflare)
                     {
                         err = Err(ScrapeError::Cloudflare);
-                        match &self.kind {
-                            EngineKind::Reqwest(client) => {
-                                let url = Url::parse(url).unwrap();
-                                if let Ok(v) = client
-                                    .post("http://127.0.0.1:8000/bypass-cloudflare")
-                                    .json(&json!({"url": url.to_string(), "refresh": false}))
-                                    .send()
-                                    .await
-                                {
-                                    if let Ok(v) = v.json::<CloudflareBypass>().await {
-                                        set_user_agent(&url, v.user_agent);
-                                        set_cookie(
-                                            &url,
-                                            v.cookies
-                                                .into_iter()
-                                                .map(|(k, v)| format!("{}={}", k, v))
-                                                .collect::<Vec<_>>()
-                                                .join("; "),
-                                        );
-                                    }
-                                }
-                            }
-                        };
+                        let url = Url::parse(url)?;
+                        if let Some(v) = self.bypass_cloudflare(&url).await {
+                            set_user_agent(&url, v.user_agent);
+                            set_cookie(
+                                &url,
+                                v.cookies
+                                    .into_iter()
+                                    .map(|(k, v)| format!("{}={}", k, v))
+                                    .collect::<Vec<_>>()
+                                    .join("; "),
+                            );
+                        }
                     } else {
                         return Ok(text);
                     }
                 }
                 Err(e) => {
-                    err = Err(ScrapeError::Reqwest(e));
+                    err = Err(e);
                 }
             }
         }
         return err;
     }
 
-    async fn request_single(&self, get: bool, url: &str) -> Result<Vec<u8>, reqwest::Error> {
+    async fn bypass_cloudflare(&self, url: &Url) -> Option<CloudflareBypass> {
+        let bypass = "http://127.0.0.1:8000/bypass-cloudflare";
+        let body = json!({"url": url.to_string(), "refresh": false});
+        match &self.kind {
+            EngineKind::Reqwest(client) => client
+                .post(bypass)
+                .json(&body)
+                .send()
+                .await
+                .ok()?
+                .json::<CloudflareBypass>()
+                .await
+                .ok(),
+            EngineKind::Curl => {
+                let mut headers = HeaderMap::new();
+                headers.append(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                let resp = curl_request(false, bypass, &headers, body.to_string().into_bytes())
+                    .await
+                    .ok()?;
+                serde_json::from_slice(&resp).ok()
+            }
+        }
+    }
+
+    async fn request_single(&self, get: bool, url: &str) -> Result<Vec<u8>, ScrapeError> {
         let mut headers = self.headers.clone();
         if !headers.contains_key(REFERER) {
             if let Some(v) = get_site_root_referer(url) {
@@ -185,6 +234,7 @@ impl Engine {
                         .bytes()
                         .await
                         .map(|v| v.to_vec())
+                        .map_err(ScrapeError::from)
                 } else {
                     client
                         .post(url)
@@ -194,8 +244,55 @@ impl Engine {
                         .bytes()
                         .await
                         .map(|v| v.to_vec())
+                        .map_err(ScrapeError::from)
                 }
             }
+            EngineKind::Curl => curl_request(get, url, &headers, vec![]).await,
         }
     }
 }
+
+/// libcurl is blocking, so every request runs on its own handle in the blocking pool
+async fn curl_request(
+    get: bool,
+    url: &str,
+    headers: &HeaderMap,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, ScrapeError> {
+    let url = url.to_owned();
+    let headers = headers
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| format!("{}: {}", k, v)))
+        .collect::<Vec<_>>();
+    tokio::task::spawn_blocking(move || {
+        let mut easy = Easy::new();
+        easy.url(&url)?;
+        easy.follow_location(true)?;
+        easy.max_redirections(MAX_REDIRECTS as u32)?;
+        easy.connect_timeout(CONNECT_TIMEOUT)?;
+        easy.timeout(TIMEOUT)?;
+        // all encodings which are supported by libcurl
+        easy.accept_encoding("")?;
+        let mut list = List::new();
+        for header in headers {
+            list.append(&header)?;
+        }
+        easy.http_headers(list)?;
+        if !get {
+            easy.post(true)?;
+            easy.post_fields_copy(&body)?;
+        }
+        let mut out = vec![];
+        {
+            let mut transfer = easy.transfer();
+            transfer.write_function(|data| {
+                out.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.perform()?;
+        }
+        Ok::<_, ScrapeError>(out)
+    })
+    .await
+    .map_err(|e| ScrapeError::Curl(e.to_string()))?
+}
@@ -51,7 +51,7 @@ macro_rules! register_post {
             pub fn $name(&self, name: &str) -> Option<(Arc<$ty>, String)> {
                 let name = name.trim();
                 if let Some(v) = name.strip_prefix("postprocessor ") {
-                    let (target, info) = v.split_once(" ").unwrap();
+                    let (target, info) = v.split_once(" ")?;
                     let _ = match Target::try_from(target) {
                         Ok(v) => match v {
                             $target => true,
@@ -105,7 +105,7 @@ impl Processors {
     ) -> Option<(Arc<dyn PreProcessor + Sync + Send>, Target)> {
         let name = name.trim();
         if let Some(v) = name.strip_prefix("preprocessor ") {
-            let (target, id) = v.split_once(" ").unwrap();
+            let (target, id) = v.split_once(" ")?;
             let target = match Target::try_from(target) {
                 Ok(v) => v,
                 Err(_) => return None,
@@ -116,8 +116,41 @@ impl Processors {
     }
 }
 
+impl Processors {
+    /// Checks the arguments of the post processor `name` refers to, so invalid `use` lines
+    /// fail when the definition is loaded. Other names are ignored
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        if let Some((processor, target)) = self.get_post_processor_meta(name) {
+            processor.validate(&target)?;
+        }
+        if let Some((processor, target)) = self.get_post_processor_search(name) {
+            processor.validate(&target)?;
+        }
+        if let Some((processor, target)) = self.get_post_processor_scraper(name) {
+            processor.validate(&target)?;
+        }
+        Ok(())
+    }
+}
+
+/// The first `N` whitespace separated arguments of a post processor
+pub fn args<const N: usize>(target: &str) -> Result<[&str; N], String> {
+    let mut args = target.split_whitespace();
+    let mut out = [""; N];
+    for (i, arg) in out.iter_mut().enumerate() {
+        *arg = args
+            .next()
+            .ok_or_else(|| format!("expected {N} arguments, found {i}"))?;
+    }
+    Ok(out)
+}
+
 pub trait PostSearchProcessor {
     fn name(&self) -> &str;
+    /// Called when the definition is loaded, `process` can rely on a valid `target`
+    fn validate(&self, _target: &str) -> Result<(), String> {
+        Ok(())
+    }
     fn process(
         &self,
         url: &str,
@@ -128,11 +161,19 @@ pub trait PostSearchProcessor {
 
 pub trait PostMetadataProcessor {
     fn name(&self) -> &str;
+    /// Called when the definition is loaded, `process` can rely on a valid `target`
+    fn validate(&self, _target: &str) -> Result<(), String> {
+        Ok(())
+    }
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str);
 }
 
 pub trait PostScraperProcessor {
     fn name(&self) -> &str;
+    /// Called when the definition is loaded, `process` can rely on a valid `target`
+    fn validate(&self, _target: &str) -> Result<(), String> {
+        Ok(())
+    }
     fn process(&self, data: Vec<String>, target: &str) -> Vec<String>;
 }
 
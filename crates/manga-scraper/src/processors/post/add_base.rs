@@ -10,6 +10,13 @@ impl PostSearchProcessor for AddBasePostProcessor {
         "add_base"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        match target.trim() {
+            "url" | "cover" => Ok(()),
+            target => Err(format!("expected url or cover, found `{target}`")),
+        }
+    }
+
     fn process(
         &self,
         url: &str,
@@ -41,6 +48,9 @@ fn add_base(base: &str, url: String) -> String {
     if url.starts_with("http") {
         url
     } else {
-        Url::parse(base).unwrap().join(&url).unwrap().to_string()
+        Url::parse(base)
+            .and_then(|base| base.join(&url))
+            .map(|v| v.to_string())
+            .unwrap_or(url)
     }
 }
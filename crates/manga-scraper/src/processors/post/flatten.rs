@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap};
 
 use scraper_module::ScrapedData;
 
-use crate::processors::PostMetadataProcessor;
+use crate::processors::{args, PostMetadataProcessor};
 
 #[derive(Default)]
 pub struct FlattenPostProcessor;
@@ -12,8 +12,14 @@ impl PostMetadataProcessor for FlattenPostProcessor {
         "flatten"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        args::<1>(target).map(|_| ())
+    }
+
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str) {
-        let key = target.split(" ").next().unwrap().trim();
+        let Ok([key]) = args(target) else {
+            return;
+        };
         let item = data.remove(key);
         match item {
             Some(ScrapedData::Map(map)) => {
@@ -22,12 +28,10 @@ impl PostMetadataProcessor for FlattenPostProcessor {
                 }
             }
             Some(ScrapedData::Arr(arr)) => {
-                if arr.iter().all(|v| v.as_map().is_some()) {
-                    for item in arr {
-                        let map = item.as_map().unwrap();
-                        for (k, v) in map {
-                            add_to_map(data, k, v);
-                        }
+                let maps = arr.iter().filter_map(|v| v.as_map()).collect::<Vec<_>>();
+                if maps.len() == arr.len() {
+                    for (k, v) in maps.into_iter().flatten() {
+                        add_to_map(data, k, v);
                     }
                 } else {
                     data.insert(key.to_owned(), ScrapedData::Arr(arr));
@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap};
 
 use scraper_module::ScrapedData;
 
-use crate::processors::PostMetadataProcessor;
+use crate::processors::{args, PostMetadataProcessor};
 
 #[derive(Default)]
 pub struct JoinProcessor;
@@ -12,10 +12,14 @@ impl PostMetadataProcessor for JoinProcessor {
         "join"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        args::<2>(target).map(|_| ())
+    }
+
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str) {
-        let mut keys = target.split(" ");
-        let key1 = keys.next().unwrap().trim();
-        let key2 = keys.next().unwrap().trim();
+        let Ok([key1, key2]) = args(target) else {
+            return;
+        };
         if let Some(item1) = data.remove(key1) {
             match item1 {
                 ScrapedData::Arr(scraped_datas) => {
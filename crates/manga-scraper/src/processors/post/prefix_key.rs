@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap};
 
 use scraper_module::ScrapedData;
 
-use crate::processors::PostMetadataProcessor;
+use crate::processors::{args, PostMetadataProcessor};
 
 #[derive(Default)]
 pub struct PrefixKeyProcessor;
@@ -37,11 +37,14 @@ impl PostMetadataProcessor for PrefixKeyProcessor {
         "prefix_key"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        args::<2>(target).map(|_| ())
+    }
+
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str) {
-        let mut keys = target.split(" ");
-        let key1 = keys.next().unwrap().trim();
-        let key2 = keys.next().unwrap().trim();
-        println!("{} {}", key1, key2);
+        let Ok([key1, key2]) = args(target) else {
+            return;
+        };
         if let Some(v) = data.remove(key1) {
             let new = split(&v, key2);
             data.insert(key1.to_owned(), new);
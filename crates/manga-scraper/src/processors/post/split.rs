@@ -33,11 +33,31 @@ fn split(v: &ScrapedData, key2: &str) -> ScrapedData {
     }
 }
 
+/// The key & the separator, the separator is the rest of the line
+fn keys(target: &str) -> Result<(&str, &str), String> {
+    let (key, separator) = target
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| "expected a key & a separator".to_owned())?;
+    match separator.trim() {
+        "" => Err("expected a key & a separator".to_owned()),
+        "\\n" => Ok((key, "\n")),
+        separator => Ok((key, separator)),
+    }
+}
+
 impl PostScraperProcessor for SplitProcessor {
     fn name(&self) -> &str {
         "split"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        match target.trim().is_empty() {
+            true => Err("expected a separator".to_owned()),
+            false => Ok(()),
+        }
+    }
+
     fn process(&self, data: Vec<String>, target: &str) -> Vec<String> {
         data.into_iter()
             .flat_map(|v| {
@@ -54,13 +74,14 @@ impl PostMetadataProcessor for SplitProcessor {
         "split"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        keys(target).map(|_| ())
+    }
+
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str) {
-        let mut keys = target.splitn(2, " ");
-        let key1 = keys.next().unwrap().trim();
-        let mut key2 = keys.next().unwrap().trim();
-        if key2 == "\\n" {
-            key2 = "\n";
-        }
+        let Ok((key1, key2)) = keys(target) else {
+            return;
+        };
         if let Some(v) = data.remove(key1) {
             let new = split(&v, key2);
             data.insert(key1.to_owned(), new.flatten_vec());
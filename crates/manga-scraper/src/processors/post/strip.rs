@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap};
 
 use scraper_module::ScrapedData;
 
-use crate::processors::PostMetadataProcessor;
+use crate::processors::{args, PostMetadataProcessor};
 #[derive(Default)]
 pub struct StripSuffixPostProcessor;
 
@@ -11,10 +11,14 @@ impl PostMetadataProcessor for StripSuffixPostProcessor {
         "strip_suffix"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        args::<2>(target).map(|_| ())
+    }
+
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str) {
-        let mut keys = target.split(" ");
-        let key1 = keys.next().unwrap().trim();
-        let key2 = keys.next().unwrap().trim();
+        let Ok([key1, key2]) = args(target) else {
+            return;
+        };
         let item1 = data.remove(key1);
         let item1_str = item1.clone().and_then(|v| v.as_str().map(|v| v.to_owned()));
         let item2_str = data
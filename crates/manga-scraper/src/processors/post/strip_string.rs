@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use scraper_module::ScrapedData;
 
-use crate::processors::PostMetadataProcessor;
+use crate::processors::{args, PostMetadataProcessor};
 
 #[derive(Default)]
 pub struct StripSuffixStringProcessor;
@@ -12,10 +12,14 @@ impl PostMetadataProcessor for StripSuffixStringProcessor {
         "strip_suffix_str"
     }
 
+    fn validate(&self, target: &str) -> Result<(), String> {
+        args::<2>(target).map(|_| ())
+    }
+
     fn process(&self, data: &mut BTreeMap<String, ScrapedData>, target: &str) {
-        let mut keys = target.split(" ");
-        let key1 = keys.next().unwrap().trim();
-        let suffix = keys.next().unwrap().trim();
+        let Ok([key1, suffix]) = args(target) else {
+            return;
+        };
         let item1 = data.remove(key1);
         if let Some(v) = item1 {
             data.insert(key1.to_owned(), strip_suffix(v, suffix));
@@ -127,7 +127,7 @@ impl ReaderScraper for InterpretedFileScraper {
         };
         let mut new_url = url.to_owned();
         for processor in self.pre_processors.iter() {
-            new_url = processor.process(&new_url, &self.engine).await.unwrap();
+            new_url = processor.process(&new_url, &self.engine).await?;
         }
         let mut get = true;
         if let Some(suffix) = &self.post_suffix {
@@ -149,7 +149,7 @@ impl ReaderScraper for InterpretedFileScraper {
             get = false;
         }
         let html = self.engine.request_str(get, &new_url).await?;
-        let url = Url::parse(&new_url).unwrap();
+        let url = Url::parse(&new_url).map_err(|_| ScraperError::InvalidUrl)?;
         let document = Html::parse_document(&html);
         let doc2 = match &chapter.container {
             Some(cont) => Some(
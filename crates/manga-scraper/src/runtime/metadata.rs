@@ -24,6 +24,7 @@ impl From<ScrapeError> for ScraperError {
             ScrapeError::Cloudflare => ScraperError::Cloudflare,
             ScrapeError::UrlParseError(_) => ScraperError::InvalidUrl,
             ScrapeError::Reqwest(error) => ScraperError::Reqwest(error.to_string()),
+            ScrapeError::Curl(error) => ScraperError::Curl(error),
         }
     }
 }
@@ -35,16 +36,18 @@ impl MetaDataScraper for InterpretedMetadata {
     ) -> Result<BTreeMap<String, ScrapedData>, ScraperError> {
         let mut new_url = url.to_owned();
         for processor in self.pre_processors.iter() {
-            new_url = processor.process(&new_url, &self.engine).await.unwrap();
+            new_url = processor.process(&new_url, &self.engine).await?;
         }
         let html = self.engine.request_str(true, &new_url).await?;
         let res = self
             .selectors
             .iter()
-            .map(|selector| selector.run(&html).unwrap())
-            .map(|(a, b)| OutData::Tuple((a, Box::new(b))))
-            .collect::<Vec<_>>();
-        let mut data = flatten(OutData::Array(res)).as_map().unwrap();
+            .map(|selector| selector.run(&html))
+            .map(|v| v.map(|(a, b)| OutData::Tuple((a, Box::new(b)))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut data = flatten(OutData::Array(res))?.as_map().ok_or_else(|| {
+            ScraperError::UnsupportedStructure("metadata has to be a map".to_owned())
+        })?;
         data.insert("url".to_string(), ScrapedData::Str(url.to_string()));
         if url != new_url {
             data.insert("new_url".to_string(), ScrapedData::Str(new_url));
@@ -55,4 +58,8 @@ impl MetaDataScraper for InterpretedMetadata {
         }
         Ok(data)
     }
+
+    async fn download_file(&self, url: &str) -> Result<Vec<u8>, ScraperError> {
+        Ok(self.engine.request(true, url).await?)
+    }
 }
@@ -33,11 +33,14 @@ fn el_to_str(data: &ElementOrText<'_>) -> String {
     }
 }
 
-pub fn flatten(data: OutData) -> ScrapedData {
-    match data {
+pub fn flatten(data: OutData) -> Result<ScrapedData, ScraperError> {
+    Ok(match data {
         OutData::String(value) => ScrapedData::Str(value),
         OutData::Array(items) => {
-            let v = items.into_iter().map(flatten).collect::<Vec<_>>();
+            let v = items
+                .into_iter()
+                .map(flatten)
+                .collect::<Result<Vec<_>, _>>()?;
             let all_arrays = v
                 .iter()
                 .all(|item| matches!(item, ScrapedData::Arr(_) | ScrapedData::Str(_)));
@@ -53,7 +56,7 @@ pub fn flatten(data: OutData) -> ScrapedData {
                         .collect(),
                 );
 
-                return temp;
+                return Ok(temp);
             }
 
             let all_maps = v.iter().all(|item| matches!(item, ScrapedData::Map(_)));
@@ -74,20 +77,22 @@ pub fn flatten(data: OutData) -> ScrapedData {
                         }
                         acc
                     });
-                return ScrapedData::Map(
+                return Ok(ScrapedData::Map(
                     data.into_iter()
                         .map(|(key, values)| (key, ScrapedData::Arr(values)))
                         .collect(),
-                );
+                ));
             }
 
-            todo!("throw error for mixed types or unsupported structures")
+            return Err(ScraperError::UnsupportedStructure(format!(
+                "cannot merge maps with strings or arrays: {v:?}"
+            )));
         }
         OutData::Tuple((opt_key, value)) => match opt_key {
-            None => ScrapedData::Arr(vec![flatten(*value)]),
-            Some(key) => ScrapedData::Map(BTreeMap::from([(key, flatten(*value))])),
+            None => ScrapedData::Arr(vec![flatten(*value)?]),
+            Some(key) => ScrapedData::Map(BTreeMap::from([(key, flatten(*value)?)])),
         },
-    }
+    })
 }
 
 impl KindBuilder {
@@ -219,10 +224,15 @@ fn join_if_possible(
             if let Some(v) = f.as_str() {
                 Ok((Some(v.to_string()), right))
             } else {
-                todo!()
+                Err(ScraperError::UnsupportedStructure(format!(
+                    "key of => has to be a single string: {f:?}"
+                )))
             }
         } else {
-            todo!()
+            Err(ScraperError::UnsupportedStructure(format!(
+                "key of => has to be a single element, found {}",
+                data.len()
+            )))
         }
     } else {
         Ok((
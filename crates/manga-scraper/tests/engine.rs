@@ -0,0 +1,111 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use manga_scraper::init::scraper::{set_cookie, set_user_agent, Engine, EngineMode};
+use url::Url;
+
+/// Answers every request with `body` & sends the received request head(and body) back
+fn stand_in(body: &'static str) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            let mut len = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(v) = line.to_lowercase().strip_prefix("content-length:") {
+                    len = v.trim().parse().unwrap();
+                }
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                request.push_str(&line);
+            }
+            let mut content = vec![0; len];
+            reader.read_exact(&mut content).unwrap();
+            request.push_str(&String::from_utf8(content).unwrap());
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            tx.send(request).unwrap();
+        }
+    });
+    (format!("http://{addr}"), rx)
+}
+
+fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        k.eq_ignore_ascii_case(name).then(|| v.trim())
+    })
+}
+
+#[tokio::test]
+async fn curl_honours_shared_cookies_and_user_agent() {
+    let (base, requests) = stand_in("<html>chapter</html>");
+    let engine = Engine::new(EngineMode::Curl, Default::default()).unwrap();
+
+    let url = format!("{base}/manga/1");
+    let body = engine.request_str(true, &url).await.unwrap();
+    assert_eq!(body, "<html>chapter</html>");
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("GET /manga/1 HTTP/1.1"));
+    assert!(header(&request, "user-agent").unwrap().contains("Firefox"));
+    assert_eq!(header(&request, "referer"), Some(base.as_str()));
+    assert_eq!(header(&request, "cookie"), None);
+
+    let parsed = Url::parse(&url).unwrap();
+    set_cookie(&parsed, "cf_clearance=abc; session=1".to_owned());
+    set_user_agent(&parsed, "stand-in-agent".to_owned());
+    engine.request(true, &url).await.unwrap();
+    let request = requests.recv().unwrap();
+    assert_eq!(header(&request, "user-agent"), Some("stand-in-agent"));
+    assert_eq!(
+        header(&request, "cookie"),
+        Some("cf_clearance=abc; session=1")
+    );
+}
+
+#[tokio::test]
+async fn curl_sends_custom_headers_and_posts() {
+    let (base, requests) = stand_in("[]");
+    let engine = Engine::new(
+        EngineMode::Curl,
+        [("x-requested-with".to_owned(), "XMLHttpRequest".to_owned())].into(),
+    )
+    .unwrap();
+
+    let body = engine
+        .request(false, &format!("{base}/api/chapters"))
+        .await
+        .unwrap();
+    assert_eq!(body, b"[]");
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /api/chapters HTTP/1.1"));
+    assert_eq!(header(&request, "x-requested-with"), Some("XMLHttpRequest"));
+}
+
+#[tokio::test]
+async fn curl_reports_connection_errors() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    let engine = Engine::new(EngineMode::Curl, Default::default()).unwrap();
+    assert!(engine
+        .request(true, &format!("http://{addr}/closed"))
+        .await
+        .is_err());
+}
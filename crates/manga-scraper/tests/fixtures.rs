@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use dashmap::DashMap;
+use manga_scraper::init::scraper::set_response_source;
+use once_cell::sync::Lazy;
+use scraper_testing::generate_tests;
+
+static RECORDED_RESPONSES: Lazy<DashMap<String, Vec<u8>>> = Lazy::new(DashMap::new);
+
+/// A request which is answered with recorded html & the expectations for its result.
+///
+/// ```text
+/// chapters|pages <url> <html file in tests/fixtures>
+/// len == <count>
+/// <index> == <page url>
+/// <index> chapter|url|names == <value>
+/// ```
+struct Section<'a> {
+    kind: &'a str,
+    url: &'a str,
+    rules: Vec<(&'a str, &'a str)>,
+}
+
+fn parse(content: &str) -> Vec<Section<'_>> {
+    set_response_source(|url| RECORDED_RESPONSES.get(url).map(|v| v.value().clone()));
+    content
+        .split("\n\n")
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|section| {
+            let mut lines = section.lines();
+            let head = lines.next().unwrap();
+            let mut head = head.split_whitespace();
+            let kind = head.next().expect("missing kind");
+            let url = head.next().expect("missing url");
+            let file = head.next().expect("missing fixture file");
+            let body = std::fs::read(Path::new("tests/fixtures").join(file))
+                .unwrap_or_else(|_| panic!("missing fixture {file}"));
+            RECORDED_RESPONSES.insert(url.to_owned(), body);
+            let rules = lines
+                .map(|line| {
+                    let (left, right) = line.split_once("==").expect(line);
+                    (left.trim(), right.trim())
+                })
+                .collect();
+            Section { kind, url, rules }
+        })
+        .collect()
+}
+
+async fn test_logic(uri: &str, content: &str) {
+    let services = manga_scraper::init::register(Path::new("../../data/")).0;
+    // register all recorded responses before any request is made
+    let sections = parse(content);
+    for section in sections {
+        let service = services.get(section.url).expect(section.url);
+        assert_eq!(service.uri, uri);
+        let reader = service.reader.as_ref().expect("site has no .scraper file");
+        let items: Vec<Vec<(&str, String)>> = match section.kind {
+            "chapters" => reader
+                .scrape_chapters(section.url)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| {
+                    vec![
+                        ("chapter", v.chapter.to_string()),
+                        ("url", v.url),
+                        ("names", v.names.join(", ")),
+                    ]
+                })
+                .collect(),
+            "pages" => reader
+                .scrape_pages(section.url)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|v| vec![("", v)])
+                .collect(),
+            kind => panic!("unknown kind {kind}"),
+        };
+        for (left, expected) in section.rules {
+            if left == "len" {
+                assert_eq!(items.len().to_string(), expected, "{}", section.url);
+                continue;
+            }
+            let (index, field) = left.split_once(' ').unwrap_or((left, ""));
+            let item = &items[index.parse::<usize>().expect(left)];
+            let (_, value) = item
+                .iter()
+                .find(|(key, _)| *key == field)
+                .unwrap_or_else(|| panic!("unknown field {field}"));
+            assert_eq!(value, expected, "{} {left}", section.url);
+        }
+    }
+}
+
+// Generates tokio::test with sturcture uri_fixture for every .fixture file
+// The html is served instead of requesting the url, so no network access is needed
+generate_tests!((test_logic, "crates/manga-scraper/tests/fixtures", "fixture"));
@@ -171,7 +171,7 @@ mod tests {
 }
 
 async fn test_logic(_: &str, content: &str) {
-    let items = manga_scraper::init::register(Path::new("../../data/")).unwrap();
+    let items = manga_scraper::init::register(Path::new("../../data/")).0;
     let queries = content.split("\n\n");
     for query in queries {
         let mut query = query.lines();
@@ -5,3 +5,32 @@ fn test_parser() {
     let v = selectors::parse(include_str!("parser.txt")).unwrap();
     assert!(v.len() > 0)
 }
+
+#[test]
+fn test_parser_error_location() {
+    let located = |s: &str| match selectors::parse(s) {
+        Err(manga_scraper::InitError::Located {
+            file: None,
+            line,
+            column,
+            error,
+        }) => (line, column, *error),
+        v => panic!("expected located error, got {v:?}"),
+    };
+    assert!(matches!(
+        located("title[text] h1\n\ncover[src img"),
+        (3, 14, manga_scraper::InitError::UnclosedKind)
+    ));
+    assert!(matches!(
+        located("title h1"),
+        (1, 9, manga_scraper::InitError::MissingKind)
+    ));
+    assert!(matches!(
+        located("rows[@] div\n  name[text] ::::"),
+        (2, 14, manga_scraper::InitError::SelectorError(_))
+    ));
+    assert!(matches!(
+        located("rows[@] div => [text] ![text h1"),
+        (1, 25, manga_scraper::InitError::UnclosedKind)
+    ));
+}
@@ -0,0 +1,104 @@
+use std::ffi::c_void;
+
+use scraper_module::plugin::{
+    free_buffer, init, method, Buffer, Host, HostRequest, HostResponse, HostVTable, Plugin,
+    PluginVTable, Str, ABI_VERSION,
+};
+
+struct TestPlugin(Host);
+
+impl Plugin for TestPlugin {
+    fn new(host: Host) -> Self {
+        Self(host)
+    }
+
+    fn url_matches(&self, url: &str) -> bool {
+        url.starts_with("https://plugin.test")
+    }
+
+    fn icon(&self) -> Result<(String, Vec<u8>), String> {
+        Ok(("svg".to_owned(), b"<svg></svg>".to_vec()))
+    }
+
+    fn pages(&self, url: &str) -> Result<Vec<String>, String> {
+        let resp = self.0.fetch(&HostRequest {
+            method: "GET".to_owned(),
+            url: url.to_owned(),
+            headers: vec![],
+            body: None,
+        })?;
+        Ok(String::from_utf8(resp.body)
+            .map_err(|e| e.to_string())?
+            .lines()
+            .map(|v| v.to_owned())
+            .collect())
+    }
+
+    fn chapters(&self, _: &str) -> Result<Vec<scraper_module::ScrapedChapter>, String> {
+        panic!("broken plugin")
+    }
+}
+
+/// Answers every request with the url as body
+extern "C" fn fetch(_: *const c_void, request: Str) -> Buffer {
+    let request: HostRequest = serde_json::from_slice(unsafe { request.as_bytes() }).unwrap();
+    let resp = Ok::<_, String>(HostResponse {
+        status: 200,
+        headers: vec![],
+        body: format!("{}/1.png\n{}/2.png", request.url, request.url).into_bytes(),
+    });
+    Buffer::from_vec(serde_json::to_vec(&resp).unwrap())
+}
+
+fn call<T: serde::de::DeserializeOwned>(
+    vtable: &PluginVTable,
+    method: u32,
+    input: impl serde::Serialize,
+) -> Result<T, String> {
+    let input = serde_json::to_vec(&input).unwrap();
+    let out = (vtable.call)(vtable.plugin, method, Str::from_bytes(&input));
+    let bytes = unsafe { out.to_vec() };
+    (vtable.free)(out);
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[test]
+fn test_plugin_calls_cross_the_c_abi() {
+    let vtable = init::<TestPlugin>(HostVTable {
+        abi_version: ABI_VERSION,
+        host: std::ptr::null(),
+        fetch,
+        free: free_buffer,
+    });
+
+    assert_eq!(
+        call::<bool>(&vtable, method::URL_MATCHES, "https://plugin.test/manga/1"),
+        Ok(true)
+    );
+    assert_eq!(
+        call::<bool>(&vtable, method::URL_MATCHES, "https://other.test"),
+        Ok(false)
+    );
+    assert_eq!(
+        call::<(String, Vec<u8>)>(&vtable, method::ICON, ()),
+        Ok(("svg".to_owned(), b"<svg></svg>".to_vec()))
+    );
+    assert_eq!(
+        call::<Vec<String>>(&vtable, method::PAGES, "https://plugin.test/c"),
+        Ok(vec![
+            "https://plugin.test/c/1.png".to_owned(),
+            "https://plugin.test/c/2.png".to_owned()
+        ])
+    );
+    // not implemented, unknown & panicking methods dont take the host down
+    assert!(call::<Vec<String>>(&vtable, method::METADATA, "https://plugin.test").is_err());
+    assert!(call::<()>(&vtable, 1000, ()).is_err());
+    assert_eq!(
+        call::<()>(&vtable, method::CHAPTERS, "https://plugin.test"),
+        Err("plugin panicked".to_owned())
+    );
+    // input which doesnt match the method
+    assert!(call::<bool>(&vtable, method::URL_MATCHES, 5).is_err());
+
+    (vtable.drop)(vtable.plugin);
+}
@@ -1,7 +1,232 @@
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use manga_scraper::InitError;
+use scraper_module::{
+    MetaDataScraper, Register, RegisterOverride, ScrapedData, ScraperError, ScraperResult,
+};
 
 #[test]
 fn test_parsing_items() {
-    let items = manga_scraper::init::register(Path::new("../../data/")).unwrap();
+    let (items, errors) = manga_scraper::init::register(Path::new("../../data/"));
+    assert!(errors.is_empty(), "{errors:?}");
     assert!(items.len() > 0)
 }
+
+#[test]
+fn test_invalid_sites_are_skipped() {
+    let root = std::env::temp_dir().join(format!("manga-scraper-register-{}", std::process::id()));
+    let external = root.join("external");
+    fs::create_dir_all(&external).unwrap();
+    let files = [
+        (
+            "valid.filter",
+            "starts_with https://valid.test\nuse reqwest\n",
+        ),
+        ("valid.svg", "<svg></svg>"),
+        ("valid.metadata", "title[text] h1\ncover[src img\n"),
+        ("no-icon.filter", "starts_with https://no-icon.test\n"),
+        (
+            "broken.filter",
+            "starts_with https://broken.test\nregex (\n",
+        ),
+        ("broken.svg", "<svg></svg>"),
+    ];
+    for (name, content) in files {
+        fs::write(external.join(name), content).unwrap();
+    }
+
+    let (services, errors) = manga_scraper::init::register(&root);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(services.len(), 1);
+    let valid = services.get("https://valid.test/manga").unwrap();
+    assert_eq!(valid.uri, "valid");
+    assert!(valid.metadata.is_none());
+    assert_eq!(valid.register.icon().1, b"<svg></svg>");
+
+    let location = |name: &str| {
+        errors
+            .iter()
+            .find_map(|e| match e {
+                InitError::Located {
+                    file: Some(file),
+                    line,
+                    column,
+                    error,
+                } if file.ends_with(name) => Some((*line, *column, error.as_ref())),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no error for {name}: {errors:?}"))
+    };
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(matches!(
+        location("valid.metadata"),
+        (2, 14, InitError::UnclosedKind)
+    ));
+    assert!(matches!(
+        location("broken.filter"),
+        (2, 7, InitError::InitParseRegexError(_))
+    ));
+    assert!(matches!(
+        location("no-icon.filter"),
+        (0, 0, InitError::MissingIcon)
+    ));
+}
+
+#[test]
+fn test_invalid_processor_arguments() {
+    let root = std::env::temp_dir().join(format!("manga-scraper-args-{}", std::process::id()));
+    let external = root.join("external");
+    fs::create_dir_all(&external).unwrap();
+    fs::write(
+        external.join("site.filter"),
+        "starts_with https://site.test\nuse postprocessor metadata join Members\nuse postprocessor search add_base url\n",
+    )
+    .unwrap();
+    fs::write(external.join("site.svg"), "<svg></svg>").unwrap();
+    fs::write(
+        external.join("search.filter"),
+        "starts_with https://search.test\nuse postprocessor search add_base link\n",
+    )
+    .unwrap();
+    fs::write(external.join("search.svg"), "<svg></svg>").unwrap();
+
+    let (services, errors) = manga_scraper::init::register(&root);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(services.len(), 0);
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    for name in ["site.filter", "search.filter"] {
+        assert!(
+            errors.iter().any(|e| matches!(
+                e,
+                InitError::Located { file: Some(file), line: 2, column: 5, error }
+                    if file.ends_with(name)
+                        && matches!(error.as_ref(), InitError::InvalidProcessorArgs(_))
+            )),
+            "{name}: {errors:?}"
+        );
+    }
+}
+
+struct PluginRegister;
+
+impl Register for PluginRegister {
+    fn get_used_processor_names(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn url_matches(&self, url: &str) -> bool {
+        url.starts_with("https://plugin.test")
+    }
+
+    fn icon(&self) -> (String, Vec<u8>) {
+        ("svg".to_owned(), b"<svg>plugin</svg>".to_vec())
+    }
+
+    fn icon_source(&self) -> Option<String> {
+        None
+    }
+}
+
+struct PluginMetadata;
+
+#[async_trait]
+impl MetaDataScraper for PluginMetadata {
+    async fn scrape_metadata(&self, _: &str) -> ScraperResult<BTreeMap<String, ScrapedData>> {
+        Err(ScraperError::Unimplemented)
+    }
+}
+
+fn plugin(uri: &str, register: bool) -> (PathBuf, RegisterOverride) {
+    (
+        PathBuf::from(format!("{uri}.so")),
+        RegisterOverride {
+            uri: uri.to_owned(),
+            register: register.then(|| Arc::new(PluginRegister) as Arc<dyn Register>),
+            metadata: Some(Arc::new(PluginMetadata)),
+            search: None,
+            reader: None,
+        },
+    )
+}
+
+#[test]
+fn test_plugins_override_sites() {
+    let root = std::env::temp_dir().join(format!("manga-scraper-override-{}", std::process::id()));
+    let external = root.join("external");
+    fs::create_dir_all(&external).unwrap();
+    for (name, content) in [
+        (
+            "site.filter",
+            "starts_with https://site.test\nuse reqwest\n",
+        ),
+        ("site.svg", "<svg></svg>"),
+        (
+            "kept.filter",
+            "starts_with https://kept.test\nuse reqwest\n",
+        ),
+        ("kept.svg", "<svg></svg>"),
+    ] {
+        fs::write(external.join(name), content).unwrap();
+    }
+
+    let (services, errors) = manga_scraper::init::register_with_overrides(
+        &root,
+        vec![
+            plugin("site", true),
+            plugin("kept", false),
+            plugin("new", true),
+            plugin("orphan", false),
+        ],
+    );
+    fs::remove_dir_all(&root).unwrap();
+
+    // the plugin replaces the `.filter` of the site
+    let site = services.get_by_uri("site").unwrap();
+    assert!(site.register.url_matches("https://plugin.test/manga"));
+    assert!(!site.register.url_matches("https://site.test/manga"));
+    assert!(site.metadata.is_some());
+    // without a register the `.filter` is kept
+    let kept = services.get("https://kept.test/manga").unwrap();
+    assert_eq!(kept.uri, "kept");
+    assert!(kept.metadata.is_some());
+    // plugins with a register can add sites
+    assert!(services.get_by_uri("new").is_some());
+    assert_eq!(services.len(), 3);
+
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(matches!(
+        &errors[0],
+        InitError::Located { file: Some(file), error, .. }
+            if file.ends_with("orphan.so")
+                && matches!(error.as_ref(), InitError::PluginWithoutSite(uri) if uri == "orphan")
+    ));
+}
+
+#[test]
+fn test_unused_processors() {
+    let names = [
+        "reqwest",
+        "postprocessor search add_base url",
+        "postprocessor metadata flatten rows",
+        "postprocessor metadata unknown",
+    ];
+    assert_eq!(
+        manga_scraper::init::parse::unused_processors(&names, &["filter", "metadata"]),
+        vec![
+            "postprocessor search add_base url",
+            "postprocessor metadata unknown"
+        ]
+    );
+    assert_eq!(
+        manga_scraper::init::parse::unused_processors(&names[..1], &["filter"]),
+        vec!["reqwest"]
+    );
+}
@@ -3,7 +3,7 @@ use std::path::Path;
 use scraper_testing::generate_tests;
 
 async fn test_logic(_: &str, content: &str) {
-    let services = manga_scraper::init::register(Path::new("../../data/")).unwrap();
+    let services = manga_scraper::init::register(Path::new("../../data/")).0;
 
     let items = content.split("\n\n").map(|v| v.trim()).collect::<Vec<_>>();
     for item in items {
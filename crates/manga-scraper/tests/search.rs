@@ -4,7 +4,7 @@ use scraper_module::Attribute;
 use scraper_testing::generate_tests;
 
 async fn test_logic_no_query(uri: &str, _: &str) {
-    let items = manga_scraper::init::register(Path::new("../../data/")).unwrap();
+    let items = manga_scraper::init::register(Path::new("../../data/")).0;
     let item = items.get_by_uri(uri).unwrap();
     let item = item.searchers.as_ref().unwrap();
     for page in 1..=2 {
@@ -26,7 +26,7 @@ async fn test_logic_no_query(uri: &str, _: &str) {
 }
 
 async fn test_logic_query(uri: &str, content: &str) {
-    let items = manga_scraper::init::register(Path::new("../../data/")).unwrap();
+    let items = manga_scraper::init::register(Path::new("../../data/")).0;
     let item = items.get_by_uri(uri).unwrap();
     let item = item.searchers.as_ref().unwrap();
     for (query, page) in content.lines().map(|v| {
@@ -0,0 +1,44 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::{
+    backends::{Channel, Message, NotificationBackend, Recipient, Report},
+    error::NotifyResult,
+};
+
+/// Keeps every message instead of sending it
+#[derive(Clone)]
+pub struct MemoryBackend {
+    channel: Channel,
+    sent: Arc<Mutex<Vec<(Recipient, Message)>>>,
+}
+
+impl MemoryBackend {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            channel,
+            sent: Arc::default(),
+        }
+    }
+
+    /// Messages which were sent so far
+    pub fn sent(&self) -> Vec<(Recipient, Message)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl NotificationBackend for MemoryBackend {
+    fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    async fn send(&self, recipient: &Recipient, message: &Message) -> NotifyResult<Report> {
+        self.sent
+            .lock()
+            .unwrap()
+            .push((recipient.clone(), message.clone()));
+        Ok(Report::default())
+    }
+}
@@ -0,0 +1,279 @@
+mod memory;
+#[cfg(feature = "push")]
+mod push;
+#[cfg(feature = "smtp")]
+mod smtp;
+#[cfg(test)]
+pub(crate) mod stand_in;
+#[cfg(feature = "webhook")]
+mod webhook;
+
+pub use memory::MemoryBackend;
+#[cfg(feature = "push")]
+pub use push::{PushBackend, VapidOptions};
+#[cfg(feature = "smtp")]
+pub use smtp::{SmtpBackend, SmtpMailer, SmtpOptions, SmtpSecurity};
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookBackend;
+
+use std::net::IpAddr;
+#[cfg(any(feature = "push", feature = "webhook"))]
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{NotifyError, NotifyResult};
+
+/// How a user is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Channel {
+    Email,
+    Push,
+    Webhook,
+}
+
+/// Browser push subscription, as returned by `PushManager.subscribe`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    /// Base64url encoded public key of the browser
+    pub p256dh: String,
+    /// Base64url encoded auth secret of the browser
+    pub auth: String,
+}
+
+/// Everything a backend might need to reach a user. Backends only read their own target
+#[derive(Debug, Clone, Default)]
+pub struct Recipient {
+    pub email: String,
+    pub webhook: Option<String>,
+    pub push: Vec<PushSubscription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    /// Id of the notification, so receivers can deduplicate
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub manga_id: String,
+    pub chapter_id: String,
+}
+
+/// Result of a successful delivery
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Push endpoints which no longer exist & should be removed
+    pub expired: Vec<String>,
+}
+
+#[async_trait]
+pub trait NotificationBackend: Send + Sync {
+    fn channel(&self) -> Channel;
+
+    async fn send(&self, recipient: &Recipient, message: &Message) -> NotifyResult<Report>;
+}
+
+/// Which webhook & push endpoints are accepted. They are chosen by users, so by default only
+/// public https urls are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EndpointPolicy {
+    pub allow_http: bool,
+    /// Accept hosts on loopback, private, link-local & other non-global addresses
+    pub allow_private: bool,
+}
+
+/// False for addresses which are not reachable on the public internet, like loopback,
+/// private networks or cloud metadata services
+fn is_global(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // shared address space of carrier grade nat
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_global(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local
+                || (first & 0xfe00) == 0xfc00
+                // link-local
+                || (first & 0xffc0) == 0xfe80
+                // documentation
+                || first == 0x2001 && ip.segments()[1] == 0x0db8)
+        }
+    }
+}
+
+/// Splits `user@host:port` into host & port
+fn split_authority(authority: &str, https: bool) -> Option<(&str, u16)> {
+    let host_port = authority.rsplit_once('@').map_or(authority, |v| v.1);
+    let default_port = if https { 443 } else { 80 };
+    let (host, port) = match host_port.strip_prefix('[') {
+        Some(rest) => {
+            let (host, port) = rest.split_once(']')?;
+            (host, port.strip_prefix(':'))
+        }
+        None => match host_port.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port,
+    };
+    Some((host, port))
+}
+
+fn invalid(endpoint: &str, reason: &str) -> NotifyError {
+    NotifyError::InvalidEndpoint(format!("{endpoint} {reason}"))
+}
+
+/// Checks that `endpoint` is an absolute https url whose host only resolves to global addresses.
+/// Called when the endpoint is saved & before every request, because dns answers can change
+pub async fn validate_endpoint(endpoint: &str, policy: EndpointPolicy) -> NotifyResult<()> {
+    let (https, rest) = match endpoint.split_once("://") {
+        Some(("https", rest)) => (true, rest),
+        Some(("http", rest)) if policy.allow_http => (false, rest),
+        _ => return Err(invalid(endpoint, "is not an https url")),
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let (host, port) = split_authority(authority, https)
+        .filter(|(host, _)| !host.is_empty() && !host.contains(char::is_whitespace))
+        .ok_or_else(|| invalid(endpoint, "has no host"))?;
+    if policy.allow_private {
+        return Ok(());
+    }
+    let addrs: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| invalid(endpoint, "cannot be resolved"))?
+            .map(|v| v.ip())
+            .collect(),
+    };
+    if addrs.is_empty() || !addrs.into_iter().all(is_global) {
+        return Err(invalid(endpoint, "is not a public address"));
+    }
+    Ok(())
+}
+
+/// Dns resolver of the http clients. Drops non-global addresses, so a host can't be switched
+/// to a private address between [`validate_endpoint`] & the request
+#[cfg(any(feature = "push", feature = "webhook"))]
+pub(crate) struct GlobalResolver;
+
+#[cfg(any(feature = "push", feature = "webhook"))]
+impl reqwest::dns::Resolve for GlobalResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        type BoxError = Box<dyn std::error::Error + Send + Sync>;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|v| is_global(v.ip()))
+                .collect();
+            if addrs.is_empty() {
+                let e: BoxError = format!("{} has no public address", name.as_str()).into();
+                return Err(e);
+            }
+            Ok::<_, BoxError>(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Client for user chosen endpoints
+#[cfg(any(feature = "push", feature = "webhook"))]
+pub(crate) fn endpoint_client(
+    timeout: std::time::Duration,
+    policy: EndpointPolicy,
+) -> NotifyResult<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none());
+    if !policy.allow_private {
+        builder = builder.dns_resolver(std::sync::Arc::new(GlobalResolver));
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{is_global, validate_endpoint, EndpointPolicy};
+
+    const LOCAL: EndpointPolicy = EndpointPolicy {
+        allow_http: true,
+        allow_private: true,
+    };
+
+    #[tokio::test]
+    async fn endpoints_need_https_and_a_host() {
+        let strict = EndpointPolicy::default();
+        assert!(validate_endpoint("https://93.184.215.14/hook", strict)
+            .await
+            .is_ok());
+        assert!(validate_endpoint("http://93.184.215.14/hook", strict)
+            .await
+            .is_err());
+        assert!(validate_endpoint("http://127.0.0.1:8080", LOCAL)
+            .await
+            .is_ok());
+        assert!(validate_endpoint("ftp://example.com", LOCAL).await.is_err());
+        assert!(validate_endpoint("https:///path", LOCAL).await.is_err());
+        assert!(validate_endpoint("example.com", LOCAL).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn private_addresses_are_rejected() {
+        let http = EndpointPolicy {
+            allow_http: true,
+            allow_private: false,
+        };
+        for endpoint in [
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.1",
+            "http://user@192.168.1.1:80",
+            "http://[::1]:8080",
+            "http://[fd00::1]",
+            "http://[::ffff:127.0.0.1]",
+        ] {
+            assert!(
+                validate_endpoint(endpoint, http).await.is_err(),
+                "{endpoint}"
+            );
+        }
+    }
+
+    #[test]
+    fn global_addresses() {
+        for (ip, global) in [
+            ("8.8.8.8", true),
+            ("2606:4700::1111", true),
+            ("100.64.0.1", false),
+            ("0.0.0.0", false),
+            ("fe80::1", false),
+            ("2001:db8::1", false),
+        ] {
+            assert_eq!(is_global(ip.parse::<IpAddr>().unwrap()), global, "{ip}");
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use web_push::{
+    ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushError, WebPushMessageBuilder,
+};
+
+use crate::{
+    backends::{
+        endpoint_client, validate_endpoint, Channel, EndpointPolicy, Message, NotificationBackend,
+        PushSubscription, Recipient, Report,
+    },
+    error::{NotifyError, NotifyResult},
+};
+
+#[derive(Debug, Clone)]
+pub struct VapidOptions {
+    /// Base64url encoded raw P-256 private key
+    pub private_key: String,
+    /// Contact of the server, `mailto:` or `https:` url
+    pub subject: String,
+}
+
+/// Sends the message as encrypted Web Push message to every subscription of the user
+pub struct PushBackend {
+    client: reqwest::Client,
+    vapid: VapidOptions,
+    /// Seconds the push service keeps the message if the browser is offline
+    ttl: u32,
+    policy: EndpointPolicy,
+}
+
+fn push_error(e: WebPushError) -> NotifyError {
+    NotifyError::Push(e.to_string())
+}
+
+impl PushBackend {
+    pub fn new(
+        vapid: VapidOptions,
+        ttl: u32,
+        timeout: Duration,
+        policy: EndpointPolicy,
+    ) -> NotifyResult<Self> {
+        Ok(Self {
+            client: endpoint_client(timeout, policy)?,
+            vapid,
+            ttl,
+            policy,
+        })
+    }
+
+    /// Returns true if the subscription expired
+    async fn send_to(&self, subscription: &PushSubscription, payload: &[u8]) -> NotifyResult<bool> {
+        validate_endpoint(&subscription.endpoint, self.policy).await?;
+        let info = SubscriptionInfo::new(
+            subscription.endpoint.as_str(),
+            subscription.p256dh.as_str(),
+            subscription.auth.as_str(),
+        );
+        let mut signature = VapidSignatureBuilder::from_base64(&self.vapid.private_key, &info)
+            .map_err(push_error)?;
+        signature.add_claim("sub", self.vapid.subject.as_str());
+        let signature = signature.build().map_err(push_error)?;
+
+        let mut builder = WebPushMessageBuilder::new(&info);
+        builder.set_ttl(self.ttl);
+        builder.set_payload(ContentEncoding::Aes128Gcm, payload);
+        builder.set_vapid_signature(signature);
+        let message = builder.build().map_err(push_error)?;
+
+        let mut request = self
+            .client
+            .post(&subscription.endpoint)
+            .header("TTL", message.ttl.to_string());
+        if let Some(payload) = message.payload {
+            request = request
+                .header("Content-Encoding", payload.content_encoding.to_str())
+                .header("Content-Type", "application/octet-stream");
+            for (key, value) in payload.crypto_headers {
+                request = request.header(key, value);
+            }
+            request = request.body(payload.content);
+        }
+        let status = request.send().await?.status();
+        match status.as_u16() {
+            404 | 410 => Ok(true),
+            _ if status.is_success() => Ok(false),
+            code => Err(NotifyError::Status(code)),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationBackend for PushBackend {
+    fn channel(&self) -> Channel {
+        Channel::Push
+    }
+
+    async fn send(&self, recipient: &Recipient, message: &Message) -> NotifyResult<Report> {
+        if recipient.push.is_empty() {
+            return Err(NotifyError::NoTarget(Channel::Push));
+        }
+        let payload = serde_json::to_vec(message).map_err(|e| NotifyError::Push(e.to_string()))?;
+        let mut report = Report::default();
+        let mut delivered = false;
+        let mut error = None;
+        for subscription in &recipient.push {
+            match self.send_to(subscription, &payload).await {
+                Ok(true) => report.expired.push(subscription.endpoint.clone()),
+                Ok(false) => delivered = true,
+                Err(e) => {
+                    log::warn!("Push to {} failed: {}", subscription.endpoint, e);
+                    error = Some(e);
+                }
+            }
+        }
+        match error {
+            Some(e) if !delivered => Err(e),
+            _ => Ok(report),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{PushBackend, VapidOptions};
+    use crate::{
+        backends::{stand_in, EndpointPolicy},
+        NotificationBackend as _, PushSubscription, Recipient, Report,
+    };
+
+    /// Public key of the browser, the generator point of P-256
+    const P256DH: &str =
+        "BGsX0fLhLEJH-Lzm5WOkQPJ3A32BLeszoPShOUXYmMKWT-NC4v4af5uO5-tKfA-eFivOM1drMV7Oy7ZAaDe_UfU";
+    const AUTH: &str = "AAECAwQFBgcICQoLDA0ODw";
+    const VAPID_KEY: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAI";
+
+    #[tokio::test]
+    async fn sends_encrypted_messages_and_reports_expired_subscriptions() {
+        let (active, mut requests) = stand_in::http(201).await;
+        let (expired, _) = stand_in::http(410).await;
+        let subscription = |endpoint: String| PushSubscription {
+            endpoint,
+            p256dh: P256DH.to_owned(),
+            auth: AUTH.to_owned(),
+        };
+        let recipient = Recipient {
+            push: vec![
+                subscription(format!("{active}/push/1")),
+                subscription(format!("{expired}/push/2")),
+            ],
+            ..Default::default()
+        };
+        let backend = PushBackend::new(
+            VapidOptions {
+                private_key: VAPID_KEY.to_owned(),
+                subject: "mailto:admin@example.com".to_owned(),
+            },
+            3600,
+            Duration::from_secs(5),
+            EndpointPolicy {
+                allow_http: true,
+                allow_private: true,
+            },
+        )
+        .unwrap();
+        let message = stand_in::message();
+
+        let report = backend.send(&recipient, &message).await.unwrap();
+        assert_eq!(
+            report,
+            Report {
+                expired: vec![format!("{expired}/push/2")]
+            }
+        );
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.path, "/push/1");
+        assert_eq!(request.header("ttl"), Some("3600"));
+        assert_eq!(request.header("content-encoding"), Some("aes128gcm"));
+        assert!(request
+            .header("authorization")
+            .is_some_and(|v| v.starts_with("vapid t=")));
+        assert!(!request.body.is_empty());
+        assert!(!String::from_utf8_lossy(&request.body).contains("New chapter"));
+    }
+}
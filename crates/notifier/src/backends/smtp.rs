@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport as _, Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backends::{Channel, Message, NotificationBackend, Recipient, Report},
+    error::{NotifyError, NotifyResult},
+    mail::{Mail, MailTransport},
+};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain text, only for local relays
+    None,
+    #[default]
+    StartTls,
+    /// Implicit tls, usually port 465
+    Tls,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpOptions {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender, e.g. `ManRead <noreply@example.com>`
+    pub from: String,
+}
+
+/// Sends plain text mails over smtp
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(options: SmtpOptions) -> NotifyResult<Self> {
+        let builder = match options.security {
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&options.host)
+            }
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&options.host)
+                    .map_err(|e| NotifyError::Email(e.to_string()))?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&options.host)
+                .map_err(|e| NotifyError::Email(e.to_string()))?,
+        };
+        let mut builder = builder.port(options.port);
+        if let Some(username) = options.username {
+            builder = builder.credentials(Credentials::new(
+                username,
+                options.password.unwrap_or_default(),
+            ));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: options
+                .from
+                .parse()
+                .map_err(|e: lettre::address::AddressError| NotifyError::Email(e.to_string()))?,
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpMailer {
+    async fn send(&self, mail: &Mail) -> NotifyResult<()> {
+        let to: Mailbox = mail
+            .to
+            .parse()
+            .map_err(|e: lettre::address::AddressError| NotifyError::Email(e.to_string()))?;
+        let email = lettre::Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body.clone())
+            .map_err(|e| NotifyError::Email(e.to_string()))?;
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| NotifyError::Email(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Sends the message as plain text email to the address of the user
+pub struct SmtpBackend {
+    mailer: SmtpMailer,
+}
+
+impl SmtpBackend {
+    pub fn new(options: SmtpOptions) -> NotifyResult<Self> {
+        Ok(Self {
+            mailer: SmtpMailer::new(options)?,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationBackend for SmtpBackend {
+    fn channel(&self) -> Channel {
+        Channel::Email
+    }
+
+    async fn send(&self, recipient: &Recipient, message: &Message) -> NotifyResult<Report> {
+        if recipient.email.is_empty() {
+            return Err(NotifyError::NoTarget(Channel::Email));
+        }
+        self.mailer
+            .send(&Mail {
+                to: recipient.email.clone(),
+                subject: message.title.clone(),
+                body: message.body.clone(),
+            })
+            .await?;
+        Ok(Report::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SmtpBackend, SmtpOptions, SmtpSecurity};
+    use crate::{
+        backends::stand_in, error::NotifyError, Channel, NotificationBackend as _, Recipient,
+    };
+
+    #[tokio::test]
+    async fn sends_plain_text_mails() {
+        let (port, mut mails) = stand_in::smtp().await;
+        let backend = SmtpBackend::new(SmtpOptions {
+            host: "127.0.0.1".to_owned(),
+            port,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "ManRead <noreply@example.com>".to_owned(),
+        })
+        .unwrap();
+        let message = stand_in::message();
+        let recipient = Recipient {
+            email: "reader@example.com".to_owned(),
+            ..Default::default()
+        };
+        backend.send(&recipient, &message).await.unwrap();
+
+        let mail = mails.recv().await.unwrap();
+        assert_eq!(mail.from, "noreply@example.com");
+        assert_eq!(mail.to, vec!["reader@example.com".to_owned()]);
+        assert!(mail.data.contains("Subject: New chapter"), "{}", mail.data);
+        assert!(
+            mail.data.contains("Chapter 2 of Manga is out"),
+            "{}",
+            mail.data
+        );
+
+        assert!(matches!(
+            backend.send(&Recipient::default(), &message).await,
+            Err(NotifyError::NoTarget(Channel::Email))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! Minimal local http & smtp servers which record what they receive
+
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _, BufReader},
+    net::TcpListener,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+};
+
+use crate::Message;
+
+/// Message every backend test sends
+pub fn message() -> Message {
+    Message {
+        id: "n1".to_owned(),
+        title: "New chapter".to_owned(),
+        body: "Chapter 2 of Manga is out".to_owned(),
+        manga_id: "m1".to_owned(),
+        chapter_id: "c2".to_owned(),
+    }
+}
+
+#[derive(Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Answers every request with `status`. Returns the base url
+pub async fn http(status: u16) -> (String, UnboundedReceiver<HttpRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (read, mut write) = stream.into_split();
+            let mut read = BufReader::new(read);
+            let mut line = String::new();
+            read.read_line(&mut line).await.unwrap();
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_owned();
+            let path = parts.next().unwrap_or_default().to_owned();
+            let mut headers = vec![];
+            loop {
+                let mut line = String::new();
+                read.read_line(&mut line).await.unwrap();
+                let Some((key, value)) = line.trim_end().split_once(':') else {
+                    break;
+                };
+                headers.push((key.to_lowercase(), value.trim().to_owned()));
+            }
+            let len = headers
+                .iter()
+                .find(|(key, _)| key == "content-length")
+                .map(|(_, v)| v.parse().unwrap())
+                .unwrap_or(0);
+            let mut body = vec![0; len];
+            read.read_exact(&mut body).await.unwrap();
+            write
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {status} Stand-In\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            let _ = tx.send(HttpRequest {
+                method,
+                path,
+                headers,
+                body,
+            });
+        }
+    });
+    (url, rx)
+}
+
+#[derive(Debug)]
+pub struct Mail {
+    pub from: String,
+    pub to: Vec<String>,
+    /// Headers & body as sent after DATA
+    pub data: String,
+}
+
+/// Accepts every mail without authentication or tls. Returns the port
+pub async fn smtp() -> (u16, UnboundedReceiver<Mail>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (read, mut write) = stream.into_split();
+            let mut read = BufReader::new(read);
+            write.write_all(b"220 stand-in ESMTP\r\n").await.unwrap();
+            let mut mail = Mail {
+                from: String::new(),
+                to: vec![],
+                data: String::new(),
+            };
+            loop {
+                let mut line = String::new();
+                if read.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let command = line.trim_end();
+                let upper = command.to_uppercase();
+                let reply = if upper.starts_with("EHLO") || upper.starts_with("HELO") {
+                    "250 stand-in\r\n"
+                } else if upper.starts_with("MAIL FROM:") {
+                    mail.from = command[10..].trim_matches(['<', '>', ' ']).to_owned();
+                    "250 OK\r\n"
+                } else if upper.starts_with("RCPT TO:") {
+                    mail.to
+                        .push(command[8..].trim_matches(['<', '>', ' ']).to_owned());
+                    "250 OK\r\n"
+                } else if upper == "DATA" {
+                    write.write_all(b"354 go ahead\r\n").await.unwrap();
+                    loop {
+                        let mut line = String::new();
+                        read.read_line(&mut line).await.unwrap();
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        mail.data.push_str(&line);
+                    }
+                    let _ = tx.send(std::mem::replace(
+                        &mut mail,
+                        Mail {
+                            from: String::new(),
+                            to: vec![],
+                            data: String::new(),
+                        },
+                    ));
+                    "250 queued\r\n"
+                } else if upper == "QUIT" {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    "250 OK\r\n"
+                };
+                write.write_all(reply.as_bytes()).await.unwrap();
+            }
+        }
+    });
+    (port, rx)
+}
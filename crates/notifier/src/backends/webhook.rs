@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::{
+    backends::{
+        endpoint_client, validate_endpoint, Channel, EndpointPolicy, Message, NotificationBackend,
+        Recipient, Report,
+    },
+    error::{NotifyError, NotifyResult},
+};
+
+/// Posts the message as json to the url of the user
+pub struct WebhookBackend {
+    client: reqwest::Client,
+    policy: EndpointPolicy,
+}
+
+impl WebhookBackend {
+    pub fn new(timeout: Duration, policy: EndpointPolicy) -> NotifyResult<Self> {
+        Ok(Self {
+            client: endpoint_client(timeout, policy)?,
+            policy,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationBackend for WebhookBackend {
+    fn channel(&self) -> Channel {
+        Channel::Webhook
+    }
+
+    async fn send(&self, recipient: &Recipient, message: &Message) -> NotifyResult<Report> {
+        let url = recipient
+            .webhook
+            .as_deref()
+            .ok_or(NotifyError::NoTarget(Channel::Webhook))?;
+        validate_endpoint(url, self.policy).await?;
+        let response = self.client.post(url).json(message).send().await?;
+        if !response.status().is_success() {
+            return Err(NotifyError::Status(response.status().as_u16()));
+        }
+        Ok(Report::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::WebhookBackend;
+    use crate::{
+        backends::stand_in, error::NotifyError, Channel, EndpointPolicy, Message,
+        NotificationBackend as _, Recipient,
+    };
+
+    /// The stand-in server listens on plain http on loopback
+    const LOCAL: EndpointPolicy = EndpointPolicy {
+        allow_http: true,
+        allow_private: true,
+    };
+
+    #[tokio::test]
+    async fn posts_the_message_as_json() {
+        let (url, mut requests) = stand_in::http(204).await;
+        let backend = WebhookBackend::new(Duration::from_secs(5), LOCAL).unwrap();
+        let recipient = Recipient {
+            webhook: Some(format!("{url}/hook")),
+            ..Default::default()
+        };
+        backend
+            .send(&recipient, &stand_in::message())
+            .await
+            .unwrap();
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/hook");
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        let received: Message = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(received, stand_in::message());
+    }
+
+    #[tokio::test]
+    async fn failures_are_reported() {
+        let (url, _requests) = stand_in::http(500).await;
+        let recipient = Recipient {
+            webhook: Some(url),
+            ..Default::default()
+        };
+        let backend = WebhookBackend::new(Duration::from_secs(5), LOCAL).unwrap();
+        assert!(matches!(
+            backend.send(&recipient, &stand_in::message()).await,
+            Err(NotifyError::Status(500))
+        ));
+
+        let backend =
+            WebhookBackend::new(Duration::from_secs(5), EndpointPolicy::default()).unwrap();
+        assert!(matches!(
+            backend.send(&recipient, &stand_in::message()).await,
+            Err(NotifyError::InvalidEndpoint(_))
+        ));
+        assert!(matches!(
+            backend
+                .send(&Recipient::default(), &stand_in::message())
+                .await,
+            Err(NotifyError::NoTarget(Channel::Webhook))
+        ));
+    }
+}
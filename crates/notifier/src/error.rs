@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+use crate::Channel;
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("no backend registered for {0:?}")]
+    Unsupported(Channel),
+    #[error("recipient has no {0:?} target")]
+    NoTarget(Channel),
+    #[error("invalid endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("endpoint responded with {0}")]
+    Status(u16),
+    #[error("request failed: {0}")]
+    Request(String),
+    #[error("email failed: {0}")]
+    Email(String),
+    #[error("push message failed: {0}")]
+    Push(String),
+}
+
+pub type NotifyResult<T> = Result<T, NotifyError>;
+
+#[cfg(any(feature = "push", feature = "webhook"))]
+impl From<reqwest::Error> for NotifyError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Request(value.to_string())
+    }
+}
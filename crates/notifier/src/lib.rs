@@ -0,0 +1,80 @@
+mod backends;
+mod error;
+pub mod mail;
+
+#[cfg(feature = "webhook")]
+pub use backends::WebhookBackend;
+pub use backends::{
+    validate_endpoint, Channel, EndpointPolicy, MemoryBackend, Message, NotificationBackend,
+    PushSubscription, Recipient, Report,
+};
+#[cfg(feature = "push")]
+pub use backends::{PushBackend, VapidOptions};
+#[cfg(feature = "smtp")]
+pub use backends::{SmtpBackend, SmtpMailer, SmtpOptions, SmtpSecurity};
+pub use error::{NotifyError, NotifyResult};
+
+use std::{collections::HashMap, sync::Arc};
+
+/// Routes messages to the backend of a channel. Channels without a backend are not delivered
+#[derive(Clone, Default)]
+pub struct Notifier {
+    backends: HashMap<Channel, Arc<dyn NotificationBackend>>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the backend of the same channel
+    pub fn with(mut self, backend: impl NotificationBackend + 'static) -> Self {
+        self.backends.insert(backend.channel(), Arc::new(backend));
+        self
+    }
+
+    pub fn supports(&self, channel: Channel) -> bool {
+        self.backends.contains_key(&channel)
+    }
+
+    pub async fn send(
+        &self,
+        channel: Channel,
+        recipient: &Recipient,
+        message: &Message,
+    ) -> NotifyResult<Report> {
+        self.backends
+            .get(&channel)
+            .ok_or(NotifyError::Unsupported(channel))?
+            .send(recipient, message)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Channel, MemoryBackend, Notifier, NotifyError, Recipient};
+    use crate::backends::stand_in;
+
+    #[tokio::test]
+    async fn messages_are_routed_by_channel() {
+        let email = MemoryBackend::new(Channel::Email);
+        let push = MemoryBackend::new(Channel::Push);
+        let notifier = Notifier::new().with(email.clone()).with(push.clone());
+        let message = stand_in::message();
+
+        notifier
+            .send(Channel::Push, &Recipient::default(), &message)
+            .await
+            .unwrap();
+        assert_eq!(push.sent().len(), 1);
+        assert!(email.sent().is_empty());
+        assert!(!notifier.supports(Channel::Webhook));
+        assert!(matches!(
+            notifier
+                .send(Channel::Webhook, &Recipient::default(), &message)
+                .await,
+            Err(NotifyError::Unsupported(Channel::Webhook))
+        ));
+    }
+}
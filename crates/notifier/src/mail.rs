@@ -0,0 +1,127 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{NotifyError, NotifyResult};
+
+/// Plain text mail to a single address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers account mails, like verification codes
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, mail: &Mail) -> NotifyResult<()>;
+}
+
+/// Writes every mail as `.eml` file into a folder instead of sending it.
+/// Useful for development & tests
+pub struct FileMailer {
+    folder: PathBuf,
+    counter: AtomicU64,
+}
+
+impl FileMailer {
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Self {
+            folder: folder.into(),
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileMailer {
+    async fn send(&self, mail: &Mail) -> NotifyResult<()> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        tokio::fs::create_dir_all(&self.folder)
+            .await
+            .map_err(|e| NotifyError::Email(e.to_string()))?;
+        let content = format!(
+            "To: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            mail.to, mail.subject, mail.body
+        );
+        tokio::fs::write(self.folder.join(format!("{millis}-{n}.eml")), content)
+            .await
+            .map_err(|e| NotifyError::Email(e.to_string()))
+    }
+}
+
+/// Keeps every mail instead of sending it
+#[derive(Clone, Default)]
+pub struct MemoryMailer {
+    sent: Arc<Mutex<Vec<Mail>>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mails which were sent so far
+    pub fn sent(&self) -> Vec<Mail> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MailTransport for MemoryMailer {
+    async fn send(&self, mail: &Mail) -> NotifyResult<()> {
+        self.sent.lock().unwrap().push(mail.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileMailer, Mail, MailTransport as _};
+
+    #[tokio::test]
+    async fn file_mailer_writes_eml_files() {
+        let folder = std::env::temp_dir().join(format!(
+            "notifier-mails-{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let mailer = FileMailer::new(&folder);
+        for subject in ["first", "second"] {
+            mailer
+                .send(&Mail {
+                    to: "reader@example.com".to_owned(),
+                    subject: subject.to_owned(),
+                    body: "Your code is 123456".to_owned(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let mut files = std::fs::read_dir(&folder)
+            .unwrap()
+            .map(|v| std::fs::read_to_string(v.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().any(|v| v.contains("Subject: first\r\n")));
+        assert!(files[0].starts_with("To: reader@example.com\r\n"));
+        assert!(files[0].ends_with("\r\n\r\nYour code is 123456\r\n"));
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+pub mod plugin;
 pub mod req;
 
 use std::{
@@ -11,11 +12,6 @@ use async_trait::async_trait;
 #[cfg(feature = "json")]
 use serde_json::Value;
 
-#[derive(Clone)]
-pub struct Functions {
-    pub guess_episode: fn(&str) -> Result<f64, ScraperError>,
-}
-
 #[derive(Clone)]
 pub struct ScrapeAccount {
     pub username: String,
@@ -25,6 +21,7 @@ pub struct ScrapeAccount {
 #[derive(Debug)]
 pub enum ScraperError {
     Reqwest(String),
+    Curl(String),
     SerdeJson(String),
     InvalidChapterNum(String),
     Utf8(String),
@@ -36,7 +33,14 @@ pub enum ScraperError {
     NoJsonFeature,
     AttrNotFound,
     NodeNotFound,
-    ApiError { status: u16, message: String },
+    /// Selector results cannot be merged, because they contain different types
+    UnsupportedStructure(String),
+    /// Error returned by a dynamic plugin
+    Plugin(String),
+    ApiError {
+        status: u16,
+        message: String,
+    },
 }
 
 impl From<ParseFloatError> for ScraperError {
@@ -74,6 +78,12 @@ impl From<reqwest::Error> for ScraperError {
 pub type ScraperResult<T> = Result<T, ScraperError>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "openapi",
+    derive(apistos::ApiComponent, schemars::JsonSchema)
+)]
+#[cfg_attr(feature = "json", serde(untagged))]
 pub enum ScrapedData {
     Str(String),
     Arr(Vec<ScrapedData>),
@@ -221,7 +231,7 @@ pub trait Register: Send + Sync {
     feature = "openapi",
     derive(apistos::ApiComponent, schemars::JsonSchema)
 )]
-#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", derive(serde::Deserialize, serde::Serialize))]
 pub enum SearchQuery {
     Simple(HashMap<String, Attribute>),
 }
@@ -299,6 +309,47 @@ impl AttributesDef {
     pub fn expend(&mut self, items: impl IntoIterator<Item = (String, AttributeDef)>) {
         self.0.extend(items);
     }
+
+    /// Attributes a searcher accepts for [SearchQuery::Simple].
+    /// Returns `None` for advanced searches, which are validated by the searcher itself
+    pub fn for_searches(searches: &ValidSearches) -> Option<Self> {
+        let mut def = Self::default();
+        match searches {
+            ValidSearches::QueryOffset => {}
+            ValidSearches::ValidSearch(valid) => def.expend([
+                (
+                    "sort".to_owned(),
+                    AttributeDef {
+                        required: false,
+                        value: AttributeValueDef::Enum(valid.sort_by.clone()),
+                    },
+                ),
+                (
+                    "desc".to_owned(),
+                    AttributeDef {
+                        required: false,
+                        value: AttributeValueDef::Bool,
+                    },
+                ),
+                (
+                    "status".to_owned(),
+                    AttributeDef {
+                        required: false,
+                        value: AttributeValueDef::Enum(valid.status.clone()),
+                    },
+                ),
+                (
+                    "tags".to_owned(),
+                    AttributeDef {
+                        required: false,
+                        value: AttributeValueDef::Arr(Box::new(AttributeValueDef::Str)),
+                    },
+                ),
+            ]),
+            ValidSearches::Advanced { .. } => return None,
+        }
+        Some(def)
+    }
 }
 
 impl Default for AttributesDef {
@@ -380,6 +431,28 @@ pub struct ExternalSearchResponse {
     pub page: usize,
 }
 
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(
+    feature = "openapi",
+    derive(apistos::ApiComponent, schemars::JsonSchema)
+)]
+pub struct ExternalSearchRequest {
+    /// uri of the site which should be searched
+    pub uri: String,
+    pub query: SearchQuery,
+}
+
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+#[cfg_attr(
+    feature = "openapi",
+    derive(apistos::ApiComponent, schemars::JsonSchema)
+)]
+pub struct ExternalMetadataResponse {
+    /// uri of the site which matched the url
+    pub uri: String,
+    pub data: BTreeMap<String, ScrapedData>,
+}
+
 #[async_trait]
 pub trait SearchScraper: Send + Sync {
     async fn search(&self, query: SearchQuery) -> ScraperResult<ExternalSearchResponse>;
@@ -387,7 +460,7 @@ pub trait SearchScraper: Send + Sync {
 }
 
 #[derive(Debug, Clone, Copy)]
-#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+#[cfg_attr(feature = "json", derive(serde::Deserialize, serde::Serialize))]
 pub enum Mode {
     Single,
     Multi,
@@ -412,11 +485,20 @@ pub trait MetaDataScraper: Send + Sync {
         &self,
         url: &str,
     ) -> Result<BTreeMap<String, ScrapedData>, ScraperError>;
+
+    /// downloads files referenced by the metadata (e.g. the cover) with the same
+    /// headers & cookies as the site
+    async fn download_file(&self, _url: &str) -> ScraperResult<Vec<u8>> {
+        Err(ScraperError::Unimplemented)
+    }
 }
 
-/// Struct used to override/register scrapers
+/// Struct used to override/register scrapers.
+/// Every capability which is set replaces the interpreted one of the site with the same uri
 pub struct RegisterOverride {
-    pub uri: &'static str,
+    pub uri: String,
+    /// Adds a new site or replaces the `.filter` of an existing one
+    pub register: Option<Arc<dyn Register>>,
     pub metadata: Option<Arc<dyn MetaDataScraper>>,
     pub search: Option<Arc<dyn SearchScraper>>,
     pub reader: Option<Arc<dyn ReaderScraper>>,
@@ -0,0 +1,327 @@
+//! Stable C ABI of dynamic scraper plugins.
+//!
+//! A plugin is a `cdylib` in `external/` which exports two functions:
+//! - [MANIFEST_SYMBOL] `extern "C" fn() -> Manifest` is read first. Plugins built for another
+//!   [ABI_VERSION] are refused before any other symbol is touched
+//! - [INIT_SYMBOL] `extern "C" fn(HostVTable) -> PluginVTable` creates the plugin
+//!
+//! Only `#[repr(C)]` types cross the boundary. Arguments & results of [PluginVTable::call] are json,
+//! so they dont depend on the layout of rust types. Memory is always freed by the side which allocated it.
+//!
+//! Plugins implement [Plugin] & use [crate::export_plugin] to export both functions.
+
+use std::ffi::c_void;
+
+/// Has to be increased on every breaking change of the types in this module or the json of a [method]
+pub const ABI_VERSION: u32 = 1;
+pub const MANIFEST_SYMBOL: &[u8] = b"manread_plugin_manifest";
+pub const INIT_SYMBOL: &[u8] = b"manread_plugin_init";
+
+/// What a plugin implements.
+/// Implemented capabilities replace the ones of the interpreted site with the same name
+pub mod capability {
+    /// Url filter & icon, replaces the `.filter` file. Plugins without it need a `.filter` with their name
+    pub const FILTER: u32 = 1;
+    pub const SEARCH: u32 = 1 << 1;
+    pub const METADATA: u32 = 1 << 2;
+    pub const READER: u32 = 1 << 3;
+}
+
+/// Ids for [PluginVTable::call] with their json input -> output.
+/// Outputs are wrapped in `Result<T, String>`
+pub mod method {
+    /// `String` -> `bool`
+    pub const URL_MATCHES: u32 = 0;
+    /// `null` -> `(String, Vec<u8>)` file extension & bytes
+    pub const ICON: u32 = 1;
+    /// `null` -> `ValidSearches`
+    pub const QUERY: u32 = 2;
+    /// `SearchQuery` -> `ExternalSearchResponse`
+    pub const SEARCH: u32 = 3;
+    /// `String` -> `BTreeMap<String, ScrapedData>`
+    pub const METADATA: u32 = 4;
+    /// `String` -> `Mode`
+    pub const MULTI: u32 = 5;
+    /// `String` -> `Vec<String>`
+    pub const PAGES: u32 = 6;
+    /// `String` -> `Vec<ScrapedChapter>`
+    pub const CHAPTERS: u32 = 7;
+    /// `String` -> `Vec<u8>`
+    pub const DOWNLOAD: u32 = 8;
+}
+
+/// Borrowed bytes
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Str {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+impl Str {
+    pub const fn new(s: &'static str) -> Self {
+        Self::from_bytes(s.as_bytes())
+    }
+
+    pub const fn from_bytes(s: &[u8]) -> Self {
+        Self {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// # Safety
+    /// Has to point to `len` bytes which are valid for `'a`
+    pub unsafe fn as_bytes<'a>(&self) -> &'a [u8] {
+        match self.len {
+            0 => &[],
+            len => std::slice::from_raw_parts(self.ptr, len),
+        }
+    }
+}
+
+/// Owned bytes. Has to be freed by the `free` fn of the side which created it
+#[repr(C)]
+pub struct Buffer {
+    pub ptr: *mut u8,
+    pub len: usize,
+    pub cap: usize,
+}
+
+impl Buffer {
+    pub fn from_vec(v: Vec<u8>) -> Self {
+        let mut v = std::mem::ManuallyDrop::new(v);
+        Self {
+            ptr: v.as_mut_ptr(),
+            len: v.len(),
+            cap: v.capacity(),
+        }
+    }
+
+    /// # Safety
+    /// Has to be created by [Buffer::from_vec] of the same binary
+    pub unsafe fn into_vec(self) -> Vec<u8> {
+        Vec::from_raw_parts(self.ptr, self.len, self.cap)
+    }
+
+    /// # Safety
+    /// Has to be created by [Buffer::from_vec] & not freed yet
+    pub unsafe fn to_vec(&self) -> Vec<u8> {
+        Str {
+            ptr: self.ptr,
+            len: self.len,
+        }
+        .as_bytes()
+        .to_vec()
+    }
+}
+
+/// Frees buffers of the binary it is compiled into
+pub extern "C" fn free_buffer(buffer: Buffer) {
+    drop(unsafe { buffer.into_vec() })
+}
+
+#[repr(C)]
+pub struct Manifest {
+    pub abi_version: u32,
+    /// uri of the site
+    pub name: Str,
+    pub version: Str,
+    /// see [capability]
+    pub capabilities: u32,
+}
+
+/// Functions of the host which can be used by the plugin
+#[repr(C)]
+pub struct HostVTable {
+    pub abi_version: u32,
+    pub host: *const c_void,
+    /// json `HostRequest` -> json `Result<HostResponse, String>`. Blocks until the response is there
+    pub fetch: extern "C" fn(host: *const c_void, request: Str) -> Buffer,
+    pub free: extern "C" fn(Buffer),
+}
+
+#[repr(C)]
+pub struct PluginVTable {
+    pub plugin: *const c_void,
+    /// Can be called from multiple threads at once, see [method]
+    pub call: extern "C" fn(plugin: *const c_void, method: u32, input: Str) -> Buffer,
+    pub free: extern "C" fn(Buffer),
+    pub drop: extern "C" fn(plugin: *const c_void),
+}
+
+#[cfg(feature = "json")]
+pub use json::*;
+
+#[cfg(feature = "json")]
+mod json {
+    use std::{
+        collections::BTreeMap,
+        ffi::c_void,
+        panic::{catch_unwind, AssertUnwindSafe},
+    };
+
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use super::{free_buffer, method, Buffer, HostVTable, PluginVTable, Str};
+    use crate::{
+        ExternalSearchResponse, Mode, ScrapedChapter, ScrapedData, SearchQuery, ValidSearches,
+    };
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct HostRequest {
+        pub method: String,
+        pub url: String,
+        pub headers: Vec<(String, String)>,
+        pub body: Option<Vec<u8>>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct HostResponse {
+        pub status: u16,
+        pub headers: Vec<(String, String)>,
+        pub body: Vec<u8>,
+    }
+
+    /// Handle of a plugin to the host
+    pub struct Host(HostVTable);
+
+    // the host functions are thread safe
+    unsafe impl Send for Host {}
+    unsafe impl Sync for Host {}
+
+    impl Host {
+        /// Sends a request with the http client of the host
+        pub fn fetch(&self, request: &HostRequest) -> Result<HostResponse, String> {
+            let input = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+            let out = (self.0.fetch)(self.0.host, Str::from_bytes(&input));
+            let bytes = unsafe { out.to_vec() };
+            (self.0.free)(out);
+            serde_json::from_slice::<Result<HostResponse, String>>(&bytes)
+                .map_err(|e| e.to_string())?
+        }
+
+        pub fn abi_version(&self) -> u32 {
+            self.0.abi_version
+        }
+    }
+
+    fn unimplemented<T>() -> Result<T, String> {
+        Err("unimplemented".to_owned())
+    }
+
+    /// Implemented by plugins. Calls are made from blocking threads, so they are allowed to block.
+    /// Only methods of the capabilities in the manifest are called
+    pub trait Plugin: Send + Sync + Sized + 'static {
+        fn new(host: Host) -> Self;
+        fn url_matches(&self, _url: &str) -> bool {
+            false
+        }
+        fn icon(&self) -> Result<(String, Vec<u8>), String> {
+            unimplemented()
+        }
+        fn query(&self) -> Result<ValidSearches, String> {
+            unimplemented()
+        }
+        fn search(&self, _query: SearchQuery) -> Result<ExternalSearchResponse, String> {
+            unimplemented()
+        }
+        fn metadata(&self, _url: &str) -> Result<BTreeMap<String, ScrapedData>, String> {
+            unimplemented()
+        }
+        fn multi(&self, _url: &str) -> Result<Mode, String> {
+            unimplemented()
+        }
+        fn pages(&self, _url: &str) -> Result<Vec<String>, String> {
+            unimplemented()
+        }
+        fn chapters(&self, _url: &str) -> Result<Vec<ScrapedChapter>, String> {
+            unimplemented()
+        }
+        fn download(&self, _url: &str) -> Result<Vec<u8>, String> {
+            unimplemented()
+        }
+    }
+
+    fn run<I: DeserializeOwned, O: Serialize>(
+        input: &[u8],
+        f: impl FnOnce(I) -> Result<O, String>,
+    ) -> Vec<u8> {
+        let out = serde_json::from_slice(input)
+            .map_err(|e| e.to_string())
+            .and_then(f);
+        serde_json::to_vec(&out).unwrap_or_else(|e| {
+            serde_json::to_vec(&Err::<(), _>(e.to_string())).unwrap_or_default()
+        })
+    }
+
+    /// Decodes the input of `method`, calls `plugin` & encodes the result
+    pub fn dispatch<P: Plugin>(plugin: &P, method: u32, input: &[u8]) -> Vec<u8> {
+        match method {
+            method::URL_MATCHES => run(input, |url: String| Ok(plugin.url_matches(&url))),
+            method::ICON => run(input, |()| plugin.icon()),
+            method::QUERY => run(input, |()| plugin.query()),
+            method::SEARCH => run(input, |query| plugin.search(query)),
+            method::METADATA => run(input, |url: String| plugin.metadata(&url)),
+            method::MULTI => run(input, |url: String| plugin.multi(&url)),
+            method::PAGES => run(input, |url: String| plugin.pages(&url)),
+            method::CHAPTERS => run(input, |url: String| plugin.chapters(&url)),
+            method::DOWNLOAD => run(input, |url: String| plugin.download(&url)),
+            method => run(input, |_: serde_json::Value| {
+                Err::<(), _>(format!("unknown method {method}"))
+            }),
+        }
+    }
+
+    /// Creates the vtable of `P`. Used by [crate::export_plugin]
+    pub fn init<P: Plugin>(host: HostVTable) -> PluginVTable {
+        extern "C" fn call<P: Plugin>(plugin: *const c_void, method: u32, input: Str) -> Buffer {
+            let plugin = unsafe { &*(plugin as *const P) };
+            let input = unsafe { input.as_bytes() };
+            // unwinding into the host would abort it
+            let out = catch_unwind(AssertUnwindSafe(|| dispatch(plugin, method, input)))
+                .unwrap_or_else(|_| {
+                    serde_json::to_vec(&Err::<(), _>("plugin panicked")).unwrap_or_default()
+                });
+            Buffer::from_vec(out)
+        }
+
+        extern "C" fn drop<P: Plugin>(plugin: *const c_void) {
+            std::mem::drop(unsafe { Box::from_raw(plugin as *mut P) })
+        }
+
+        PluginVTable {
+            plugin: Box::into_raw(Box::new(P::new(Host(host)))) as *const c_void,
+            call: call::<P>,
+            free: free_buffer,
+            drop: drop::<P>,
+        }
+    }
+
+    /// Exports `$ty` as plugin for the site `$name`
+    ///
+    /// ```ignore
+    /// scraper_module::export_plugin!(MySite, "my-site", env!("CARGO_PKG_VERSION"), capability::SEARCH);
+    /// ```
+    #[macro_export]
+    macro_rules! export_plugin {
+        ($ty:ty, $name:expr, $version:expr, $capabilities:expr) => {
+            #[no_mangle]
+            pub extern "C" fn manread_plugin_manifest() -> $crate::plugin::Manifest {
+                $crate::plugin::Manifest {
+                    abi_version: $crate::plugin::ABI_VERSION,
+                    name: $crate::plugin::Str::new($name),
+                    version: $crate::plugin::Str::new($version),
+                    capabilities: $capabilities,
+                }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn manread_plugin_init(
+                host: $crate::plugin::HostVTable,
+            ) -> $crate::plugin::PluginVTable {
+                $crate::plugin::init::<$ty>(host)
+            }
+        };
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{tokenize, Token};
+
+/// What the word at the cursor describes
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompletionTarget {
+    /// Name of a field or a word which is searched in titles, names & descriptions
+    Field,
+    /// Value of the field with this name
+    Value(String),
+}
+
+/// Word at the cursor which should be replaced by a suggestion
+#[derive(Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub target: CompletionTarget,
+    /// Part of the word before the cursor without quotes
+    pub prefix: String,
+    /// Char offset where the replacement starts
+    pub start: usize,
+    /// Char offset where the replacement ends
+    pub end: usize,
+}
+
+/// Finds the word at `cursor` (char offset) & whether it is a field or the value of a field.
+/// Works for incomplete queries like `tag:"big` which can't be parsed yet
+pub fn completion_at(s: &str, cursor: usize) -> Completion {
+    let cursor = cursor.min(s.chars().count());
+    let tokens = tokenize(s);
+    let word = tokens.iter().position(|v| {
+        matches!(v.item, Token::Word(_)) && v.pos.start < cursor && cursor <= v.pos.end
+    });
+    let (start, end, prefix) = match word.map(|i| &tokens[i]) {
+        Some(token) => {
+            let prefix = token
+                .item
+                .to_string()
+                .chars()
+                .take(cursor - token.pos.start)
+                .collect::<String>();
+            let prefix = prefix
+                .strip_prefix(['"', '\''])
+                .unwrap_or(&prefix)
+                .to_owned();
+            (token.pos.start, token.pos.end, prefix)
+        }
+        None => (cursor, cursor, String::new()),
+    };
+    let before = word.unwrap_or_else(|| tokens.iter().take_while(|v| v.pos.end <= cursor).count());
+    let mut before = tokens[..before]
+        .iter()
+        .rev()
+        .filter(|v| v.item != Token::Space)
+        .map(|v| &v.item);
+    let target = match before.next() {
+        Some(Token::Colon) => match before.next() {
+            Some(Token::ExclamationMark) => before.next(),
+            v => v,
+        }
+        .and_then(|v| match v {
+            Token::Word(key) => Some(CompletionTarget::Value(key.clone())),
+            _ => None,
+        })
+        .unwrap_or(CompletionTarget::Field),
+        _ => CompletionTarget::Field,
+    };
+    Completion {
+        target,
+        prefix,
+        start,
+        end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completion_at, Completion, CompletionTarget};
+
+    #[test]
+    fn completion_targets() {
+        assert_eq!(
+            completion_at("a ta", 4),
+            Completion {
+                target: CompletionTarget::Field,
+                prefix: "ta".to_owned(),
+                start: 2,
+                end: 4,
+            }
+        );
+        assert_eq!(
+            completion_at("x t!:\"big b", 9),
+            Completion {
+                target: CompletionTarget::Value("t".to_owned()),
+                prefix: "big".to_owned(),
+                start: 5,
+                end: 11,
+            }
+        );
+        assert_eq!(
+            completion_at("list: ", 6),
+            Completion {
+                target: CompletionTarget::Value("list".to_owned()),
+                prefix: String::new(),
+                start: 6,
+                end: 6,
+            }
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt::{Display, Formatter};
+
+use crate::Pos;
+
+/// Problem in a query. Positions are char offsets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+    /// Tokens which would be valid at this position
+    pub expected: Vec<&'static str>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(pos: Pos, message: String, expected: &[&'static str]) -> Self {
+        Self {
+            start: pos.start,
+            end: pos.end,
+            message,
+            expected: expected.to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"start": {}, "end": {}, "message": {}, "expected": [{}]}}"#,
+            self.start,
+            self.end,
+            json_string(&self.message),
+            self.expected
+                .iter()
+                .map(|v| json_string(v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.start, self.end)?;
+        if !self.expected.is_empty() {
+            write!(f, ", expected {}", self.expected.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Messages contain user input, so they need to be escaped
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
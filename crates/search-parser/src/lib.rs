@@ -1,3 +1,5 @@
+mod complete;
+mod diagnostic;
 mod display;
 mod shape;
 mod to_json;
@@ -16,13 +18,16 @@ cfg_if::cfg_if! {
     }
 }
 
+pub use complete::{completion_at, Completion, CompletionTarget};
+pub use diagnostic::Diagnostic;
 use shape::{Array, Item, ItemData, ItemOrArray, ItemValue};
 use to_json::ToJson;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+/// Returns `{"query": Array, "diagnostics": Diagnostic[]}`
 #[wasm_bindgen]
-pub fn parse_query(item: &str, or_default: bool) -> Result<String, String> {
-    parse_str(item, or_default, &HashMap::new()).map(|v| v.item.to_json())
+pub fn parse_query(item: &str, or_default: bool) -> String {
+    parse_with_diagnostics(item, or_default, &HashMap::new()).to_json()
 }
 
 pub struct WPos<T: PartialEq + Eq + Display + ToJson> {
@@ -170,7 +175,10 @@ impl Display for Token2 {
     }
 }
 
-fn recursion(tokens: &mut impl Iterator<Item = WPos<Token>>) -> (Vec<WPos<Token2>>, Option<Pos>) {
+fn recursion(
+    tokens: &mut impl Iterator<Item = WPos<Token>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (Vec<WPos<Token2>>, Option<Pos>) {
     let mut items = vec![];
     while let Some(token) = tokens.next() {
         match token.item {
@@ -178,7 +186,14 @@ fn recursion(tokens: &mut impl Iterator<Item = WPos<Token>>) -> (Vec<WPos<Token2
             Token::Colon => items.push(WPos::new(token.pos, Token2::Colon)),
             Token::ExclamationMark => items.push(WPos::new(token.pos, Token2::ExclamationMark)),
             Token::Open => {
-                let (tokens, end_pos) = recursion(tokens);
+                let (tokens, end_pos) = recursion(tokens, diagnostics);
+                if end_pos.is_none() {
+                    diagnostics.push(Diagnostic::new(
+                        token.pos,
+                        "unclosed `(`".to_owned(),
+                        &[")"],
+                    ));
+                }
                 let pos = Pos {
                     start: token.pos.start,
                     end: tokens
@@ -241,11 +256,8 @@ impl Display for Token {
     }
 }
 
-pub fn parse_str(
-    s: &str,
-    or_default: bool,
-    kind_map: &HashMap<String, ItemKind>,
-) -> Result<WPos<Array>, String> {
+/// Splits the query into words & operators. Positions are char offsets
+fn tokenize(s: &str) -> Vec<WPos<Token>> {
     let tokens = s
         .chars()
         .enumerate()
@@ -266,17 +278,73 @@ pub fn parse_str(
             _ => WPos::nnew(i, Token::Char(c)),
         })
         .collect::<Vec<_>>();
-    let tokens = process_words(tokens);
-    let (tokens, _) = recursion(&mut tokens.into_iter());
-    tokens_to_array(
+    process_words(tokens)
+}
+
+/// Result of [`parse_with_diagnostics`]
+pub struct Parsed {
+    /// Everything which could be parsed. Invalid items are skipped
+    pub query: WPos<Array>,
+    /// Sorted by position
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Parsed {
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"query": {}, "diagnostics": [{}]}}"#,
+            self.query.item.to_json(),
+            self.diagnostics
+                .iter()
+                .map(|v| v.to_json())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// Parses the query & recovers from errors, so the valid part can still be used
+pub fn parse_with_diagnostics(
+    s: &str,
+    or_default: bool,
+    kind_map: &HashMap<String, ItemKind>,
+) -> Parsed {
+    let mut diagnostics = vec![];
+    let mut tokens = tokenize(s).into_iter();
+    let mut items = vec![];
+    loop {
+        let (mut part, close) = recursion(&mut tokens, &mut diagnostics);
+        items.append(&mut part);
+        match close {
+            Some(pos) => diagnostics.push(Diagnostic::new(pos, "unmatched `)`".to_owned(), &[])),
+            None => break,
+        }
+    }
+    let query = tokens_to_array(
         kind_map,
+        &mut diagnostics,
         Pos {
             start: 0,
-            end: s.len(),
+            end: s.chars().count(),
         },
-        tokens,
+        items,
         or_default,
-    )
+    );
+    diagnostics.sort_by_key(|v| (v.start, v.end));
+    Parsed { query, diagnostics }
+}
+
+/// Parses the query & fails with the first diagnostic
+pub fn parse_str(
+    s: &str,
+    or_default: bool,
+    kind_map: &HashMap<String, ItemKind>,
+) -> Result<WPos<Array>, String> {
+    let parsed = parse_with_diagnostics(s, or_default, kind_map);
+    match parsed.diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.to_string()),
+        None => Ok(parsed.query),
+    }
 }
 
 enum States {
@@ -285,8 +353,9 @@ enum States {
     StrNo(WPos<String>),
     Str(WPos<String>),
     ValueBuilder(WPos<String>, bool, Option<WPos<String>>),
-    OrAnd(bool),
-    OrAndNot(bool),
+    /// Operator at the start of an array & its position
+    OrAnd(bool, Pos),
+    OrAndNot(bool, Pos),
     Array {
         or: bool,
         not: bool,
@@ -296,17 +365,98 @@ enum States {
     ArrayFinish(WPos<Array>),
 }
 
+/// Name of a token in diagnostics
+fn describe(token: &Token2) -> String {
+    match token {
+        Token2::Space => "space".to_owned(),
+        Token2::Colon => "`:`".to_owned(),
+        Token2::ExclamationMark => "`!`".to_owned(),
+        Token2::Or => "`|`".to_owned(),
+        Token2::And => "`&`".to_owned(),
+        Token2::Eq => "`=`".to_owned(),
+        Token2::Smaller => "`<`".to_owned(),
+        Token2::Bigger => "`>`".to_owned(),
+        Token2::Word(w) => format!("`{w}`"),
+        Token2::Group(_) => "group".to_owned(),
+    }
+}
+
+fn unexpected(diagnostics: &mut Vec<Diagnostic>, token: &WPos<Token2>, expected: &[&'static str]) {
+    diagnostics.push(Diagnostic::new(
+        token.pos,
+        format!("unexpected {}", describe(&token.item)),
+        expected,
+    ));
+}
+
+fn enum_item(name: WPos<String>, not: bool, or_post: Option<bool>) -> ItemOrArray {
+    ItemOrArray::Item(WPos::new(
+        name.pos,
+        Item {
+            not,
+            or_post,
+            data: ItemData {
+                name,
+                value: ItemValue::None,
+            },
+        },
+    ))
+}
+
+/// Parses the value with the kind of the key. Returns `None` & adds a diagnostic if it is invalid
+fn value_item(
+    kind_map: &HashMap<String, ItemKind>,
+    diagnostics: &mut Vec<Diagnostic>,
+    key: WPos<String>,
+    not: bool,
+    value: Option<WPos<String>>,
+) -> Option<ItemOrArray> {
+    let pos = value
+        .as_ref()
+        .map(|v| v.pos.join(key.pos))
+        .unwrap_or(key.pos);
+    let value = match value {
+        Some(v) => match kind_map
+            .get(&key.item)
+            .copied()
+            .unwrap_or_default()
+            .parse(&v.item)
+        {
+            Ok(value) => value,
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(v.pos, e, &[]));
+                return None;
+            }
+        },
+        None => ItemValue::None,
+    };
+    Some(ItemOrArray::Item(WPos::new(
+        pos,
+        Item {
+            not,
+            or_post: None,
+            data: ItemData { value, name: key },
+        },
+    )))
+}
+
 fn tokens_to_array(
     kind_map: &HashMap<String, ItemKind>,
+    diagnostics: &mut Vec<Diagnostic>,
     range: Pos,
     tokens: Vec<WPos<Token2>>,
     or_default: bool,
-) -> Result<WPos<Array>, String> {
+) -> WPos<Array> {
     let mut tokens = tokens.into_iter().collect::<VecDeque<_>>();
     let mut states = States::None;
 
     let mut items = vec![];
+    // last token which isnt a space, if it was an operator
+    let mut operator = None;
     while let Some(token) = tokens.pop_front() {
+        if token.item != Token2::Space {
+            operator = matches!(token.item, Token2::Or | Token2::And).then_some(token.pos);
+        }
         states = match states {
             States::None => match token.item {
                 Token2::Space => States::None,
@@ -314,8 +464,8 @@ fn tokens_to_array(
                 Token2::ExclamationMark => {
                     States::StrBuilder(WPos::new(token.pos, "!".to_string()))
                 }
-                Token2::Or => States::OrAnd(true),
-                Token2::And => States::OrAnd(false),
+                Token2::Or => States::OrAnd(true, token.pos),
+                Token2::And => States::OrAnd(false, token.pos),
                 Token2::Eq => States::StrBuilder(WPos::new(token.pos, "=".to_string())),
                 Token2::Smaller => States::StrBuilder(WPos::new(token.pos, "<".to_string())),
                 Token2::Bigger => States::StrBuilder(WPos::new(token.pos, ">".to_string())),
@@ -332,31 +482,11 @@ fn tokens_to_array(
                 Token2::Colon => States::ValueBuilder(build, false, None),
                 Token2::ExclamationMark => States::StrNo(build),
                 Token2::Or => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        build.pos,
-                        Item {
-                            not: false,
-                            or_post: Some(true),
-                            data: ItemData {
-                                name: build,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(build, false, Some(true)));
                     States::None
                 }
                 Token2::And => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        build.pos,
-                        Item {
-                            not: false,
-                            or_post: Some(false),
-                            data: ItemData {
-                                name: build,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(build, false, Some(false)));
                     States::None
                 }
                 Token2::Eq => States::StrBuilder(join(Some(build), "=", token.pos)),
@@ -364,17 +494,7 @@ fn tokens_to_array(
                 Token2::Bigger => States::StrBuilder(join(Some(build), ">", token.pos)),
                 Token2::Word(w) => States::StrBuilder(join(Some(build), w.as_str(), token.pos)),
                 Token2::Group((tokens, tokens_pos)) => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        build.pos,
-                        Item {
-                            not: false,
-                            or_post: None,
-                            data: ItemData {
-                                name: build,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(build, false, None));
                     States::Array {
                         not: false,
                         or: or_default,
@@ -384,78 +504,31 @@ fn tokens_to_array(
                 }
             },
             States::StrNo(s) => match token.item {
-                Token2::Space => Err("unexpected Space".to_owned())?,
                 Token2::Colon => States::ValueBuilder(s, true, None),
-                Token2::ExclamationMark => Err("unexpected ExclamationMark".to_owned())?,
-                Token2::Or => Err("unexpected Or".to_owned())?,
-                Token2::And => Err("unexpected And".to_owned())?,
-                Token2::Eq => Err("unexpected Eq".to_owned())?,
-                Token2::Smaller => Err("unexpected Smaller".to_owned())?,
-                Token2::Bigger => Err("unexpected Bigger".to_owned())?,
-                Token2::Word(_) => Err("unexpected Word".to_owned())?,
-                Token2::Group(_) => Err("unexpected group".to_owned())?,
+                _ => {
+                    // treat it as a negated word
+                    unexpected(diagnostics, &token, &[":"]);
+                    items.push(enum_item(s, true, None));
+                    tokens.push_front(token);
+                    States::None
+                }
             },
             States::Str(s) => match token.item {
                 Token2::Space => States::Str(s),
-                Token2::Colon => Err("unexpected Colon".to_owned())?,
-                Token2::ExclamationMark => Err("unexpected ExclamationMark".to_owned())?,
                 Token2::Or => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        s.pos,
-                        Item {
-                            not: false,
-                            or_post: Some(true),
-                            data: ItemData {
-                                name: s,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(s, false, Some(true)));
                     States::None
                 }
                 Token2::And => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        s.pos,
-                        Item {
-                            not: false,
-                            or_post: Some(false),
-                            data: ItemData {
-                                name: s,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(s, false, Some(false)));
                     States::None
                 }
-                Token2::Eq => Err("unexpected Eq".to_owned())?,
-                Token2::Smaller => Err("unexpected Smaller".to_owned())?,
-                Token2::Bigger => Err("unexpected Bigger".to_owned())?,
                 Token2::Word(w) => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        s.pos,
-                        Item {
-                            not: false,
-                            or_post: None,
-                            data: ItemData {
-                                name: s,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(s, false, None));
                     States::StrBuilder(WPos::new(token.pos, w))
                 }
                 Token2::Group((tokens, tokens_pos)) => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        s.pos,
-                        Item {
-                            not: false,
-                            or_post: None,
-                            data: ItemData {
-                                name: s,
-                                value: ItemValue::None,
-                            },
-                        },
-                    )));
+                    items.push(enum_item(s, false, None));
                     States::Array {
                         or: or_default,
                         not: false,
@@ -463,53 +536,58 @@ fn tokens_to_array(
                         tokens_pos,
                     }
                 }
+                Token2::Colon
+                | Token2::ExclamationMark
+                | Token2::Eq
+                | Token2::Smaller
+                | Token2::Bigger => {
+                    unexpected(diagnostics, &token, &["word", "(", "|", "&"]);
+                    States::Str(s)
+                }
             },
-            States::OrAnd(v) => match token.item {
-                Token2::Space => Err("unexpected Space".to_owned())?,
-                Token2::Colon => Err("unexpected Colon".to_owned())?,
-                Token2::ExclamationMark => States::OrAndNot(v),
+            States::OrAnd(v, pos) => match token.item {
+                Token2::ExclamationMark => States::OrAndNot(v, pos),
                 Token2::Group((tokens, tokens_pos)) => States::Array {
                     not: false,
                     or: v,
                     tokens,
                     tokens_pos,
                 },
-                Token2::Or => Err("unexpected Or".to_owned())?,
-                Token2::And => Err("unexpected And".to_owned())?,
-                Token2::Eq => Err("unexpected Eq".to_owned())?,
-                Token2::Smaller => Err("unexpected Smaller".to_owned())?,
-                Token2::Bigger => Err("unexpected Bigger".to_owned())?,
-                Token2::Word(_) => Err("unexpected Word".to_owned())?,
+                _ => {
+                    // ignore the operator
+                    unexpected(diagnostics, &token, &["(", "!"]);
+                    tokens.push_front(token);
+                    States::None
+                }
             },
-            States::OrAndNot(v) => match token.item {
-                Token2::Space => Err("unexpected Space".to_owned())?,
-                Token2::Colon => Err("unexpected Colon".to_owned())?,
-                Token2::ExclamationMark => Err("unexpected ExclamationMark".to_owned())?,
+            States::OrAndNot(v, _) => match token.item {
                 Token2::Group((tokens, tokens_pos)) => States::Array {
                     not: true,
                     or: v,
                     tokens,
                     tokens_pos,
                 },
-                Token2::Or => Err("unexpected Or".to_owned())?,
-                Token2::And => Err("unexpected And".to_owned())?,
-                Token2::Eq => Err("unexpected Eq".to_owned())?,
-                Token2::Smaller => Err("unexpected Smaller".to_owned())?,
-                Token2::Bigger => Err("unexpected Bigger".to_owned())?,
-                Token2::Word(_) => Err("unexpected Word".to_owned())?,
+                _ => {
+                    unexpected(diagnostics, &token, &["("]);
+                    tokens.push_front(token);
+                    States::None
+                }
             },
             States::Array {
                 or,
                 not,
-                tokens,
+                tokens: array_tokens,
                 tokens_pos,
             } => {
-                let mut array = tokens_to_array(kind_map, tokens_pos, tokens, or_default)?;
+                let mut array =
+                    tokens_to_array(kind_map, diagnostics, tokens_pos, array_tokens, or_default);
                 array.item.or = or;
                 array.item.not = not;
+                tokens.push_front(token);
                 States::ArrayFinish(array)
             }
             States::ArrayFinish(mut arr) => match token.item {
+                Token2::Space => States::ArrayFinish(arr),
                 Token2::Or => {
                     arr.item.or_post = Some(true);
                     items.push(ItemOrArray::Array(arr));
@@ -528,10 +606,6 @@ fn tokens_to_array(
             },
             States::ValueBuilder(key, not, value) => match token.item {
                 Token2::Space => States::ValueBuilder(key, not, value),
-                Token2::Colon => Err("unexpected Colon".to_owned())?,
-                Token2::ExclamationMark => Err("unexpected ExclamationMark".to_owned())?,
-                Token2::Or => Err("unexpected Or".to_owned())?,
-                Token2::And => Err("unexpected And".to_owned())?,
                 Token2::Eq => States::ValueBuilder(key, not, Some(join(value, "=", token.pos))),
                 Token2::Smaller => {
                     States::ValueBuilder(key, not, Some(join(value, "<", token.pos)))
@@ -539,27 +613,7 @@ fn tokens_to_array(
                 Token2::Bigger => States::ValueBuilder(key, not, Some(join(value, ">", token.pos))),
                 Token2::Word(w) => States::ValueBuilder(key, not, Some(join(value, &w, token.pos))),
                 Token2::Group((tokens, tokens_pos)) => {
-                    items.push(ItemOrArray::Item(WPos::new(
-                        value
-                            .as_ref()
-                            .map(|v| v.pos.join(key.pos))
-                            .unwrap_or(key.pos),
-                        Item {
-                            not,
-                            or_post: None,
-                            data: ItemData {
-                                value: match value {
-                                    Some(v) => kind_map
-                                        .get(&key.item)
-                                        .copied()
-                                        .unwrap_or_default()
-                                        .parse(&v.item)?,
-                                    None => ItemValue::None,
-                                },
-                                name: key,
-                            },
-                        },
-                    )));
+                    items.extend(value_item(kind_map, diagnostics, key, not, value));
                     States::Array {
                         or: or_default,
                         not: false,
@@ -567,79 +621,49 @@ fn tokens_to_array(
                         tokens_pos,
                     }
                 }
+                Token2::Colon | Token2::ExclamationMark | Token2::Or | Token2::And => {
+                    unexpected(diagnostics, &token, &["value", "("]);
+                    States::ValueBuilder(key, not, value)
+                }
             },
         };
     }
 
     match states {
-        States::None => {}
-        States::StrBuilder(wpos) => items.push(ItemOrArray::Item(WPos::new(
-            wpos.pos,
-            Item {
-                not: false,
-                or_post: None,
-                data: ItemData {
-                    name: wpos,
-                    value: ItemValue::None,
-                },
-            },
-        ))),
-        States::StrNo(wpos) => items.push(ItemOrArray::Item(WPos::new(
-            wpos.pos,
-            Item {
-                not: true,
-                or_post: None,
-                data: ItemData {
-                    name: wpos,
-                    value: ItemValue::None,
-                },
-            },
-        ))),
-        States::Str(wpos) => items.push(ItemOrArray::Item(WPos::new(
-            wpos.pos,
-            Item {
-                not: false,
-                or_post: None,
-                data: ItemData {
-                    name: wpos,
-                    value: ItemValue::None,
-                },
-            },
-        ))),
-        States::ValueBuilder(wpos, not, wpos1) => items.push(ItemOrArray::Item(WPos::new(
-            wpos.pos,
-            Item {
-                not,
-                or_post: None,
-                data: ItemData {
-                    value: match wpos1 {
-                        Some(v) => kind_map
-                            .get(&wpos.item)
-                            .copied()
-                            .unwrap_or_default()
-                            .parse(&v.item)?,
-                        None => ItemValue::None,
-                    },
-                    name: wpos,
-                },
-            },
-        ))),
-        States::OrAnd(_) => {}
-        States::OrAndNot(_) => {}
+        States::None => {
+            if let Some(pos) = operator {
+                diagnostics.push(Diagnostic::new(
+                    pos,
+                    "expected an item after the operator".to_owned(),
+                    &["word", "("],
+                ))
+            }
+        }
+        States::StrBuilder(wpos) => items.push(enum_item(wpos, false, None)),
+        States::StrNo(wpos) => items.push(enum_item(wpos, true, None)),
+        States::Str(wpos) => items.push(enum_item(wpos, false, None)),
+        States::ValueBuilder(wpos, not, wpos1) => {
+            items.extend(value_item(kind_map, diagnostics, wpos, not, wpos1))
+        }
+        States::OrAnd(_, pos) | States::OrAndNot(_, pos) => diagnostics.push(Diagnostic::new(
+            pos,
+            "expected a group after the operator".to_owned(),
+            &["("],
+        )),
         States::Array {
             or,
             not,
             tokens,
             tokens_pos,
         } => {
-            let mut array = tokens_to_array(kind_map, tokens_pos, tokens, or_default)?;
+            let mut array = tokens_to_array(kind_map, diagnostics, tokens_pos, tokens, or_default);
             array.item.or = or;
             array.item.not = not;
             items.push(ItemOrArray::Array(array));
         }
         States::ArrayFinish(wpos) => items.push(ItemOrArray::Array(wpos)),
     }
-    Ok(WPos::new(
+    WPos::new(
         range,
         Array {
             or: or_default,
@@ -647,7 +671,7 @@ fn tokens_to_array(
             or_post: None,
             items,
         },
-    ))
+    )
 }
 
 fn process_words(tokens: Vec<WPos<Token>>) -> Vec<WPos<Token>> {
@@ -779,3 +803,106 @@ fn parse<T: FromStr>(s: &str) -> Result<(bool, bool, T), String> {
             .map_err(|_| format!("Failed to parse: {}", num))?,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{parse_str, parse_with_diagnostics, shape::ItemOrArray, Diagnostic, ItemKind};
+
+    fn diagnostics(s: &str) -> Vec<(usize, usize, String)> {
+        parse_with_diagnostics(s, false, &HashMap::new())
+            .diagnostics
+            .into_iter()
+            .map(|v| (v.start, v.end, v.message))
+            .collect()
+    }
+
+    fn names(s: &str) -> Vec<String> {
+        parse_with_diagnostics(s, false, &HashMap::new())
+            .query
+            .item
+            .items
+            .iter()
+            .map(|v| match v {
+                ItemOrArray::Item(item) => item.item.data.name.item.clone(),
+                ItemOrArray::Array(_) => "()".to_owned(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_query_has_no_diagnostics() {
+        assert!(diagnostics("a | b tag:x (c & !d:y)").is_empty());
+        assert!(parse_str("a (b)", false, &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn unbalanced_parentheses() {
+        assert_eq!(diagnostics("(a"), vec![(0, 1, "unclosed `(`".to_owned())]);
+        assert_eq!(
+            diagnostics("a) b"),
+            vec![(1, 2, "unmatched `)`".to_owned())]
+        );
+        // items after the stray `)` are still parsed
+        assert_eq!(names("a) b"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn dangling_operators() {
+        assert_eq!(
+            diagnostics("a &"),
+            vec![(2, 3, "expected an item after the operator".to_owned())]
+        );
+        assert_eq!(
+            diagnostics("(a) |"),
+            vec![(4, 5, "expected an item after the operator".to_owned())]
+        );
+        assert_eq!(names("a &"), vec!["a"]);
+    }
+
+    #[test]
+    fn recovers_after_unexpected_tokens() {
+        let parsed = parse_with_diagnostics("foo! bar", false, &HashMap::new());
+        assert_eq!(
+            parsed.diagnostics,
+            vec![Diagnostic {
+                start: 4,
+                end: 5,
+                message: "unexpected space".to_owned(),
+                expected: vec![":"],
+            }]
+        );
+        assert_eq!(names("foo! bar"), vec!["foo", "bar"]);
+        assert_eq!(names("(a)|(b)"), vec!["()", "()"]);
+        // the operator belongs to the first group
+        let parsed = parse_with_diagnostics("(a) | (b)", false, &HashMap::new());
+        match &parsed.query.item.items[..] {
+            [ItemOrArray::Array(a), ItemOrArray::Array(b)] => {
+                assert_eq!(a.item.or_post, Some(true));
+                assert!(!b.item.or);
+            }
+            _ => panic!("expected two groups"),
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_skipped() {
+        let kinds = HashMap::from([("c".to_owned(), ItemKind::CmpInt)]);
+        let parsed = parse_with_diagnostics("c:>x a", false, &kinds);
+        assert_eq!(parsed.diagnostics.len(), 1);
+        assert_eq!(
+            (parsed.diagnostics[0].start, parsed.diagnostics[0].end),
+            (2, 6)
+        );
+        assert!(parse_str("c:>x", false, &kinds).is_err());
+    }
+
+    #[test]
+    fn diagnostics_json_is_escaped() {
+        let json = parse_with_diagnostics("a :\"b", false, &HashMap::new()).to_json();
+        assert!(
+            json.contains(r#""diagnostics": [{"start": 2, "end": 3, "message": "unexpected `:`""#)
+        );
+    }
+}
@@ -4,6 +4,7 @@ use aes_gcm::{
 };
 use bytes::{Bytes, BytesMut};
 use futures_core::Stream;
+use futures_util::{stream, StreamExt};
 use pin_project_lite::pin_project;
 use std::{
     collections::VecDeque,
@@ -13,17 +14,21 @@ use std::{
 };
 
 use crate::backends::{
-    AesOptions, ByteStream, KeyValueStore, Object, Options, StorageReader, StorageWriter,
+    AesOptions, ByteRange, ByteStream, ContentRange, KeyValueStore, Object, Options,
+    RangeNotSatisfiable, StorageReader, StorageWriter,
 };
 
 const TAG_LEN: usize = 16;
 const LEN_LEN: usize = 4;
+/// Longest range which is decrypted into memory before it is returned
+const MAX_RANGE: u64 = 8 * 1024 * 1024;
 
 fn try_parse_frames(
     cipher: &Aes256Gcm,
     nonce_prefix: &[u8; 8],
     counter: &mut u32,
     aad: &[u8],
+    skip: &mut u64,
     buf: &mut BytesMut,
     out_queue: &mut VecDeque<Result<Bytes, io::Error>>,
 ) -> Result<(), io::Error> {
@@ -46,6 +51,12 @@ fn try_parse_frames(
         }
 
         let mut frame = buf.split_to(needed);
+        // frames before the range don't need to be decrypted, only counted
+        if len as u64 <= *skip {
+            *skip -= len as u64;
+            *counter = counter.wrapping_add(1);
+            continue;
+        }
         let payload = &mut frame[LEN_LEN..];
         let (ct, rest) = payload.split_at_mut(len);
         let tag_bytes: &[u8] = &rest[..TAG_LEN];
@@ -64,7 +75,8 @@ fn try_parse_frames(
             .decrypt_in_place_detached(nonce, aad, ct, tag)
             .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "AES-GCM auth failed"))?;
 
-        out_queue.push_back(Ok(Bytes::copy_from_slice(ct)));
+        out_queue.push_back(Ok(Bytes::copy_from_slice(&ct[*skip as usize..])));
+        *skip = 0;
     }
 }
 
@@ -76,12 +88,30 @@ pin_project! {
         nonce_prefix: [u8; 8],
         counter: u32,
         aad: Vec<u8>,
+        skip: u64,
         buf: BytesMut,
         out_queue: VecDeque<Result<Bytes, io::Error>>,
         done: bool,
     }
 }
 
+/// Ends the stream after `len` bytes, without reading the rest of the input
+fn limit_stream(mut source: ByteStream, mut len: u64) -> ByteStream {
+    Box::pin(stream::poll_fn(move |cx| {
+        if len == 0 {
+            return Poll::Ready(None);
+        }
+        match source.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                let take = len.min(chunk.len() as u64);
+                len -= take;
+                Poll::Ready(Some(Ok(chunk.slice(..take as usize))))
+            }
+            other => other,
+        }
+    }))
+}
+
 pub struct EncryptedStorage<S, K> {
     inner: S,
     mapper: K,
@@ -99,36 +129,76 @@ where
     S: StorageReader,
     K: KeyValueStore<AesOptions>,
 {
+    /// Frames have different lengths, so ranges are read from the start of the object.
+    /// The total length is unknown, ranges without an end or longer than [`MAX_RANGE`] are
+    /// ignored. A range is decrypted before the object is returned, so its end is clamped to the
+    /// bytes which exist & a start past the end is not satisfiable
     async fn get(&self, key: &str, options: &Options) -> Result<Object, io::Error> {
         if options.content_length_only {
             return self.inner.get(key, options).await;
         }
 
-        let mut obj = self.inner.get(key, options).await?;
         let aes = self
             .mapper
             .get(key)
             .await
             .map_err(|err| io::Error::other(err.to_string()))?;
 
-        match &aes {
-            Some(options) => {
-                let decrypting = Aes256GcmChunkedDecrypt::new(
-                    obj.stream,
-                    options.key,
-                    options.nonce,
-                    options.counter,
-                    options.aad.clone(),
-                );
-
-                obj.stream = Box::pin(decrypting);
-
-                obj.content_length = None;
-
-                Ok(obj)
+        let Some(aes) = aes else {
+            return self.inner.get(key, options).await;
+        };
+
+        let range = match options.range {
+            Some(ByteRange::From {
+                start,
+                end: Some(end),
+            }) => {
+                if end < start {
+                    return Err(RangeNotSatisfiable::io(None));
+                }
+                (end - start < MAX_RANGE).then_some((start, end))
             }
-            None => Ok(obj),
+            _ => None,
+        };
+        let mut obj = self
+            .inner
+            .get(
+                key,
+                &Options {
+                    range: None,
+                    ..options.clone()
+                },
+            )
+            .await?;
+
+        let decrypting =
+            Aes256GcmChunkedDecrypt::new(obj.stream, aes.key, aes.nonce, aes.counter, aes.aad);
+
+        let Some((start, end)) = range else {
+            obj.stream = Box::pin(decrypting);
+            obj.content_length = None;
+            obj.range = None;
+            return Ok(obj);
+        };
+
+        let mut ranged = limit_stream(Box::pin(decrypting.skip(start)), end - start + 1);
+        let mut body = BytesMut::new();
+        while let Some(chunk) = ranged.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        if body.is_empty() {
+            return Err(RangeNotSatisfiable::io(None));
         }
+        let length = body.len() as u64;
+        obj.stream = Box::pin(stream::once(async move { Ok(body.freeze()) }));
+        obj.content_length = Some(length);
+        obj.range = Some(ContentRange {
+            start,
+            end: start + length - 1,
+            total: None,
+        });
+
+        Ok(obj)
     }
 }
 
@@ -153,11 +223,18 @@ where
             nonce_prefix,
             counter: counter0,
             aad,
+            skip: 0,
             buf: BytesMut::new(),
             out_queue: VecDeque::new(),
             done: false,
         }
     }
+
+    /// Drops the first `bytes` of the plaintext
+    pub fn skip(mut self, bytes: u64) -> Self {
+        self.skip = bytes;
+        self
+    }
 }
 
 impl<S> Stream for Aes256GcmChunkedDecrypt<S>
@@ -193,6 +270,7 @@ where
                         this.nonce_prefix,
                         this.counter,
                         this.aad,
+                        this.skip,
                         this.buf,
                         this.out_queue,
                     ) {
@@ -207,6 +285,7 @@ where
                         this.nonce_prefix,
                         this.counter,
                         this.aad,
+                        this.skip,
                         this.buf,
                         this.out_queue,
                     ) {
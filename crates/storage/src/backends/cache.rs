@@ -90,14 +90,23 @@ impl<S: StorageReader> StorageReader for CacheBackend<S> {
             Err(e) => return Err(e),
         }
 
-        let obj = self.inner.get(key, options).await?;
-
         if options.cache_download {
+            // the whole object is cached, the range is read from the cache
+            let obj = self
+                .inner
+                .get(
+                    key,
+                    &Options {
+                        range: None,
+                        ..options.clone()
+                    },
+                )
+                .await?;
             self.sr.write(key, obj.stream).await?;
 
             self.sr.get(key, options).await
         } else {
-            Ok(obj)
+            self.inner.get(key, options).await
         }
     }
 }
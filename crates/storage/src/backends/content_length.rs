@@ -9,7 +9,9 @@ use std::{
 use futures_util::{stream, TryStreamExt as _};
 use serde::{Deserialize, Serialize};
 
-use crate::backends::{ByteStream, KeyValueStore, Object, Options, StorageReader, StorageWriter};
+use crate::backends::{
+    ByteRange, ByteStream, KeyValueStore, Object, Options, StorageReader, StorageWriter,
+};
 
 pub struct ContentLengthStorage<S, K> {
     inner: S,
@@ -65,18 +67,42 @@ where
                 content_type: None,
                 etag: None,
                 last_modified: None,
+                range: None,
             });
         }
 
-        let mut obj = self.inner.get(key, options).await?;
-
-        if let Some(content_length) = self
+        let Some(content_length) = self
             .content_lengths
             .get(key)
             .await
             .map_err(|err| io::Error::other(err.to_string()))?
-        {
-            obj.content_length = Some(content_length.val);
+        else {
+            return self.inner.get(key, options).await;
+        };
+        let total = content_length.val;
+
+        // resolved here, so backends which don't know the length get a closed range
+        let range = options.resolve_range(total)?;
+        let mut obj = self
+            .inner
+            .get(
+                key,
+                &Options {
+                    range: range.map(|v| ByteRange::From {
+                        start: v.start,
+                        end: Some(v.end),
+                    }),
+                    ..options.clone()
+                },
+            )
+            .await?;
+
+        match obj.range.as_mut() {
+            Some(applied) => {
+                applied.total = Some(total);
+                obj.content_length = Some(applied.length());
+            }
+            None => obj.content_length = Some(total),
         }
 
         Ok(obj)
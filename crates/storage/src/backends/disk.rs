@@ -1,12 +1,15 @@
 use std::{
-    io,
+    io::{self, SeekFrom},
     path::{Component, Path, PathBuf},
 };
 
 use async_tempfile::TempFile;
 use bytes::Bytes;
 use futures_util::{stream, StreamExt, TryStreamExt};
-use tokio::{fs::File, io::AsyncWriteExt as _};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt as _, AsyncSeekExt as _, AsyncWriteExt as _},
+};
 use tokio_util::io::ReaderStream;
 
 use crate::backends::{ByteStream, Object, Options, StorageReader, StorageWriter};
@@ -118,17 +121,22 @@ impl StorageWriter for DiskStorage {
 
 #[async_trait::async_trait]
 impl StorageReader for DiskStorage {
-    async fn get(&self, key: &str, _: &Options) -> Result<Object, std::io::Error> {
+    async fn get(&self, key: &str, options: &Options) -> Result<Object, std::io::Error> {
         let path = self.key_path(key)?;
         let meta = tokio::fs::metadata(&path).await?;
         let mime = mime_guess::from_path(&path).first();
         let lm = meta.modified().ok();
 
-        let len = Some(meta.len());
+        let range = options.resolve_range(meta.len())?;
+        let len = Some(range.map_or(meta.len(), |v| v.length()));
+        let (start, take) = range.map_or((0, u64::MAX), |v| (v.start, v.length()));
 
         let stream = stream::once(async move {
-            let file = File::open(path).await?;
-            let stream = ReaderStream::new(file).map(|r| r.map(Bytes::from));
+            let mut file = File::open(path).await?;
+            if start > 0 {
+                file.seek(SeekFrom::Start(start)).await?;
+            }
+            let stream = ReaderStream::new(file.take(take)).map(|r| r.map(Bytes::from));
             Ok::<_, io::Error>(stream)
         })
         .try_flatten();
@@ -139,6 +147,7 @@ impl StorageReader for DiskStorage {
             content_type: mime,
             etag: None,
             last_modified: lm,
+            range,
         })
     }
 }
@@ -146,6 +155,7 @@ impl StorageReader for DiskStorage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backends::{ByteRange, RangeNotSatisfiable};
     use uuid::Uuid;
 
     fn test_root() -> PathBuf {
@@ -182,4 +192,43 @@ mod tests {
         tokio::fs::remove_dir_all(root).await?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn get_reads_ranges() -> Result<(), io::Error> {
+        let root = test_root();
+        tokio::fs::create_dir_all(&root).await?;
+
+        let storage = DiskStorage::new(&root);
+        let key = "range/file.bin";
+        let write_stream: ByteStream = Box::pin(stream::once(async move {
+            Ok::<Bytes, io::Error>(Bytes::from_static(b"0123456789"))
+        }));
+        storage.write(key, write_stream).await?;
+
+        let options = Options {
+            range: Some(ByteRange::Suffix(3)),
+            ..Options::default()
+        };
+        let object = storage.get(key, &options).await?;
+        assert_eq!(object.content_length, Some(3));
+        assert_eq!(object.range.map(|v| (v.start, v.end)), Some((7, 9)));
+        let body: Vec<Bytes> = object.stream.try_collect().await?;
+        assert_eq!(body.concat(), b"789");
+
+        let options = Options {
+            range: Some(ByteRange::From {
+                start: 10,
+                end: None,
+            }),
+            ..Options::default()
+        };
+        let err = match storage.get(key, &options).await {
+            Ok(_) => panic!("range after the end should not be satisfiable"),
+            Err(err) => err,
+        };
+        assert!(RangeNotSatisfiable::find(&err).is_some());
+
+        tokio::fs::remove_dir_all(root).await?;
+        Ok(())
+    }
 }
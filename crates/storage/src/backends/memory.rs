@@ -62,13 +62,17 @@ impl StorageWriter for MemStorage {
 }
 #[async_trait::async_trait]
 impl StorageReader for MemStorage {
-    async fn get(&self, key: &str, _: &Options) -> Result<Object, std::io::Error> {
+    async fn get(&self, key: &str, options: &Options) -> Result<Object, std::io::Error> {
         let map = self.inner.read().await;
-        let data = map
+        let mut data = map
             .get(key)
             .cloned()
             .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "not found"))?;
 
+        let range = options.resolve_range(data.len() as u64)?;
+        if let Some(range) = range {
+            data = data.slice(range.start as usize..=range.end as usize);
+        }
         let len = data.len() as u64;
 
         let stream = stream::once(async move { Ok::<Bytes, std::io::Error>(data) });
@@ -80,6 +84,7 @@ impl StorageReader for MemStorage {
             content_type: None,
             last_modified: None,
             etag: None,
+            range,
         })
     }
 }
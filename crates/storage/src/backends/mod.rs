@@ -26,7 +26,7 @@ use rand::{rngs::OsRng, TryRngCore};
 pub use s3::{S3Storage, S3StorageOptions, S3UploadAcl};
 use serde::{Deserialize, Serialize};
 
-use std::{pin::Pin, sync::Arc, time::SystemTime};
+use std::{io, pin::Pin, sync::Arc, time::SystemTime};
 
 use bytes::Bytes;
 use futures_core::Stream;
@@ -39,6 +39,8 @@ pub struct Object {
     pub content_type: Option<mime::Mime>,
     pub etag: Option<String>,
     pub last_modified: Option<SystemTime>,
+    /// Set if [`Options::range`] was applied, `content_length` is the length of the range then
+    pub range: Option<ContentRange>,
 }
 
 //TODO: cache policy: when set cache_download => on manga image + download next 2 chapters and 2 prev; cleanup cache: on next chapter
@@ -72,10 +74,82 @@ impl AesOptions {
     }
 }
 
+/// Byte range of a read, like the http `Range` header. Ends are inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Till the end of the object if `end` is `None`
+    From { start: u64, end: Option<u64> },
+    /// Last n bytes
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// First & last byte in an object of `total` bytes, `None` if no byte is in range
+    pub fn resolve(&self, total: u64) -> Option<(u64, u64)> {
+        let last = total.checked_sub(1)?;
+        match *self {
+            Self::From { start, end } => {
+                let end = end.map_or(last, |v| v.min(last));
+                (start <= end).then_some((start, end))
+            }
+            Self::Suffix(0) => None,
+            Self::Suffix(n) => Some((total.saturating_sub(n), last)),
+        }
+    }
+
+    /// Value of the http `Range` header
+    pub fn header_value(&self) -> String {
+        match self {
+            Self::From { start, end: None } => format!("bytes={start}-"),
+            Self::From {
+                start,
+                end: Some(end),
+            } => format!("bytes={start}-{end}"),
+            Self::Suffix(n) => format!("bytes=-{n}"),
+        }
+    }
+}
+
+/// Part of the object which was read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    pub start: u64,
+    /// Inclusive
+    pub end: u64,
+    /// Length of the whole object, `None` if the backend doesn't know it
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// The requested range has no byte in the object.
+/// Returned inside of an [`io::Error`] with kind [`io::ErrorKind::InvalidInput`]
+#[derive(Debug, thiserror::Error)]
+#[error("range not satisfiable")]
+pub struct RangeNotSatisfiable {
+    pub total: Option<u64>,
+}
+
+impl RangeNotSatisfiable {
+    pub(crate) fn io(total: Option<u64>) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, Self { total })
+    }
+
+    pub fn find(err: &io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
 #[derive(Clone)]
 pub struct Options {
     pub cache_download: bool,
     pub content_length_only: bool,
+    /// Only read these bytes. Backends which can't apply it return the whole object without [`Object::range`]
+    pub range: Option<ByteRange>,
 }
 
 impl Default for Options {
@@ -83,10 +157,28 @@ impl Default for Options {
         Options {
             cache_download: false,
             content_length_only: false,
+            range: None,
         }
     }
 }
 
+impl Options {
+    /// Applies [`Self::range`] to an object of `total` bytes
+    pub(crate) fn resolve_range(&self, total: u64) -> io::Result<Option<ContentRange>> {
+        let Some(range) = self.range else {
+            return Ok(None);
+        };
+        let (start, end) = range
+            .resolve(total)
+            .ok_or_else(|| RangeNotSatisfiable::io(Some(total)))?;
+        Ok(Some(ContentRange {
+            start,
+            end,
+            total: Some(total),
+        }))
+    }
+}
+
 #[async_trait::async_trait]
 pub trait StorageReader: Send + Sync + 'static {
     /// reads file as stream
@@ -352,6 +444,7 @@ mod tests {
                 content_type: None,
                 etag: None,
                 last_modified: None,
+                range: None,
             })
         }
     }
@@ -382,4 +475,175 @@ mod tests {
         assert_eq!(got, payload);
         Ok(())
     }
+
+    fn ranged(range: ByteRange) -> Options {
+        Options {
+            range: Some(range),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn byte_ranges_resolve_like_http() {
+        let range = |start, end| ByteRange::From { start, end };
+        assert_eq!(range(0, Some(9)).resolve(100), Some((0, 9)));
+        assert_eq!(range(10, None).resolve(100), Some((10, 99)));
+        assert_eq!(range(95, Some(200)).resolve(100), Some((95, 99)));
+        assert_eq!(ByteRange::Suffix(10).resolve(100), Some((90, 99)));
+        assert_eq!(ByteRange::Suffix(200).resolve(100), Some((0, 99)));
+        assert_eq!(range(100, None).resolve(100), None);
+        assert_eq!(range(9, Some(1)).resolve(100), None);
+        assert_eq!(ByteRange::Suffix(0).resolve(100), None);
+        assert_eq!(range(0, None).resolve(0), None);
+    }
+
+    #[tokio::test]
+    async fn mem_reads_ranges() -> Result<(), std::io::Error> {
+        let storage = MemStorage::new();
+        let payload = b"0123456789abcdefghij";
+        storage
+            .write("plain/range", stream_from_parts(vec![payload.to_vec()]))
+            .await?;
+
+        let obj = storage
+            .get(
+                "plain/range",
+                &ranged(ByteRange::From {
+                    start: 5,
+                    end: Some(9),
+                }),
+            )
+            .await?;
+        assert_eq!(obj.content_length, Some(5));
+        assert_eq!(
+            obj.range,
+            Some(ContentRange {
+                start: 5,
+                end: 9,
+                total: Some(20)
+            })
+        );
+        assert_eq!(read_all(obj.stream).await?, b"56789");
+
+        let err = match storage
+            .get(
+                "plain/range",
+                &ranged(ByteRange::From {
+                    start: 20,
+                    end: None,
+                }),
+            )
+            .await
+        {
+            Ok(_) => panic!("range after the end should not be satisfiable"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            RangeNotSatisfiable::find(&err).map(|v| v.total),
+            Some(Some(20))
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "encode")]
+    #[tokio::test]
+    async fn aes_reads_ranges_across_frames() -> Result<(), std::io::Error> {
+        let storage = EncryptedStorage::new(ChunkingMemStorage::new(5), TestAesMapper::default());
+        let payload = b"encrypted-ranges-span-several-frames-of-different-sizes".to_vec();
+        storage
+            .write(
+                "enc/range",
+                stream_from_parts(vec![
+                    payload[..7].to_vec(),
+                    payload[7..30].to_vec(),
+                    payload[30..].to_vec(),
+                ]),
+            )
+            .await?;
+
+        for (start, end) in [(0, 6), (3, 12), (7, 29), (10, 40), (31, 54), (50, 100)] {
+            let obj = storage
+                .get(
+                    "enc/range",
+                    &ranged(ByteRange::From {
+                        start,
+                        end: Some(end),
+                    }),
+                )
+                .await?;
+            let end = end.min(payload.len() as u64 - 1);
+            assert_eq!(obj.range.map(|v| (v.start, v.end)), Some((start, end)));
+            assert_eq!(obj.content_length, Some(end - start + 1));
+            assert_eq!(
+                read_all(obj.stream).await?,
+                &payload[start as usize..end as usize + 1]
+            );
+        }
+
+        let err = match storage
+            .get(
+                "enc/range",
+                &ranged(ByteRange::From {
+                    start: 60,
+                    end: Some(70),
+                }),
+            )
+            .await
+        {
+            Ok(_) => panic!("range after the end should not be satisfiable"),
+            Err(err) => err,
+        };
+        assert!(RangeNotSatisfiable::find(&err).is_some());
+
+        let obj = storage
+            .get("enc/range", &ranged(ByteRange::Suffix(5)))
+            .await?;
+        assert_eq!(obj.range, None, "the total length is unknown");
+        assert_eq!(read_all(obj.stream).await?, payload);
+        Ok(())
+    }
+
+    #[cfg(feature = "encode")]
+    #[tokio::test]
+    async fn content_length_resolves_ranges_for_aes() -> Result<(), std::io::Error> {
+        let storage = ContentLengthStorage::new(
+            EncryptedStorage::new(MemStorage::new(), TestAesMapper::default()),
+            InMemoryKeyValueStore::new(),
+        );
+        let payload = b"content-length-knows-the-plaintext-length";
+        storage
+            .write(
+                "enc/suffix",
+                stream_from_parts(vec![payload[..9].to_vec(), payload[9..].to_vec()]),
+            )
+            .await?;
+
+        let obj = storage
+            .get("enc/suffix", &ranged(ByteRange::Suffix(6)))
+            .await?;
+        let total = payload.len() as u64;
+        assert_eq!(obj.content_length, Some(6));
+        assert_eq!(
+            obj.range,
+            Some(ContentRange {
+                start: total - 6,
+                end: total - 1,
+                total: Some(total)
+            })
+        );
+        assert_eq!(read_all(obj.stream).await?, b"length");
+
+        let obj = storage
+            .get(
+                "enc/suffix",
+                &ranged(ByteRange::From {
+                    start: 30,
+                    end: Some(1000),
+                }),
+            )
+            .await?;
+        assert_eq!(obj.range.map(|v| v.end), Some(total - 1));
+        assert_eq!(read_all(obj.stream).await?, &payload[30..]);
+        Ok(())
+    }
 }
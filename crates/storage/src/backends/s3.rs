@@ -6,6 +6,7 @@ use std::{
 use aws_config::BehaviorVersion;
 use aws_sdk_s3::{
     config::{Credentials, Region},
+    error::ProvideErrorMetadata as _,
     primitives::ByteStream as S3ByteStream,
     types::ObjectCannedAcl,
     Client,
@@ -16,7 +17,10 @@ use serde::{Deserialize, Serialize};
 use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
-use crate::backends::{ByteStream, KeyValueStore, Object, Options, StorageReader, StorageWriter};
+use crate::backends::{
+    ByteStream, ContentRange, KeyValueStore, Object, Options, RangeNotSatisfiable, StorageReader,
+    StorageWriter,
+};
 
 #[derive(Clone, Copy, Debug, Default)]
 pub enum S3UploadAcl {
@@ -329,7 +333,7 @@ impl<K> StorageReader for S3Storage<K>
 where
     K: KeyValueStore<Payload>,
 {
-    async fn get(&self, key: &str, _: &Options) -> Result<Object, io::Error> {
+    async fn get(&self, key: &str, options: &Options) -> Result<Object, io::Error> {
         Self::validate_key(key)?;
         let object_id = Self::required_object_id(&self.key_map, key).await?;
 
@@ -338,9 +342,17 @@ where
             .get_object()
             .bucket(&self.bucket)
             .key(object_id)
+            .set_range(options.range.map(|v| v.header_value()))
             .send()
             .await
-            .map_err(|err| Self::s3_err("get_object", err))?;
+            .map_err(|err| {
+                let code = err.as_service_error().and_then(|v| v.code());
+                if options.range.is_some() && code == Some("InvalidRange") {
+                    RangeNotSatisfiable::io(None)
+                } else {
+                    Self::s3_err("get_object", err)
+                }
+            })?;
 
         let content_length = out
             .content_length()
@@ -352,6 +364,7 @@ where
         let last_modified = out
             .last_modified()
             .and_then(|value| std::time::SystemTime::try_from(*value).ok());
+        let range = out.content_range().and_then(parse_content_range);
         let stream: ByteStream = Box::pin(ReaderStream::new(out.body.into_async_read()));
 
         Ok(Object {
@@ -360,17 +373,38 @@ where
             content_type,
             etag,
             last_modified,
+            range,
         })
     }
 }
 
+/// Parses `bytes 0-99/1234`
+fn parse_content_range(value: &str) -> Option<ContentRange> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    Some(ContentRange {
+        start: start.parse().ok()?,
+        end: end.parse().ok()?,
+        total: total.parse().ok(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::S3Storage;
+    use super::{parse_content_range, S3Storage};
     use crate::backends::{s3::Payload, InMemoryKeyValueStore, KeyValueStore};
 
     type DefaultS3Storage = S3Storage<InMemoryKeyValueStore<Payload>>;
 
+    #[test]
+    fn parses_content_range() {
+        let range = parse_content_range("bytes 10-19/100").expect("valid range");
+        assert_eq!((range.start, range.end, range.total), (10, 19, Some(100)));
+        let range = parse_content_range("bytes 0-0/*").expect("valid range");
+        assert_eq!(range.total, None);
+        assert!(parse_content_range("bytes */100").is_none());
+    }
+
     #[test]
     fn validate_key_accepts_safe_relative_paths() {
         assert!(DefaultS3Storage::validate_key("temp/abc").is_ok());
@@ -3,15 +3,26 @@ use std::{
     sync::Arc,
 };
 
-use crate::{backends::StorageWriter, error::StorageResult, FileBuilderExt, StorageError};
+use crate::{
+    backends::StorageWriter,
+    error::StorageResult,
+    workers::{
+        hash::{HashHandle, HashSink},
+        variants,
+    },
+    FileBuilderExt, StorageError,
+};
 
 pub struct FileBuilder {
     pub(crate) temp_id: String,
     pub(crate) target_id: PathBuf,
     pub(crate) ext: Option<(&'static str, &'static str)>,
     pub(crate) dims: Option<(u32, u32)>,
+    pub(crate) size: Option<u64>,
     pub(crate) allowed_drop: bool,
     pub(crate) writer: Arc<dyn StorageWriter>,
+    /// Perceptual hash job of images. Cancelled when the builder is dropped without attaching it
+    pub(crate) hash: Option<HashHandle>,
 }
 
 impl FileBuilder {
@@ -34,6 +45,10 @@ impl FileBuilder {
         self.dims.map(|(_, h)| h)
     }
 
+    fn size_impl(&self) -> Option<u64> {
+        self.size
+    }
+
     fn ext_impl(&self) -> StorageResult<&str> {
         self.ext.map(|v| v.1).ok_or(StorageError::MissingExtension)
     }
@@ -49,6 +64,8 @@ impl FileBuilder {
         validate_target_key(&self.target_id)?;
         let key = self.target_id.to_string_lossy().to_string();
         self.writer.rename(&self.temp_id, &key).await?;
+        // the original might have been replaced
+        variants::invalidate(self.writer.as_ref(), &key).await;
         Ok(())
     }
 
@@ -109,6 +126,10 @@ macro_rules! builder_wrapper {
             fn ext(&self) -> StorageResult<&str> {
                 self.b.ext_impl()
             }
+
+            fn size(&self) -> Option<u64> {
+                self.b.size_impl()
+            }
         }
         impl From<FileBuilder> for $name {
             fn from(b: FileBuilder) -> Self {
@@ -173,6 +194,13 @@ impl CoverFileBuilder {
 }
 
 impl MangaPageFileBuilder {
+    /// Passes the perceptual hash to `sink` for `target` once it is computed
+    pub fn attach_hash(&mut self, target: String, sink: Arc<dyn HashSink>) {
+        if let Some(hash) = self.b.hash.take() {
+            hash.attach(target, sink);
+        }
+    }
+
     pub async fn build(self, page: usize) -> StorageResult<()> {
         self.b.add_path(page.to_string()).build().await
     }
@@ -4,8 +4,10 @@ mod error;
 mod temp;
 mod workers;
 
+pub use backends::ByteRange;
 pub use backends::CacheBackend;
 pub use backends::ContentLengthStorage;
+pub use backends::ContentRange;
 pub use backends::DelayStorage;
 #[cfg(feature = "disk")]
 pub use backends::DiskStorage;
@@ -16,6 +18,7 @@ pub use backends::KeyValueStore;
 pub use backends::MemStorage;
 pub use backends::Object;
 pub use backends::Options;
+pub use backends::RangeNotSatisfiable;
 pub use backends::StorageReader;
 pub use backends::StorageWriter;
 #[cfg(feature = "s3")]
@@ -23,6 +26,7 @@ pub use backends::{S3Storage, S3StorageOptions, S3UploadAcl};
 
 use std::{
     collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -46,9 +50,13 @@ use crate::{
     temp::{FileTempData, TempData},
     workers::{
         containers::{ContainerPayload, ContainerWorker, MagicContainerWorker},
+        hash::{HashHandle, HashPool, HashQueue},
         media::{file_to_bytestream, DefaultMediaWorker, MediaWorker},
+        variants::{self, encode_variant, variant_key},
     },
 };
+pub use workers::hash::{decode_hash, HashSink, HashSinkError};
+pub use workers::variants::{ImageVariant, VariantFormat, WIDTH_BUCKETS};
 
 #[cfg(test)]
 pub(crate) use workers::containers::{CHAPTER_MAGIC, MANGA_MAGIC};
@@ -62,12 +70,21 @@ pub struct StorageSystem {
     path: PathBuf,
     container_worker: Arc<dyn ContainerWorker + Send + Sync>,
     media_worker: Arc<dyn MediaWorker + Send + Sync>,
+    hashes: Arc<HashPool>,
+    /// Variants which are being generated, so each is only encoded once
+    variant_locks: dashmap::DashMap<String, Arc<Mutex<()>>>,
+    /// Variants which were larger than their original & the hash of that original,
+    /// so they are not encoded again until the original changes
+    larger_variants: dashmap::DashMap<String, u64>,
 }
 
 struct StoredFile {
     ext: Option<(&'static str, &'static str)>,
     dims: Option<(u32, u32)>,
+    size: Option<u64>,
     state: EntryState,
+    /// Hash job of the file, cancelled when the entry is dropped without being taken
+    hash: HashHandle,
 }
 
 enum EntryState {
@@ -80,11 +97,14 @@ impl StoredFile {
     fn new_processing(
         ext: Option<(&'static str, &'static str)>,
         state_tx: watch::Sender<()>,
+        hash: HashHandle,
     ) -> Self {
         Self {
             ext,
             dims: None,
+            size: None,
             state: EntryState::Processing { state_tx },
+            hash,
         }
     }
 
@@ -92,9 +112,11 @@ impl StoredFile {
         &mut self,
         handle: String,
         dims: Option<(u32, u32)>,
+        size: Option<u64>,
         ext: Option<(&'static str, &'static str)>,
     ) {
         self.dims = dims;
+        self.size = size;
         self.ext = ext;
         self.state = EntryState::Uploaded { handle };
     }
@@ -108,6 +130,8 @@ pub trait FileBuilderExt {
     fn width(&self) -> Option<u32>;
     fn height(&self) -> Option<u32>;
     fn ext(&self) -> StorageResult<&str>;
+    /// Bytes of the file, if known
+    fn size(&self) -> Option<u64>;
 }
 
 async fn get_random_image(folder: &Path) -> Option<PathBuf> {
@@ -190,6 +214,9 @@ impl StorageSystem {
             inflight_sem: Arc::new(Semaphore::new(Self::inflight_limit(transcode_limit))),
             container_worker,
             media_worker,
+            hashes: Arc::new(HashPool::new(transcode_limit)),
+            variant_locks: Default::default(),
+            larger_variants: Default::default(),
         }
     }
 
@@ -226,7 +253,11 @@ impl StorageSystem {
             let mut map = self.files.lock().await;
             map.insert(
                 id.clone(),
-                StoredFile::new_processing(None, state_tx.clone()),
+                StoredFile::new_processing(
+                    None,
+                    state_tx.clone(),
+                    HashHandle::new(self.hashes.clone(), id.clone()),
+                ),
             );
         }
 
@@ -234,21 +265,31 @@ impl StorageSystem {
         let sem = self.transcode_sem.clone();
         let writer = self.writer.clone();
         let media_worker = self.media_worker.clone();
+        let hashes = HashQueue {
+            pool: self.hashes.clone(),
+            id: id.clone(),
+        };
         let id2 = id.clone();
 
         tokio::spawn(async move {
             let _inflight_permit = inflight_permit;
-            let result =
-                std::panic::AssertUnwindSafe(media_worker.process_and_upload(source, writer, sem))
-                    .catch_unwind()
-                    .await;
+            let result = std::panic::AssertUnwindSafe(
+                media_worker.process_and_upload(source, writer, sem, hashes),
+            )
+            .catch_unwind()
+            .await;
 
             let updated = {
                 let mut map = files.lock().await;
                 if let Some(entry) = map.get_mut(&id2) {
                     match result {
                         Ok(Ok(upload)) => {
-                            entry.mark_uploaded(upload.handle, upload.dims, upload.ext);
+                            entry.mark_uploaded(
+                                upload.handle,
+                                upload.dims,
+                                upload.size,
+                                upload.ext,
+                            );
                         }
                         Ok(Err(error)) => {
                             entry.mark_failed(error);
@@ -306,16 +347,20 @@ impl StorageSystem {
         let key = file.temp_id.clone();
         drop(file);
 
-        let object = self.reader.get(&key, &Default::default()).await?;
-        let bytes = object
+        let bytes = self.read_bytes(&key).await?;
+        self.writer.delete(&key).await?;
+        Ok(bytes)
+    }
+
+    async fn read_bytes(&self, key: &str) -> StorageResult<Vec<u8>> {
+        let object = self.reader.get(key, &Default::default()).await?;
+        Ok(object
             .stream
             .try_fold(Vec::new(), |mut acc, chunk| async move {
                 acc.extend_from_slice(&chunk);
                 Ok(acc)
             })
-            .await?;
-        self.writer.delete(&key).await?;
-        Ok(bytes)
+            .await?)
     }
 
     pub async fn take(&self, id: FileId) -> StorageResult<FileBuilder> {
@@ -334,15 +379,18 @@ impl StorageSystem {
                         };
                         return Ok(FileBuilder {
                             dims: entry.dims,
+                            size: entry.size,
                             ext: entry.ext,
                             temp_id: handle,
                             target_id: PathBuf::new(),
                             allowed_drop: false,
                             writer: self.writer.clone(),
+                            hash: Some(entry.hash),
                         });
                     }
                     EntryState::Processing { state_tx } => Some(state_tx.subscribe()),
                     EntryState::Failed { .. } => {
+                        // dropping the entry cancels its hash job
                         let error = match map.remove(id.inner_ref()) {
                             Some(stored) => match stored.state {
                                 EntryState::Failed { error } => error,
@@ -367,9 +415,101 @@ impl StorageSystem {
 
     pub async fn delete_key(&self, key: &str) -> StorageResult<()> {
         self.writer.delete(key).await?;
+        variants::invalidate(self.writer.as_ref(), key).await;
         Ok(())
     }
 
+    /// Gets `key` as `variant`. Missing variants are generated from the original & stored next to it.
+    /// Originals which cannot be decoded are returned as they are
+    pub async fn get_variant(
+        &self,
+        key: &str,
+        variant: &ImageVariant,
+        options: &Options,
+    ) -> StorageResult<Object> {
+        let Some(target) = variant_key(key, variant) else {
+            return Ok(self.reader.get(key, options).await?);
+        };
+        let with_type = |mut obj: Object| {
+            obj.content_type = obj.content_type.or_else(|| variant.format.mime());
+            obj
+        };
+        if let Ok(obj) = self.reader.get(&target, options).await {
+            return Ok(with_type(obj));
+        }
+
+        let lock = self
+            .variant_locks
+            .entry(target.clone())
+            .or_default()
+            .clone();
+        let result = {
+            let _guard = lock.lock().await;
+            self.generate_variant(key, &target, variant).await
+        };
+        // requests which wait for the lock hold a clone & need the same entry
+        self.variant_locks
+            .remove_if(&target, |_, v| Arc::strong_count(v) == 2);
+        if result? {
+            Ok(with_type(self.reader.get(&target, options).await?))
+        } else {
+            Ok(self.reader.get(key, options).await?)
+        }
+    }
+
+    /// Returns false if the original cannot be decoded or is smaller than the variant
+    async fn generate_variant(
+        &self,
+        key: &str,
+        target: &str,
+        variant: &ImageVariant,
+    ) -> StorageResult<bool> {
+        // another request generated it while this one waited
+        if self.reader.get(target, &Default::default()).await.is_ok() {
+            return Ok(true);
+        }
+        let original = self.read_bytes(key).await?;
+        let hash = {
+            let mut hasher = DefaultHasher::new();
+            original.hash(&mut hasher);
+            hasher.finish()
+        };
+        if self.larger_variants.get(target).is_some_and(|v| *v == hash) {
+            return Ok(false);
+        }
+        let _permit = self
+            .transcode_sem
+            .acquire()
+            .await
+            .map_err(|_| ProcessingError::SemaphoreClosed)?;
+        let ext = key
+            .rsplit_once('.')
+            .map(|v| v.1.to_owned())
+            .unwrap_or_default();
+        let variant = *variant;
+        let encoded =
+            tokio::task::spawn_blocking(move || encode_variant(&original, &ext, &variant))
+                .await
+                .map_err(ProcessingError::ImageWorkerJoin)?;
+        match encoded {
+            Ok(None) => {
+                self.larger_variants.insert(target.to_owned(), hash);
+                Ok(false)
+            }
+            Ok(Some(bytes)) => {
+                let stream = futures_util::stream::once(async move {
+                    Ok::<_, std::io::Error>(bytes::Bytes::from(bytes))
+                });
+                self.writer.write(target, Box::pin(stream)).await?;
+                Ok(true)
+            }
+            Err(e) => {
+                log::warn!("Failed to create variant {target}: {e}");
+                Ok(false)
+            }
+        }
+    }
+
     pub async fn get_user_cover(&self, id: Option<FileId>) -> StorageResult<UserCoverFileBuilder> {
         let item = match id {
             Some(id) => self.take(id).await.map(UserCoverFileBuilder::from),
@@ -384,6 +524,7 @@ impl StorageSystem {
                 self.writer.write(&id, file_to_bytestream(f)).await?;
                 Ok(UserCoverFileBuilder::from(FileBuilder {
                     dims: None,
+                    size: None,
                     allowed_drop: false,
                     temp_id: id.to_string(),
                     ext: {
@@ -401,6 +542,7 @@ impl StorageSystem {
                     },
                     target_id: PathBuf::new(),
                     writer: self.writer.clone(),
+                    hash: None,
                 }))
             }
         }?;
@@ -464,10 +606,11 @@ mod tests {
         temp::{MemoryTempData, TempData},
         workers::{
             containers::MagicContainerWorker,
+            hash::HashQueue,
             media::{MediaWorker, PreparedUpload},
         },
-        CoverFileBuilder, FileId, MemStorage, RegisterTempResult, StorageError, StorageSystem,
-        CHAPTER_MAGIC, MANGA_MAGIC,
+        CoverFileBuilder, FileId, ImageVariant, MemStorage, RegisterTempResult, StorageError,
+        StorageSystem, VariantFormat, CHAPTER_MAGIC, MANGA_MAGIC,
     };
 
     async fn read_all_bytes(
@@ -617,6 +760,7 @@ mod tests {
 
         assert_eq!(fb.ext, Some(("image/png", "png")));
         assert_eq!(fb.dims, Some((3, 2)));
+        assert_eq!(fb.size, Some(payload.len() as u64));
 
         CoverFileBuilder::from(fb)
             .build("png-kept", 0)
@@ -626,6 +770,63 @@ mod tests {
         assert_eq!(got, payload);
     }
 
+    #[tokio::test]
+    async fn variants_are_generated_once_and_invalidated_with_the_original() {
+        let backend = Arc::new(MemStorage::new());
+        let storage = match StorageSystem::new(std::env::temp_dir().as_path(), backend).await {
+            Ok(v) => v,
+            Err(e) => panic!("storage init failed: {e}"),
+        };
+
+        let payload = png_bytes(400, 200);
+        let mut tf = storage
+            .new_temp_file()
+            .await
+            .unwrap_or_else(|_| panic!("tempfile create failed"));
+        tf.write_all(&payload)
+            .await
+            .expect("write to tempfile should succeed");
+        tf.sync_all().await.expect("sync should succeed");
+        let id = unwrap_single_register(storage.register_temp_file(tf).await, "register");
+        let fb = unwrap_storage(storage.take(id).await, "take");
+        CoverFileBuilder::from(fb)
+            .build("variant", 0)
+            .await
+            .unwrap_or_else(|_| panic!("build failed"));
+
+        let variant = ImageVariant::new(Some(300), VariantFormat::Webp);
+        let obj = unwrap_storage(
+            storage
+                .get_variant("covers/variant.png", &variant, &Default::default())
+                .await,
+            "variant",
+        );
+        assert_eq!(
+            obj.content_type.map(|v| v.to_string()).as_deref(),
+            Some("image/webp")
+        );
+        let got = read_all_bytes(&storage.reader, "covers/variant@320.webp").await;
+        let img = image::load_from_memory(&got).expect("variant should decode");
+        assert_eq!((img.width(), img.height()), (320, 160));
+
+        // originals are never upscaled
+        let large = ImageVariant::new(Some(1000), VariantFormat::Original);
+        unwrap_storage(
+            storage
+                .get_variant("covers/variant.png", &large, &Default::default())
+                .await,
+            "large variant",
+        );
+        let got = read_all_bytes(&storage.reader, "covers/variant@1280.png").await;
+        let img = image::load_from_memory(&got).expect("variant should decode");
+        assert_eq!((img.width(), img.height()), (400, 200));
+
+        unwrap_storage(storage.delete_key("covers/variant.png").await, "delete");
+        for key in ["covers/variant@320.webp", "covers/variant@1280.png"] {
+            assert!(storage.reader.get(key, &Default::default()).await.is_err());
+        }
+    }
+
     #[tokio::test]
     async fn register_take_unsupported_image_is_converted_before_upload() {
         let backend = Arc::new(MemStorage::new());
@@ -788,11 +989,36 @@ mod tests {
             _source: Arc<dyn TempData>,
             _writer: Arc<dyn StorageWriter + Send + Sync>,
             _transcode_sem: Arc<tokio::sync::Semaphore>,
+            _hashes: HashQueue,
         ) -> Result<PreparedUpload, ProcessingError> {
             panic!("intentional panic for test")
         }
     }
 
+    /// Queues the hash like the default worker, but the upload fails
+    struct FailingUploadMediaWorker;
+
+    #[async_trait::async_trait]
+    impl MediaWorker for FailingUploadMediaWorker {
+        async fn detect_ext(
+            &self,
+            _source: &Arc<dyn TempData>,
+        ) -> Option<(&'static str, &'static str)> {
+            None
+        }
+
+        async fn process_and_upload(
+            &self,
+            source: Arc<dyn TempData>,
+            _writer: Arc<dyn StorageWriter + Send + Sync>,
+            _transcode_sem: Arc<tokio::sync::Semaphore>,
+            hashes: HashQueue,
+        ) -> Result<PreparedUpload, ProcessingError> {
+            hashes.submit(source);
+            Err(ProcessingError::SemaphoreClosed)
+        }
+    }
+
     struct SlowCountingMediaWorker {
         in_flight: Arc<AtomicUsize>,
         peak: Arc<AtomicUsize>,
@@ -830,6 +1056,7 @@ mod tests {
             _source: Arc<dyn TempData>,
             _writer: Arc<dyn StorageWriter + Send + Sync>,
             _transcode_sem: Arc<tokio::sync::Semaphore>,
+            _hashes: HashQueue,
         ) -> Result<PreparedUpload, ProcessingError> {
             let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
             self.record_peak(now);
@@ -838,6 +1065,7 @@ mod tests {
             Ok(PreparedUpload {
                 handle: format!("temp/{}", uuid::Uuid::new_v4()),
                 dims: None,
+                size: None,
                 ext: None,
             })
         }
@@ -878,6 +1106,51 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn failed_and_dropped_entries_cancel_their_hash_jobs() {
+        let backend = Arc::new(MemStorage::new());
+        let storage = StorageSystem::build_with_components(
+            std::env::temp_dir().as_path(),
+            backend.clone(),
+            backend,
+            Arc::new(MagicContainerWorker),
+            Arc::new(FailingUploadMediaWorker),
+            1,
+        );
+        let hashes = storage.hashes.clone();
+
+        let mut ids = vec![];
+        for _ in 0..2 {
+            let mut tf = storage
+                .new_temp_file()
+                .await
+                .expect("tempfile create failed");
+            tf.write_all(&png_bytes(16, 16))
+                .await
+                .expect("write to tempfile should succeed");
+            tf.sync_all().await.expect("sync should succeed");
+            ids.push(unwrap_single_register(
+                storage.register_temp_file(tf).await,
+                "register",
+            ));
+        }
+
+        assert!(matches!(
+            storage.take(ids[0].clone()).await,
+            Err(StorageError::Processing(ProcessingError::SemaphoreClosed))
+        ));
+        // the second file is never taken
+        drop(storage);
+
+        for _ in 0..500 {
+            if hashes.is_idle() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("hash jobs were not cancelled");
+    }
+
     #[tokio::test]
     async fn register_many_limits_inflight_background_workers() {
         let backend = Arc::new(MemStorage::new());
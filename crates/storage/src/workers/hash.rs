@@ -0,0 +1,322 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use img_hash::{HashAlg, HasherConfig, ImageHash};
+use tokio::sync::Semaphore;
+
+use crate::temp::TempData;
+
+pub type HashSinkError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Receives the perceptual hash of a file, e.g. to store it in the record of a page
+#[async_trait::async_trait]
+pub trait HashSink: Send + Sync {
+    async fn store_hash(&self, target: &str, hash: String) -> Result<(), HashSinkError>;
+}
+
+enum HashState {
+    /// Waits for a free worker
+    Waiting,
+    Processing,
+    /// Hashed, but no target is attached yet
+    DoneProcessing(String),
+}
+
+struct HashJob {
+    state: HashState,
+    target: Option<(String, Arc<dyn HashSink>)>,
+    cancelled: bool,
+}
+
+/// Computes perceptual hashes of uploaded images off the request path.
+///
+/// Jobs are removed once their hash was flushed, on errors & when they get cancelled.
+/// A target can be attached in any state. Only [`HashState::DoneProcessing`] flushes it right away,
+/// the other states will reach it & flush then
+pub(crate) struct HashPool {
+    jobs: Mutex<HashMap<String, HashJob>>,
+    workers: Semaphore,
+}
+
+impl HashPool {
+    pub(crate) fn new(workers: usize) -> Self {
+        Self {
+            jobs: Mutex::default(),
+            workers: Semaphore::new(workers.max(1)),
+        }
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, HashMap<String, HashJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues the file `id` for hashing
+    pub(crate) fn submit(self: &Arc<Self>, id: String, source: Arc<dyn TempData>) {
+        self.jobs().insert(
+            id.clone(),
+            HashJob {
+                state: HashState::Waiting,
+                target: None,
+                cancelled: false,
+            },
+        );
+        let pool = self.clone();
+        tokio::spawn(async move { pool.run(id, source).await });
+    }
+
+    async fn run(&self, id: String, source: Arc<dyn TempData>) {
+        let Ok(_permit) = self.workers.acquire().await else {
+            self.jobs().remove(&id);
+            return;
+        };
+        {
+            let mut jobs = self.jobs();
+            match jobs.get_mut(&id) {
+                Some(job) if !job.cancelled => job.state = HashState::Processing,
+                _ => {
+                    jobs.remove(&id);
+                    return;
+                }
+            }
+        }
+
+        let hash = match source.read_all().await {
+            Ok(bytes) => tokio::task::spawn_blocking(move || hash_image(&bytes))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|v| v.map_err(|e| e.to_string())),
+            Err(e) => Err(e.to_string()),
+        };
+        drop(source);
+
+        let hash = match hash {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("Failed to hash {id}: {e}");
+                self.jobs().remove(&id);
+                return;
+            }
+        };
+        let flush = {
+            let mut jobs = self.jobs();
+            let Some(job) = jobs.get_mut(&id) else {
+                return;
+            };
+            if job.cancelled {
+                jobs.remove(&id);
+                return;
+            }
+            match job.target.take() {
+                Some(target) => {
+                    jobs.remove(&id);
+                    Some(target)
+                }
+                None => {
+                    job.state = HashState::DoneProcessing(hash.clone());
+                    None
+                }
+            }
+        };
+        if let Some((target, sink)) = flush {
+            store(&target, sink, hash).await;
+        }
+    }
+
+    /// Flushes the hash of `id` to `target` once it is known. Does nothing if `id` is not hashed
+    pub(crate) fn attach(self: &Arc<Self>, id: &str, target: String, sink: Arc<dyn HashSink>) {
+        let hash = {
+            let mut jobs = self.jobs();
+            let Some(job) = jobs.get_mut(id) else {
+                return;
+            };
+            if !matches!(job.state, HashState::DoneProcessing(_)) {
+                job.target = Some((target, sink));
+                return;
+            }
+            match jobs.remove(id).map(|v| v.state) {
+                Some(HashState::DoneProcessing(hash)) => hash,
+                _ => return,
+            }
+        };
+        tokio::spawn(async move { store(&target, sink, hash).await });
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_idle(&self) -> bool {
+        self.jobs().is_empty()
+    }
+
+    /// The job is dropped instead of being processed or flushed
+    pub(crate) fn cancel(&self, id: &str) {
+        let mut jobs = self.jobs();
+        let Some(job) = jobs.get_mut(id) else {
+            return;
+        };
+        match job.state {
+            HashState::DoneProcessing(_) => {
+                jobs.remove(id);
+            }
+            HashState::Waiting | HashState::Processing => job.cancelled = true,
+        }
+    }
+}
+
+async fn store(target: &str, sink: Arc<dyn HashSink>, hash: String) {
+    if let Err(e) = sink.store_hash(target, hash).await {
+        log::error!("Failed to store hash of {target}: {e}");
+    }
+}
+
+/// Gradient hash, encoded as base64
+pub(crate) fn hash_image(bytes: &[u8]) -> Result<String, image::ImageError> {
+    let img = image::load_from_memory(bytes)?;
+    Ok(HasherConfig::new()
+        .hash_alg(HashAlg::Gradient)
+        .to_hasher()
+        .hash_image(&img)
+        .to_base64())
+}
+
+/// Bits of a hash created by [`hash_image`]. Compare them with the hamming distance
+pub fn decode_hash(hash: &str) -> Option<Vec<u8>> {
+    ImageHash::<Box<[u8]>>::from_base64(hash)
+        .ok()
+        .map(|v| v.as_bytes().to_vec())
+}
+
+/// Lets a media worker queue the file it processes
+pub(crate) struct HashQueue {
+    pub(crate) pool: Arc<HashPool>,
+    pub(crate) id: String,
+}
+
+impl HashQueue {
+    pub(crate) fn submit(self, source: Arc<dyn TempData>) {
+        self.pool.submit(self.id, source);
+    }
+}
+
+/// Hash job of a taken file. Dropping it without attaching a target cancels the job
+pub(crate) struct HashHandle {
+    pool: Arc<HashPool>,
+    id: Option<String>,
+}
+
+impl HashHandle {
+    pub(crate) fn new(pool: Arc<HashPool>, id: String) -> Self {
+        Self { pool, id: Some(id) }
+    }
+
+    pub(crate) fn attach(mut self, target: String, sink: Arc<dyn HashSink>) {
+        if let Some(id) = self.id.take() {
+            self.pool.attach(&id, target, sink);
+        }
+    }
+}
+
+impl Drop for HashHandle {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.pool.cancel(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use image::{DynamicImage, ImageFormat};
+
+    use crate::temp::{MemoryTempData, TempData};
+
+    use super::{HashHandle, HashPool, HashSink, HashSinkError, HashState};
+
+    #[derive(Default)]
+    struct RecordingSink {
+        stored: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl HashSink for RecordingSink {
+        async fn store_hash(&self, target: &str, hash: String) -> Result<(), HashSinkError> {
+            self.stored.lock().unwrap().push((target.to_owned(), hash));
+            Ok(())
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Arc<dyn TempData> {
+        let mut cursor = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut cursor, ImageFormat::Png)
+            .expect("png encode should succeed");
+        Arc::new(MemoryTempData::from_bytes(cursor.into_inner()))
+    }
+
+    async fn wait_until(pool: &HashPool, done: impl Fn(&HashPool) -> bool) {
+        for _ in 0..500 {
+            if done(pool) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("hash pool did not settle");
+    }
+
+    #[tokio::test]
+    async fn target_attached_in_any_state_receives_the_hash() {
+        let pool = Arc::new(HashPool::new(1));
+        let sink = Arc::new(RecordingSink::default());
+
+        pool.submit("early".to_owned(), png(16, 16));
+        HashHandle::new(pool.clone(), "early".to_owned()).attach("page-1".to_owned(), sink.clone());
+        pool.submit("late".to_owned(), png(32, 8));
+        wait_until(&pool, |p| {
+            matches!(
+                p.jobs().get("late").map(|v| &v.state),
+                Some(HashState::DoneProcessing(_))
+            )
+        })
+        .await;
+        HashHandle::new(pool.clone(), "late".to_owned()).attach("page-2".to_owned(), sink.clone());
+
+        wait_until(&pool, |p| p.jobs().is_empty()).await;
+        wait_until(&pool, |_| sink.stored.lock().unwrap().len() == 2).await;
+        let stored = sink.stored.lock().unwrap().clone();
+        assert!(stored.iter().any(|(target, _)| target == "page-1"));
+        assert!(stored.iter().any(|(target, _)| target == "page-2"));
+        assert!(stored.iter().all(|(_, hash)| !hash.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn dropped_handles_cancel_jobs() {
+        let pool = Arc::new(HashPool::new(1));
+        let sink = Arc::new(RecordingSink::default());
+
+        pool.submit("first".to_owned(), png(16, 16));
+        pool.submit("second".to_owned(), png(16, 16));
+        drop(HashHandle::new(pool.clone(), "first".to_owned()));
+        drop(HashHandle::new(pool.clone(), "second".to_owned()));
+        wait_until(&pool, |p| p.jobs().is_empty()).await;
+
+        // attaching after a cancel does nothing
+        HashHandle::new(pool.clone(), "first".to_owned()).attach("page".to_owned(), sink.clone());
+        assert!(sink.stored.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn undecodable_files_are_dropped() {
+        let pool = Arc::new(HashPool::new(1));
+        pool.submit(
+            "broken".to_owned(),
+            Arc::new(MemoryTempData::from_bytes(b"not an image".to_vec())),
+        );
+        wait_until(&pool, |p| p.jobs().is_empty()).await;
+    }
+}
@@ -12,11 +12,14 @@ use crate::{
     backends::{ByteStream, StorageWriter},
     error::ProcessingError,
     temp::{MemoryTempData, TempData},
+    workers::hash::HashQueue,
 };
 
 pub(crate) struct PreparedUpload {
     pub(crate) handle: String,
     pub(crate) dims: Option<(u32, u32)>,
+    /// Bytes of the uploaded file
+    pub(crate) size: Option<u64>,
     pub(crate) ext: Option<(&'static str, &'static str)>,
 }
 
@@ -28,6 +31,7 @@ pub(crate) trait MediaWorker: Send + Sync {
         source: Arc<dyn TempData>,
         writer: Arc<dyn StorageWriter + Send + Sync>,
         transcode_sem: Arc<Semaphore>,
+        hashes: HashQueue,
     ) -> Result<PreparedUpload, ProcessingError>;
 }
 
@@ -57,6 +61,7 @@ impl MediaWorker for DefaultMediaWorker {
         source: Arc<dyn TempData>,
         writer: Arc<dyn StorageWriter + Send + Sync>,
         transcode_sem: Arc<Semaphore>,
+        hashes: HashQueue,
     ) -> Result<PreparedUpload, ProcessingError> {
         let _permit = transcode_sem
             .acquire()
@@ -72,9 +77,10 @@ impl MediaWorker for DefaultMediaWorker {
 
         let mut final_ext = ext;
         let mut dims = None;
+        let size;
         let upload_handle = format!("temp/{}", uuid::Uuid::new_v4());
         if is_image {
-            //TODO: spawn hashing task, which will run in a worker pool. States are, waiting, done-processing, processing. Items that are truly done or had an error get removed. its possible to attach an id to them or mark them as canceled in any state. marked as canceled will get removed & not further processed. if the id is set it will flush the hash to the db. it is only used at done-processing. wake up on done-processing and the others should just set the id, because it will reach done processing anyway and then flush it to the db.
+            hashes.submit(source.clone());
         }
 
         if is_image && !allowed {
@@ -83,10 +89,10 @@ impl MediaWorker for DefaultMediaWorker {
                 .await
                 .map_err(ProcessingError::ReadTempFile)?;
 
-            let (jpeg_bytes, size) = tokio::task::spawn_blocking(move || {
+            let (jpeg_bytes, dimensions) = tokio::task::spawn_blocking(move || {
                 let img = image::load_from_memory(&buffer)?;
                 let mut out = Vec::new();
-                let size = (img.width(), img.height());
+                let dimensions = (img.width(), img.height());
                 let rgb = img.to_rgb8();
                 let mut enc = image::codecs::jpeg::JpegEncoder::new(&mut out);
                 enc.encode(
@@ -95,14 +101,15 @@ impl MediaWorker for DefaultMediaWorker {
                     rgb.height(),
                     image::ColorType::Rgb8.into(),
                 )?;
-                Ok::<_, image::ImageError>((out, size))
+                Ok::<_, image::ImageError>((out, dimensions))
             })
             .await
             .map_err(ProcessingError::ImageWorkerJoin)?
             .map_err(ProcessingError::ImageConversion)?;
 
-            dims = Some(size);
+            dims = Some(dimensions);
             final_ext = Some(("image/jpeg", "jpeg"));
+            size = Some(jpeg_bytes.len() as u64);
 
             let converted: Arc<dyn TempData> = Arc::new(MemoryTempData::from_bytes(jpeg_bytes));
             writer
@@ -116,6 +123,7 @@ impl MediaWorker for DefaultMediaWorker {
                 .await
                 .map_err(ProcessingError::UploadConverted)?;
         } else {
+            size = source.len().await.ok();
             if is_image {
                 let s = source.clone();
                 let s = s
@@ -149,6 +157,7 @@ impl MediaWorker for DefaultMediaWorker {
         Ok(PreparedUpload {
             handle: upload_handle,
             dims,
+            size,
             ext: final_ext,
         })
     }
@@ -1,2 +1,4 @@
 pub(crate) mod containers;
+pub(crate) mod hash;
 pub(crate) mod media;
+pub(crate) mod variants;
@@ -0,0 +1,274 @@
+use std::{io::Cursor, str::FromStr};
+
+use image::{
+    codecs::avif::AvifEncoder, error::ImageFormatHint, imageops::FilterType, DynamicImage,
+    ImageFormat,
+};
+
+use crate::backends::StorageWriter;
+
+/// Requested widths are rounded up to one of these, so only a few variants are stored per image
+pub const WIDTH_BUCKETS: [u32; 4] = [160, 320, 640, 1280];
+
+/// Originals with other extensions are always served as they are.
+/// gifs would lose their animation
+const DECODABLE: [&str; 4] = ["png", "jpeg", "jpg", "webp"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariantFormat {
+    /// Same format as the original
+    #[default]
+    Original,
+    Webp,
+    Avif,
+}
+
+impl VariantFormat {
+    const ALL: [VariantFormat; 3] = [Self::Original, Self::Webp, Self::Avif];
+
+    fn ext(self) -> Option<&'static str> {
+        match self {
+            Self::Original => None,
+            Self::Webp => Some("webp"),
+            Self::Avif => Some("avif"),
+        }
+    }
+
+    pub fn mime(self) -> Option<mime::Mime> {
+        self.ext().and_then(|v| mime_guess::from_ext(v).first())
+    }
+}
+
+impl FromStr for VariantFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "original" => Ok(Self::Original),
+            "webp" => Ok(Self::Webp),
+            "avif" => Ok(Self::Avif),
+            _ => Err(format!("unknown image format {s}")),
+        }
+    }
+}
+
+/// Derived version of a stored image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageVariant {
+    /// Max width. Images are never upscaled
+    pub width: Option<u32>,
+    pub format: VariantFormat,
+}
+
+impl ImageVariant {
+    /// Widths above the largest bucket get the full width
+    pub fn new(width: Option<u32>, format: VariantFormat) -> Self {
+        Self {
+            width: width.and_then(|w| WIDTH_BUCKETS.iter().copied().find(|b| *b >= w)),
+            format,
+        }
+    }
+
+    fn is_original(&self) -> bool {
+        self.width.is_none() && self.format == VariantFormat::Original
+    }
+}
+
+/// Splits `covers/a.png` into `covers/a` & `png`
+fn split_ext(key: &str) -> Option<(&str, &str)> {
+    let (stem, ext) = key.rsplit_once('.')?;
+    if ext.contains('/') || stem.ends_with('/') {
+        return None;
+    }
+    Some((stem, ext))
+}
+
+/// Variants are siblings of the original, `covers/a.png` as webp with width 320 is `covers/a@320.webp`.
+/// Returns `None` if the original should be served
+pub(crate) fn variant_key(key: &str, variant: &ImageVariant) -> Option<String> {
+    if variant.is_original() {
+        return None;
+    }
+    let (stem, ext) = split_ext(key)?;
+    if !DECODABLE.contains(&ext.to_ascii_lowercase().as_str()) {
+        return None;
+    }
+    let width = variant
+        .width
+        .map_or_else(|| "full".to_owned(), |v| v.to_string());
+    Some(format!(
+        "{stem}@{width}.{}",
+        variant.format.ext().unwrap_or(ext)
+    ))
+}
+
+fn variant_keys(key: &str) -> Vec<String> {
+    let widths = WIDTH_BUCKETS.iter().copied().map(Some).chain([None]);
+    widths
+        .flat_map(|width| {
+            VariantFormat::ALL
+                .into_iter()
+                .filter_map(move |format| variant_key(key, &ImageVariant { width, format }))
+        })
+        .collect()
+}
+
+/// Removes every variant of `key`. Called when the original is deleted or replaced
+pub(crate) async fn invalidate(writer: &dyn StorageWriter, key: &str) {
+    let keys = variant_keys(key);
+    let deleted = futures_util::future::join_all(keys.iter().map(|v| writer.delete(v))).await;
+    for (key, result) in keys.iter().zip(deleted) {
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Failed to delete image variant {key}: {e}"),
+        }
+    }
+}
+
+/// Resizes & re-encodes the original.
+/// Returns `None` if the format changes & the original is smaller, the webp encoder is lossless
+/// & loses against photos
+pub(crate) fn encode_variant(
+    bytes: &[u8],
+    original_ext: &str,
+    variant: &ImageVariant,
+) -> Result<Option<Vec<u8>>, image::ImageError> {
+    let mut img = image::load_from_memory(bytes)?;
+    if let Some(width) = variant.width.filter(|w| *w < img.width()) {
+        img = img.resize(width, u32::MAX, FilterType::Triangle);
+    }
+    let format = match variant.format {
+        VariantFormat::Original => ImageFormat::from_extension(original_ext).ok_or_else(|| {
+            image::ImageError::Unsupported(
+                ImageFormatHint::PathExtension(original_ext.into()).into(),
+            )
+        })?,
+        VariantFormat::Webp => ImageFormat::WebP,
+        VariantFormat::Avif => ImageFormat::Avif,
+    };
+    // jpeg has no alpha channel, the other encoders only take 8 bit rgb(a)
+    let img = if format != ImageFormat::Jpeg && img.color().has_alpha() {
+        DynamicImage::ImageRgba8(img.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+
+    let mut out = Cursor::new(Vec::new());
+    if format == ImageFormat::Avif {
+        // the default speed takes seconds for a single page
+        img.write_with_encoder(AvifEncoder::new_with_speed_quality(&mut out, 8, 80))?;
+    } else {
+        img.write_to(&mut out, format)?;
+    }
+    let out = out.into_inner();
+    if variant.format != VariantFormat::Original && out.len() >= bytes.len() {
+        return Ok(None);
+    }
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::stream;
+
+    use crate::backends::{ByteStream, MemStorage, Options, StorageReader, StorageWriter};
+
+    use super::{encode_variant, invalidate, variant_key, ImageVariant, VariantFormat};
+
+    #[test]
+    fn widths_are_rounded_up_to_buckets() {
+        assert_eq!(
+            ImageVariant::new(Some(1), VariantFormat::Webp).width,
+            Some(160)
+        );
+        assert_eq!(
+            ImageVariant::new(Some(321), VariantFormat::Webp).width,
+            Some(640)
+        );
+        assert_eq!(
+            ImageVariant::new(Some(5000), VariantFormat::Webp).width,
+            None
+        );
+    }
+
+    #[test]
+    fn variants_are_siblings_of_the_original() {
+        let webp = ImageVariant::new(Some(300), VariantFormat::Webp);
+        assert_eq!(
+            variant_key("covers/a.png", &webp).as_deref(),
+            Some("covers/a@320.webp")
+        );
+        let resized = ImageVariant::new(Some(300), VariantFormat::Original);
+        assert_eq!(
+            variant_key("mangas/m/c/v/1.jpeg", &resized).as_deref(),
+            Some("mangas/m/c/v/1@320.jpeg")
+        );
+        let avif = ImageVariant::new(None, VariantFormat::Avif);
+        assert_eq!(
+            variant_key("covers/a.png", &avif).as_deref(),
+            Some("covers/a@full.avif")
+        );
+        assert_eq!(variant_key("covers/a.png", &ImageVariant::default()), None);
+        assert_eq!(variant_key("covers/a.gif", &webp), None);
+        assert_eq!(variant_key("covers.d/a", &webp), None);
+    }
+
+    #[test]
+    fn larger_variants_are_not_used() {
+        let mut seed = 1u32;
+        let noise = image::RgbImage::from_fn(128, 128, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let [r, g, b, _] = seed.to_be_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(noise)
+            .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+            .expect("jpeg encode should succeed");
+        let jpeg = jpeg.into_inner();
+
+        let webp = ImageVariant::new(None, VariantFormat::Webp);
+        let encoded = encode_variant(&jpeg, "jpg", &webp).expect("encode should succeed");
+        assert_eq!(
+            encoded, None,
+            "lossless webp of noise is larger than the jpeg"
+        );
+        let resized = ImageVariant::new(Some(100), VariantFormat::Original);
+        let encoded = encode_variant(&jpeg, "jpg", &resized).expect("encode should succeed");
+        assert!(encoded.is_some());
+    }
+
+    #[tokio::test]
+    async fn invalidate_removes_all_variants() {
+        let storage = Arc::new(MemStorage::new());
+        for key in [
+            "covers/a.png",
+            "covers/a@320.webp",
+            "covers/a@full.avif",
+            "covers/b@320.webp",
+        ] {
+            let data: ByteStream = Box::pin(stream::iter([Ok(bytes::Bytes::from_static(b"x"))]));
+            storage
+                .write(key, data)
+                .await
+                .expect("write should succeed");
+        }
+
+        invalidate(storage.as_ref(), "covers/a.png").await;
+        for (key, exists) in [
+            ("covers/a.png", true),
+            ("covers/a@320.webp", false),
+            ("covers/a@full.avif", false),
+            ("covers/b@320.webp", true),
+        ] {
+            assert_eq!(
+                storage.get(key, &Options::default()).await.is_ok(),
+                exists,
+                "{key}"
+            );
+        }
+    }
+}